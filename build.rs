@@ -3,6 +3,9 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 fn main() {
+    #[cfg(feature = "grpc")]
+    compile_grpc_service();
+
     // Source path: ../out_lib/fpcalc.exe relative to the crate root
     let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
     let source_path = Path::new(&manifest_dir).join("./out_lib/fpcalc.exe");
@@ -55,3 +58,55 @@ fn main() {
         println!("cargo:warning=Failed to copy fpcalc.exe: {}", e);
     }
 }
+
+/// Generate the gRPC service stubs from the hand-written prost messages in `src/grpc.rs`,
+/// so `protoc` isn't needed (`proto/audio_sorter.proto` describes them for clients).
+#[cfg(feature = "grpc")]
+fn compile_grpc_service() {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    let method = |name: &str, route: &str, input: &str, output: &str| {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("crate::grpc::{}", input))
+            .output_type(format!("crate::grpc::{}", output))
+            .codec_path("tonic::codec::ProstCodec")
+    };
+
+    let service = Service::builder()
+        .name("AudioSorter")
+        .package("audio_sorter")
+        .method(
+            method(
+                "start_scan",
+                "StartScan",
+                "StartScanRequest",
+                "StartScanResponse",
+            )
+            .build(),
+        )
+        .method(
+            method(
+                "watch_scan",
+                "WatchScan",
+                "WatchScanRequest",
+                "ScanProgress",
+            )
+            .server_streaming()
+            .build(),
+        )
+        .method(
+            method(
+                "list_tracks",
+                "ListTracks",
+                "ListTracksRequest",
+                "ListTracksResponse",
+            )
+            .build(),
+        )
+        .method(method("get_stats", "GetStats", "GetStatsRequest", "Stats").build())
+        .build();
+
+    Builder::new().compile(&[service]);
+}
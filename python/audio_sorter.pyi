@@ -0,0 +1,26 @@
+from os import PathLike
+from typing import Any, Dict, List, Optional, Tuple, Union
+
+_Path = Union[str, PathLike]
+Metadata = Dict[str, Any]
+
+class Library:
+    def __init__(self, index_dir: _Path) -> None: ...
+    @property
+    def index_dir(self) -> str: ...
+    def __len__(self) -> int: ...
+    def tracks(self) -> List[Dict[str, Any]]: ...
+    def track(self, path: _Path) -> Optional[Dict[str, Any]]: ...
+    def vector(self, path: _Path) -> Optional[List[float]]: ...
+    def vectors(self) -> Dict[str, List[float]]: ...
+    def similar(self, path: _Path, n: int = 20) -> List[Tuple[str, float, float]]: ...
+
+def fingerprint(path: _Path) -> Tuple[float, str]: ...
+def analyze(
+    path: _Path, analyzers: List[str] = ...
+) -> Tuple[Metadata, Optional[List[float]]]: ...
+def identify(
+    path: _Path, client_id: Optional[str] = None
+) -> Tuple[Metadata, Optional[List[float]]]: ...
+def distance(a: List[float], b: List[float]) -> float: ...
+def feature_names(n: int) -> List[str]: ...
@@ -0,0 +1,230 @@
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use audio_sorter::analysis_store::{self, SharedAnalysis};
+use audio_sorter::fingerprint::compute_fingerprint;
+use audio_sorter::lookup_cache::LookupCache;
+use audio_sorter::lookup_queue::LookupQueue;
+use audio_sorter::merge_policy::MergePolicy;
+use audio_sorter::neighbors::{self, SharedNeighbors};
+use audio_sorter::reanalyze::{self, Scope};
+use audio_sorter::scanner::Pipeline;
+use audio_sorter::similarity::{self, SimilaritySettings};
+use audio_sorter::storage::{self, AudioLibrary, SharedLibrary};
+use audio_sorter::{analyzers, http, organizer, worker, ScanArgs};
+
+fn runtime_error(e: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{:#}", e))
+}
+
+/// Hand a serializable value to Python as plain dicts and lists, in the
+/// same shape as the JSON files and HTTP API.
+fn to_python<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|e| runtime_error(e.into()))?;
+    Ok(py
+        .import_bound("json")?
+        .call_method1("loads", (json,))?
+        .unbind())
+}
+
+/// An index directory written by `audio_sorter scan`. Reads reload
+/// `index.json`, `analysis.bin` and `neighbors.bin` when a scan rewrites
+/// them, so a long-running notebook keeps seeing current data.
+#[pyclass(module = "audio_sorter", frozen)]
+struct Library {
+    index_dir: PathBuf,
+    library: SharedLibrary,
+    analysis: SharedAnalysis,
+    neighbors: SharedNeighbors,
+    similarity: SimilaritySettings,
+}
+
+#[pymethods]
+impl Library {
+    #[new]
+    fn new(py: Python<'_>, index_dir: PathBuf) -> PyResult<Self> {
+        py.allow_threads(|| {
+            let index_path = index_dir.join("index.json");
+            anyhow::ensure!(
+                storage::stored_path(&index_path).exists(),
+                "No index found at {:?}",
+                index_path
+            );
+            // Surface a corrupt index here rather than as an empty library
+            AudioLibrary::load(&index_path)?;
+            Ok(Self {
+                library: SharedLibrary::new(index_path),
+                analysis: SharedAnalysis::new(index_dir.join("analysis.bin")),
+                neighbors: SharedNeighbors::new(index_dir.join("neighbors.bin")),
+                similarity: SimilaritySettings::load(&index_dir.join("similarity.json"))?,
+                index_dir,
+            })
+        })
+        .map_err(runtime_error)
+    }
+
+    #[getter]
+    fn index_dir(&self) -> PathBuf {
+        self.index_dir.clone()
+    }
+
+    fn __len__(&self) -> usize {
+        self.library.snapshot_now().library.files.len()
+    }
+
+    /// Every indexed track (path, sizes, timestamps and `metadata`), sorted
+    /// like the dashboard's default order.
+    fn tracks(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let library = self.library.snapshot_now().library;
+        to_python(py, &library.sorted_tracks())
+    }
+
+    /// The indexed track at `path`, or `None`.
+    fn track(&self, py: Python<'_>, path: PathBuf) -> PyResult<PyObject> {
+        let library = self.library.snapshot_now().library;
+        to_python(py, &library.files.get(&path))
+    }
+
+    /// The bliss feature vector of `path`, or `None` when it was not analyzed.
+    fn vector(&self, path: PathBuf) -> Option<Vec<f32>> {
+        self.analysis.get().get(&path).cloned()
+    }
+
+    /// All bliss feature vectors by path.
+    fn vectors(&self) -> HashMap<PathBuf, Vec<f32>> {
+        self.analysis.get().data.clone()
+    }
+
+    /// Up to `n` tracks most similar to `path` as `(path, distance, score)`,
+    /// ranked by score (lower is closer) like `/api/recommend`, including
+    /// the genre and era weighting of `similarity.json`. Empty when `path`
+    /// has no analysis.
+    #[pyo3(signature = (path, n = 20))]
+    fn similar(&self, path: PathBuf, n: usize) -> Vec<(PathBuf, f32, f32)> {
+        let library = self.library.snapshot_now().library;
+        let index = self.neighbors.get();
+        let Some(candidates) = neighbors::lookup(&index, &self.analysis, &path) else {
+            return Vec::new();
+        };
+        let seed = library.files.get(&path).map(|t| &t.metadata);
+        let mut ranked: Vec<(PathBuf, f32, f32)> = candidates
+            .into_iter()
+            .map(|(candidate, distance)| {
+                let meta = library.files.get(&candidate).map(|t| &t.metadata);
+                let score = similarity::score(&self.similarity, distance, seed, meta);
+                (candidate, distance, score)
+            })
+            .collect();
+        ranked.sort_by(|a, b| a.2.total_cmp(&b.2));
+        ranked.truncate(n);
+        ranked
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Library({:?}, tracks={})",
+            self.index_dir,
+            self.library.snapshot_now().library.files.len()
+        )
+    }
+}
+
+/// Chromaprint fingerprint of `path` as `(duration_secs, fingerprint)`.
+/// Needs `fpcalc`, like scans.
+#[pyfunction]
+fn fingerprint(py: Python<'_>, path: PathBuf) -> PyResult<(f64, String)> {
+    py.allow_threads(|| compute_fingerprint(&path))
+        .map_err(runtime_error)
+}
+
+/// Local tags plus the offline analysis of `path` as `(metadata, vector)`:
+/// quality and loudness, the bliss feature vector and the features of the
+/// named analyzers (e.g. `["silence"]`). Nothing is looked up online.
+#[pyfunction]
+#[pyo3(signature = (path, analyzers = Vec::new()))]
+fn analyze(
+    py: Python<'_>,
+    path: PathBuf,
+    analyzers: Vec<String>,
+) -> PyResult<(PyObject, Option<Vec<f32>>)> {
+    let (meta, vector) = py
+        .allow_threads(|| -> anyhow::Result<_> {
+            analyzers::configure(&analyzers)?;
+            let mut meta = organizer::read_tags(&path)?;
+            let vector = reanalyze::reanalyze_file(
+                &path,
+                &[Scope::Bliss, Scope::Loudness, Scope::Features],
+                &mut meta,
+            )?;
+            Ok((meta, vector))
+        })
+        .map_err(runtime_error)?;
+    Ok((to_python(py, &meta)?, vector))
+}
+
+/// Run the scan pipeline on one file without touching any index, as
+/// `(metadata, vector)`. With an AcoustID `client_id` the fingerprint is
+/// looked up online and merged with the local tags; without one only the
+/// local tags are used.
+#[pyfunction]
+#[pyo3(signature = (path, client_id = None))]
+fn identify(
+    py: Python<'_>,
+    path: PathBuf,
+    client_id: Option<String>,
+) -> PyResult<(PyObject, Option<Vec<f32>>)> {
+    let (meta, vector) = py
+        .allow_threads(|| -> anyhow::Result<_> {
+            let args = ScanArgs {
+                input_dir: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+                output_dir: PathBuf::new(),
+                offline: client_id.is_none(),
+                client_id,
+                dry_run: false,
+                limit: None,
+                sample: None,
+                include_hidden: false,
+                memory_budget_mb: None,
+                background: false,
+                profile: None,
+            };
+            let ctx = worker::ScanContext {
+                args: &args,
+                cache: &LookupCache::new(args.offline),
+                queue: &LookupQueue::default(),
+                policy: &MergePolicy::default(),
+                bars: None,
+            };
+            worker::process_file(&path, &ctx, &http::client()?, Pipeline::default())
+        })
+        .map_err(runtime_error)?;
+    Ok((to_python(py, &meta)?, vector))
+}
+
+/// Distance between two bliss feature vectors; NaN when their lengths differ.
+#[pyfunction]
+fn distance(a: Vec<f32>, b: Vec<f32>) -> f32 {
+    analysis_store::euclidean_distance(&a, &b)
+}
+
+/// Names of the entries of a bliss feature vector of length `n`.
+#[pyfunction]
+fn feature_names(n: usize) -> Vec<String> {
+    analysis_store::feature_names(n)
+}
+
+#[pymodule]
+#[pyo3(name = "audio_sorter")]
+fn audio_sorter_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    analyzers::register_builtins();
+    m.add_class::<Library>()?;
+    m.add_function(wrap_pyfunction!(fingerprint, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze, m)?)?;
+    m.add_function(wrap_pyfunction!(identify, m)?)?;
+    m.add_function(wrap_pyfunction!(distance, m)?)?;
+    m.add_function(wrap_pyfunction!(feature_names, m)?)?;
+    Ok(())
+}
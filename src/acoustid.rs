@@ -1,11 +1,129 @@
 use anyhow::{Context, Result};
 use reqwest::blocking::Client;
 use serde::Deserialize; // Using blocking for simplicity in this flow, or async if main is async
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::lookup_cache::{self, LookupCache};
+
+/// Lookup endpoint of the public AcoustID service.
+pub const DEFAULT_LOOKUP_URL: &str = "https://api.acoustid.org/v2/lookup";
+
+/// Upper bound for one lookup, so a stalled connection can't hang a scan worker.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Idle keep-alive connections kept per host; roughly one per rayon worker.
+const MAX_IDLE_PER_HOST: usize = 16;
+
+/// AcoustID error codes after which no further lookup in this run can succeed.
+const ERROR_INVALID_API_KEY: u32 = 4;
+const ERROR_INVALID_USER_API_KEY: u32 = 6;
+const ERROR_TOO_MANY_REQUESTS: u32 = 14;
+
+/// Track ID from the AcoustID documentation, looked up to validate a key.
+const PROBE_TRACK_ID: &str = "9ff43b6a-4f16-427c-93c2-92307ca505e0";
+
+/// Why online lookups were suspended for the rest of the run, if they were.
+static SUSPENDED: Mutex<Option<String>> = Mutex::new(None);
+/// Lookups refused while suspended (tracks that fell back to local tags).
+static SKIPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Where fingerprint lookups are sent. `ACOUSTID_API_URL` overrides the base URL
+/// (e.g. `http://acoustid.lan/v2` for a self-hosted acoustid-server or a caching
+/// proxy), `ACOUSTID_API_TOKEN` is sent as a bearer token to servers that need one,
+/// and `ACOUSTID_PROXY` routes lookups through an HTTP(S) proxy.
+pub struct Endpoint {
+    pub lookup_url: String,
+    pub token: Option<String>,
+    pub proxy: Option<String>,
+}
+
+impl Endpoint {
+    fn from_env() -> Self {
+        let lookup_url = std::env::var("ACOUSTID_API_URL")
+            .ok()
+            .map(|base| base.trim().trim_end_matches('/').to_string())
+            .filter(|base| !base.is_empty())
+            .map(|base| {
+                if base.ends_with("/lookup") {
+                    base
+                } else {
+                    format!("{}/lookup", base)
+                }
+            })
+            .unwrap_or_else(|| DEFAULT_LOOKUP_URL.to_string());
+        let token = std::env::var("ACOUSTID_API_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty());
+        let proxy = std::env::var("ACOUSTID_PROXY")
+            .ok()
+            .filter(|p| !p.is_empty());
+        Self {
+            lookup_url,
+            token,
+            proxy,
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.lookup_url == DEFAULT_LOOKUP_URL
+    }
+}
+
+/// The configured endpoint, read from the environment (and `.env`) on first use.
+pub fn endpoint() -> &'static Endpoint {
+    static ENDPOINT: OnceLock<Endpoint> = OnceLock::new();
+    ENDPOINT.get_or_init(Endpoint::from_env)
+}
+
+/// A lookup that failed for reasons unrelated to the track itself (network,
+/// server error, rejected key, exhausted quota); retrying later may succeed.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct Unavailable(pub String);
+
+/// Whether `e` is worth queueing for a later retry rather than a final "no match".
+pub fn is_unavailable(e: &anyhow::Error) -> bool {
+    e.chain().any(|c| c.is::<Unavailable>())
+}
+
+fn build_client(endpoint: &Endpoint) -> Result<Client> {
+    let mut builder = crate::http::builder()?
+        .timeout(REQUEST_TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .pool_max_idle_per_host(MAX_IDLE_PER_HOST)
+        .user_agent(concat!("audio-sorter/", env!("CARGO_PKG_VERSION")));
+    // A lookup-specific proxy wins over AUDIO_SORTER_PROXY
+    if let Some(proxy) = &endpoint.proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy).context("Invalid ACOUSTID_PROXY")?);
+    }
+    builder
+        .build()
+        .context("Failed to build AcoustID HTTP client")
+}
+
+/// The client shared by every lookup, so rayon workers reuse pooled
+/// connections and TLS sessions instead of reconnecting per file.
+pub fn client() -> Result<&'static Client> {
+    static CLIENT: OnceLock<Result<Client, String>> = OnceLock::new();
+    CLIENT
+        .get_or_init(|| build_client(endpoint()).map_err(|e| format!("{:#}", e)))
+        .as_ref()
+        .map_err(|e| anyhow::anyhow!("{}", e))
+}
 
 #[derive(Debug, Deserialize)]
 pub struct AcoustIdResponse {
     pub status: String,
     pub results: Option<Vec<AcoustIdResult>>,
+    pub error: Option<AcoustIdError>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcoustIdError {
+    pub code: Option<u32>,
+    pub message: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -15,11 +133,14 @@ pub struct AcoustIdResult {
     pub recordings: Option<Vec<Recording>>,
 }
 
+/// Self-hosted servers without MusicBrainz metadata return only the `id`.
 #[derive(Debug, Deserialize)]
 pub struct Recording {
     pub id: String,
     pub title: Option<String>,
     pub artists: Option<Vec<Artist>>,
+    /// Length in seconds
+    pub duration: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,34 +149,168 @@ pub struct Artist {
     pub name: String,
 }
 
+/// The reason online lookups are suspended, if a fatal API error was seen.
+pub fn suspended() -> Option<String> {
+    SUSPENDED.lock().ok().and_then(|s| s.clone())
+}
+
+/// Number of lookups refused since the suspension.
+pub fn skipped_lookups() -> usize {
+    SKIPPED.load(Ordering::Relaxed)
+}
+
+/// Stop sending lookups for the rest of the run; later calls fail fast.
+pub fn suspend(reason: String) {
+    if let Ok(mut s) = SUSPENDED.lock() {
+        if s.is_none() {
+            eprintln!(
+                "AcoustID: {}. Suspending online lookups for this run.",
+                reason
+            );
+            crate::events::emit(
+                crate::events::Level::Warn,
+                "lookup",
+                format!("AcoustID: {}. Online lookups suspended", reason),
+                None,
+            );
+            *s = Some(reason);
+        }
+    }
+}
+
+/// Lift a suspension, e.g. before a new scan from the web UI.
+pub fn resume() {
+    if let Ok(mut s) = SUSPENDED.lock() {
+        *s = None;
+    }
+    SKIPPED.store(0, Ordering::Relaxed);
+}
+
+/// Errors that will repeat for every file: a bad key or an exhausted quota.
+fn fatal_reason(http_status: reqwest::StatusCode, error: Option<&AcoustIdError>) -> Option<String> {
+    match error.and_then(|e| e.code) {
+        Some(ERROR_INVALID_API_KEY) | Some(ERROR_INVALID_USER_API_KEY) => Some(format!(
+            "API key rejected ({})",
+            error.map(|e| e.message.as_str()).unwrap_or_default()
+        )),
+        Some(ERROR_TOO_MANY_REQUESTS) => Some("rate limit / quota exceeded".to_string()),
+        _ if http_status == reqwest::StatusCode::TOO_MANY_REQUESTS => {
+            Some("rate limit / quota exceeded (HTTP 429)".to_string())
+        }
+        _ if http_status == reqwest::StatusCode::UNAUTHORIZED
+            || http_status == reqwest::StatusCode::FORBIDDEN =>
+        {
+            Some(format!("access denied (HTTP {})", http_status))
+        }
+        _ => None,
+    }
+}
+
+fn send(params: &[(&str, &str)]) -> Result<(reqwest::StatusCode, String)> {
+    let endpoint = endpoint();
+    let mut request = client()?.post(&endpoint.lookup_url).form(params);
+    if let Some(token) = &endpoint.token {
+        request = request.bearer_auth(token);
+    }
+    let resp = request
+        .send()
+        .context("Failed to send request to AcoustID")?;
+    let status = resp.status();
+    let body = resp.text().context("Failed to read AcoustID response")?;
+    Ok((status, body))
+}
+
+/// Check `client_id` with a lookup by track ID before a run starts. Fails on a
+/// rejected key or exhausted quota; network problems only produce a warning,
+/// since the cache may still answer most lookups.
+pub fn validate_key(client_id: &str) -> Result<()> {
+    let endpoint = endpoint();
+    let params = [("client", client_id), ("trackid", PROBE_TRACK_ID)];
+    let (status, body) = match send(&params) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!(
+                "Warning: could not reach AcoustID to validate the key: {:#}",
+                e
+            );
+            return Ok(());
+        }
+    };
+    let parsed: Option<AcoustIdResponse> = serde_json::from_str(&body).ok();
+    let error = parsed.as_ref().and_then(|p| p.error.as_ref());
+    if let Some(reason) = fatal_reason(status, error) {
+        anyhow::bail!("AcoustID {}", reason);
+    }
+    if !status.is_success() {
+        eprintln!(
+            "Warning: AcoustID key check returned HTTP {}: {}",
+            status,
+            error.map(|e| e.message.as_str()).unwrap_or_default()
+        );
+        return Ok(());
+    }
+    if endpoint.is_default() {
+        println!("AcoustID key OK (public service limit: 3 requests/second).");
+    } else {
+        println!("AcoustID key OK ({}).", endpoint.lookup_url);
+    }
+    Ok(())
+}
+
 pub fn lookup_fingerprint(
+    cache: Option<&LookupCache>,
     client_id: &str,
     duration: f64,
     fingerprint: &str,
 ) -> Result<AcoustIdResponse> {
-    let client = Client::new();
-    let url = "https://api.acoustid.org/v2/lookup";
-
-    let params = [
-        ("client", client_id),
-        ("meta", "recordings+compress"), // requesting recordings
-        ("duration", &duration.round().to_string()),
-        ("fingerprint", fingerprint),
-    ];
-
-    let resp = client
-        .post(url)
-        .form(&params)
-        .send()
-        .context("Failed to send request to AcoustID")?;
+    let endpoint = endpoint();
+    let mut key = lookup_cache::acoustid_key(fingerprint, duration);
+    if !endpoint.is_default() {
+        // Another server's database may answer differently
+        key = format!("{}@{}", key, endpoint.lookup_url);
+    }
+    let body = lookup_cache::cached(cache, key, || {
+        // Cached responses still replay while suspended
+        if let Some(reason) = suspended() {
+            SKIPPED.fetch_add(1, Ordering::Relaxed);
+            return Err(Unavailable(format!("Online lookups suspended: {}", reason)).into());
+        }
+
+        let duration = duration.round().to_string();
+        let params = [
+            ("client", client_id),
+            ("meta", "recordings+compress"), // requesting recordings
+            ("duration", duration.as_str()),
+            ("fingerprint", fingerprint),
+        ];
+        let (status, body) = send(&params).map_err(|e| Unavailable(format!("{:#}", e)))?;
 
-    if !resp.status().is_success() {
+        let parsed: Option<AcoustIdResponse> = serde_json::from_str(&body).ok();
+        if let Some(reason) = fatal_reason(status, parsed.as_ref().and_then(|p| p.error.as_ref())) {
+            suspend(reason.clone());
+            return Err(Unavailable(format!("AcoustID {}", reason)).into());
+        }
+        if status.is_server_error() {
+            return Err(Unavailable(format!("AcoustID API returned error: {}", status)).into());
+        }
+        if !status.is_success() {
+            return Err(anyhow::anyhow!("AcoustID API returned error: {}", status));
+        }
+
+        Ok(body)
+    })?;
+
+    let parsed: AcoustIdResponse =
+        serde_json::from_str(&body).context("Failed to parse AcoustID response")?;
+    if parsed.status != "ok" {
         return Err(anyhow::anyhow!(
-            "AcoustID API returned error: {}",
-            resp.status()
+            "AcoustID lookup failed: {}",
+            parsed
+                .error
+                .as_ref()
+                .map(|e| e.message.as_str())
+                .unwrap_or(&parsed.status)
         ));
     }
-
-    let parsed: AcoustIdResponse = resp.json().context("Failed to parse AcoustID response")?;
     Ok(parsed)
 }
@@ -3,34 +3,151 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
 
 #[derive(Serialize, Deserialize, Default, Debug)]
 pub struct AnalysisStore {
     // Map absolute path -> analysis data
     pub data: HashMap<PathBuf, Vec<f32>>,
+    /// On-disk encoding, kept from the loaded file so rewrites don't undo
+    /// `index encode-vectors`
+    #[serde(skip)]
+    pub encoding: VectorEncoding,
+}
+
+/// How vectors are written to `analysis.bin`. In memory they are always `f32`.
+#[derive(clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorEncoding {
+    /// Full precision (the original layout)
+    #[default]
+    F32,
+    /// Half precision, half the size
+    F16,
+    /// 8-bit codes with a per-vector offset and scale, a quarter of the size
+    I8,
+}
+
+#[derive(Serialize, Deserialize)]
+enum EncodedVector {
+    F16(Vec<u16>),
+    I8 {
+        min: f32,
+        scale: f32,
+        codes: Vec<u8>,
+    },
+}
+
+/// Layout of quantized stores, written after `QUANTIZED_MAGIC`. Files
+/// without the magic are plain `f32` stores.
+#[derive(Serialize, Deserialize)]
+struct QuantizedStore {
+    encoding: VectorEncoding,
+    data: HashMap<PathBuf, EncodedVector>,
+}
+
+const QUANTIZED_MAGIC: &[u8; 4] = b"ASQ1";
+
+fn encode_f16(vector: &[f32]) -> EncodedVector {
+    EncodedVector::F16(
+        vector
+            .iter()
+            .map(|&x| half::f16::from_f32(x).to_bits())
+            .collect(),
+    )
+}
+
+/// Affine 8-bit quantization over the vector's own range, so the error per
+/// component is at most half a step of (max - min) / 255.
+fn encode_i8(vector: &[f32]) -> EncodedVector {
+    let min = vector.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = vector.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    if vector.is_empty() || max <= min {
+        // Empty or constant vector
+        return EncodedVector::I8 {
+            min: if min.is_finite() { min } else { 0.0 },
+            scale: 0.0,
+            codes: vec![0; vector.len()],
+        };
+    }
+    let scale = (max - min) / 255.0;
+    let codes = vector
+        .iter()
+        .map(|&x| ((x - min) / scale).round().clamp(0.0, 255.0) as u8)
+        .collect();
+    EncodedVector::I8 { min, scale, codes }
+}
+
+fn decode(vector: EncodedVector) -> Vec<f32> {
+    match vector {
+        EncodedVector::F16(bits) => bits
+            .into_iter()
+            .map(|b| half::f16::from_bits(b).to_f32())
+            .collect(),
+        EncodedVector::I8 { min, scale, codes } => {
+            codes.into_iter().map(|c| min + c as f32 * scale).collect()
+        }
+    }
 }
 
 impl AnalysisStore {
-    /// Load from a binary file. Returns empty store if file doesn't exist.
+    /// Load from a binary file, dequantizing if needed. Returns empty store if
+    /// file doesn't exist.
     pub fn load(path: &Path) -> Result<Self> {
         if !path.exists() {
             return Ok(Self::default());
         }
         let bytes = fs::read(path).context("Failed to read analysis store file")?;
+        if let Some(body) = bytes.strip_prefix(QUANTIZED_MAGIC) {
+            let stored: QuantizedStore =
+                bincode::deserialize(body).context("Failed to deserialize analysis store")?;
+            return Ok(Self {
+                data: stored
+                    .data
+                    .into_iter()
+                    .map(|(path, vector)| (path, decode(vector)))
+                    .collect(),
+                encoding: stored.encoding,
+            });
+        }
         let store = bincode::deserialize(&bytes).context("Failed to deserialize analysis store")?;
         Ok(store)
     }
 
-    /// Save to a binary file.
+    /// Save to a binary file in `self.encoding`.
     pub fn save(&self, path: &Path) -> Result<()> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).context("Failed to create analysis store directory")?;
         }
-        let bytes = bincode::serialize(self).context("Failed to serialize analysis store")?;
-        fs::write(path, bytes).context("Failed to write analysis store file")?;
+        let bytes = match self.encoding {
+            VectorEncoding::F32 => {
+                bincode::serialize(self).context("Failed to serialize analysis store")?
+            }
+            VectorEncoding::F16 => self.quantized_bytes(encode_f16)?,
+            VectorEncoding::I8 => self.quantized_bytes(encode_i8)?,
+        };
+        // Write then rename, so the server never reloads a half-written store
+        let tmp = path.with_extension("bin.tmp");
+        fs::write(&tmp, bytes).context("Failed to write analysis store file")?;
+        fs::rename(&tmp, path).context("Failed to replace analysis store file")?;
         Ok(())
     }
 
+    fn quantized_bytes(&self, encode: fn(&[f32]) -> EncodedVector) -> Result<Vec<u8>> {
+        let stored = QuantizedStore {
+            encoding: self.encoding,
+            data: self
+                .data
+                .iter()
+                .map(|(path, vector)| (path.clone(), encode(vector)))
+                .collect(),
+        };
+        let mut bytes = QUANTIZED_MAGIC.to_vec();
+        bytes.extend(bincode::serialize(&stored).context("Failed to serialize analysis store")?);
+        Ok(bytes)
+    }
+
     /// Insert or update a vector for a file path.
     pub fn insert(&mut self, path: PathBuf, analysis: Vec<f32>) {
         self.data.insert(path, analysis);
@@ -46,3 +163,96 @@ impl AnalysisStore {
         self.data.remove(path);
     }
 }
+
+fn store_mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Analysis store kept resident by the web server, so recommendations and
+/// track details don't deserialize `analysis.bin` per request. Reloaded only
+/// when the file changes on disk (after a scan or reanalyze).
+pub struct SharedAnalysis {
+    path: PathBuf,
+    inner: RwLock<(Option<SystemTime>, Arc<AnalysisStore>)>,
+}
+
+impl SharedAnalysis {
+    pub fn new(path: PathBuf) -> Self {
+        let mtime = store_mtime(&path);
+        let store = AnalysisStore::load(&path).unwrap_or_else(|e| {
+            eprintln!("Could not load analysis store: {}. Starting empty.", e);
+            AnalysisStore::default()
+        });
+        Self {
+            path,
+            inner: RwLock::new((mtime, Arc::new(store))),
+        }
+    }
+
+    /// Current store, reloading first if the file was modified.
+    pub fn get(&self) -> Arc<AnalysisStore> {
+        self.get_with_modified().1
+    }
+
+    /// Current store and the modification time it was loaded at, for HTTP
+    /// cache validation.
+    pub fn get_with_modified(&self) -> (Option<SystemTime>, Arc<AnalysisStore>) {
+        let mtime = store_mtime(&self.path);
+        {
+            let inner = self.inner.read().unwrap();
+            if inner.0 == mtime {
+                return inner.clone();
+            }
+        }
+
+        let mut inner = self.inner.write().unwrap();
+        // Another request may have reloaded while we waited for the lock
+        if inner.0 != mtime {
+            match AnalysisStore::load(&self.path) {
+                Ok(store) => *inner = (mtime, Arc::new(store)),
+                Err(e) => eprintln!("Could not reload analysis store: {}", e),
+            }
+        }
+        inner.clone()
+    }
+
+    /// Force the next `get` to re-read the store from disk.
+    pub fn invalidate(&self) {
+        self.inner.write().unwrap().0 = None;
+    }
+}
+
+/// Human-readable names for the bliss feature vector, in `Analysis::as_vec` order.
+/// Chroma features fill the remainder; their count depends on the bliss feature version.
+pub fn feature_names(len: usize) -> Vec<String> {
+    const NAMED: [&str; 10] = [
+        "Tempo",
+        "Zero-crossing rate",
+        "Spectral centroid (mean)",
+        "Spectral centroid (std)",
+        "Spectral rolloff (mean)",
+        "Spectral rolloff (std)",
+        "Spectral flatness (mean)",
+        "Spectral flatness (std)",
+        "Loudness (mean)",
+        "Loudness (std)",
+    ];
+    (0..len)
+        .map(|i| match NAMED.get(i) {
+            Some(name) => name.to_string(),
+            None => format!("Chroma {}", i - NAMED.len() + 1),
+        })
+        .collect()
+}
+
+/// Distance between two analysis vectors. Returns NaN when dimensions differ.
+pub fn euclidean_distance(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return f32::NAN;
+    }
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
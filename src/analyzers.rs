@@ -0,0 +1,157 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+use utoipa::ToSchema;
+
+use crate::decode::DecodedAudio;
+
+/// Output of the registered analyzers for one track, keyed
+/// `<analyzer>.<name>` (e.g. `silence.start_secs`).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, ToSchema)]
+#[serde(default)]
+pub struct Features {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, f64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub vectors: BTreeMap<String, Vec<f32>>,
+}
+
+impl Features {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.vectors.is_empty()
+    }
+
+    /// Add `other` with its keys prefixed by `analyzer`.
+    fn merge(&mut self, analyzer: &str, other: Features) {
+        for (name, value) in other.fields {
+            self.fields.insert(format!("{}.{}", analyzer, name), value);
+        }
+        for (name, vector) in other.vectors {
+            self.vectors
+                .insert(format!("{}.{}", analyzer, name), vector);
+        }
+    }
+}
+
+/// A feature extractor run by scans and `reanalyze --scope features` on the
+/// decoded audio of every file. Implementations are registered once at
+/// startup with `register` and enabled per library by name in `scan.json`
+/// (`"analyzers": ["silence"]`); all enabled analyzers share one decode.
+pub trait Analyzer: Send + Sync {
+    /// Unique name, used in `scan.json` and as the prefix of stored keys
+    fn name(&self) -> &'static str;
+
+    /// Named scalar fields and vectors for one file. A failure leaves this
+    /// analyzer's keys out for the file; other analyzers still run.
+    fn analyze(&self, audio: &DecodedAudio) -> Result<Features>;
+}
+
+struct Registry {
+    available: RwLock<Vec<Arc<dyn Analyzer>>>,
+    /// Enabled by `configure`, in the order named
+    active: RwLock<Vec<Arc<dyn Analyzer>>>,
+}
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| Registry {
+        available: RwLock::new(Vec::new()),
+        active: RwLock::new(Vec::new()),
+    })
+}
+
+/// Make `analyzer` available to `configure`, replacing one with the same name.
+pub fn register(analyzer: Arc<dyn Analyzer>) {
+    let mut available = registry().available.write().unwrap();
+    available.retain(|a| a.name() != analyzer.name());
+    available.push(analyzer);
+}
+
+/// Register the analyzers that ship with audio_sorter.
+pub fn register_builtins() {
+    register(Arc::new(Silence));
+}
+
+/// The registered analyzers called `names`; fails on an unknown name.
+pub fn resolve(names: &[String]) -> Result<Vec<Arc<dyn Analyzer>>> {
+    let available = registry().available.read().unwrap();
+    names
+        .iter()
+        .map(|name| {
+            available
+                .iter()
+                .find(|a| a.name() == name)
+                .cloned()
+                .ok_or_else(|| {
+                    let known: Vec<_> = available.iter().map(|a| a.name()).collect();
+                    anyhow::anyhow!(
+                        "Unknown analyzer '{}' (available: {})",
+                        name,
+                        known.join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Enable the analyzers called `names` for later `analyze_file` calls.
+pub fn configure(names: &[String]) -> Result<()> {
+    let analyzers = resolve(names)?;
+    *registry().active.write().unwrap() = analyzers;
+    Ok(())
+}
+
+pub fn any_active() -> bool {
+    !registry().active.read().unwrap().is_empty()
+}
+
+/// Decode `path` once and run every enabled analyzer on it.
+pub fn analyze_file(path: &Path) -> Result<Features> {
+    if !any_active() {
+        return Ok(Features::default());
+    }
+    Ok(analyze(&DecodedAudio::load(path)?))
+}
+
+/// Run every enabled analyzer on already decoded audio.
+pub fn analyze(audio: &DecodedAudio) -> Features {
+    let active = registry().active.read().unwrap().clone();
+    let mut features = Features::default();
+    for analyzer in active {
+        if let Ok(output) = analyzer.analyze(audio) {
+            features.merge(analyzer.name(), output);
+        }
+    }
+    features
+}
+
+/// Level below which a frame counts as silent (-60 dBFS)
+const SILENCE_THRESHOLD: f32 = 0.001;
+
+/// Leading and trailing silence, for trimming gaps between tracks.
+struct Silence;
+
+impl Analyzer for Silence {
+    fn name(&self) -> &'static str {
+        "silence"
+    }
+
+    fn analyze(&self, audio: &DecodedAudio) -> Result<Features> {
+        let loud = |frame: &[f32]| frame.iter().any(|s| s.abs() > SILENCE_THRESHOLD);
+        let frames = audio.frames();
+        let first = audio.frame_iter().position(loud).unwrap_or(frames);
+        let last = audio.frame_iter().rposition(loud).map_or(frames, |i| i + 1);
+        let rate = audio.info.sample_rate as f64;
+        let mut features = Features::default();
+        features
+            .fields
+            .insert("start_secs".to_string(), first as f64 / rate);
+        features.fields.insert(
+            "end_secs".to_string(),
+            frames.saturating_sub(last.max(first)) as f64 / rate,
+        );
+        Ok(features)
+    }
+}
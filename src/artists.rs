@@ -0,0 +1,159 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+use crate::analysis_store::{euclidean_distance, AnalysisStore};
+use crate::scanner;
+use crate::spoken_word::ContentKind;
+use crate::storage::AudioLibrary;
+
+/// Stable ID for an artist name, case- and whitespace-insensitive.
+pub fn artist_id(name: &str) -> String {
+    let key = name.trim().to_lowercase();
+    format!("{:016x}", scanner::stable_path_hash(Path::new(&key)))
+}
+
+/// An artist's tracks summarized by the mean of their bliss vectors.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct ArtistProfile {
+    pub id: String,
+    /// Most common spelling among the artist's tracks
+    pub name: String,
+    pub track_count: usize,
+    #[serde(skip)]
+    pub centroid: Vec<f32>,
+    #[serde(skip)]
+    pub tracks: Vec<PathBuf>,
+}
+
+/// Profiles of every artist with analyzed music tracks. Vectors of a
+/// different length than the library's usual one (older bliss versions)
+/// are left out of the means.
+pub fn profiles(library: &AudioLibrary, store: &AnalysisStore) -> Vec<ArtistProfile> {
+    let mut lengths: HashMap<usize, usize> = HashMap::new();
+    for v in store.data.values() {
+        *lengths.entry(v.len()).or_default() += 1;
+    }
+    let Some(dim) = lengths
+        .into_iter()
+        .max_by_key(|&(len, n)| (n, len))
+        .map(|(len, _)| len)
+    else {
+        return Vec::new();
+    };
+
+    struct Acc {
+        names: HashMap<String, usize>,
+        sum: Vec<f32>,
+        tracks: Vec<PathBuf>,
+    }
+    let mut by_artist: HashMap<String, Acc> = HashMap::new();
+    for track in library.files.values() {
+        let name = track.metadata.artist.trim();
+        if name.is_empty() || track.metadata.content_kind == ContentKind::SpokenWord {
+            continue;
+        }
+        let Some(vector) = store.get(&track.path).filter(|v| v.len() == dim) else {
+            continue;
+        };
+        let acc = by_artist.entry(artist_id(name)).or_insert_with(|| Acc {
+            names: HashMap::new(),
+            sum: vec![0.0; dim],
+            tracks: Vec::new(),
+        });
+        *acc.names.entry(name.to_string()).or_default() += 1;
+        for (s, x) in acc.sum.iter_mut().zip(vector) {
+            *s += x;
+        }
+        acc.tracks.push(track.path.clone());
+    }
+
+    let mut profiles: Vec<ArtistProfile> = by_artist
+        .into_iter()
+        .map(|(id, acc)| {
+            let n = acc.tracks.len() as f32;
+            let name = acc
+                .names
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+                .map(|(name, _)| name)
+                .unwrap_or_default();
+            let mut tracks = acc.tracks;
+            tracks.sort();
+            ArtistProfile {
+                id,
+                name,
+                track_count: tracks.len(),
+                centroid: acc.sum.into_iter().map(|s| s / n).collect(),
+                tracks,
+            }
+        })
+        .collect();
+    profiles.sort_by_key(|p| p.name.to_lowercase());
+    profiles
+}
+
+/// Artists closest to `profiles[seed]` by centroid distance, closest first.
+pub fn similar(profiles: &[ArtistProfile], seed: usize, limit: usize) -> Vec<(usize, f32)> {
+    let target = &profiles[seed].centroid;
+    let mut distances: Vec<(usize, f32)> = profiles
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != seed)
+        .map(|(i, p)| (i, euclidean_distance(target, &p.centroid)))
+        .filter(|(_, d)| !d.is_nan())
+        .collect();
+    distances.sort_by(|a, b| a.1.total_cmp(&b.1));
+    distances.truncate(limit);
+    distances
+}
+
+/// Artist radio: one track from the seed artist and each similar artist in
+/// turn, cycling through them again until `count` tracks are queued. Each
+/// artist contributes its tracks closest to the seed artist's sound first.
+pub fn radio(
+    profiles: &[ArtistProfile],
+    seed: usize,
+    store: &AnalysisStore,
+    count: usize,
+    artists: usize,
+    skip: &HashSet<&Path>,
+) -> Vec<(usize, PathBuf)> {
+    let target = &profiles[seed].centroid;
+    let order: Vec<usize> = std::iter::once(seed)
+        .chain(similar(profiles, seed, artists).into_iter().map(|(i, _)| i))
+        .collect();
+
+    // Per artist, candidate tracks ordered by closeness to the seed centroid
+    let mut pools: Vec<std::vec::IntoIter<(PathBuf, f32)>> = order
+        .iter()
+        .map(|&i| {
+            let mut tracks: Vec<(PathBuf, f32)> = profiles[i]
+                .tracks
+                .iter()
+                .filter(|p| !skip.contains(p.as_path()))
+                .filter_map(|p| Some((p.clone(), euclidean_distance(target, store.get(p)?))))
+                .collect();
+            tracks.sort_by(|a, b| a.1.total_cmp(&b.1));
+            tracks.into_iter()
+        })
+        .collect();
+
+    let mut queue = Vec::new();
+    while queue.len() < count {
+        let before = queue.len();
+        for (slot, pool) in pools.iter_mut().enumerate() {
+            if queue.len() >= count {
+                break;
+            }
+            if let Some((path, _)) = pool.next() {
+                queue.push((order[slot], path));
+            }
+        }
+        if queue.len() == before {
+            break;
+        }
+    }
+    queue
+}
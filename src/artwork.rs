@@ -0,0 +1,187 @@
+use anyhow::{Context, Result};
+use lofty::{Picture, PictureType, Tag, TagExt, TaggedFileExt};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::storage::AudioLibrary;
+
+/// File names treated as folder art, in order of preference (matched case-insensitively).
+const FOLDER_ART_NAMES: &[&str] = &[
+    "cover.jpg",
+    "cover.jpeg",
+    "cover.png",
+    "folder.jpg",
+    "folder.png",
+    "front.jpg",
+    "front.png",
+];
+
+/// How to reconcile a folder where only some tracks carry embedded art.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtworkMode {
+    /// Only report
+    Report,
+    /// Embed the folder image into tracks that have no embedded art
+    Embed,
+    /// Write the first embedded image to `cover.jpg`/`cover.png` where the folder has none
+    Extract,
+}
+
+#[derive(Debug)]
+pub struct FolderArt {
+    pub folder: PathBuf,
+    pub folder_image: Option<PathBuf>,
+    pub with_embedded: Vec<PathBuf>,
+    pub without_embedded: Vec<PathBuf>,
+}
+
+impl FolderArt {
+    /// Some tracks embed art and others rely on the folder image (or have none).
+    pub fn is_inconsistent(&self) -> bool {
+        !self.with_embedded.is_empty() && !self.without_embedded.is_empty()
+    }
+}
+
+pub fn find_folder_image(dir: &Path) -> Option<PathBuf> {
+    let entries: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .collect();
+    FOLDER_ART_NAMES.iter().find_map(|name| {
+        entries
+            .iter()
+            .find(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.eq_ignore_ascii_case(name))
+            })
+            .cloned()
+    })
+}
+
+fn embedded_pictures(path: &Path) -> Result<Vec<Picture>> {
+    let tagged_file = lofty::Probe::open(path)
+        .context("Failed to open file for probing")?
+        .guess_file_type()
+        .context("Failed to detect file type")?
+        .read()
+        .context("Failed to read file tags")?;
+    Ok(tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())
+        .map(|t| t.pictures().to_vec())
+        .unwrap_or_default())
+}
+
+/// The front cover embedded in `path`, or else its first picture.
+pub fn front_cover(path: &Path) -> Result<Option<Picture>> {
+    let mut pictures = embedded_pictures(path)?;
+    let front = pictures
+        .iter()
+        .position(|p| p.pic_type() == PictureType::CoverFront)
+        .unwrap_or(0);
+    Ok((!pictures.is_empty()).then(|| pictures.swap_remove(front)))
+}
+
+/// Inspect every indexed folder's art. Unreadable files count as having none.
+pub fn survey(library: &AudioLibrary) -> Vec<FolderArt> {
+    let mut folders: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for track in library.sorted_tracks() {
+        if let Some(parent) = track.path.parent() {
+            folders
+                .entry(parent.to_path_buf())
+                .or_default()
+                .push(track.path.clone());
+        }
+    }
+
+    folders
+        .into_iter()
+        .map(|(folder, tracks)| {
+            let (with_embedded, without_embedded) = tracks
+                .into_iter()
+                .partition(|p| embedded_pictures(p).is_ok_and(|pics| !pics.is_empty()));
+            FolderArt {
+                folder_image: find_folder_image(&folder),
+                folder,
+                with_embedded,
+                without_embedded,
+            }
+        })
+        .collect()
+}
+
+fn embed_image(track: &Path, image: &Path) -> Result<()> {
+    let mut reader = fs::File::open(image).context("Failed to open folder image")?;
+    let mut picture = Picture::from_reader(&mut reader).context("Unsupported image format")?;
+    picture.set_pic_type(PictureType::CoverFront);
+
+    let mut tagged_file = lofty::Probe::open(track)
+        .context("Failed to open file for probing")?
+        .guess_file_type()
+        .context("Failed to detect file type")?
+        .read()
+        .context("Failed to read file tags")?;
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file.primary_tag_mut().unwrap();
+    tag.push_picture(picture);
+    tag.save_to_path(track)
+        .context("Failed to write file tags")?;
+    Ok(())
+}
+
+/// `cover.png` for PNG data, `cover.jpg` otherwise.
+pub fn cover_file_name(data: &[u8]) -> &'static str {
+    if data.starts_with(b"\x89PNG") {
+        "cover.png"
+    } else {
+        "cover.jpg"
+    }
+}
+
+/// `Embed` fills every track without embedded art in folders that have an image;
+/// `Extract` writes a folder image where there is none. Returns the number of files written.
+pub fn reconcile(folders: &[FolderArt], mode: ArtworkMode, dry_run: bool) -> Result<usize> {
+    let mut written = 0;
+    for folder in folders {
+        match mode {
+            ArtworkMode::Report => {}
+            ArtworkMode::Embed => {
+                let Some(image) = &folder.folder_image else {
+                    continue;
+                };
+                for track in &folder.without_embedded {
+                    println!("[embed] {:?} <- {:?}", track, image);
+                    if !dry_run {
+                        embed_image(track, image)
+                            .with_context(|| format!("Failed to embed art into {:?}", track))?;
+                    }
+                    written += 1;
+                }
+            }
+            ArtworkMode::Extract => {
+                if folder.folder_image.is_some() {
+                    continue;
+                }
+                let Some(source) = folder.with_embedded.first() else {
+                    continue;
+                };
+                let Some(picture) = front_cover(source)? else {
+                    continue;
+                };
+                let target = folder.folder.join(cover_file_name(picture.data()));
+                println!("[extract] {:?} -> {:?}", source, target);
+                if !dry_run {
+                    fs::write(&target, picture.data())
+                        .with_context(|| format!("Failed to write {:?}", target))?;
+                }
+                written += 1;
+            }
+        }
+    }
+    Ok(written)
+}
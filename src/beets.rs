@@ -0,0 +1,212 @@
+use anyhow::{Context, Result};
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags, Row};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::organizer::{MetadataSource, TrackMetadata};
+use crate::playlist_import::{self, PathMap};
+use crate::storage::{self, AudioLibrary};
+
+/// One row of the `items` table of a beets `library.db`, path remapped.
+#[derive(Debug)]
+pub struct BeetsItem {
+    pub path: PathBuf,
+    pub metadata: TrackMetadata,
+    /// When beets imported the file (UNIX seconds), 0 if unknown
+    pub added: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct BeetsImport {
+    pub added: usize,
+    pub updated: usize,
+    /// Items whose (remapped) file does not exist on this machine
+    pub missing: Vec<PathBuf>,
+}
+
+/// Columns of a result row by name. Columns differ between beets versions
+/// (and plugins add their own), so every field is optional.
+struct Columns(HashMap<String, usize>);
+
+impl Columns {
+    fn value<'r>(&self, row: &'r Row, name: &str) -> Option<ValueRef<'r>> {
+        row.get_ref(*self.0.get(name)?).ok()
+    }
+
+    /// Text or blob column as a string; beets writes "" for unset fields.
+    fn text(&self, row: &Row, name: &str) -> Option<String> {
+        let s = match self.value(row, name)? {
+            ValueRef::Text(b) | ValueRef::Blob(b) => String::from_utf8_lossy(b).trim().to_string(),
+            _ => return None,
+        };
+        (!s.is_empty()).then_some(s)
+    }
+
+    /// Integer column; beets writes 0 for unset numbers.
+    fn number(&self, row: &Row, name: &str) -> Option<u32> {
+        match self.value(row, name)? {
+            ValueRef::Integer(n) => u32::try_from(n).ok().filter(|n| *n > 0),
+            _ => None,
+        }
+    }
+
+    fn real(&self, row: &Row, name: &str) -> Option<f64> {
+        let value = match self.value(row, name)? {
+            ValueRef::Real(r) => r,
+            ValueRef::Integer(n) => n as f64,
+            _ => return None,
+        };
+        (value > 0.0).then_some(value)
+    }
+
+    /// `path` is stored as the raw bytes of the file name.
+    fn path(&self, row: &Row) -> Option<String> {
+        match self.value(row, "path")? {
+            ValueRef::Text(b) | ValueRef::Blob(b) => Some(String::from_utf8_lossy(b).into_owned()),
+            _ => None,
+        }
+    }
+}
+
+fn item_metadata(columns: &Columns, row: &Row) -> TrackMetadata {
+    let recording_mbid = columns.text(row, "mb_trackid");
+    let mut meta = TrackMetadata {
+        title: columns.text(row, "title").unwrap_or_default(),
+        artist: columns.text(row, "artist").unwrap_or_default(),
+        album: columns.text(row, "album"),
+        album_artist: columns.text(row, "albumartist"),
+        genre: columns.text(row, "genre"),
+        year: columns.number(row, "year"),
+        disc_number: columns.number(row, "disc"),
+        track_number: columns.number(row, "track"),
+        original_year: columns.number(row, "original_year"),
+        duration: columns.real(row, "length").unwrap_or(0.0),
+        fingerprint: columns.text(row, "acoustid_fingerprint"),
+        work_mbid: columns.text(row, "mb_workid"),
+        // Tagged from a MusicBrainz match
+        source: if recording_mbid.is_some() {
+            MetadataSource::Online
+        } else {
+            MetadataSource::Tags
+        },
+        recording_mbid,
+        ..Default::default()
+    };
+    meta.normalize_unicode();
+    meta
+}
+
+/// Read every item of a beets library, with `maps` applied to its paths.
+pub fn read(db: &Path, maps: &[PathMap]) -> Result<Vec<BeetsItem>> {
+    let conn = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open beets library {:?}", db))?;
+    let mut stmt = conn
+        .prepare("SELECT * FROM items")
+        .context("Not a beets library (no items table)")?;
+    let columns = Columns(
+        stmt.column_names()
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| (name.to_string(), i))
+            .collect(),
+    );
+    let mut rows = stmt.query([])?;
+    let mut items = Vec::new();
+    while let Some(row) = rows.next()? {
+        let Some(path) = columns.path(row) else {
+            continue;
+        };
+        items.push(BeetsItem {
+            path: PathBuf::from(playlist_import::remap(&path, maps)),
+            metadata: item_metadata(&columns, row),
+            added: columns.real(row, "added").unwrap_or(0.0) as u64,
+        });
+    }
+    Ok(items)
+}
+
+fn set<T>(field: &mut Option<T>, value: Option<T>, fill_missing_only: bool) {
+    if value.is_some() && (field.is_none() || !fill_missing_only) {
+        *field = value;
+    }
+}
+
+fn set_text(field: &mut String, value: String, fill_missing_only: bool) {
+    if !value.is_empty() && (field.is_empty() || !fill_missing_only) {
+        *field = value;
+    }
+}
+
+/// Apply beets' tags on top of an indexed track's metadata, keeping what
+/// was measured locally (quality, analysis results, content kind).
+fn apply(meta: &mut TrackMetadata, beets: TrackMetadata, fill_missing_only: bool) {
+    let f = fill_missing_only;
+    set_text(&mut meta.title, beets.title, f);
+    set_text(&mut meta.artist, beets.artist, f);
+    set(&mut meta.album, beets.album, f);
+    set(&mut meta.album_artist, beets.album_artist, f);
+    set(&mut meta.genre, beets.genre, f);
+    set(&mut meta.year, beets.year, f);
+    set(&mut meta.disc_number, beets.disc_number, f);
+    set(&mut meta.track_number, beets.track_number, f);
+    set(&mut meta.original_year, beets.original_year, f);
+    set(&mut meta.recording_mbid, beets.recording_mbid, f);
+    set(&mut meta.work_mbid, beets.work_mbid, f);
+    // Measured values only fill gaps
+    set(&mut meta.fingerprint, beets.fingerprint, true);
+    if meta.duration <= 0.0 {
+        meta.duration = beets.duration;
+    }
+    if !f && meta.recording_mbid.is_some() {
+        meta.source = MetadataSource::Online;
+    }
+}
+
+/// Merge beets items into the index: indexed files get beets' tags (or only
+/// the ones they lack), other files on disk are added with beets' metadata.
+pub fn merge(
+    library: &mut AudioLibrary,
+    items: Vec<BeetsItem>,
+    fill_missing_only: bool,
+    now: u64,
+) -> BeetsImport {
+    let by_key: HashMap<PathBuf, PathBuf> = library
+        .files
+        .keys()
+        .map(|p| (storage::path_key(p), p.clone()))
+        .collect();
+    let mut result = BeetsImport::default();
+    for item in items {
+        let indexed = if library.files.contains_key(&item.path) {
+            Some(item.path.clone())
+        } else {
+            by_key.get(&storage::path_key(&item.path)).cloned()
+        };
+        if let Some(track) = indexed.and_then(|p| library.files.get_mut(&p)) {
+            apply(&mut track.metadata, item.metadata, fill_missing_only);
+            result.updated += 1;
+            continue;
+        }
+
+        let Ok(fs_meta) = std::fs::metadata(&item.path) else {
+            result.missing.push(item.path);
+            continue;
+        };
+        let mtime = fs_meta
+            .modified()
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        library.upsert(item.path.clone(), fs_meta.len(), mtime, now, item.metadata);
+        if item.added > 0 {
+            if let Some(track) = library.files.get_mut(&item.path) {
+                track.added_at = item.added;
+            }
+        }
+        result.added += 1;
+    }
+    result
+}
@@ -0,0 +1,427 @@
+use anyhow::{Context, Result};
+use lofty::{Accessor, ItemKey, Tag, TagExt, TaggedFileExt};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+use crate::organizer::TrackMetadata;
+use crate::storage::AudioLibrary;
+
+/// Undo batches kept in `edit_history.json`.
+const MAX_HISTORY: usize = 20;
+
+/// Fields to overwrite. Unset fields are left alone; an empty string (or year 0)
+/// removes the value.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, ToSchema)]
+#[serde(default)]
+pub struct FieldChanges {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u32>,
+}
+
+impl FieldChanges {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Current values of the fields this change touches, in the same encoding.
+    fn snapshot(&self, meta: &TrackMetadata) -> Self {
+        let opt = |v: &Option<String>| v.clone().unwrap_or_default();
+        Self {
+            title: self.title.as_ref().map(|_| meta.title.clone()),
+            artist: self.artist.as_ref().map(|_| meta.artist.clone()),
+            album: self.album.as_ref().map(|_| opt(&meta.album)),
+            album_artist: self.album_artist.as_ref().map(|_| opt(&meta.album_artist)),
+            genre: self.genre.as_ref().map(|_| opt(&meta.genre)),
+            year: self.year.map(|_| meta.year.unwrap_or(0)),
+        }
+    }
+
+    fn apply_to(&self, meta: &mut TrackMetadata) {
+        let opt = |v: &str| Some(v.trim().to_string()).filter(|s| !s.is_empty());
+        if let Some(title) = &self.title {
+            meta.title = title.trim().to_string();
+        }
+        if let Some(artist) = &self.artist {
+            meta.artist = artist.trim().to_string();
+        }
+        if let Some(album) = &self.album {
+            meta.album = opt(album);
+        }
+        if let Some(album_artist) = &self.album_artist {
+            meta.album_artist = opt(album_artist);
+        }
+        if let Some(genre) = &self.genre {
+            meta.genre = opt(genre);
+        }
+        if let Some(year) = self.year {
+            meta.year = Some(year).filter(|y| *y > 0);
+        }
+        meta.normalize_unicode();
+    }
+
+    /// `(field, old, new)` for every touched field whose value actually changes.
+    fn diff(&self, meta: &TrackMetadata) -> Vec<(&'static str, String, String)> {
+        let mut new_meta = meta.clone();
+        self.apply_to(&mut new_meta);
+        let (old, new) = (self.snapshot(meta), self.snapshot(&new_meta));
+
+        let year = |y: Option<u32>| y.map(|y| if y == 0 { String::new() } else { y.to_string() });
+        [
+            ("title", old.title, new.title),
+            ("artist", old.artist, new.artist),
+            ("album", old.album, new.album),
+            ("album_artist", old.album_artist, new.album_artist),
+            ("genre", old.genre, new.genre),
+            ("year", year(old.year), year(new.year)),
+        ]
+        .into_iter()
+        .filter_map(|(field, old, new)| match (old, new) {
+            // Untouched fields are None on both sides
+            (Some(old), Some(new)) if old != new => Some((field, old, new)),
+            _ => None,
+        })
+        .collect()
+    }
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct BulkEditRequest {
+    /// Track IDs as returned by `/api/tracks`
+    pub ids: Vec<String>,
+    pub set: FieldChanges,
+    /// Only report what would change, without touching files or the index
+    #[serde(default)]
+    pub preview: bool,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct FieldChange {
+    pub id: String,
+    pub path: String,
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Serialize, Debug, Default, ToSchema)]
+pub struct BulkEditResult {
+    /// False for previews and when nothing changed
+    pub applied: bool,
+    pub changes: Vec<FieldChange>,
+    /// Files whose tags could not be written (left unchanged in the index)
+    pub errors: Vec<String>,
+}
+
+/// Previous values of one bulk edit, enough to restore it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EditBatch {
+    applied_at: u64,
+    tracks: Vec<(PathBuf, FieldChanges)>,
+}
+
+fn load_history(path: &Path) -> Result<Vec<EditBatch>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).context("Failed to read edit history")?;
+    serde_json::from_str(&content).context("Failed to parse edit history")
+}
+
+fn save_history(path: &Path, history: &[EditBatch]) -> Result<()> {
+    let content =
+        serde_json::to_string_pretty(history).context("Failed to serialize edit history")?;
+    fs::write(path, content).context("Failed to write edit history")
+}
+
+/// Write the touched fields into the file's primary tag, creating one if needed.
+pub fn write_tags(path: &Path, changes: &FieldChanges) -> Result<()> {
+    let mut tagged_file = lofty::Probe::open(path)
+        .context("Failed to open file for probing")?
+        .guess_file_type()
+        .context("Failed to detect file type")?
+        .read()
+        .context("Failed to read file tags")?;
+
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file.primary_tag_mut().unwrap();
+
+    let text = |v: &String| Some(v.trim().to_string()).filter(|s| !s.is_empty());
+    if let Some(title) = &changes.title {
+        match text(title) {
+            Some(v) => tag.set_title(v),
+            None => tag.remove_title(),
+        }
+    }
+    if let Some(artist) = &changes.artist {
+        match text(artist) {
+            Some(v) => tag.set_artist(v),
+            None => tag.remove_artist(),
+        }
+    }
+    if let Some(album) = &changes.album {
+        match text(album) {
+            Some(v) => tag.set_album(v),
+            None => tag.remove_album(),
+        }
+    }
+    if let Some(album_artist) = &changes.album_artist {
+        match text(album_artist) {
+            Some(v) => {
+                tag.insert_text(ItemKey::AlbumArtist, v);
+            }
+            None => tag.remove_key(&ItemKey::AlbumArtist),
+        }
+    }
+    if let Some(genre) = &changes.genre {
+        match text(genre) {
+            Some(v) => tag.set_genre(v),
+            None => tag.remove_genre(),
+        }
+    }
+    if let Some(year) = changes.year {
+        match year {
+            0 => tag.remove_year(),
+            y => tag.set_year(y),
+        }
+    }
+
+    tag.save_to_path(path)
+        .context("Failed to write file tags")?;
+    Ok(())
+}
+
+/// Write `changes` to each track's file and update the index entry to match.
+/// Returns the fields that changed and the previous values of the tracks that were written.
+fn write_tracks(
+    library: &mut AudioLibrary,
+    edits: Vec<(PathBuf, FieldChanges)>,
+    result: &mut BulkEditResult,
+) -> Vec<(PathBuf, FieldChanges)> {
+    let mut previous = Vec::new();
+    for (path, changes) in edits {
+        let Some(track) = library.files.get_mut(&path) else {
+            continue;
+        };
+        let diff = changes.diff(&track.metadata);
+        if diff.is_empty() {
+            continue;
+        }
+
+        if let Err(e) = write_tags(&path, &changes) {
+            result.errors.push(format!("{}: {:#}", path.display(), e));
+            continue;
+        }
+
+        previous.push((path.clone(), changes.snapshot(&track.metadata)));
+        changes.apply_to(&mut track.metadata);
+        // Keep the next scan from treating our own write as an external change
+        if let Ok(fs_meta) = fs::metadata(&path) {
+            track.file_size = fs_meta.len();
+            if let Some(mtime) = fs_meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            {
+                track.modified_time = mtime.as_secs();
+            }
+        }
+
+        for (field, old, new) in diff {
+            result.changes.push(FieldChange {
+                id: track.id.clone(),
+                path: path.to_string_lossy().into_owned(),
+                field: field.to_string(),
+                old,
+                new,
+            });
+        }
+    }
+    previous
+}
+
+/// What an applied edit or undo changed outside the index, so it can be
+/// taken back when the index that goes with it can't be saved.
+#[derive(Debug, Default)]
+pub struct Revert {
+    history_path: PathBuf,
+    /// Edit history from before the change; `None` when it was not touched
+    history: Option<Vec<EditBatch>>,
+    /// Tag values the written files had before
+    written: Vec<(PathBuf, FieldChanges)>,
+}
+
+impl Revert {
+    /// Write the old tags back and restore the edit history, best effort.
+    pub fn run(self) {
+        for (path, previous) in self.written.iter().rev() {
+            if let Err(e) = write_tags(path, previous) {
+                eprintln!("Could not restore tags of {:?}: {:#}", path, e);
+            }
+        }
+        if let Some(history) = &self.history {
+            if let Err(e) = save_history(&self.history_path, history) {
+                eprintln!("Could not restore edit history: {:#}", e);
+            }
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Report what a bulk edit would change, without touching files or the index.
+/// Unknown IDs are ignored; callers validate them first.
+pub fn preview(library: &AudioLibrary, request: &BulkEditRequest) -> BulkEditResult {
+    let mut result = BulkEditResult::default();
+    for track in request.ids.iter().filter_map(|id| library.find_by_id(id)) {
+        for (field, old, new) in request.set.diff(&track.metadata) {
+            result.changes.push(FieldChange {
+                id: track.id.clone(),
+                path: track.path.to_string_lossy().into_owned(),
+                field: field.to_string(),
+                old,
+                new,
+            });
+        }
+    }
+    result
+}
+
+/// Apply a bulk edit. The previous values are recorded in `history_path` for
+/// `undo` before any file is written. Unknown IDs are ignored; callers
+/// validate them first.
+pub fn apply(
+    library: &mut AudioLibrary,
+    request: &BulkEditRequest,
+    history_path: &Path,
+) -> Result<(BulkEditResult, Revert)> {
+    let edits: Vec<(PathBuf, FieldChanges)> = request
+        .ids
+        .iter()
+        .filter_map(|id| library.find_by_id(id))
+        .filter(|t| !request.set.diff(&t.metadata).is_empty())
+        .map(|t| (t.path.clone(), request.set.clone()))
+        .collect();
+    let mut result = BulkEditResult::default();
+    if edits.is_empty() {
+        return Ok((result, Revert::default()));
+    }
+
+    let original = load_history(history_path)?;
+    let planned: Vec<(PathBuf, FieldChanges)> = edits
+        .iter()
+        .map(|(path, changes)| {
+            (
+                path.clone(),
+                changes.snapshot(&library.files[path].metadata),
+            )
+        })
+        .collect();
+    let mut history = original.clone();
+    history.push(EditBatch {
+        applied_at: unix_now(),
+        tracks: planned,
+    });
+    let excess = history.len().saturating_sub(MAX_HISTORY);
+    history.drain(..excess);
+    save_history(history_path, &history)?;
+
+    let written = write_tracks(library, edits, &mut result);
+    let revert = Revert {
+        history_path: history_path.to_path_buf(),
+        history: Some(original),
+        written: written.clone(),
+    };
+    // Only keep the files that were actually written in the undo entry
+    let batch = history.last_mut().unwrap();
+    if written.len() != batch.tracks.len() {
+        batch.tracks = written;
+        if batch.tracks.is_empty() {
+            history.pop();
+        }
+        if let Err(e) = save_history(history_path, &history) {
+            revert.run();
+            return Err(e);
+        }
+    }
+    result.applied = !revert.written.is_empty();
+    Ok((result, revert))
+}
+
+/// Revert the most recent bulk edit. `applied` is false when there is nothing to undo.
+pub fn undo(library: &mut AudioLibrary, history_path: &Path) -> Result<(BulkEditResult, Revert)> {
+    let original = load_history(history_path)?;
+    let mut history = original.clone();
+    let mut result = BulkEditResult::default();
+    let Some(batch) = history.pop() else {
+        return Ok((result, Revert::default()));
+    };
+
+    let written = write_tracks(library, batch.tracks, &mut result);
+    let revert = Revert {
+        history_path: history_path.to_path_buf(),
+        history: Some(original),
+        written,
+    };
+    if let Err(e) = save_history(history_path, &history) {
+        revert.run();
+        return Err(e);
+    }
+    result.applied = true;
+    Ok((result, revert))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_lists_only_changing_fields() {
+        let mut library = AudioLibrary::default();
+        let meta = TrackMetadata {
+            title: "Song".into(),
+            artist: "Band".into(),
+            ..Default::default()
+        };
+        library.upsert(PathBuf::from("a.mp3"), 1, 1, 1, meta);
+        let id = library.files[Path::new("a.mp3")].id.clone();
+        let request = BulkEditRequest {
+            ids: vec![id, "unknown".into()],
+            set: FieldChanges {
+                artist: Some("Band".into()),
+                year: Some(1999),
+                ..Default::default()
+            },
+            preview: true,
+        };
+
+        let result = preview(&library, &request);
+        assert!(!result.applied);
+        let fields: Vec<(&str, &str, &str)> = result
+            .changes
+            .iter()
+            .map(|c| (c.field.as_str(), c.old.as_str(), c.new.as_str()))
+            .collect();
+        assert_eq!(fields, [("year", "", "1999")]);
+    }
+}
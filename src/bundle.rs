@@ -0,0 +1,198 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::analysis_store::AnalysisStore;
+use crate::storage::{self, AudioLibrary, IndexedTrack};
+
+const BUNDLE_FORMAT: &str = "audio-sorter-bundle";
+const BUNDLE_VERSION: u32 = 1;
+
+/// Optional index-directory files carried verbatim (settings and history).
+const EXTRA_FILES: &[&str] = &[
+    "playlists.json",
+    "cleanup.json",
+    "merge_policy.json",
+    "scan_history.json",
+];
+
+/// A portable, self-describing copy of an index directory. Track paths are
+/// stored relative to `library_root` with `/` separators, so the bundle can be
+/// imported under a different root or on another OS.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Bundle {
+    pub format: String,
+    pub version: u32,
+    /// UNIX timestamp (seconds)
+    pub created_at: u64,
+    /// Common root of every track at export time
+    pub library_root: PathBuf,
+    /// Indexed tracks with `path` and `vocal_pair` relative to the root
+    pub tracks: Vec<IndexedTrack>,
+    /// bliss analysis keyed by relative path
+    pub analysis: HashMap<String, Vec<f32>>,
+    /// File name -> JSON contents of `EXTRA_FILES` present at export
+    pub files: HashMap<String, serde_json::Value>,
+}
+
+/// Deepest directory containing every path.
+fn common_root<'a>(paths: impl Iterator<Item = &'a Path>) -> PathBuf {
+    let mut root: Option<PathBuf> = None;
+    for path in paths {
+        let parent = path.parent().unwrap_or(Path::new(""));
+        root = Some(match root {
+            None => parent.to_path_buf(),
+            Some(r) => r
+                .components()
+                .zip(parent.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    root.unwrap_or_default()
+}
+
+fn to_relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(s) => Some(s.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Inverse of `to_relative`, using the importing platform's separators so the
+/// paths match what the scanner produces there.
+fn from_relative(root: &Path, relative: &str) -> PathBuf {
+    root.join(relative.split('/').collect::<PathBuf>())
+}
+
+/// Bundle the index directory's index, analysis store and settings.
+pub fn export(index_dir: &Path) -> Result<Bundle> {
+    let library = AudioLibrary::load(&index_dir.join("index.json"))?;
+    let analysis = AnalysisStore::load(&index_dir.join("analysis.bin"))?;
+    let root = common_root(library.files.keys().map(PathBuf::as_path));
+
+    let tracks = library
+        .files
+        .into_values()
+        .map(|mut track| {
+            track.path = PathBuf::from(to_relative(&root, &track.path));
+            if let Some(pair) = track.metadata.vocal_pair.take() {
+                track.metadata.vocal_pair = Some(PathBuf::from(to_relative(&root, &pair)));
+            }
+            track
+        })
+        .collect();
+
+    let mut files = HashMap::new();
+    for name in EXTRA_FILES {
+        let path = index_dir.join(name);
+        if path.exists() {
+            let content =
+                fs::read_to_string(&path).with_context(|| format!("Failed to read {}", name))?;
+            let value = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", name))?;
+            files.insert(name.to_string(), value);
+        }
+    }
+
+    Ok(Bundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        analysis: analysis
+            .data
+            .into_iter()
+            .map(|(path, vec)| (to_relative(&root, &path), vec))
+            .collect(),
+        library_root: root,
+        tracks,
+        files,
+    })
+}
+
+pub fn write(bundle: &Bundle, path: &Path) -> Result<()> {
+    let content = serde_json::to_string(bundle).context("Failed to serialize bundle")?;
+    fs::write(path, content).context("Failed to write bundle")?;
+    Ok(())
+}
+
+pub fn read(path: &Path) -> Result<Bundle> {
+    let content = fs::read_to_string(path).context("Failed to read bundle")?;
+    parse(&content)
+}
+
+pub fn parse(content: &str) -> Result<Bundle> {
+    let bundle: Bundle = serde_json::from_str(content).context("Failed to parse bundle")?;
+    if bundle.format != BUNDLE_FORMAT {
+        anyhow::bail!("Not an audio-sorter bundle (format '{}')", bundle.format);
+    }
+    if bundle.version > BUNDLE_VERSION {
+        anyhow::bail!(
+            "Bundle version {} is newer than this build supports ({})",
+            bundle.version,
+            BUNDLE_VERSION
+        );
+    }
+    Ok(bundle)
+}
+
+/// Rebuild the library with absolute paths under `root`, recomputing track IDs.
+fn library_under(tracks: Vec<IndexedTrack>, root: &Path) -> AudioLibrary {
+    let mut library = AudioLibrary::default();
+    for mut track in tracks {
+        track.path = from_relative(root, &track.path.to_string_lossy());
+        track.id = storage::track_id(&track.path);
+        if let Some(pair) = track.metadata.vocal_pair.take() {
+            track.metadata.vocal_pair = Some(from_relative(root, &pair.to_string_lossy()));
+        }
+        library.files.insert(track.path.clone(), track);
+    }
+    library
+}
+
+impl Bundle {
+    /// The bundled library at its original export paths.
+    pub fn into_library(self) -> AudioLibrary {
+        library_under(self.tracks, &self.library_root)
+    }
+}
+
+/// Write the bundle into `index_dir`, placing tracks under `root` (the export
+/// root when `None`). Track IDs are recomputed for the new paths.
+pub fn import(bundle: Bundle, index_dir: &Path, root: Option<&Path>) -> Result<usize> {
+    let root = root.unwrap_or(&bundle.library_root).to_path_buf();
+    fs::create_dir_all(index_dir).context("Failed to create index directory")?;
+
+    let library = library_under(bundle.tracks, &root);
+    let count = library.files.len();
+    library.save(&index_dir.join("index.json"))?;
+
+    let mut analysis = AnalysisStore::default();
+    for (relative, vec) in bundle.analysis {
+        analysis.insert(from_relative(&root, &relative), vec);
+    }
+    analysis.save(&index_dir.join("analysis.bin"))?;
+
+    for (name, value) in bundle.files {
+        if !EXTRA_FILES.contains(&name.as_str()) {
+            continue;
+        }
+        let content = serde_json::to_string_pretty(&value)
+            .with_context(|| format!("Failed to serialize {}", name))?;
+        fs::write(index_dir.join(&name), content)
+            .with_context(|| format!("Failed to write {}", name))?;
+    }
+    Ok(count)
+}
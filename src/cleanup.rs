@@ -0,0 +1,261 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::organizer::TrackMetadata;
+
+/// Metadata fields a cleanup rule can rewrite.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    Title,
+    Artist,
+    Album,
+}
+
+impl Field {
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Title => "title",
+            Field::Artist => "artist",
+            Field::Album => "album",
+        }
+    }
+}
+
+/// One regex find/replace, e.g. strip `(Official Audio)` from titles.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Rule {
+    pub fields: Vec<Field>,
+    /// Regex; `(?i)` makes it case-insensitive
+    pub find: String,
+    /// Replacement, may use `$1`-style capture references
+    #[serde(default)]
+    pub replace: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Casing {
+    #[default]
+    AsIs,
+    /// Capitalize each word except short function words ("of", "the", ...)
+    TitleCase,
+}
+
+/// Normalization applied to one field after the find/replace rules.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct FieldPolicy {
+    pub casing: Casing,
+    /// Collapse runs of whitespace into a single space
+    pub collapse_spaces: bool,
+    /// Rewrite ALL-CAPS values (5+ letters, so "ABBA" or "AC/DC" survive) in title case
+    pub fix_all_caps: bool,
+}
+
+/// Per-field normalization policies.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct NormalizePolicies {
+    pub title: FieldPolicy,
+    pub artist: FieldPolicy,
+    pub album: FieldPolicy,
+}
+
+impl NormalizePolicies {
+    fn for_field(&self, field: Field) -> &FieldPolicy {
+        match field {
+            Field::Title => &self.title,
+            Field::Artist => &self.artist,
+            Field::Album => &self.album,
+        }
+    }
+}
+
+/// Contents of `cleanup.json` in the index directory.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct CleanupConfig {
+    pub rules: Vec<Rule>,
+    pub normalize: NormalizePolicies,
+}
+
+/// Compiled cleanup rules and normalization policies, applied to metadata
+/// (from tags or online lookups) as it enters the index.
+#[derive(Default)]
+pub struct Cleanup {
+    rules: Vec<(Vec<Field>, Regex, String)>,
+    normalize: NormalizePolicies,
+}
+
+impl Cleanup {
+    pub fn new(config: CleanupConfig) -> Result<Self> {
+        let rules = config
+            .rules
+            .into_iter()
+            .map(|r| {
+                let re = Regex::new(&r.find)
+                    .with_context(|| format!("Invalid cleanup rule regex: {}", r.find))?;
+                Ok((r.fields, re, r.replace))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            rules,
+            normalize: config.normalize,
+        })
+    }
+
+    /// Load `cleanup.json`. A missing file means no cleanup.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).context("Failed to read cleanup config")?;
+        let config: CleanupConfig =
+            serde_json::from_str(&content).context("Failed to parse cleanup config JSON")?;
+        Self::new(config)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.normalize == NormalizePolicies::default()
+    }
+
+    fn clean_value(&self, field: Field, value: &str) -> String {
+        let mut value = value.to_string();
+        for (fields, re, replace) in &self.rules {
+            if fields.contains(&field) {
+                value = re.replace_all(&value, replace.as_str()).into_owned();
+            }
+        }
+
+        let policy = self.normalize.for_field(field);
+        if policy.collapse_spaces {
+            value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        if policy.fix_all_caps && is_all_caps(&value) {
+            value = title_case(&value.to_lowercase());
+        }
+        if policy.casing == Casing::TitleCase {
+            value = title_case(&value);
+        }
+        value.trim().to_string()
+    }
+
+    /// Rewrite the metadata in place. Returns `(field, old, new)` for each changed field.
+    /// Text is always brought to Unicode NFC first, which is not reported as a change.
+    pub fn apply(&self, meta: &mut TrackMetadata) -> Vec<(&'static str, String, String)> {
+        meta.normalize_unicode();
+        let mut changes = Vec::new();
+        if self.is_empty() {
+            return changes;
+        }
+
+        let mut update = |field: Field, value: &mut String| {
+            let cleaned = self.clean_value(field, value);
+            if cleaned != *value {
+                changes.push((
+                    field.label(),
+                    std::mem::replace(value, cleaned.clone()),
+                    cleaned,
+                ));
+            }
+        };
+        update(Field::Title, &mut meta.title);
+        update(Field::Artist, &mut meta.artist);
+        if let Some(album) = meta.album.as_mut() {
+            update(Field::Album, album);
+        }
+        if meta.album.as_deref() == Some("") {
+            meta.album = None;
+        }
+        changes
+    }
+}
+
+/// Articles, conjunctions and short prepositions kept lowercase inside a
+/// title-cased value (never as its first or last word).
+const SMALL_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "feat.", "for", "in", "nor", "of", "on", "or",
+    "the", "to", "vs", "vs.",
+];
+
+fn is_all_caps(s: &str) -> bool {
+    let letters: Vec<char> = s.chars().filter(|c| c.is_alphabetic()).collect();
+    letters.len() >= 5
+        && letters.iter().all(|c| !c.is_lowercase())
+        && s.chars().any(|c| c.is_uppercase())
+}
+
+/// Uppercase the first letter of each word, leaving the rest untouched so
+/// names like "McCartney" or "AC/DC" keep their casing. Small words stay
+/// lowercase only between other words, so "Plan A" keeps its "A".
+fn title_case(s: &str) -> String {
+    let words: Vec<&str> = s.split(' ').collect();
+    let last = words.len() - 1;
+    words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            let lower = word.to_lowercase();
+            if i > 0 && i < last && SMALL_WORDS.contains(&lower.as_str()) {
+                return lower;
+            }
+            // Skip leading punctuation such as "(" in "(live)"
+            match word.char_indices().find(|(_, c)| c.is_alphabetic()) {
+                Some((idx, c)) => format!(
+                    "{}{}{}",
+                    &word[..idx],
+                    c.to_uppercase(),
+                    &word[idx + c.len_utf8()..]
+                ),
+                None => word.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_case_keeps_small_words_lowercase_between_words_only() {
+        let cases = [
+            ("the sound of silence", "The Sound of Silence"),
+            ("plan a", "Plan A"),
+            ("a day in the life", "A Day in the Life"),
+            ("what i got", "What I Got"),
+            ("i want to break free", "I Want to Break Free"),
+            ("songs we sing to", "Songs We Sing To"),
+            ("(live) at wembley", "(Live) at Wembley"),
+            ("McCartney and AC/DC", "McCartney and AC/DC"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(title_case(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn all_caps_values_are_title_cased() {
+        let cleanup = Cleanup::new(CleanupConfig {
+            normalize: NormalizePolicies {
+                title: FieldPolicy {
+                    fix_all_caps: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            cleanup.clean_value(Field::Title, "I AM A MAN OF CONSTANT SORROW"),
+            "I Am a Man of Constant Sorrow"
+        );
+        assert_eq!(cleanup.clean_value(Field::Title, "ABBA"), "ABBA");
+    }
+}
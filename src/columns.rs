@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use utoipa::ToSchema;
+
+use crate::storage::IndexedTrack;
+
+/// bliss maps the detected tempo from 0-206 BPM onto -1..1
+const BLISS_MAX_BPM: f32 = 206.0;
+
+/// Library table column to sort `GET /api/tracks` by.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    Title,
+    Artist,
+    Album,
+    OriginalArtist,
+    Genre,
+    Year,
+    Duration,
+    Bitrate,
+    Bpm,
+    Size,
+    Path,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Tempo in BPM from the first (tempo) feature of a bliss vector.
+pub fn estimated_bpm(analysis: &[f32]) -> Option<f32> {
+    let tempo = *analysis.first()?;
+    Some((tempo + 1.0) / 2.0 * BLISS_MAX_BPM)
+}
+
+enum Value {
+    Text(String),
+    Number(f64),
+}
+
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Text(x), Value::Text(y)) => x.cmp(y),
+        (Value::Number(x), Value::Number(y)) => x.total_cmp(y),
+        _ => Ordering::Equal,
+    }
+}
+
+fn value(track: &IndexedTrack, key: SortKey, bpm: Option<f32>) -> Option<Value> {
+    let m = &track.metadata;
+    let text = |s: Option<&str>| {
+        let s = s?.trim().to_lowercase();
+        (!s.is_empty()).then_some(Value::Text(s))
+    };
+    match key {
+        SortKey::Title => text(Some(&m.title)),
+        SortKey::Artist => text(Some(&m.artist)),
+        SortKey::Album => text(m.album.as_deref()),
+        SortKey::OriginalArtist => text(m.original_artist.as_deref()),
+        SortKey::Genre => text(m.genre.as_deref()),
+        SortKey::Path => text(track.path.to_str()),
+        SortKey::Year => m.year.map(|y| Value::Number(y as f64)),
+        SortKey::Duration => Some(Value::Number(m.duration)),
+        SortKey::Bitrate => m
+            .quality
+            .as_ref()
+            .and_then(|q| q.bitrate_kbps)
+            .map(|b| Value::Number(b as f64)),
+        SortKey::Bpm => bpm.map(|b| Value::Number(b as f64)),
+        SortKey::Size => Some(Value::Number(track.file_size as f64)),
+    }
+}
+
+/// Stable sort by one column; tracks without a value for it (no album, not
+/// analyzed, ...) go last in either order.
+pub fn sort(
+    tracks: Vec<&IndexedTrack>,
+    key: SortKey,
+    order: SortOrder,
+    bpm: impl Fn(&IndexedTrack) -> Option<f32>,
+) -> Vec<&IndexedTrack> {
+    let mut keyed: Vec<(Option<Value>, &IndexedTrack)> = tracks
+        .into_iter()
+        .map(|t| (value(t, key, bpm(t)), t))
+        .collect();
+    keyed.sort_by(|a, b| match (&a.0, &b.0) {
+        (Some(x), Some(y)) => match order {
+            SortOrder::Asc => compare(x, y),
+            SortOrder::Desc => compare(y, x),
+        },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    keyed.into_iter().map(|(_, t)| t).collect()
+}
@@ -0,0 +1,172 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use utoipa::ToSchema;
+
+use crate::storage::{AudioLibrary, IndexedTrack};
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ValueCount {
+    /// Empty when the tag is missing
+    pub value: String,
+    pub count: usize,
+}
+
+/// One tag that not every file in a folder agrees on.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct FieldDisagreement {
+    /// `album`, `album_artist` or `year`
+    pub field: String,
+    /// Most common value (ties prefer a present value)
+    pub majority: String,
+    pub values: Vec<ValueCount>,
+    /// Tracks whose value differs from the majority
+    pub dissenting_ids: Vec<String>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct FolderIssue {
+    pub folder: String,
+    pub track_count: usize,
+    /// Several albums with 2+ tracks each: a mixed folder rather than a stray tag,
+    /// so "apply majority" is not offered for the album field
+    pub mixed_albums: bool,
+    pub disagreements: Vec<FieldDisagreement>,
+}
+
+/// An album (by album artist or artist) whose tracks live in several folders.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ScatteredAlbum {
+    pub artist: String,
+    pub album: String,
+    pub folders: Vec<String>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ConsistencyReport {
+    pub folders: Vec<FolderIssue>,
+    pub scattered: Vec<ScatteredAlbum>,
+}
+
+fn album_artist(track: &IndexedTrack) -> &str {
+    track
+        .metadata
+        .album_artist
+        .as_deref()
+        .unwrap_or(&track.metadata.artist)
+}
+
+fn folder_of(track: &IndexedTrack) -> String {
+    track
+        .path
+        .parent()
+        .unwrap_or(Path::new(""))
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn disagreement(
+    field: &str,
+    tracks: &[&IndexedTrack],
+    value: impl Fn(&IndexedTrack) -> String,
+) -> Option<FieldDisagreement> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for t in tracks {
+        *counts.entry(value(t)).or_default() += 1;
+    }
+    if counts.len() < 2 {
+        return None;
+    }
+
+    let mut values: Vec<ValueCount> = counts
+        .into_iter()
+        .map(|(value, count)| ValueCount { value, count })
+        .collect();
+    values.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then(a.value.is_empty().cmp(&b.value.is_empty()))
+    });
+    let majority = values[0].value.clone();
+    let dissenting_ids = tracks
+        .iter()
+        .filter(|t| value(t) != majority)
+        .map(|t| t.id.clone())
+        .collect();
+
+    Some(FieldDisagreement {
+        field: field.to_string(),
+        majority,
+        values,
+        dissenting_ids,
+    })
+}
+
+/// Flag folders whose files disagree on album-level tags and albums spread over
+/// several folders. Single-file folders are never flagged.
+pub fn check(library: &AudioLibrary) -> ConsistencyReport {
+    let mut by_folder: BTreeMap<String, Vec<&IndexedTrack>> = BTreeMap::new();
+    let mut album_folders: BTreeMap<(String, String), (String, String, BTreeSet<String>)> =
+        BTreeMap::new();
+
+    for track in library.sorted_tracks() {
+        let folder = folder_of(track);
+        by_folder.entry(folder.clone()).or_default().push(track);
+
+        if let Some(album) = track.metadata.album.as_deref() {
+            let artist = album_artist(track);
+            album_folders
+                .entry((artist.to_lowercase(), album.to_lowercase()))
+                .or_insert_with(|| (artist.to_string(), album.to_string(), BTreeSet::new()))
+                .2
+                .insert(folder);
+        }
+    }
+
+    let mut folders = Vec::new();
+    for (folder, tracks) in by_folder {
+        if tracks.len() < 2 {
+            continue;
+        }
+
+        let album = |t: &IndexedTrack| t.metadata.album.clone().unwrap_or_default();
+        let disagreements: Vec<FieldDisagreement> = [
+            disagreement("album", &tracks, album),
+            disagreement("album_artist", &tracks, |t| {
+                t.metadata.album_artist.clone().unwrap_or_default()
+            }),
+            disagreement("year", &tracks, |t| {
+                t.metadata.year.map(|y| y.to_string()).unwrap_or_default()
+            }),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if disagreements.is_empty() {
+            continue;
+        }
+
+        let mixed_albums = disagreements
+            .iter()
+            .find(|d| d.field == "album")
+            .is_some_and(|d| d.values.iter().filter(|v| v.count >= 2).count() >= 2);
+        folders.push(FolderIssue {
+            folder,
+            track_count: tracks.len(),
+            mixed_albums,
+            disagreements,
+        });
+    }
+
+    let scattered = album_folders
+        .into_values()
+        .filter(|(_, _, folders)| folders.len() > 1)
+        .map(|(artist, album, folders)| ScatteredAlbum {
+            artist,
+            album,
+            folders: folders.into_iter().collect(),
+        })
+        .collect();
+
+    ConsistencyReport { folders, scattered }
+}
@@ -0,0 +1,213 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Basic properties of the default audio track of a file.
+#[derive(Debug, Clone)]
+pub struct StreamInfo {
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub bits_per_sample: Option<u32>,
+    /// Encoder delay in frames (MP3 LAME header, AAC iTunSMPB), for gapless playback
+    pub encoder_delay: Option<u32>,
+    /// Frames of encoder padding at the end of the stream
+    pub encoder_padding: Option<u32>,
+}
+
+/// Packet-by-packet decoder at the file's native sample rate.
+///
+/// Unlike the bliss decoder (which resamples to 22050 Hz mono), this keeps the
+/// original signal so signal-level checks (spectral cutoff, clipping) are meaningful.
+pub struct AudioStream {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    buffer: Option<SampleBuffer<f32>>,
+    /// `buffer` holds the first packet, decoded by `open` and not yet returned
+    primed: bool,
+    pub info: StreamInfo,
+}
+
+impl AudioStream {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).context("Failed to open audio file")?;
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
+            hint.with_extension(ext);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                mss,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .context("Unsupported audio format")?;
+        let format = probed.format;
+
+        let track = format.default_track().context("No audio track found")?;
+        let track_id = track.id;
+        let params = track.codec_params.clone();
+
+        let decoder = symphonia::default::get_codecs()
+            .make(&params, &DecoderOptions::default())
+            .context("Unsupported codec")?;
+
+        let codec = symphonia::default::get_codecs()
+            .get_codec(params.codec)
+            .map(|d| d.short_name.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let info = StreamInfo {
+            codec,
+            sample_rate: params.sample_rate.unwrap_or(44100),
+            channels: params.channels.map(|c| c.count()).unwrap_or(2).max(1),
+            bits_per_sample: params.bits_per_sample,
+            encoder_delay: params.delay.filter(|d| *d > 0),
+            encoder_padding: params.padding.filter(|p| *p > 0),
+        };
+
+        let mut stream = Self {
+            format,
+            decoder,
+            track_id,
+            buffer: None,
+            primed: false,
+            info,
+        };
+        // Decode the first packet up front so `info` describes the actual signal
+        stream.primed = stream.decode_packet()?;
+        Ok(stream)
+    }
+
+    /// Decode the next packet. Returns interleaved samples, or `None` at end of stream.
+    pub fn next_chunk(&mut self) -> Result<Option<&[f32]>> {
+        if self.primed {
+            self.primed = false;
+        } else if !self.decode_packet()? {
+            return Ok(None);
+        }
+        Ok(self.buffer.as_ref().map(|b| b.samples()))
+    }
+
+    /// Decode the next packet of the track into `buffer`, taking the channel
+    /// count and sample rate from the decoded signal. Returns `false` at end of stream.
+    fn decode_packet(&mut self) -> Result<bool> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(p) => p,
+                Err(SymphoniaError::IoError(e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    return Ok(false)
+                }
+                Err(SymphoniaError::ResetRequired) => return Ok(false),
+                Err(e) => return Err(e).context("Failed to read packet"),
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(d) => d,
+                // Corrupt frames are skipped, same as most players do
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(e) => return Err(e).context("Failed to decode packet"),
+            };
+
+            let spec = *decoded.spec();
+            // Headers may omit or misstate the layout; trust the decoded signal
+            self.info.channels = spec.channels.count().max(1);
+            self.info.sample_rate = spec.rate;
+            let frames = decoded.capacity() as u64;
+            let needs_alloc = match &self.buffer {
+                Some(b) => b.capacity() < decoded.capacity() * spec.channels.count(),
+                None => true,
+            };
+            if needs_alloc {
+                self.buffer = Some(SampleBuffer::<f32>::new(frames, spec));
+            }
+
+            let buffer = self.buffer.as_mut().unwrap();
+            buffer.copy_interleaved_ref(decoded);
+            return Ok(true);
+        }
+    }
+}
+
+/// A whole file decoded to interleaved samples at its native sample rate;
+/// the input of `analyzers::Analyzer`.
+pub struct DecodedAudio {
+    pub info: StreamInfo,
+    pub samples: Vec<f32>,
+}
+
+impl DecodedAudio {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut stream = AudioStream::open(path)?;
+        let mut samples = Vec::new();
+        while let Some(chunk) = stream.next_chunk()? {
+            samples.extend_from_slice(chunk);
+        }
+        Ok(Self {
+            info: stream.info,
+            samples,
+        })
+    }
+
+    /// Samples per channel.
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.info.channels
+    }
+
+    /// Interleaved samples of each frame.
+    pub fn frame_iter(&self) -> std::slice::Chunks<'_, f32> {
+        self.samples.chunks(self.info.channels)
+    }
+}
+
+/// Peak envelope of the file in `points` buckets (0.0..=1.0), for waveform display.
+/// `duration` sizes the buckets; audio past it lands in the last bucket.
+pub fn waveform(path: &Path, duration: f64, points: usize) -> Result<Vec<f32>> {
+    let mut stream = AudioStream::open(path)?;
+    let channels = stream.info.channels;
+    let total_frames = (duration * stream.info.sample_rate as f64).max(1.0);
+    let frames_per_point = (total_frames / points.max(1) as f64).max(1.0);
+
+    let mut peaks = vec![0f32; points.max(1)];
+    let mut frame_pos = 0u64;
+    while let Some(chunk) = stream.next_chunk()? {
+        for frame in chunk.chunks_exact(channels) {
+            let bucket = ((frame_pos as f64 / frames_per_point) as usize).min(peaks.len() - 1);
+            let peak = frame.iter().fold(0f32, |m, s| m.max(s.abs()));
+            peaks[bucket] = peaks[bucket].max(peak.min(1.0));
+            frame_pos += 1;
+        }
+    }
+    Ok(peaks)
+}
+
+/// Average interleaved frames down to a single channel.
+pub fn downmix_into(interleaved: &[f32], channels: usize, out: &mut Vec<f32>) {
+    if channels <= 1 {
+        out.extend_from_slice(interleaved);
+        return;
+    }
+    out.extend(
+        interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+    );
+}
@@ -0,0 +1,324 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bundle;
+use crate::organizer::TrackMetadata;
+use crate::storage::{AudioLibrary, IndexedTrack};
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    /// Same path, different tags
+    Retagged,
+    /// Same recording at a new path (tags may have changed too)
+    Moved,
+}
+
+impl ChangeKind {
+    fn label(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Retagged => "retagged",
+            ChangeKind::Moved => "moved",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct FieldDiff {
+    pub field: &'static str,
+    /// Empty when the tag was missing
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TrackChange {
+    pub change: ChangeKind,
+    pub path: String,
+    /// Previous location of a moved track
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldDiff>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct LibraryDiff {
+    pub added: usize,
+    pub removed: usize,
+    pub retagged: usize,
+    pub moved: usize,
+    pub changes: Vec<TrackChange>,
+}
+
+/// Load a snapshot: an index directory, an `index.json` or an `index export` bundle.
+pub fn load_snapshot(path: &Path) -> Result<AudioLibrary> {
+    if path.is_dir() {
+        return AudioLibrary::load(&path.join("index.json"));
+    }
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read snapshot {:?}", path))?;
+    let value: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse snapshot {:?}", path))?;
+    if value.get("format").is_some() {
+        Ok(bundle::parse(&content)?.into_library())
+    } else {
+        AudioLibrary::load(path)
+    }
+}
+
+fn text(v: &Option<String>) -> String {
+    v.clone().unwrap_or_default()
+}
+
+fn number(v: Option<u32>) -> String {
+    v.map(|n| n.to_string()).unwrap_or_default()
+}
+
+/// Tag fields that differ between two versions of a track.
+fn field_diffs(old: &TrackMetadata, new: &TrackMetadata) -> Vec<FieldDiff> {
+    let pairs = [
+        ("title", old.title.clone(), new.title.clone()),
+        ("artist", old.artist.clone(), new.artist.clone()),
+        ("album", text(&old.album), text(&new.album)),
+        (
+            "album_artist",
+            text(&old.album_artist),
+            text(&new.album_artist),
+        ),
+        ("genre", text(&old.genre), text(&new.genre)),
+        ("year", number(old.year), number(new.year)),
+        (
+            "disc_number",
+            number(old.disc_number),
+            number(new.disc_number),
+        ),
+        (
+            "track_number",
+            number(old.track_number),
+            number(new.track_number),
+        ),
+    ];
+    pairs
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(field, old, new)| FieldDiff { field, old, new })
+        .collect()
+}
+
+/// What identifies the same recording across paths: the fingerprint, or
+/// size plus tags for tracks indexed without one.
+fn identity(track: &IndexedTrack) -> String {
+    match &track.metadata.fingerprint {
+        Some(fp) => fp.clone(),
+        None => format!(
+            "{}\u{0}{}\u{0}{}",
+            track.file_size, track.metadata.artist, track.metadata.title
+        ),
+    }
+}
+
+fn display(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+pub fn diff(old: &AudioLibrary, new: &AudioLibrary) -> LibraryDiff {
+    let mut result = LibraryDiff::default();
+
+    let mut removed: Vec<&IndexedTrack> = old
+        .sorted_tracks()
+        .into_iter()
+        .filter(|t| !new.files.contains_key(&t.path))
+        .collect();
+    // Removed tracks by identity, consumed as they are matched to moves
+    let mut removed_by_identity: HashMap<String, Vec<&PathBuf>> = HashMap::new();
+    for t in &removed {
+        removed_by_identity
+            .entry(identity(t))
+            .or_default()
+            .push(&t.path);
+    }
+    let mut moved_from: HashSet<&PathBuf> = HashSet::new();
+
+    for track in new.sorted_tracks() {
+        match old.files.get(&track.path) {
+            Some(before) => {
+                let fields = field_diffs(&before.metadata, &track.metadata);
+                if !fields.is_empty() {
+                    result.retagged += 1;
+                    result.changes.push(TrackChange {
+                        change: ChangeKind::Retagged,
+                        path: display(&track.path),
+                        old_path: None,
+                        fields,
+                    });
+                }
+            }
+            None => {
+                let origin = removed_by_identity
+                    .get_mut(&identity(track))
+                    .and_then(|paths| paths.pop());
+                match origin {
+                    Some(old_path) => {
+                        moved_from.insert(old_path);
+                        result.moved += 1;
+                        result.changes.push(TrackChange {
+                            change: ChangeKind::Moved,
+                            path: display(&track.path),
+                            old_path: Some(display(old_path)),
+                            fields: field_diffs(&old.files[old_path].metadata, &track.metadata),
+                        });
+                    }
+                    None => {
+                        result.added += 1;
+                        result.changes.push(TrackChange {
+                            change: ChangeKind::Added,
+                            path: display(&track.path),
+                            old_path: None,
+                            fields: Vec::new(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    removed.retain(|t| !moved_from.contains(&t.path));
+    result.removed = removed.len();
+    result
+        .changes
+        .extend(removed.into_iter().map(|t| TrackChange {
+            change: ChangeKind::Removed,
+            path: display(&t.path),
+            old_path: None,
+            fields: Vec::new(),
+        }));
+    result
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One row per changed field (or per track when no field changed).
+pub fn to_csv(diff: &LibraryDiff) -> String {
+    let mut out = String::from("change,path,old_path,field,old,new\n");
+    for c in &diff.changes {
+        let prefix = format!(
+            "{},{},{}",
+            c.change.label(),
+            csv_field(&c.path),
+            csv_field(c.old_path.as_deref().unwrap_or(""))
+        );
+        if c.fields.is_empty() {
+            out.push_str(&prefix);
+            out.push_str(",,,\n");
+        }
+        for f in &c.fields {
+            out.push_str(&format!(
+                "{},{},{},{}\n",
+                prefix,
+                f.field,
+                csv_field(&f.old),
+                csv_field(&f.new)
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(library: &mut AudioLibrary, path: &str, title: &str, fingerprint: &str) {
+        let meta = TrackMetadata {
+            title: title.to_string(),
+            artist: "Band".to_string(),
+            fingerprint: Some(fingerprint.to_string()),
+            ..Default::default()
+        };
+        library.upsert(PathBuf::from(path), 1, 1, 1, meta);
+    }
+
+    #[test]
+    fn reports_added_removed_retagged_and_moved_tracks() {
+        let mut old = AudioLibrary::default();
+        track(&mut old, "/m/kept.mp3", "Kept", "fp-kept");
+        track(&mut old, "/m/retag.mp3", "Old Title", "fp-retag");
+        track(&mut old, "/m/gone.mp3", "Gone", "fp-gone");
+        track(&mut old, "/m/old/moved.mp3", "Moved", "fp-moved");
+
+        let mut new = AudioLibrary::default();
+        track(&mut new, "/m/kept.mp3", "Kept", "fp-kept");
+        track(&mut new, "/m/retag.mp3", "New Title", "fp-retag");
+        track(&mut new, "/m/new/moved.mp3", "Moved", "fp-moved");
+        track(&mut new, "/m/added.mp3", "Added", "fp-added");
+
+        let diff = diff(&old, &new);
+        assert_eq!(
+            (diff.added, diff.removed, diff.retagged, diff.moved),
+            (1, 1, 1, 1)
+        );
+        let find = |kind: ChangeKind| {
+            diff.changes
+                .iter()
+                .find(|c| c.change == kind)
+                .unwrap_or_else(|| panic!("no {:?} change", kind))
+        };
+        assert_eq!(find(ChangeKind::Added).path, "/m/added.mp3");
+        assert_eq!(find(ChangeKind::Removed).path, "/m/gone.mp3");
+        let retagged = find(ChangeKind::Retagged);
+        assert_eq!(retagged.path, "/m/retag.mp3");
+        assert_eq!(retagged.fields.len(), 1);
+        assert_eq!(
+            (retagged.fields[0].field, retagged.fields[0].old.as_str()),
+            ("title", "Old Title")
+        );
+        let moved = find(ChangeKind::Moved);
+        assert_eq!(moved.old_path.as_deref(), Some("/m/old/moved.mp3"));
+        assert!(moved.fields.is_empty());
+    }
+
+    #[test]
+    fn identical_libraries_have_no_changes() {
+        let mut library = AudioLibrary::default();
+        track(&mut library, "/m/a.mp3", "A", "fp-a");
+        let diff = diff(&library, &library.clone());
+        assert!(diff.changes.is_empty());
+        assert_eq!(to_csv(&diff), "change,path,old_path,field,old,new\n");
+    }
+
+    #[test]
+    fn csv_has_one_row_per_changed_field() {
+        let mut old = AudioLibrary::default();
+        track(&mut old, "/m/a.mp3", "Old, Title", "fp-a");
+        let mut new = AudioLibrary::default();
+        track(&mut new, "/m/a.mp3", "New \"Title\"", "fp-a");
+        track(&mut new, "/m/b.mp3", "B", "fp-b");
+
+        assert_eq!(
+            to_csv(&diff(&old, &new)),
+            "change,path,old_path,field,old,new\n\
+             added,/m/b.mp3,,,,\n\
+             retagged,/m/a.mp3,,title,\"Old, Title\",\"New \"\"Title\"\"\"\n"
+        );
+    }
+}
@@ -0,0 +1,389 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::acoustid;
+use crate::analysis_store::AnalysisStore;
+use crate::analyzers;
+use crate::cleanup::Cleanup;
+use crate::hooks::HookSettings;
+use crate::lookup_cache::LookupCache;
+use crate::lookup_queue::LookupQueue;
+use crate::merge_policy::MergePolicy;
+use crate::neighbors;
+use crate::notify::NotifySettings;
+use crate::playlist;
+use crate::presets::PresetStore;
+use crate::radio::PlayHistory;
+use crate::scanner::ScanSettings;
+use crate::share::ShareStore;
+use crate::similarity::SimilaritySettings;
+use crate::storage::{self, AudioLibrary};
+
+/// Paths listed under a check before the rest are only counted
+const EXAMPLES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    pub fn label(self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        }
+    }
+}
+
+/// Result of one `doctor` check, with what to do about it.
+#[derive(Debug)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: Status::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Settings and state files in the index directory with their loaders.
+fn settings_checks(index_dir: &Path) -> Vec<Check> {
+    type Loader = fn(&Path) -> Result<()>;
+    let files: [(&str, Loader); 12] = [
+        ("scan.json", |p| {
+            let settings = ScanSettings::load(p)?;
+            analyzers::resolve(&settings.analyzers).map(drop)
+        }),
+        ("cleanup.json", |p| Cleanup::load(p).map(drop)),
+        ("merge_policy.json", |p| MergePolicy::load(p).map(drop)),
+        ("similarity.json", |p| SimilaritySettings::load(p).map(drop)),
+        ("notify.json", |p| NotifySettings::load(p).map(drop)),
+        ("hooks.json", |p| HookSettings::load(p).map(drop)),
+        ("playlists.json", |p| playlist::load_stored(p).map(drop)),
+        ("filter_presets.json", |p| PresetStore::load(p).map(drop)),
+        ("shares.json", |p| ShareStore::load(p).map(drop)),
+        ("play_history.json", |p| PlayHistory::load(p).map(drop)),
+        ("pending_lookups.json", |p| LookupQueue::load(p).map(drop)),
+        ("lookup_cache.json", |p| {
+            LookupCache::load(p, true).map(drop)
+        }),
+    ];
+    files
+        .into_iter()
+        .map(|(name, load)| {
+            let path = index_dir.join(name);
+            if !path.exists() {
+                return Check::ok(name, "not present, defaults apply");
+            }
+            match load(&path) {
+                Ok(()) => Check::ok(name, "valid"),
+                Err(e) => Check::fail(
+                    name,
+                    format!("{:#}", e),
+                    format!(
+                        "Fix the file against the README example, or remove {:?} to go back to the defaults",
+                        path
+                    ),
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Whether files can be created in `dir`, tested by writing one.
+fn write_check(name: &str, dir: &Path) -> Check {
+    if !dir.is_dir() {
+        return Check::fail(
+            name,
+            format!("{:?} does not exist", dir),
+            format!("Create it: mkdir -p {:?}", dir),
+        );
+    }
+    let probe = dir.join(".audio_sorter_write_test");
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            Check::ok(name, format!("{:?} is writable", dir))
+        }
+        Err(e) => Check::fail(
+            name,
+            format!("Cannot write to {:?}: {}", dir, e),
+            "Run as the user owning the directory, or grant write access (chown / chmod)",
+        ),
+    }
+}
+
+/// Checks that only read local files: settings and index directory permissions.
+/// `serve` runs these at startup.
+pub fn quick_checks(index_dir: &Path) -> Vec<Check> {
+    let mut checks = vec![write_check("index directory", index_dir)];
+    checks.extend(settings_checks(index_dir));
+    checks
+}
+
+fn examples<'a>(paths: impl Iterator<Item = &'a Path>) -> String {
+    let mut paths: Vec<_> = paths.collect();
+    paths.sort();
+    let mut out: Vec<String> = paths
+        .iter()
+        .take(EXAMPLES)
+        .map(|p| format!("{:?}", p))
+        .collect();
+    if paths.len() > EXAMPLES {
+        out.push(format!("and {} more", paths.len() - EXAMPLES));
+    }
+    out.join(", ")
+}
+
+/// Index and analysis consistency: unreadable files, tracks missing on disk,
+/// vectors without a track, tracks without a vector and vectors of another
+/// bliss feature version.
+fn index_checks(index_dir: &Path) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let index_path = index_dir.join("index.json");
+    if !storage::stored_path(&index_path).exists() {
+        checks.push(Check::warn(
+            "index.json",
+            "no index yet",
+            "Run `audio_sorter scan --input-dir <music> --output-dir <this directory>`",
+        ));
+        return checks;
+    }
+    let library = match AudioLibrary::load(&index_path) {
+        Ok(library) => library,
+        Err(e) => {
+            checks.push(Check::fail(
+                "index.json",
+                format!("{:#}", e),
+                "Restore it from a backup or an `index export` bundle, or delete it and rescan",
+            ));
+            return checks;
+        }
+    };
+    checks.push(Check::ok(
+        "index.json",
+        format!("{} tracks", library.files.len()),
+    ));
+
+    let missing: Vec<&Path> = library
+        .files
+        .keys()
+        .filter(|p| !p.exists())
+        .map(|p| p.as_path())
+        .collect();
+    checks.push(if missing.is_empty() {
+        Check::ok("indexed files", "all present on disk")
+    } else {
+        Check::warn(
+            "indexed files",
+            format!(
+                "{} indexed files no longer exist: {}",
+                missing.len(),
+                examples(missing.iter().copied())
+            ),
+            "If the library moved, re-import with `index export` / `index import --root <new location>`; otherwise restore the files, or run `scan` to drop them",
+        )
+    });
+
+    let store = match AnalysisStore::load(&index_dir.join("analysis.bin")) {
+        Ok(store) => store,
+        Err(e) => {
+            checks.push(Check::fail(
+                "analysis.bin",
+                format!("{:#}", e),
+                "Delete analysis.bin and run `audio_sorter reanalyze --scope bliss`",
+            ));
+            return checks;
+        }
+    };
+    checks.push(Check::ok(
+        "analysis.bin",
+        format!("{} vectors", store.data.len()),
+    ));
+
+    let orphans: Vec<&Path> = store
+        .data
+        .keys()
+        .filter(|p| !library.files.contains_key(*p))
+        .map(|p| p.as_path())
+        .collect();
+    if !orphans.is_empty() {
+        checks.push(Check::warn(
+            "orphaned vectors",
+            format!(
+                "{} vectors belong to no indexed track: {}",
+                orphans.len(),
+                examples(orphans.iter().copied())
+            ),
+            "Harmless but unused; `index export` followed by `index import` keeps only indexed tracks",
+        ));
+    }
+
+    let settings = ScanSettings::load(&index_dir.join("scan.json")).unwrap_or_default();
+    let unanalyzed: Vec<&Path> = library
+        .files
+        .keys()
+        .filter(|p| settings.pipeline(p).analysis && !store.data.contains_key(*p))
+        .map(|p| p.as_path())
+        .collect();
+    if !unanalyzed.is_empty() {
+        checks.push(Check::warn(
+            "missing vectors",
+            format!(
+                "{} tracks have no bliss vector (no recommendations or radio): {}",
+                unanalyzed.len(),
+                examples(unanalyzed.iter().copied())
+            ),
+            "Run `audio_sorter reanalyze --scope bliss`",
+        ));
+    }
+
+    // Vector length changes with the bliss feature version
+    let mut lengths: HashMap<usize, usize> = HashMap::new();
+    for vector in store.data.values() {
+        *lengths.entry(vector.len()).or_default() += 1;
+    }
+    if let Some((&expected, _)) = lengths.iter().max_by_key(|(len, n)| (**n, **len)) {
+        let mismatched: Vec<&Path> = store
+            .data
+            .iter()
+            .filter(|(_, v)| v.len() != expected)
+            .map(|(p, _)| p.as_path())
+            .collect();
+        if !mismatched.is_empty() {
+            let other: HashSet<usize> = mismatched.iter().map(|p| store.data[*p].len()).collect();
+            checks.push(Check::warn(
+                "vector versions",
+                format!(
+                    "{} vectors have {:?} features instead of {} (analyzed by another version): {}",
+                    mismatched.len(),
+                    other,
+                    expected,
+                    examples(mismatched.iter().copied())
+                ),
+                "Run `audio_sorter reanalyze --scope bliss` so similarity compares like with like",
+            ));
+        }
+    }
+
+    if neighbors::is_stale(index_dir) {
+        checks.push(Check::warn(
+            "neighbors.bin",
+            "missing or older than analysis.bin",
+            "Rebuilt in the background when `serve` starts or a scan finishes",
+        ));
+    }
+    checks
+}
+
+/// Whether Chromaprint's `fpcalc`, used for every fingerprint, can be started.
+fn fpcalc_check() -> Check {
+    match Command::new("fpcalc").arg("-version").output() {
+        Ok(output) => Check::ok(
+            "fpcalc",
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ),
+        Err(e) => Check::fail(
+            "fpcalc",
+            format!("Cannot run fpcalc: {}", e),
+            "Install Chromaprint (https://acoustid.org/chromaprint) and put fpcalc on the PATH",
+        ),
+    }
+}
+
+fn acoustid_check(client_id: Option<&str>) -> Check {
+    let Some(client_id) = client_id.filter(|id| !id.is_empty()) else {
+        return Check::warn(
+            "AcoustID key",
+            "not set; scans run offline",
+            "Register an application at https://acoustid.org/new-application and set ACOUSTID_CLIENT_ID",
+        );
+    };
+    match acoustid::validate_key(client_id) {
+        Ok(()) => Check::ok("AcoustID key", "accepted (or service unreachable, see above)"),
+        Err(e) => Check::fail(
+            "AcoustID key",
+            format!("{:#}", e),
+            "Check ACOUSTID_CLIENT_ID against https://acoustid.org/my-applications, or wait for the quota to reset",
+        ),
+    }
+}
+
+/// Every check: `quick_checks`, index consistency, the input directory,
+/// external tools and (unless `offline`) the AcoustID key.
+pub fn run(
+    index_dir: &Path,
+    input_dir: Option<&Path>,
+    client_id: Option<&str>,
+    offline: bool,
+) -> Vec<Check> {
+    let mut checks = quick_checks(index_dir);
+    checks.extend(index_checks(index_dir));
+    if let Some(input_dir) = input_dir {
+        checks.push(match fs::read_dir(input_dir) {
+            Ok(_) => Check::ok("input directory", format!("{:?} is readable", input_dir)),
+            Err(e) => Check::fail(
+                "input directory",
+                format!("Cannot read {:?}: {}", input_dir, e),
+                "Check the path (mounted drive?) and that this user may read it",
+            ),
+        });
+    }
+    checks.push(fpcalc_check());
+    if !offline {
+        checks.push(acoustid_check(client_id));
+    }
+    checks
+}
+
+/// Print checks as aligned lines; `verbose` also lists passing ones.
+pub fn print(checks: &[Check], verbose: bool) {
+    for check in checks {
+        if check.status == Status::Ok && !verbose {
+            continue;
+        }
+        println!(
+            "[{:<4}] {}: {}",
+            check.status.label(),
+            check.name,
+            check.detail
+        );
+        if let Some(fix) = &check.fix {
+            println!("       fix: {}", fix);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+use utoipa::ToSchema;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::organizer;
+use crate::storage::IndexedTrack;
+
+/// Bytes buffered before a chunk is handed to the response body
+const CHUNK_BYTES: usize = 256 * 1024;
+
+/// Body of `POST /api/download`.
+#[derive(Deserialize, Debug, Default, ToSchema)]
+#[serde(default)]
+pub struct DownloadRequest {
+    /// Track IDs from `/api/tracks`
+    pub ids: Vec<String>,
+    /// Stored playlist from `playlists.json`; its tracks come first, in order
+    pub playlist: Option<String>,
+}
+
+/// Archive entry names in playlist order ("01 - Artist - Title.flac"),
+/// unique within the archive.
+pub fn entry_names(tracks: &[&IndexedTrack]) -> Vec<String> {
+    let width = tracks.len().to_string().len().max(2);
+    let mut used = HashSet::new();
+    tracks
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let meta = &t.metadata;
+            let stem = if meta.title.trim().is_empty() {
+                t.path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            } else if meta.artist.trim().is_empty() {
+                meta.title.clone()
+            } else {
+                format!("{} - {}", meta.artist, meta.title)
+            };
+            let ext = t
+                .path
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy().to_lowercase()))
+                .unwrap_or_default();
+            let base = organizer::sanitize_component(&format!("{:0width$} - {}", i + 1, stem));
+            let mut name = format!("{}{}", base, ext);
+            let mut n = 1;
+            while !used.insert(name.to_lowercase()) {
+                name = format!("{} ({}){}", base, n, ext);
+                n += 1;
+            }
+            name
+        })
+        .collect()
+}
+
+/// Extended M3U referring to the archive's own entries.
+pub fn render_m3u(tracks: &[&IndexedTrack], names: &[String]) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    for (t, name) in tracks.iter().zip(names) {
+        let meta = &t.metadata;
+        out.push_str(&format!(
+            "#EXTINF:{},{} - {}\n{}\n",
+            meta.duration.round() as i64,
+            meta.artist,
+            meta.title,
+            name
+        ));
+    }
+    out
+}
+
+/// Write a zip of `files` (source path, entry name) plus `playlist.m3u`.
+/// Audio is already compressed, so entries are stored as-is.
+pub fn write_zip<W: Write + Seek>(out: W, files: &[(PathBuf, String)], m3u: &str) -> Result<()> {
+    let mut zip = ZipWriter::new(out);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+    for (path, name) in files {
+        let mut file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+        zip.start_file(name.as_str(), options)?;
+        io::copy(&mut file, &mut zip).with_context(|| format!("Failed to read {:?}", path))?;
+    }
+    zip.start_file("playlist.m3u", options)?;
+    zip.write_all(m3u.as_bytes())?;
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// Build the zip in a temporary file, since the zip writer seeks back to fill
+/// in entry sizes, then copy it to `out`.
+pub fn stream_zip<W: Write>(mut out: W, files: &[(PathBuf, String)], m3u: &str) -> Result<()> {
+    let tmp = std::env::temp_dir().join(format!(
+        "audio_sorter_download_{:016x}.zip",
+        rand::random::<u64>()
+    ));
+    let result = (|| {
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&tmp)
+            .context("Failed to create temporary archive")?;
+        write_zip(&mut file, files, m3u)?;
+        file.seek(SeekFrom::Start(0))?;
+        io::copy(&mut file, &mut out)?;
+        out.flush()?;
+        Ok(())
+    })();
+    let _ = std::fs::remove_file(&tmp);
+    result
+}
+
+/// Blocking `Write` end of a streamed response body.
+pub struct ChannelWriter {
+    tx: mpsc::Sender<io::Result<Bytes>>,
+    buf: Vec<u8>,
+}
+
+impl ChannelWriter {
+    pub fn new(tx: mpsc::Sender<io::Result<Bytes>>) -> Self {
+        Self {
+            tx,
+            buf: Vec::with_capacity(CHUNK_BYTES),
+        }
+    }
+
+    fn send(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::replace(
+            &mut self.buf,
+            Vec::with_capacity(CHUNK_BYTES),
+        ));
+        // The receiver is gone when the client disconnected
+        self.tx
+            .blocking_send(Ok(chunk))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Download cancelled"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= CHUNK_BYTES {
+            self.send()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    #[test]
+    fn zip_holds_the_files_and_the_playlist() {
+        let dir = std::env::temp_dir().join(format!("audio_sorter_zip_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.flac");
+        let b = dir.join("b.mp3");
+        std::fs::write(&a, b"first").unwrap();
+        std::fs::write(&b, b"second").unwrap();
+        let files = vec![
+            (a, "01 - A.flac".to_string()),
+            (b, "02 - B.mp3".to_string()),
+        ];
+
+        let mut streamed = Vec::new();
+        stream_zip(&mut streamed, &files, "#EXTM3U\n").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut archive = ZipArchive::new(Cursor::new(streamed)).unwrap();
+        let mut read = |name: &str| {
+            let mut content = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+        assert_eq!(read("01 - A.flac"), "first");
+        assert_eq!(read("02 - B.mp3"), "second");
+        assert_eq!(read("playlist.m3u"), "#EXTM3U\n");
+        assert_eq!(archive.len(), 3);
+    }
+}
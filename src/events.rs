@@ -0,0 +1,91 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+/// Events kept for clients that connect mid-scan
+const RECENT_EVENTS: usize = 500;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+/// One entry of the dashboard event log (`GET /api/events`).
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct LogEvent {
+    /// Increases by one per event for the lifetime of the process
+    pub seq: u64,
+    /// UNIX timestamp (seconds)
+    pub at: u64,
+    pub level: Level,
+    /// What produced the event: "scan", "file", "lookup", "save", ...
+    pub kind: String,
+    pub message: String,
+    pub path: Option<String>,
+}
+
+struct EventLog {
+    tx: broadcast::Sender<LogEvent>,
+    recent: Mutex<VecDeque<LogEvent>>,
+    next_seq: AtomicU64,
+}
+
+fn log() -> &'static EventLog {
+    static LOG: OnceLock<EventLog> = OnceLock::new();
+    LOG.get_or_init(|| EventLog {
+        tx: broadcast::channel(RECENT_EVENTS).0,
+        recent: Mutex::new(VecDeque::with_capacity(RECENT_EVENTS)),
+        next_seq: AtomicU64::new(1),
+    })
+}
+
+/// Record an event and pass it to connected dashboards. Cheap when nobody
+/// listens, so the CLI can share code paths that emit.
+pub fn emit(level: Level, kind: &str, message: impl Into<String>, path: Option<&Path>) {
+    let log = log();
+    let event = LogEvent {
+        seq: log.next_seq.fetch_add(1, Ordering::Relaxed),
+        at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        level,
+        kind: kind.to_string(),
+        message: message.into(),
+        path: path.map(|p| p.to_string_lossy().into_owned()),
+    };
+    if let Ok(mut recent) = log.recent.lock() {
+        if recent.len() == RECENT_EVENTS {
+            recent.pop_front();
+        }
+        recent.push_back(event.clone());
+    }
+    // No receivers is not an error worth reporting
+    let _ = log.tx.send(event);
+}
+
+/// Buffered events after `since` (all when `None`) plus a receiver for new
+/// ones. Subscribing first means nothing falls between the two.
+pub fn subscribe(since: Option<u64>) -> (Vec<LogEvent>, broadcast::Receiver<LogEvent>) {
+    let log = log();
+    let rx = log.tx.subscribe();
+    let recent = log
+        .recent
+        .lock()
+        .map(|r| {
+            r.iter()
+                .filter(|e| since.is_none_or(|s| e.seq > s))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    (recent, rx)
+}
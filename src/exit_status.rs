@@ -0,0 +1,70 @@
+use anyhow::Result;
+use serde::Serialize;
+
+/// Process exit codes of `scan`, for cron jobs and wrapper scripts.
+/// Other failures (e.g. the index could not be written) exit with 1.
+pub const CLEAN: i32 = 0;
+pub const FAILED: i32 = 1;
+/// Finished, but some files could not be read or decoded
+pub const FILE_ERRORS: i32 = 2;
+/// Interrupted; the work done so far was saved
+pub const ABORTED: i32 = 3;
+/// Nothing was processed because of bad arguments or settings files
+pub const CONFIG_ERROR: i32 = 4;
+
+/// Context marking an error as a configuration problem (exit code 4).
+#[derive(Debug, thiserror::Error)]
+#[error("configuration error")]
+pub struct ConfigError;
+
+fn is_config_error(e: &anyhow::Error) -> bool {
+    e.chain().any(|c| c.is::<ConfigError>())
+}
+
+/// File counts of a finished job.
+#[derive(Serialize, Debug, Default)]
+pub struct JobCounts {
+    pub processed: usize,
+    pub errors: usize,
+    /// Unchanged since the last run
+    pub skipped: usize,
+    pub quarantined: usize,
+    /// Dropped from the index because the file was deleted
+    pub removed: usize,
+    /// Not started because the job was interrupted
+    pub remaining: usize,
+}
+
+#[derive(Serialize)]
+struct Summary<'a> {
+    command: &'a str,
+    status: &'static str,
+    exit_code: i32,
+    #[serde(flatten)]
+    counts: Option<&'a JobCounts>,
+    elapsed_secs: u64,
+    error: Option<String>,
+}
+
+/// Print the final `SUMMARY {json}` line on stdout and return the exit code.
+pub fn report(command: &str, result: &Result<JobCounts>, elapsed_secs: u64) -> i32 {
+    let (status, exit_code) = match result {
+        Ok(c) if c.remaining > 0 => ("aborted", ABORTED),
+        Ok(c) if c.errors > 0 => ("file_errors", FILE_ERRORS),
+        Ok(_) => ("clean", CLEAN),
+        Err(e) if is_config_error(e) => ("config_error", CONFIG_ERROR),
+        Err(_) => ("failed", FAILED),
+    };
+    let summary = Summary {
+        command,
+        status,
+        exit_code,
+        counts: result.as_ref().ok(),
+        elapsed_secs,
+        error: result.as_ref().err().map(|e| format!("{:#}", e)),
+    };
+    if let Ok(line) = serde_json::to_string(&summary) {
+        println!("SUMMARY {}", line);
+    }
+    exit_code
+}
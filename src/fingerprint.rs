@@ -1,4 +1,6 @@
 use anyhow::{Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use std::path::Path;
 use std::process::Command;
 
@@ -42,3 +44,120 @@ pub fn compute_fingerprint(path: &Path) -> Result<(f64, String)> {
 
     Ok((duration, fingerprint))
 }
+
+/// Seconds of audio covered by one raw fingerprint item (Chromaprint's
+/// default algorithm: 4096-sample frames at 11025 Hz, one item per third).
+pub const ITEM_SECS: f64 = 0.1238;
+
+/// Reads little-endian bit groups as packed by Chromaprint.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, bits: usize) -> Option<u32> {
+        let mut value = 0;
+        for i in 0..bits {
+            let byte = *self.bytes.get((self.pos + i) / 8)?;
+            value |= (((byte >> ((self.pos + i) % 8)) & 1) as u32) << i;
+        }
+        self.pos += bits;
+        Some(value)
+    }
+
+    /// Skip to the next byte boundary.
+    fn align(&mut self) {
+        self.pos = self.pos.div_ceil(8) * 8;
+    }
+}
+
+/// Decompress an fpcalc fingerprint string into its raw 32-bit items.
+/// `None` when the string isn't a valid compressed fingerprint.
+pub fn decode(fingerprint: &str) -> Option<Vec<u32>> {
+    let data = URL_SAFE_NO_PAD
+        .decode(fingerprint.trim_end_matches('='))
+        .ok()?;
+    if data.len() < 4 {
+        return None;
+    }
+    let count = u32::from_be_bytes([0, data[1], data[2], data[3]]) as usize;
+    let mut reader = BitReader {
+        bytes: &data[4..],
+        pos: 0,
+    };
+
+    // 3-bit deltas between set bit positions, 0 ends an item, 7 means the
+    // delta continues in a 5-bit exception stored after all normal values
+    let mut deltas = Vec::new();
+    let mut ended = 0;
+    while ended < count {
+        let delta = reader.read(3)?;
+        if delta == 0 {
+            ended += 1;
+        }
+        deltas.push(delta);
+    }
+    reader.align();
+    for delta in deltas.iter_mut().filter(|d| **d == 7) {
+        *delta += reader.read(5)?;
+    }
+
+    // Each item is stored XORed with the previous one
+    let mut items = Vec::with_capacity(count);
+    let (mut value, mut bit) = (0u32, 0u32);
+    for delta in deltas {
+        if delta == 0 {
+            let previous = items.last().copied().unwrap_or(0);
+            items.push(value ^ previous);
+            value = 0;
+            bit = 0;
+            continue;
+        }
+        bit += delta;
+        if bit > 32 {
+            return None;
+        }
+        value |= 1 << (bit - 1);
+    }
+    Some(items)
+}
+
+/// Best match between two raw fingerprints.
+#[derive(Debug, Clone, Copy)]
+pub struct Alignment {
+    /// Items of `b` before the point lining up with the start of `a`;
+    /// negative when `b` starts later in the recording than `a`
+    pub offset: isize,
+    /// 1.0 for identical overlapping audio, about 0 for unrelated audio
+    pub similarity: f32,
+}
+
+/// Overlap needed before an offset is considered (about 10 seconds)
+const MIN_OVERLAP_ITEMS: usize = 80;
+
+/// Try every offset up to `max_offset` items in either direction and keep the
+/// one with the lowest bit error rate over the overlapping items.
+pub fn align(a: &[u32], b: &[u32], max_offset: usize) -> Option<Alignment> {
+    let max_offset = max_offset as isize;
+    let mut best: Option<Alignment> = None;
+    for offset in -max_offset..=max_offset {
+        // a[i] lines up with b[i + offset]
+        let start = (-offset).max(0) as usize;
+        let end = (a.len() as isize).min(b.len() as isize - offset);
+        if end <= start as isize || ((end as usize) - start) < MIN_OVERLAP_ITEMS {
+            continue;
+        }
+        let end = end as usize;
+        let errors: u32 = (start..end)
+            .map(|i| (a[i] ^ b[(i as isize + offset) as usize]).count_ones())
+            .sum();
+        let error_rate = errors as f32 / ((end - start) * 32) as f32;
+        // Unrelated audio differs in about half of the bits
+        let similarity = (1.0 - 2.0 * error_rate).max(0.0);
+        if best.is_none_or(|b| similarity > b.similarity) {
+            best = Some(Alignment { offset, similarity });
+        }
+    }
+    best
+}
@@ -0,0 +1,580 @@
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+use std::time::Duration;
+
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::{
+    DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+    MethodDescriptorProto, OneofDescriptorProto, ServiceDescriptorProto,
+};
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+
+use crate::index_writer::IndexWriter;
+use crate::organizer;
+use crate::scan_manager::{self, ScanManager};
+use crate::storage::{IndexedTrack, SharedLibrary};
+
+mod proto {
+    tonic::include_proto!("audio_sorter.AudioSorter");
+}
+
+use proto::audio_sorter_server::{AudioSorter, AudioSorterServer};
+
+// Messages are declared by hand so no protoc is needed; tags must stay stable
+// and match proto/audio_sorter.proto, which clients generate stubs from.
+
+/// The schema clients see through gRPC reflection.
+const PROTO: &str = include_str!("../proto/audio_sorter.proto");
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StartScanRequest {
+    /// Skip AcoustID/MusicBrainz even if ACOUSTID_CLIENT_ID is set
+    #[prost(bool, tag = "1")]
+    pub offline: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StartScanResponse {
+    #[prost(bool, tag = "1")]
+    pub started: bool,
+    #[prost(string, tag = "2")]
+    pub error: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WatchScanRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ScanProgress {
+    #[prost(bool, tag = "1")]
+    pub is_scanning: bool,
+    #[prost(uint64, tag = "2")]
+    pub files_total: u64,
+    #[prost(uint64, tag = "3")]
+    pub files_processed: u64,
+    #[prost(string, tag = "4")]
+    pub current_file: String,
+    #[prost(uint64, tag = "5")]
+    pub elapsed_secs: u64,
+    #[prost(uint64, tag = "6")]
+    pub errors: u64,
+    #[prost(float, tag = "7")]
+    pub cpu_usage: f32,
+    #[prost(uint64, tag = "8")]
+    pub memory_usage: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListTracksRequest {
+    /// Comma-separated vocal languages, e.g. `ja` or `zxx,en`
+    #[prost(string, optional, tag = "1")]
+    pub vocal_language: Option<String>,
+    #[prost(uint32, optional, tag = "2")]
+    pub year: Option<u32>,
+    #[prost(uint32, optional, tag = "3")]
+    pub decade: Option<u32>,
+    /// 0 returns every match
+    #[prost(uint32, tag = "4")]
+    pub limit: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Track {
+    #[prost(string, tag = "1")]
+    pub path: String,
+    #[prost(string, tag = "2")]
+    pub title: String,
+    #[prost(string, tag = "3")]
+    pub artist: String,
+    #[prost(string, optional, tag = "4")]
+    pub album: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub genre: Option<String>,
+    #[prost(uint32, optional, tag = "6")]
+    pub year: Option<u32>,
+    #[prost(double, tag = "7")]
+    pub duration: f64,
+    #[prost(string, optional, tag = "8")]
+    pub vocal_language: Option<String>,
+    #[prost(uint64, tag = "9")]
+    pub file_size: u64,
+    #[prost(string, optional, tag = "10")]
+    pub recording_mbid: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListTracksResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub tracks: Vec<Track>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetStatsRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DecadeCount {
+    #[prost(uint32, tag = "1")]
+    pub decade: u32,
+    #[prost(uint64, tag = "2")]
+    pub count: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Stats {
+    #[prost(uint64, tag = "1")]
+    pub total_tracks: u64,
+    #[prost(uint64, tag = "2")]
+    pub total_size: u64,
+    #[prost(message, repeated, tag = "3")]
+    pub decades: Vec<DecadeCount>,
+    #[prost(uint64, tag = "4")]
+    pub unknown_year: u64,
+}
+
+impl From<scan_manager::ScanProgress> for ScanProgress {
+    fn from(p: scan_manager::ScanProgress) -> Self {
+        Self {
+            is_scanning: p.is_scanning,
+            files_total: p.files_total as u64,
+            files_processed: p.files_processed as u64,
+            current_file: p.current_file,
+            elapsed_secs: p.elapsed_secs,
+            errors: p.errors as u64,
+            cpu_usage: p.resources.cpu_usage,
+            memory_usage: p.resources.memory_usage,
+        }
+    }
+}
+
+impl From<&IndexedTrack> for Track {
+    fn from(t: &IndexedTrack) -> Self {
+        let m = &t.metadata;
+        Self {
+            path: t.path.to_string_lossy().into_owned(),
+            title: m.title.clone(),
+            artist: m.artist.clone(),
+            album: m.album.clone(),
+            genre: m.genre.clone(),
+            year: m.year,
+            duration: m.duration,
+            vocal_language: m.vocal_language.clone(),
+            file_size: t.file_size,
+            recording_mbid: m.recording_mbid.clone(),
+        }
+    }
+}
+
+/// gRPC control API sharing the scan manager and library with the web server.
+pub struct GrpcService {
+    pub index_dir: PathBuf,
+    pub input_dir: Option<PathBuf>,
+    pub scan_manager: Arc<ScanManager>,
+    pub library: Arc<SharedLibrary>,
+    pub writer: IndexWriter,
+}
+
+type ProgressStream = Pin<Box<dyn Stream<Item = Result<ScanProgress, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl AudioSorter for GrpcService {
+    async fn start_scan(
+        &self,
+        request: Request<StartScanRequest>,
+    ) -> Result<Response<StartScanResponse>, Status> {
+        let input_dir = self
+            .input_dir
+            .clone()
+            .ok_or_else(|| Status::failed_precondition("No input directory configured"))?;
+
+        let client_id = std::env::var("ACOUSTID_CLIENT_ID").ok();
+        let offline = request.into_inner().offline || client_id.is_none();
+
+        let response = match self.scan_manager.start_scan(
+            input_dir,
+            self.index_dir.clone(),
+            offline,
+            client_id,
+            self.writer.clone(),
+        ) {
+            Ok(()) => StartScanResponse {
+                started: true,
+                error: String::new(),
+            },
+            Err(e) => StartScanResponse {
+                started: false,
+                error: e.to_string(),
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    type WatchScanStream = ProgressStream;
+
+    /// Push progress every 500 ms until the scan finishes (the final snapshot is included).
+    async fn watch_scan(
+        &self,
+        _request: Request<WatchScanRequest>,
+    ) -> Result<Response<Self::WatchScanStream>, Status> {
+        let scan_manager = self.scan_manager.clone();
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+
+        tokio::spawn(async move {
+            loop {
+                let progress = scan_manager.get_progress();
+                let done = !progress.is_scanning;
+                if tx.send(Ok(progress.into())).await.is_err() || done {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+        });
+
+        Ok(Response::new(Box::pin(
+            tokio_stream::wrappers::ReceiverStream::new(rx),
+        )))
+    }
+
+    async fn list_tracks(
+        &self,
+        request: Request<ListTracksRequest>,
+    ) -> Result<Response<ListTracksResponse>, Status> {
+        let req = request.into_inner();
+        let languages: Option<Vec<String>> = req.vocal_language.map(|l| {
+            l.split(',')
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect()
+        });
+
+        let library = self.library.get();
+        let mut tracks: Vec<Track> = library
+            .sorted_tracks()
+            .into_iter()
+            .filter(|t| match &languages {
+                Some(langs) => t
+                    .metadata
+                    .vocal_language
+                    .as_ref()
+                    .is_some_and(|l| langs.contains(l)),
+                None => true,
+            })
+            .filter(|t| req.year.is_none() || t.metadata.year == req.year)
+            .filter(|t| match req.decade {
+                Some(d) => t.metadata.year.map(organizer::decade) == Some(d),
+                None => true,
+            })
+            .map(Track::from)
+            .collect();
+        if req.limit > 0 {
+            tracks.truncate(req.limit as usize);
+        }
+
+        Ok(Response::new(ListTracksResponse { tracks }))
+    }
+
+    async fn get_stats(
+        &self,
+        _request: Request<GetStatsRequest>,
+    ) -> Result<Response<Stats>, Status> {
+        let library = self.library.get();
+
+        let mut decades: BTreeMap<u32, u64> = BTreeMap::new();
+        let mut unknown_year = 0;
+        for track in library.files.values() {
+            match track.metadata.year {
+                Some(y) => *decades.entry(organizer::decade(y)).or_default() += 1,
+                None => unknown_year += 1,
+            }
+        }
+
+        Ok(Response::new(Stats {
+            total_tracks: library.files.len() as u64,
+            total_size: library.files.values().map(|t| t.file_size).sum(),
+            decades: decades
+                .into_iter()
+                .map(|(decade, count)| DecadeCount { decade, count })
+                .collect(),
+            unknown_year,
+        }))
+    }
+}
+
+pub async fn serve(service: GrpcService, port: u16) {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    println!("gRPC control API available at {}", addr);
+
+    let reflection = match tonic_reflection::server::Builder::configure()
+        .register_file_descriptor_set(descriptor_set())
+        .build_v1()
+    {
+        Ok(reflection) => reflection,
+        Err(e) => {
+            eprintln!("gRPC reflection unavailable: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = tonic::transport::Server::builder()
+        .add_service(AudioSorterServer::new(service))
+        .add_service(reflection)
+        .serve(addr)
+        .await
+    {
+        eprintln!("gRPC server failed: {}", e);
+    }
+}
+
+/// `PROTO` as a descriptor set. Understands the subset of proto3 the file
+/// uses: one package, scalar / message / `optional` / `repeated` fields and
+/// unary or server-streaming methods.
+fn descriptor_set() -> FileDescriptorSet {
+    let text: String = PROTO
+        .lines()
+        .map(|line| line.split("//").next().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let spaced = ["{", "}", "(", ")", ";", "="]
+        .iter()
+        .fold(text, |t, p| t.replace(p, &format!(" {} ", p)));
+    let mut tokens = spaced.split_whitespace().peekable();
+
+    let mut file = FileDescriptorProto {
+        name: Some("audio_sorter.proto".to_string()),
+        syntax: Some("proto3".to_string()),
+        ..Default::default()
+    };
+    let mut package = String::new();
+    let type_name = |package: &str, name: &str| format!(".{}.{}", package, name);
+    while let Some(token) = tokens.next() {
+        match token {
+            "package" => {
+                package = tokens.next().unwrap_or_default().to_string();
+                file.package = Some(package.clone());
+            }
+            "service" => {
+                let mut service = ServiceDescriptorProto {
+                    name: tokens.next().map(str::to_string),
+                    ..Default::default()
+                };
+                tokens.next(); // {
+                while tokens.peek() == Some(&"rpc") {
+                    tokens.next();
+                    let name = tokens.next().map(str::to_string);
+                    let side = |tokens: &mut Peekable<SplitWhitespace>| {
+                        tokens.next(); // (
+                        let streaming = tokens.next_if_eq(&"stream").is_some();
+                        let message = tokens.next().unwrap_or_default();
+                        tokens.next(); // )
+                        (streaming, type_name(&package, message))
+                    };
+                    let (client_streaming, input) = side(&mut tokens);
+                    tokens.next(); // returns
+                    let (server_streaming, output) = side(&mut tokens);
+                    tokens.next(); // ;
+                    service.method.push(MethodDescriptorProto {
+                        name,
+                        input_type: Some(input),
+                        output_type: Some(output),
+                        client_streaming: Some(client_streaming),
+                        server_streaming: Some(server_streaming),
+                        ..Default::default()
+                    });
+                }
+                tokens.next(); // }
+                file.service.push(service);
+            }
+            "message" => {
+                let mut message = DescriptorProto {
+                    name: tokens.next().map(str::to_string),
+                    ..Default::default()
+                };
+                tokens.next(); // {
+                while let Some(first) = tokens.next().filter(|t| *t != "}") {
+                    let (label, ty) = match first {
+                        "optional" | "repeated" => (first, tokens.next().unwrap_or_default()),
+                        _ => ("", first),
+                    };
+                    let name = tokens.next().unwrap_or_default().to_string();
+                    tokens.next(); // =
+                    let number = tokens.next().and_then(|n| n.parse().ok());
+                    tokens.next(); // ;
+                    let scalar = match ty {
+                        "bool" => Some(Type::Bool),
+                        "string" => Some(Type::String),
+                        "float" => Some(Type::Float),
+                        "double" => Some(Type::Double),
+                        "uint32" => Some(Type::Uint32),
+                        "uint64" => Some(Type::Uint64),
+                        _ => None,
+                    };
+                    let mut field = FieldDescriptorProto {
+                        json_name: Some(json_name(&name)),
+                        number,
+                        label: Some(if label == "repeated" {
+                            Label::Repeated as i32
+                        } else {
+                            Label::Optional as i32
+                        }),
+                        r#type: Some(scalar.unwrap_or(Type::Message) as i32),
+                        type_name: scalar.is_none().then(|| type_name(&package, ty)),
+                        ..Default::default()
+                    };
+                    // proto3 `optional` is a one-field oneof named after the field
+                    if label == "optional" {
+                        field.proto3_optional = Some(true);
+                        field.oneof_index = Some(message.oneof_decl.len() as i32);
+                        message.oneof_decl.push(OneofDescriptorProto {
+                            name: Some(format!("_{}", name)),
+                            ..Default::default()
+                        });
+                    }
+                    field.name = Some(name);
+                    message.field.push(field);
+                }
+                file.message_type.push(message);
+            }
+            _ => {}
+        }
+    }
+    FileDescriptorSet { file: vec![file] }
+}
+
+/// lowerCamelCase JSON name protoc gives a snake_case field.
+fn json_name(name: &str) -> String {
+    let mut out = String::new();
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+
+    fn message<'a>(set: &'a FileDescriptorSet, name: &str) -> &'a DescriptorProto {
+        set.file[0]
+            .message_type
+            .iter()
+            .find(|m| m.name() == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn descriptor_matches_the_service() {
+        let set = descriptor_set();
+        let file = &set.file[0];
+        assert_eq!(file.package(), "audio_sorter");
+        assert_eq!(file.message_type.len(), 10);
+        let service = &file.service[0];
+        assert_eq!(service.name(), "AudioSorter");
+        let methods: Vec<(&str, bool)> = service
+            .method
+            .iter()
+            .map(|m| (m.name(), m.server_streaming()))
+            .collect();
+        assert_eq!(
+            methods,
+            [
+                ("StartScan", false),
+                ("WatchScan", true),
+                ("ListTracks", false),
+                ("GetStats", false)
+            ]
+        );
+        assert_eq!(
+            service.method[1].output_type(),
+            ".audio_sorter.ScanProgress"
+        );
+
+        let year = &message(&set, "ListTracksRequest").field[1];
+        assert_eq!((year.name(), year.number()), ("year", 2));
+        assert_eq!(year.r#type(), Type::Uint32);
+        assert!(year.proto3_optional());
+        let decades = &message(&set, "Stats").field[2];
+        assert_eq!(decades.label(), Label::Repeated);
+        assert_eq!(decades.type_name(), ".audio_sorter.DecadeCount");
+        assert_eq!(json_name("recording_mbid"), "recordingMbid");
+
+        assert!(tonic_reflection::server::Builder::configure()
+            .register_file_descriptor_set(set)
+            .build_v1()
+            .is_ok());
+    }
+
+    /// Tags written by the hand-declared message, read off the wire.
+    fn wire_tags(bytes: &[u8]) -> Vec<i32> {
+        let mut buf = bytes;
+        let mut tags = Vec::new();
+        while !buf.is_empty() {
+            let key = prost::encoding::decode_varint(&mut buf).unwrap();
+            tags.push((key >> 3) as i32);
+            match key & 7 {
+                0 => {
+                    prost::encoding::decode_varint(&mut buf).unwrap();
+                }
+                1 => buf = &buf[8..],
+                2 => {
+                    let len = prost::encoding::decode_varint(&mut buf).unwrap() as usize;
+                    buf = &buf[len..];
+                }
+                5 => buf = &buf[4..],
+                wire => panic!("unexpected wire type {}", wire),
+            }
+        }
+        tags
+    }
+
+    #[test]
+    fn proto_tags_match_the_declared_messages() {
+        let set = descriptor_set();
+        let numbers = |name: &str| -> Vec<i32> {
+            message(&set, name)
+                .field
+                .iter()
+                .map(|f| f.number())
+                .collect()
+        };
+        let track = Track {
+            path: "a".into(),
+            title: "b".into(),
+            artist: "c".into(),
+            album: Some(String::new()),
+            genre: Some(String::new()),
+            year: Some(0),
+            duration: 1.0,
+            vocal_language: Some(String::new()),
+            file_size: 1,
+            recording_mbid: Some(String::new()),
+        };
+        assert_eq!(wire_tags(&track.encode_to_vec()), numbers("Track"));
+        let progress = ScanProgress {
+            is_scanning: true,
+            files_total: 1,
+            files_processed: 1,
+            current_file: "a".into(),
+            elapsed_secs: 1,
+            errors: 1,
+            cpu_usage: 1.0,
+            memory_usage: 1,
+        };
+        assert_eq!(
+            wire_tags(&progress.encode_to_vec()),
+            numbers("ScanProgress")
+        );
+    }
+}
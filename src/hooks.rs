@@ -0,0 +1,192 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::exit_status::JobCounts;
+use crate::organizer::TrackMetadata;
+
+/// Contents of `hooks.json` in the index directory, e.g.
+/// `{"on_track_identified": "python3 push.py", "on_scan_complete": "notify-send done"}`.
+/// Commands run through the shell (`sh -c`, `cmd /C` on Windows) with the
+/// event as JSON on stdin and its main fields in `AUDIO_SORTER_*` variables.
+/// A missing file means no hooks.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct HookSettings {
+    /// Run once per successfully processed file
+    pub on_track_identified: Option<String>,
+    /// Run when a scan finishes or is interrupted
+    pub on_scan_complete: Option<String>,
+    /// A hook still running after this long is killed
+    pub timeout_secs: u64,
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        Self {
+            on_track_identified: None,
+            on_scan_complete: None,
+            timeout_secs: 30,
+        }
+    }
+}
+
+impl HookSettings {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).context("Failed to read hook settings")?;
+        serde_json::from_str(&content).context("Failed to parse hook settings JSON")
+    }
+}
+
+fn shell(command: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    }
+    #[cfg(not(windows))]
+    {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+/// Run `command` with `payload` on stdin and `env` set, killing it after `timeout`.
+fn run(command: &str, payload: &str, env: &[(&str, String)], timeout: Duration) -> Result<()> {
+    let mut child = shell(command)
+        .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to start hook `{}`", command))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that ignores stdin closes it early; that is not an error
+        let _ = stdin.write_all(payload.as_bytes());
+    }
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            anyhow::ensure!(
+                status.success(),
+                "Hook `{}` exited with {}",
+                command,
+                status
+            );
+            return Ok(());
+        }
+        if started.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("Hook `{}` timed out after {:?}", command, timeout);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+enum Event {
+    Track(PathBuf, Box<TrackMetadata>),
+}
+
+/// Runs the hooks of one scan on a background thread, in the order events
+/// arrive, so a slow hook does not hold up the scan. Hook failures are
+/// printed and never fail the scan.
+pub struct HookRunner {
+    settings: HookSettings,
+    index_dir: PathBuf,
+    tx: Option<mpsc::Sender<Event>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl HookRunner {
+    /// Hooks from `hooks.json` in `index_dir`; none when it is missing or invalid.
+    pub fn load(index_dir: &Path) -> Self {
+        let settings = HookSettings::load(&index_dir.join("hooks.json")).unwrap_or_else(|e| {
+            eprintln!("Hooks disabled: {:#}", e);
+            HookSettings::default()
+        });
+        let (tx, worker) = match settings.on_track_identified.clone() {
+            Some(command) => {
+                let (tx, rx) = mpsc::channel::<Event>();
+                let timeout = Duration::from_secs(settings.timeout_secs);
+                let dir = index_dir.to_path_buf();
+                let worker = std::thread::spawn(move || {
+                    for Event::Track(path, meta) in rx {
+                        let payload = json!({
+                            "event": "track_identified",
+                            "index_dir": dir,
+                            "path": path,
+                            "metadata": meta,
+                        });
+                        let env = [
+                            ("AUDIO_SORTER_EVENT", "track_identified".to_string()),
+                            ("AUDIO_SORTER_PATH", path.to_string_lossy().into_owned()),
+                            ("AUDIO_SORTER_TITLE", meta.title.clone()),
+                            ("AUDIO_SORTER_ARTIST", meta.artist.clone()),
+                            ("AUDIO_SORTER_ALBUM", meta.album.clone().unwrap_or_default()),
+                        ];
+                        if let Err(e) = run(&command, &payload.to_string(), &env, timeout) {
+                            eprintln!("{:#}", e);
+                        }
+                    }
+                });
+                (Some(tx), Some(worker))
+            }
+            None => (None, None),
+        };
+        Self {
+            settings,
+            index_dir: index_dir.to_path_buf(),
+            tx,
+            worker,
+        }
+    }
+
+    /// Queue `on_track_identified` for a processed file.
+    pub fn track_identified(&self, path: &Path, meta: &TrackMetadata) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(Event::Track(path.to_path_buf(), Box::new(meta.clone())));
+        }
+    }
+
+    /// Wait for queued track hooks, then run `on_scan_complete`.
+    pub fn scan_complete(mut self, counts: &JobCounts) {
+        drop(self.tx.take());
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+        let Some(command) = &self.settings.on_scan_complete else {
+            return;
+        };
+        let payload = json!({
+            "event": "scan_complete",
+            "index_dir": self.index_dir,
+            "counts": counts,
+        });
+        let env = [
+            ("AUDIO_SORTER_EVENT", "scan_complete".to_string()),
+            (
+                "AUDIO_SORTER_INDEX_DIR",
+                self.index_dir.to_string_lossy().into_owned(),
+            ),
+            ("AUDIO_SORTER_PROCESSED", counts.processed.to_string()),
+            ("AUDIO_SORTER_ERRORS", counts.errors.to_string()),
+            ("AUDIO_SORTER_REMAINING", counts.remaining.to_string()),
+        ];
+        let timeout = Duration::from_secs(self.settings.timeout_secs);
+        if let Err(e) = run(command, &payload.to_string(), &env, timeout) {
+            eprintln!("{:#}", e);
+        }
+    }
+}
@@ -1,58 +1,96 @@
-pub const HTML_CONTENT: &str = r#"
+pub const HTML_CONTENT: &str = r##"
 <!DOCTYPE html>
 <html lang="en">
 <head>
     <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0, viewport-fit=cover">
+    <meta name="theme-color" content="#4f46e5">
+    <meta name="apple-mobile-web-app-capable" content="yes">
     <title>Audio Sorter Dashboard</title>
+    <link rel="manifest" href="__BASE_PATH__/manifest.webmanifest">
+    <link rel="icon" href="__BASE_PATH__/icon.svg" type="image/svg+xml">
+    <link rel="apple-touch-icon" href="__BASE_PATH__/icon.svg">
     <script src="https://cdn.tailwindcss.com"></script>
     <script src="https://unpkg.com/vue@3/dist/vue.global.js"></script>
     <script src="https://cdn.jsdelivr.net/npm/chart.js"></script>
 </head>
 <body class="bg-gray-100 text-gray-800">
-    <div id="app" class="min-h-screen p-8">
-        <header class="mb-8 flex justify-between items-center bg-white p-4 rounded-lg shadow">
+    <div id="app" class="min-h-screen p-3 md:p-8">
+        <header class="mb-4 md:mb-8 flex flex-col md:flex-row md:justify-between md:items-center gap-3 bg-white p-4 rounded-lg shadow">
             <div>
-                <h1 class="text-3xl font-bold text-indigo-600">Audio Library Dashboard</h1>
+                <h1 class="text-2xl md:text-3xl font-bold text-indigo-600">{{ t('app.title') }}</h1>
                 <div class="text-sm text-gray-500 mt-1">
-                    Loaded {{ tracks.length }} tracks
+                    {{ t('app.loaded', { n: tracks.length }) }}
                 </div>
             </div>
-            <div class="flex space-x-4">
+            <div class="flex items-center gap-2 md:gap-4 overflow-x-auto whitespace-nowrap -mx-4 px-4 md:mx-0 md:px-0">
                 <button 
                     @click="activeTab = 'library'" 
-                    class="px-4 py-2 rounded font-medium"
+                    class="shrink-0 px-3 md:px-4 py-2 rounded font-medium"
                     :class="activeTab === 'library' ? 'bg-indigo-100 text-indigo-700' : 'text-gray-600 hover:bg-gray-50'">
-                    Library
+                    {{ t('tab.library') }}
                 </button>
                 <button 
                     @click="activeTab = 'duplicates'" 
-                    class="px-4 py-2 rounded font-medium"
+                    class="shrink-0 px-3 md:px-4 py-2 rounded font-medium"
                     :class="activeTab === 'duplicates' ? 'bg-indigo-100 text-indigo-700' : 'text-gray-600 hover:bg-gray-50'">
-                    Duplicates ({{ duplicateGroups.length }})
+                    {{ t('tab.duplicates', { n: duplicateGroups.length }) }}
                 </button>
-                <div class="border-l pl-4"></div>
+                <button 
+                    @click="activeTab = 'works'; fetchWorks()" 
+                    class="shrink-0 px-3 md:px-4 py-2 rounded font-medium"
+                    :class="activeTab === 'works' ? 'bg-indigo-100 text-indigo-700' : 'text-gray-600 hover:bg-gray-50'">
+                    {{ t('tab.covers', { n: works.length }) }}
+                </button>
+                <button 
+                    @click="activeTab = 'recent'; fetchRecent()" 
+                    class="shrink-0 px-3 md:px-4 py-2 rounded font-medium"
+                    :class="activeTab === 'recent' ? 'bg-indigo-100 text-indigo-700' : 'text-gray-600 hover:bg-gray-50'">
+                    {{ t('tab.recent') }}
+                </button>
+                <button 
+                    @click="activeTab = 'albums'; fetchConsistency()" 
+                    class="shrink-0 px-3 md:px-4 py-2 rounded font-medium"
+                    :class="activeTab === 'albums' ? 'bg-indigo-100 text-indigo-700' : 'text-gray-600 hover:bg-gray-50'">
+                    {{ t('tab.albums') }}
+                </button>
+                <button 
+                    @click="activeTab = 'issues'; fetchIssues()" 
+                    class="shrink-0 px-3 md:px-4 py-2 rounded font-medium"
+                    :class="activeTab === 'issues' ? 'bg-indigo-100 text-indigo-700' : 'text-gray-600 hover:bg-gray-50'">
+                    {{ t('tab.issues') }}
+                </button>
+                <button 
+                    @click="activeTab = 'storage'; fetchTimeline()" 
+                    class="shrink-0 px-3 md:px-4 py-2 rounded font-medium"
+                    :class="activeTab === 'storage' ? 'bg-indigo-100 text-indigo-700' : 'text-gray-600 hover:bg-gray-50'">
+                    {{ t('tab.storage') }}
+                </button>
+                <div class="hidden md:block border-l pl-4"></div>
                 <button 
                     @click="startScan" 
                     :disabled="isScanning"
-                    class="bg-indigo-600 text-white px-4 py-2 rounded hover:bg-indigo-700 disabled:opacity-50 disabled:cursor-not-allowed flex items-center">
+                    class="shrink-0 bg-indigo-600 text-white px-4 py-2 rounded hover:bg-indigo-700 disabled:opacity-50 disabled:cursor-not-allowed flex items-center">
                     <span v-if="isScanning" class="mr-2 animate-spin">⟳</span>
-                    {{ isScanning ? 'Scanning...' : 'Scan Library' }}
+                    {{ isScanning ? t('scan.scanning') : t('scan.start') }}
                 </button>
+                <select :value="locale" @change="setLocale($event.target.value)" :title="t('app.language')" class="shrink-0 p-2 border border-gray-300 rounded text-sm">
+                    <option v-for="l in locales" :key="l" :value="l">{{ LANGUAGE_NAMES[l] || l }}</option>
+                </select>
             </div>
         </header>
 
         <!-- Scan Status Panel -->
-        <div v-if="isScanning || scanStatus.elapsed_secs > 0" class="bg-white p-6 rounded-lg shadow mb-8 border-l-4 border-indigo-500">
+        <div v-if="isScanning || scanStatus.elapsed_secs > 0" class="bg-white p-4 md:p-6 rounded-lg shadow mb-4 md:mb-8 border-l-4 border-indigo-500">
             <h2 class="text-lg font-bold mb-4 flex justify-between">
-                <span>Scan Progress</span>
-                <span class="text-sm font-normal text-gray-500">Elapsed: {{ formatTime(scanStatus.elapsed_secs) }}</span>
+                <span>{{ t('scan.progress') }}</span>
+                <span class="text-sm font-normal text-gray-500">{{ t('scan.elapsed', { time: formatTime(scanStatus.elapsed_secs) }) }}</span>
             </h2>
             
             <div class="mb-4">
                 <div class="flex justify-between text-sm mb-1">
-                    <span>Processed: {{ scanStatus.files_processed }} / {{ scanStatus.files_total || '?' }}</span>
-                    <span>Errors: {{ scanStatus.errors }}</span>
+                    <span>{{ t('scan.processed', { done: scanStatus.files_processed, total: scanStatus.files_total || '?' }) }}</span>
+                    <span>{{ t('scan.errors', { n: scanStatus.errors }) }}</span>
                 </div>
                 <!-- Progress Bar -->
                 <div class="w-full bg-gray-200 rounded-full h-2.5">
@@ -60,21 +98,24 @@ pub const HTML_CONTENT: &str = r#"
                          :style="{ width: percentComplete + '%' }"></div>
                 </div>
                 <div class="text-xs text-gray-500 mt-1 truncate">
-                    Currently: {{ scanStatus.current_file }}
+                    {{ t('scan.current', { file: scanStatus.current_file }) }}
+                </div>
+                <div v-if="scanStatus.online_suspended" class="text-xs text-yellow-800 bg-yellow-50 border border-yellow-200 rounded mt-2 p-2">
+                    {{ t('scan.suspended', { reason: scanStatus.online_suspended }) }} <code>lookups flush</code>
                 </div>
             </div>
 
             <!-- Resource Monitor -->
             <div class="grid grid-cols-2 gap-4">
                 <div class="bg-gray-50 p-3 rounded">
-                    <span class="text-xs text-gray-500 uppercase">CPU Usage</span>
+                    <span class="text-xs text-gray-500 uppercase">{{ t('scan.cpu') }}</span>
                     <div class="text-xl font-mono">{{ scanStatus.resources.cpu_usage.toFixed(1) }}%</div>
                     <div class="w-full bg-gray-200 h-1 mt-1 rounded">
                          <div class="bg-green-500 h-1 rounded transition-all duration-500" :style="{ width: Math.min(scanStatus.resources.cpu_usage, 100) + '%' }"></div>
                     </div>
                 </div>
                 <div class="bg-gray-50 p-3 rounded">
-                    <span class="text-xs text-gray-500 uppercase">Memory Usage</span>
+                    <span class="text-xs text-gray-500 uppercase">{{ t('scan.memory') }}</span>
                     <div class="text-xl font-mono">{{ formatBytes(scanStatus.resources.memory_usage) }}</div>
                 </div>
             </div>
@@ -83,84 +124,174 @@ pub const HTML_CONTENT: &str = r#"
         <!-- Library View -->
         <div v-show="activeTab === 'library'">
             <!-- Stats Cards -->
-            <div class="grid grid-cols-1 md:grid-cols-3 gap-6 mb-8">
+            <div class="grid grid-cols-1 md:grid-cols-3 gap-3 md:gap-6 mb-4 md:mb-8">
                 <div class="bg-white p-6 rounded-lg shadow">
-                    <h3 class="text-gray-500 text-sm font-uppercase">Total Tracks</h3>
+                    <h3 class="text-gray-500 text-sm font-uppercase">{{ t('stats.total_tracks') }}</h3>
                     <p class="text-4xl font-bold mt-2">{{ tracks.length }}</p>
                 </div>
                 <div class="bg-white p-6 rounded-lg shadow">
-                    <h3 class="text-gray-500 text-sm font-uppercase">Total Library Size</h3>
+                    <h3 class="text-gray-500 text-sm font-uppercase">{{ t('stats.total_size') }}</h3>
                     <p class="text-4xl font-bold mt-2">{{ formatBytes(totalSize) }}</p>
                 </div>
                  <div class="bg-white p-6 rounded-lg shadow">
-                    <h3 class="text-gray-500 text-sm font-uppercase">Unique Artists</h3>
+                    <h3 class="text-gray-500 text-sm font-uppercase">{{ t('stats.unique_artists') }}</h3>
                     <p class="text-4xl font-bold mt-2">{{ uniqueArtists }}</p>
                 </div>
             </div>
 
             <!-- Search Bar -->
-            <div class="bg-white p-4 rounded-lg shadow mb-6">
+            <div class="bg-white p-4 rounded-lg shadow mb-6 flex flex-col sm:flex-row gap-2 sm:gap-4">
                 <input 
                     v-model="searchQuery" 
                     type="text" 
-                    placeholder="Search by artist, title, or album..." 
-                    class="w-full p-2 border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-indigo-500"
+                    :placeholder="t('library.search')" 
+                    class="flex-1 p-2 border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-indigo-500"
                 >
+                <select v-model="decadeFilter" class="p-2 border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-indigo-500">
+                    <option value="">{{ t('library.all_decades') }}</option>
+                    <option v-for="d in decades" :key="d.decade" :value="d.decade">{{ t('library.decade', { decade: d.decade, n: d.count }) }}</option>
+                </select>
+                <div class="flex items-center gap-1 text-sm text-gray-600">
+                    <input v-model.number="minMinutes" type="number" min="0" :placeholder="t('library.min_minutes')" class="w-20 p-2 border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-indigo-500">
+                    <span>–</span>
+                    <input v-model.number="maxMinutes" type="number" min="0" :placeholder="t('library.max_minutes')" class="w-20 p-2 border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-indigo-500">
+                </div>
+                <details class="relative">
+                    <summary class="p-2 border border-gray-300 rounded cursor-pointer select-none list-none">{{ formatFilter.length ? formatFilter.join(', ') : t('library.all_formats') }}</summary>
+                    <div class="absolute right-0 z-10 mt-1 w-40 bg-white border border-gray-200 rounded shadow p-2">
+                        <label v-for="f in formats" :key="f" class="flex items-center space-x-2 py-1 text-sm">
+                            <input type="checkbox" :value="f" v-model="formatFilter">
+                            <span>{{ f }}</span>
+                        </label>
+                    </div>
+                </details>
+                <span v-if="genreFilter" class="self-center inline-flex items-center px-3 py-1 text-sm rounded-full bg-blue-100 text-blue-800">
+                    {{ t('library.genre_filter', { genre: genreFilter }) }}
+                    <button @click="filterGenre('')" class="ml-2 font-bold hover:text-blue-600" :title="t('library.clear_filter')">×</button>
+                </span>
+                <details class="relative">
+                    <summary class="p-2 border border-gray-300 rounded cursor-pointer select-none list-none">{{ t('library.columns') }}</summary>
+                    <div class="absolute right-0 z-10 mt-1 w-48 bg-white border border-gray-200 rounded shadow p-2">
+                        <label v-for="c in COLUMNS" :key="c" class="flex items-center space-x-2 py-1 text-sm">
+                            <input type="checkbox" :checked="showColumn(c)" @change="toggleColumn(c)">
+                            <span>{{ t('column.' + c) }}</span>
+                        </label>
+                    </div>
+                </details>
+            </div>
+
+            <!-- Filter Presets -->
+            <div class="flex flex-wrap items-center gap-2 mb-6">
+                <span v-for="p in presets" :key="p.name" class="inline-flex items-center px-3 py-1 text-sm rounded-full bg-indigo-100 text-indigo-800">
+                    <button @click="applyPreset(p)" class="hover:underline">{{ p.name }}</button>
+                    <button @click="deletePreset(p)" class="ml-2 font-bold hover:text-indigo-600" :title="t('library.delete_preset')">×</button>
+                </span>
+                <button @click="savePreset" class="px-3 py-1 text-sm rounded-full border border-dashed border-indigo-400 text-indigo-600 hover:bg-indigo-50">+ {{ t('library.save_preset') }}</button>
+            </div>
+
+            <!-- Bulk Edit -->
+            <div v-if="selectedIds.length > 0" class="bg-white p-4 rounded-lg shadow mb-6 border-l-4 border-indigo-500">
+                <div class="flex items-center justify-between mb-3">
+                    <span class="font-semibold">{{ t('bulk.selected', { n: selectedIds.length }) }}</span>
+                    <div class="flex items-center gap-3">
+                        <button @click="downloadSelected" :disabled="downloading" class="text-sm text-indigo-600 hover:underline disabled:opacity-50">⬇ {{ downloading ? t('bulk.downloading') : t('bulk.download') }}</button>
+                        <button @click="clearSelection" class="text-sm text-gray-500 hover:underline">{{ t('bulk.clear') }}</button>
+                    </div>
+                </div>
+                <p class="text-xs text-gray-500 mb-2">{{ t('bulk.hint') }}</p>
+                <div class="grid grid-cols-1 md:grid-cols-4 gap-3 mb-3">
+                    <input v-model="bulkFields.artist" :placeholder="t('field.artist')" class="p-2 border border-gray-300 rounded">
+                    <input v-model="bulkFields.album" :placeholder="t('field.album')" class="p-2 border border-gray-300 rounded">
+                    <input v-model="bulkFields.genre" :placeholder="t('field.genre')" class="p-2 border border-gray-300 rounded">
+                    <input v-model.number="bulkFields.year" type="number" :placeholder="t('field.year')" class="p-2 border border-gray-300 rounded">
+                </div>
+                <div class="flex space-x-2">
+                    <button @click="runBulkEdit(true)" class="px-4 py-2 rounded border border-indigo-600 text-indigo-600 hover:bg-indigo-50">{{ t('bulk.preview') }}</button>
+                    <button @click="runBulkEdit(false)" :disabled="!bulkPreview" class="px-4 py-2 rounded bg-indigo-600 text-white hover:bg-indigo-700 disabled:opacity-50">{{ t('bulk.apply') }}</button>
+                </div>
+                <p v-if="bulkMessage" class="text-sm mt-3" :class="bulkMessageError ? 'text-red-600' : 'text-green-700'">{{ bulkMessage }}</p>
+                <table v-if="bulkPreview && bulkPreview.length" class="w-full text-sm mt-3">
+                    <tr v-for="c in bulkPreview" :key="c.id + c.field" class="border-t">
+                        <td class="py-1 pr-4 text-gray-500">{{ fileName(c.path) }}</td>
+                        <td class="py-1 pr-4">{{ c.field }}</td>
+                        <td class="py-1 pr-4 line-through text-red-600">{{ c.old || t('common.empty') }}</td>
+                        <td class="py-1 text-green-700">{{ c.new || t('common.empty') }}</td>
+                    </tr>
+                </table>
+            </div>
+            <div v-if="lastEditUndoable" class="bg-green-50 p-3 rounded-lg mb-6 flex items-center justify-between text-sm">
+                <span class="text-green-800">{{ t('bulk.applied') }}</span>
+                <button @click="undoBulkEdit" class="px-3 py-1 rounded bg-white border border-green-600 text-green-700 hover:bg-green-100">{{ t('common.undo') }}</button>
             </div>
 
             <!-- Data Table -->
-            <div class="bg-white rounded-lg shadow overflow-hidden">
+            <div class="bg-white rounded-lg shadow overflow-x-auto">
                 <table class="min-w-full leading-normal">
                     <thead>
                         <tr>
-                            <th class="px-5 py-3 border-b-2 border-gray-200 bg-gray-50 text-left text-xs font-semibold text-gray-600 uppercase tracking-wider">Title</th>
-                            <th class="px-5 py-3 border-b-2 border-gray-200 bg-gray-50 text-left text-xs font-semibold text-gray-600 uppercase tracking-wider">Artist</th>
-                            <th class="px-5 py-3 border-b-2 border-gray-200 bg-gray-50 text-left text-xs font-semibold text-gray-600 uppercase tracking-wider">Album</th>
-                            <th class="px-5 py-3 border-b-2 border-gray-200 bg-gray-50 text-left text-xs font-semibold text-gray-600 uppercase tracking-wider">Original Artist</th>
-                            <th class="px-5 py-3 border-b-2 border-gray-200 bg-gray-50 text-left text-xs font-semibold text-gray-600 uppercase tracking-wider">Size</th>
-                            <th class="px-5 py-3 border-b-2 border-gray-200 bg-gray-50 text-center text-xs font-semibold text-gray-600 uppercase tracking-wider">Actions</th>
+                            <th class="pl-3 md:pl-5 py-3 border-b-2 border-gray-200 bg-gray-50 w-8"><input type="checkbox" :checked="allVisibleSelected" @change="toggleAllVisible" :title="t('library.select_all')"></th>
+                            <th @click="sortBy('title')" class="px-3 md:px-5 py-3 border-b-2 border-gray-200 bg-gray-50 text-left text-xs font-semibold text-gray-600 uppercase tracking-wider cursor-pointer select-none hover:text-indigo-600">{{ t('field.title') }}{{ sortIndicator('title') }}</th>
+                            <th v-for="c in columns" :key="c" @click="sortBy(c)"
+                                class="px-3 md:px-5 py-3 border-b-2 border-gray-200 bg-gray-50 text-left text-xs font-semibold text-gray-600 uppercase tracking-wider cursor-pointer select-none hover:text-indigo-600"
+                                :class="c === 'artist' ? '' : 'hidden md:table-cell'">{{ t('column.' + c) }}{{ sortIndicator(c) }}</th>
+                            <th class="px-3 md:px-5 py-3 border-b-2 border-gray-200 bg-gray-50 text-center text-xs font-semibold text-gray-600 uppercase tracking-wider">{{ t('field.actions') }}</th>
                         </tr>
                     </thead>
                     <tbody>
                         <tr v-for="track in filteredTracks" :key="track.path">
-                            <td class="px-5 py-5 border-b border-gray-200 bg-white text-sm">
+                            <td class="pl-3 md:pl-5 py-3 md:py-5 border-b border-gray-200 bg-white">
+                                <input type="checkbox" :value="track.id" v-model="selectedIds">
+                            </td>
+                            <td class="px-3 md:px-5 py-3 md:py-5 border-b border-gray-200 bg-white text-sm">
                                 <div class="flex items-center">
-                                    <div class="ml-3">
+                                    <div class="md:ml-3 min-w-0">
                                         <p class="text-gray-900 whitespace-no-wrap font-medium">
-                                            {{ track.metadata.title || 'Unknown Title' }}
+                                            <a :href="`${BASE_PATH}/track/${track.id}`" @click.prevent="openTrack(track)" class="hover:text-indigo-600 hover:underline">{{ track.metadata.title || t('track.unknown_title') }}</a>
+                                            <span v-if="track.metadata.content_kind === 'spoken_word'" class="ml-2 px-2 text-xs font-semibold rounded-full bg-yellow-100 text-yellow-800">{{ t('content.spoken_word') }}</span>
+                                        </p>
+                                        <p v-if="track.metadata.album" class="md:hidden text-gray-500 text-xs">{{ track.metadata.album }}</p>
+                                        <p v-if="!showColumn('path')" class="text-gray-400 text-xs break-all">{{ track.path }}</p>
+                                        <div v-if="!showColumn('genre') && track.genres.length" class="flex flex-wrap gap-1 mt-1">
+                                            <button v-for="g in track.genres" :key="g" @click="filterGenre(g)" class="px-2 text-xs leading-5 rounded-full bg-blue-100 text-blue-800 hover:bg-blue-200" :title="t('library.filter_genre')">{{ g }}</button>
+                                        </div>
+                                        <p v-if="track.metadata.vocal_pair" class="text-pink-600 text-xs" :title="track.metadata.vocal_pair">
+                                            🎤 {{ track.metadata.vocal_language === 'zxx' ? t('track.vocal_version') : t('track.off_vocal_version') }}: {{ fileName(track.metadata.vocal_pair) }}
                                         </p>
-                                        <p class="text-gray-400 text-xs">{{ track.path }}</p>
                                     </div>
                                 </div>
                             </td>
-                            <td class="px-5 py-5 border-b border-gray-200 bg-white text-sm">
-                                <p class="text-gray-900 whitespace-no-wrap">{{ track.metadata.artist || 'Unknown Artist' }}</p>
-                            </td>
-                            <td class="px-5 py-5 border-b border-gray-200 bg-white text-sm">
-                                <p class="text-gray-900 whitespace-no-wrap">{{ track.metadata.album || '-' }}</p>
-                            </td>
-                             <td class="px-5 py-5 border-b border-gray-200 bg-white text-sm">
-                                <span v-if="track.metadata.original_artist" class="px-2 inline-flex text-xs leading-5 font-semibold rounded-full bg-green-100 text-green-800">
-                                    {{ track.metadata.original_artist }}
-                                </span>
-                                 <span v-else class="text-gray-400">-</span>
-                            </td>
-                            <td class="px-5 py-5 border-b border-gray-200 bg-white text-sm">
-                                <p class="text-gray-900 whitespace-no-wrap">{{ formatBytes(track.file_size) }}</p>
+                            <td v-for="c in columns" :key="c" class="px-3 md:px-5 py-3 md:py-5 border-b border-gray-200 bg-white text-sm"
+                                :class="c === 'artist' ? '' : 'hidden md:table-cell'">
+                                <template v-if="c === 'original_artist'">
+                                    <span v-if="track.metadata.original_artist" @click="showWork(track)" class="px-2 inline-flex text-xs leading-5 font-semibold rounded-full bg-green-100 text-green-800 cursor-pointer hover:bg-green-200" :title="t('library.show_work')">
+                                        {{ track.metadata.original_artist }}
+                                    </span>
+                                    <span v-else class="text-gray-400">-</span>
+                                </template>
+                                <template v-else-if="c === 'album'">
+                                    <p class="text-gray-900 whitespace-no-wrap">{{ track.metadata.album || '-' }}</p>
+                                    <p v-if="track.metadata.year && !showColumn('year')" class="text-gray-400 text-xs">{{ track.metadata.year }}</p>
+                                </template>
+                                <div v-else-if="c === 'genre'" class="flex flex-wrap gap-1">
+                                    <button v-for="g in track.genres" :key="g" @click="filterGenre(g)" class="px-2 text-xs leading-5 rounded-full bg-blue-100 text-blue-800 hover:bg-blue-200" :title="t('library.filter_genre')">{{ g }}</button>
+                                    <span v-if="!track.genres.length" class="text-gray-400">-</span>
+                                </div>
+                                <p v-else-if="c === 'path'" class="text-gray-500 text-xs break-all">{{ track.path }}</p>
+                                <p v-else class="text-gray-900 whitespace-no-wrap">{{ cellText(track, c) }}</p>
                             </td>
-                            <td class="px-5 py-5 border-b border-gray-200 bg-white text-sm text-center">
-                                <button @click="findSimilar(track)" class="bg-purple-500 hover:bg-purple-600 text-white text-xs px-3 py-1 rounded transition-colors" title="Find Similar Songs">
-                                    🎵 Similar
+                            <td class="px-3 md:px-5 py-3 md:py-5 border-b border-gray-200 bg-white text-sm text-center">
+                                <button @click="findSimilar(track)" class="bg-purple-500 hover:bg-purple-600 text-white text-xs px-3 py-1 rounded transition-colors" :title="t('library.similar_hint')">
+                                    🎵 {{ t('library.similar') }}
                                 </button>
                             </td>
                         </tr>
                     </tbody>
                 </table>
                  <div v-if="filteredTracks.length === 0" class="p-4 text-center text-gray-500">
-                    No tracks found matching your search.
+                    {{ t('library.no_match') }}
                 </div>
                  <div v-if="filteredTracks.length >= 100" class="p-2 text-center text-xs text-gray-400 bg-gray-50">
-                    Showing first 100 matches ({{ filteredTracks.length }} total)
+                    {{ t('library.first_100', { n: filteredTracks.length }) }}
                 </div>
             </div>
         </div>
@@ -168,24 +299,39 @@ pub const HTML_CONTENT: &str = r#"
         <!-- Duplicates View -->
         <div v-show="activeTab === 'duplicates'">
             <div v-if="duplicateGroups.length === 0" class="bg-white p-8 rounded-lg shadow text-center text-gray-500">
-                <h3 class="text-xl font-medium">No Duplicates Found</h3>
-                <p class="mt-2">Runs a scan to detect duplicate files based on audio fingerprints.</p>
+                <h3 class="text-xl font-medium">{{ t('dup.none') }}</h3>
+                <p class="mt-2">{{ t('dup.none_hint') }}</p>
             </div>
             
             <div v-else class="space-y-6">
-                <div v-for="(group, idx) in duplicateGroups" :key="idx" class="bg-white rounded-lg shadow overflow-hidden">
+                <div v-for="(group, idx) in duplicateGroups" :key="idx" class="bg-white rounded-lg shadow overflow-x-auto">
                     <div class="bg-red-50 px-4 py-2 border-b border-red-100 flex justify-between items-center">
-                        <span class="text-red-800 font-medium">Duplicate Group #{{ idx + 1 }}</span>
-                        <span class="text-xs text-red-600 bg-red-100 px-2 py-1 rounded">{{ group.length }} files</span>
+                        <span class="text-red-800 font-medium">{{ t('dup.group', { n: idx + 1 }) }}</span>
+                        <span class="text-xs text-red-600 bg-red-100 px-2 py-1 rounded">{{ t('dup.files', { n: group.length }) }}</span>
                     </div>
                     <table class="min-w-full">
                         <tbody>
-                            <tr v-for="track in group" :key="track.path" class="border-b last:border-0 hover:bg-gray-50">
+                            <tr v-for="track in group" :key="track.path" class="border-b last:border-0 hover:bg-gray-50" :class="track.is_best ? 'bg-green-50' : ''">
                                 <td class="px-4 py-3 text-sm">
-                                    <div class="font-medium">{{ track.metadata.title }}</div>
-                                    <div class="text-xs text-gray-500">{{ track.path }}</div>
+                                    <div class="font-medium">
+                                        {{ track.metadata.title }}
+                                        <span v-if="track.is_best" class="ml-2 px-2 text-xs font-semibold rounded-full bg-green-100 text-green-800">{{ t('dup.best') }}</span>
+                                    </div>
+                                    <div class="text-xs text-gray-500 break-all">{{ track.path }}</div>
+                                    <div class="text-xs text-gray-600 mt-1">{{ track.reasons.join(' · ') }}</div>
+                                </td>
+                                <td class="hidden sm:table-cell px-4 py-3 text-sm text-right text-gray-500">
+                                    <div v-if="track.metadata.quality">
+                                        {{ t('dup.clipping', { pct: (track.metadata.quality.clipping_ratio * 100).toFixed(2) }) }}
+                                    </div>
+                                    <div v-if="track.metadata.quality" class="text-xs">
+                                        {{ t('dup.loudness_war', { score: track.metadata.quality.loudness_war_score.toFixed(0) }) }}
+                                    </div>
                                 </td>
-                                <td class="px-4 py-3 text-sm text-right">
+                                <td class="px-4 py-3 text-sm text-right font-mono">
+                                    {{ track.quality_score != null ? track.quality_score.toFixed(1) : '-' }}
+                                </td>
+                                <td class="hidden sm:table-cell px-4 py-3 text-sm text-right">
                                     {{ track.metadata.artist }}
                                 </td>
                                 <td class="px-4 py-3 text-sm text-right text-gray-500">
@@ -201,32 +347,289 @@ pub const HTML_CONTENT: &str = r#"
             </div>
         </div>
 
+        <!-- Works (Original vs. Covers) View -->
+        <div v-show="activeTab === 'works'">
+            <div v-if="works.length === 0" class="bg-white p-8 rounded-lg shadow text-center text-gray-500">
+                <h3 class="text-xl font-medium">{{ t('works.none') }}</h3>
+                <p class="mt-2">{{ t('works.none_hint') }}</p>
+            </div>
+
+            <div v-else class="space-y-6">
+                <div v-for="work in works" :key="work.key" :id="'work-' + work.key"
+                     class="bg-white rounded-lg shadow overflow-x-auto"
+                     :class="selectedWorkKey === work.key ? 'ring-2 ring-indigo-500' : ''">
+                    <div class="bg-green-50 px-4 py-2 border-b border-green-100 flex justify-between items-center">
+                        <span class="text-green-800 font-medium">{{ work.title }} <span class="text-green-600 font-normal">{{ t('works.originally_by', { artist: work.original_artist }) }}<span v-if="work.original_year"> ({{ work.original_year }})</span></span></span>
+                        <span class="text-xs text-green-700 bg-green-100 px-2 py-1 rounded">{{ t('works.covers', { n: work.covers.length }) }}</span>
+                    </div>
+                    <table class="min-w-full">
+                        <tbody>
+                            <tr class="border-b bg-gray-50">
+                                <td class="px-4 py-2 text-xs uppercase text-gray-500 w-24">{{ t('works.original') }}</td>
+                                <td v-if="work.original" class="px-4 py-2 text-sm">
+                                    <div class="font-medium">{{ work.original.title }} — {{ work.original.artist }}</div>
+                                    <div class="text-xs text-gray-500">{{ work.original.path }}</div>
+                                </td>
+                                <td v-else class="px-4 py-2 text-sm text-gray-400">{{ t('works.not_in_library') }}</td>
+                            </tr>
+                            <tr v-for="cover in work.covers" :key="cover.path" class="border-b last:border-0 hover:bg-gray-50">
+                                <td class="px-4 py-2 text-xs uppercase text-gray-500">{{ t('works.cover') }}</td>
+                                <td class="px-4 py-2 text-sm">
+                                    <div class="font-medium">{{ cover.title }} — {{ cover.artist }}</div>
+                                    <div class="text-xs text-gray-500">{{ cover.path }}</div>
+                                </td>
+                            </tr>
+                        </tbody>
+                    </table>
+                </div>
+            </div>
+        </div>
+
+        <!-- Album Consistency View -->
+        <div v-show="activeTab === 'albums'">
+            <div v-if="lastEditUndoable" class="bg-green-50 p-3 rounded-lg mb-6 flex items-center justify-between text-sm">
+                <span class="text-green-800">{{ bulkMessage || t('albums.fix_applied') }}</span>
+                <button @click="undoBulkEdit().then(fetchConsistency)" class="px-3 py-1 rounded bg-white border border-green-600 text-green-700 hover:bg-green-100">{{ t('common.undo') }}</button>
+            </div>
+            <div v-if="consistency.folders.length === 0 && consistency.scattered.length === 0" class="bg-white p-8 rounded-lg shadow text-center text-gray-500">
+                {{ t('albums.all_agree') }}
+            </div>
+            <div v-for="issue in consistency.folders" :key="issue.folder" class="bg-white rounded-lg shadow mb-4 p-4">
+                <div class="font-medium">{{ issue.folder }}</div>
+                <div class="text-xs text-gray-500 mb-2">
+                    {{ t('albums.tracks', { n: issue.track_count }) }}
+                    <span v-if="issue.mixed_albums" class="ml-2 px-2 font-semibold rounded-full bg-yellow-100 text-yellow-800">{{ t('albums.mixed') }}</span>
+                </div>
+                <div v-for="d in issue.disagreements" :key="d.field" class="flex flex-col sm:flex-row sm:items-center sm:justify-between gap-2 border-t py-2 text-sm">
+                    <div>
+                        <span class="font-semibold mr-2">{{ t('field.' + d.field) }}</span>
+                        <span v-for="v in d.values" :key="v.value" class="mr-3" :class="v.value === d.majority ? 'text-green-700' : 'text-red-600'">
+                            {{ v.value || t('common.missing') }} × {{ v.count }}
+                        </span>
+                    </div>
+                    <button v-if="d.majority !== '' && !(d.field === 'album' && issue.mixed_albums)"
+                            @click="applyMajority(d)"
+                            class="px-3 py-1 rounded bg-indigo-600 text-white text-xs hover:bg-indigo-700">
+                        {{ t('albums.apply_majority', { value: d.majority, n: d.dissenting_ids.length }) }}
+                    </button>
+                </div>
+            </div>
+            <div v-if="consistency.scattered.length" class="bg-white rounded-lg shadow p-4">
+                <h3 class="font-semibold mb-2">{{ t('albums.scattered') }}</h3>
+                <div v-for="a in consistency.scattered" :key="a.artist + a.album" class="border-t py-2 text-sm">
+                    <div class="font-medium">{{ a.artist }} — {{ a.album }}</div>
+                    <div v-for="f in a.folders" :key="f" class="text-xs text-gray-500">{{ f }}</div>
+                </div>
+            </div>
+        </div>
+
+        <!-- Needs Attention View -->
+        <div v-show="activeTab === 'issues'">
+            <div class="bg-white p-4 rounded-lg shadow mb-6 flex flex-wrap gap-2 text-sm">
+                <button @click="issueKind = ''" class="px-3 py-1 rounded-full"
+                        :class="issueKind === '' ? 'bg-indigo-600 text-white' : 'bg-gray-100 text-gray-700'">
+                    {{ t('issues.all', { n: issueTotal }) }}
+                </button>
+                <button v-for="(count, kind) in issueReport.counts" :key="kind" @click="issueKind = kind" class="px-3 py-1 rounded-full"
+                        :class="issueKind === kind ? 'bg-indigo-600 text-white' : 'bg-gray-100 text-gray-700'">
+                    {{ t('issue_kind.' + kind) }} ({{ count }})
+                </button>
+            </div>
+            <div v-if="bulkMessage" class="p-3 rounded-lg mb-6 text-sm" :class="bulkMessageError ? 'bg-red-50 text-red-700' : 'bg-green-50 text-green-800'">
+                {{ bulkMessage }}
+                <button v-if="lastEditUndoable && !bulkMessageError" @click="undoBulkEdit().then(fetchIssues)" class="ml-3 underline">{{ t('common.undo') }}</button>
+            </div>
+            <div class="bg-white rounded-lg shadow overflow-x-auto">
+                <div v-for="(issue, i) in visibleIssues" :key="issue.kind + issue.path + i" class="border-b last:border-0 p-3 flex flex-col sm:flex-row sm:items-center sm:justify-between gap-2 text-sm">
+                    <div class="min-w-0">
+                        <span class="px-2 mr-2 text-xs font-semibold rounded-full bg-yellow-100 text-yellow-800">{{ t('issue_kind.' + issue.kind) }}</span>
+                        <span>{{ issue.message }}</span>
+                        <div class="text-xs text-gray-500 truncate">{{ issue.path }}</div>
+                    </div>
+                    <div class="flex space-x-2 shrink-0 sm:ml-4">
+                        <button v-if="issue.fix" @click="applyIssueFix(issue)" class="px-3 py-1 rounded bg-indigo-600 text-white text-xs hover:bg-indigo-700">{{ t('issues.apply_fix') }}</button>
+                        <button v-if="issue.track_id" @click="editIssueTrack(issue)" class="px-3 py-1 rounded border text-xs hover:bg-gray-50">{{ t('issues.edit_tags') }}</button>
+                        <button v-if="issue.track_id" @click="openTrack({ id: issue.track_id })" class="px-3 py-1 rounded border text-xs hover:bg-gray-50">{{ t('issues.details') }}</button>
+                    </div>
+                </div>
+                <div v-if="visibleIssues.length === 0" class="p-4 text-center text-gray-500">{{ t('issues.none') }}</div>
+            </div>
+        </div>
+
+        <!-- Storage Analytics View -->
+        <div v-show="activeTab === 'storage'">
+            <div class="bg-white p-6 rounded-lg shadow mb-6">
+                <h3 class="font-semibold mb-3">{{ t('storage.growth') }}</h3>
+                <canvas ref="growthCanvas" height="90"></canvas>
+                <p v-if="storage.snapshots.length === 0" class="text-sm text-gray-500">{{ t('storage.no_scans') }}</p>
+            </div>
+            <div class="grid grid-cols-1 md:grid-cols-3 gap-6">
+                <div class="bg-white p-6 rounded-lg shadow">
+                    <h3 class="font-semibold mb-3">{{ t('storage.by_format') }}</h3>
+                    <canvas ref="formatCanvas"></canvas>
+                </div>
+                <div v-for="list in [[t('storage.top_artists'), storage.top_artists], [t('storage.top_albums'), storage.top_albums]]" :key="list[0]" class="bg-white p-6 rounded-lg shadow">
+                    <h3 class="font-semibold mb-3">{{ list[0] }}</h3>
+                    <table class="w-full text-sm">
+                        <tr v-for="e in list[1]" :key="e.name" class="border-b last:border-0">
+                            <td class="py-1 pr-2">{{ e.name }}</td>
+                            <td class="py-1 text-right text-gray-500 whitespace-nowrap">{{ formatBytes(e.bytes) }} · {{ e.tracks }}</td>
+                        </tr>
+                    </table>
+                </div>
+            </div>
+        </div>
+
+        <!-- Recently Added / Modified View -->
+        <div v-show="activeTab === 'recent'">
+            <div class="bg-white p-4 rounded-lg shadow mb-6 flex flex-wrap items-center gap-2 sm:gap-4">
+                <span class="text-sm text-gray-600">{{ t('recent.show_last') }}</span>
+                <select v-model.number="recentDays" @change="fetchRecent" class="p-2 border border-gray-300 rounded">
+                    <option :value="1">{{ t('recent.one_day') }}</option>
+                    <option :value="7">{{ t('recent.days', { n: 7 }) }}</option>
+                    <option :value="30">{{ t('recent.days', { n: 30 }) }}</option>
+                </select>
+                <span class="text-sm text-gray-500">{{ t('recent.summary', { added: recentAdded, updated: recentTracks.length - recentAdded }) }}</span>
+            </div>
+            <div class="bg-white rounded-lg shadow overflow-x-auto">
+                <table class="min-w-full">
+                    <tbody>
+                        <tr v-for="track in recentTracks" :key="track.path" class="border-b last:border-0 hover:bg-gray-50">
+                            <td class="px-4 py-3 text-sm w-24">
+                                <span class="px-2 text-xs font-semibold rounded-full"
+                                      :class="track.change === 'added' ? 'bg-green-100 text-green-800' : 'bg-blue-100 text-blue-800'">
+                                    {{ t('recent.' + track.change) }}
+                                </span>
+                            </td>
+                            <td class="px-4 py-3 text-sm">
+                                <div class="font-medium">{{ track.metadata.title || t('track.unknown_title') }} — {{ track.metadata.artist || t('track.unknown_artist') }}</div>
+                                <div class="text-xs text-gray-500">{{ track.path }}</div>
+                                <div v-if="track.metadata.overwritten_fields && track.metadata.overwritten_fields.length" class="text-xs text-orange-600 mt-1">
+                                    {{ t('recent.overwrote', { fields: track.metadata.overwritten_fields.join(', ') }) }}
+                                </div>
+                            </td>
+                            <td class="px-4 py-3 text-sm text-right text-gray-500">
+                                {{ new Date(track.scanned_at * 1000).toLocaleString() }}
+                            </td>
+                        </tr>
+                    </tbody>
+                </table>
+                <div v-if="recentTracks.length === 0" class="p-4 text-center text-gray-500">
+                    {{ t('recent.none') }}
+                </div>
+            </div>
+        </div>
+
+        <!-- Track Detail View -->
+        <div v-if="activeTab === 'track'">
+            <button @click="closeTrack" class="text-indigo-600 hover:underline text-sm mb-4">{{ t('detail.back') }}</button>
+            <div v-if="!trackDetail" class="bg-white p-8 rounded-lg shadow text-center text-gray-500">
+                {{ trackDetailError || t('common.loading') }}
+            </div>
+            <div v-else class="space-y-6">
+                <div class="bg-white p-6 rounded-lg shadow">
+                    <h2 class="text-2xl font-bold">{{ trackDetail.metadata.title || t('track.unknown_title') }}</h2>
+                    <p class="text-gray-600">{{ trackDetail.metadata.artist || t('track.unknown_artist') }}<span v-if="trackDetail.metadata.album"> — {{ trackDetail.metadata.album }}</span></p>
+                    <p class="text-xs text-gray-400 mt-1">{{ trackDetail.path }}</p>
+                    <div class="flex flex-wrap items-center gap-2 mt-3 text-sm">
+                        <button @click="shareTrack" class="px-3 py-1 rounded border border-indigo-600 text-indigo-600 hover:bg-indigo-50">🔗 {{ t('detail.share') }}</button>
+                        <template v-if="shareLink">
+                            <input :value="shareLink.url" readonly @focus="$event.target.select()" class="flex-1 min-w-0 p-1 border border-gray-300 rounded text-xs">
+                            <span class="text-xs text-gray-500">{{ t('detail.share_expires', { date: new Date(shareLink.expires_at * 1000).toLocaleString(locale) }) }}</span>
+                        </template>
+                        <span v-if="shareError" class="text-xs text-red-500">{{ shareError }}</span>
+                    </div>
+                    <canvas ref="waveformCanvas" class="w-full mt-4" height="80"></canvas>
+                    <p v-if="waveformError" class="text-xs text-red-500">{{ waveformError }}</p>
+                </div>
+
+                <div class="grid grid-cols-1 md:grid-cols-3 gap-6">
+                    <div class="bg-white p-6 rounded-lg shadow">
+                        <h3 class="font-semibold mb-3">{{ t('detail.metadata') }}</h3>
+                        <table class="w-full text-sm">
+                            <tr v-for="row in detailRows" :key="row[0]"><td class="text-gray-500 pr-4 py-1">{{ row[0] }}</td><td>{{ row[1] }}</td></tr>
+                            <tr>
+                                <td class="text-gray-500 pr-4 py-1">{{ t('field.genre') }}</td>
+                                <td>
+                                    <div v-if="trackDetail.genres.length" class="flex flex-wrap gap-1">
+                                        <button v-for="g in trackDetail.genres" :key="g" @click="filterGenre(g)" class="px-2 text-xs leading-5 rounded-full bg-blue-100 text-blue-800 hover:bg-blue-200" :title="t('library.filter_genre')">{{ g }}</button>
+                                    </div>
+                                    <span v-else>-</span>
+                                </td>
+                            </tr>
+                        </table>
+                    </div>
+                    <div class="bg-white p-6 rounded-lg shadow">
+                        <h3 class="font-semibold mb-3">{{ t('detail.provenance') }}</h3>
+                        <table class="w-full text-sm">
+                            <tr><td class="text-gray-500 pr-4 py-1">{{ t('detail.source') }}</td><td>{{ trackDetail.metadata.source === 'online' ? 'AcoustID / MusicBrainz' : t('detail.source_local') }}</td></tr>
+                            <tr v-if="trackDetail.metadata.acoustid_score != null"><td class="text-gray-500 pr-4 py-1">{{ t('detail.acoustid_score') }}</td><td>{{ (trackDetail.metadata.acoustid_score * 100).toFixed(1) }}%</td></tr>
+                            <tr v-if="trackDetail.metadata.recording_mbid"><td class="text-gray-500 pr-4 py-1">{{ t('detail.recording') }}</td><td><a class="text-indigo-600 hover:underline" :href="`https://musicbrainz.org/recording/${trackDetail.metadata.recording_mbid}`" target="_blank">{{ trackDetail.metadata.recording_mbid }}</a></td></tr>
+                            <tr v-if="trackDetail.metadata.work_mbid"><td class="text-gray-500 pr-4 py-1">{{ t('detail.work') }}</td><td><a class="text-indigo-600 hover:underline" :href="`https://musicbrainz.org/work/${trackDetail.metadata.work_mbid}`" target="_blank">{{ trackDetail.metadata.work_mbid }}</a></td></tr>
+                            <tr v-if="trackDetail.metadata.overwritten_fields.length"><td class="text-gray-500 pr-4 py-1">{{ t('detail.overwrote') }}</td><td class="text-orange-600">{{ trackDetail.metadata.overwritten_fields.join(', ') }}</td></tr>
+                        </table>
+                        <div v-if="trackDetail.metadata.match_candidates && trackDetail.metadata.match_candidates.length > 1" class="mt-3">
+                            <div class="text-xs uppercase text-gray-500 mb-1">{{ t('detail.candidates') }}</div>
+                            <table class="w-full text-xs">
+                                <tr v-for="c in trackDetail.metadata.match_candidates" :key="c.recording_mbid" :class="c.recording_mbid === trackDetail.metadata.recording_mbid ? 'font-semibold' : 'text-gray-600'">
+                                    <td class="pr-2 py-0.5"><a class="hover:underline" :href="`https://musicbrainz.org/recording/${c.recording_mbid}`" target="_blank">{{ c.title || c.recording_mbid.slice(0, 8) }}</a><span v-if="c.artist"> — {{ c.artist }}</span></td>
+                                    <td class="pr-2 text-right">{{ c.duration ? formatTime(Math.round(c.duration)) : '-' }}</td>
+                                    <td class="text-right" :title="t('detail.candidate_score', { pct: (c.score * 100).toFixed(1) })">{{ (c.rank * 100).toFixed(0) }}</td>
+                                </tr>
+                            </table>
+                        </div>
+                    </div>
+                    <div class="bg-white p-6 rounded-lg shadow">
+                        <h3 class="font-semibold mb-3">{{ t('detail.stream') }}</h3>
+                        <table v-if="trackDetail.metadata.quality" class="w-full text-sm">
+                            <tr><td class="text-gray-500 pr-4 py-1">{{ t('detail.codec') }}</td><td>{{ trackDetail.metadata.quality.codec }}{{ trackDetail.metadata.quality.lossless ? t('detail.lossless') : '' }}</td></tr>
+                            <tr v-if="trackDetail.metadata.quality.bitrate_kbps"><td class="text-gray-500 pr-4 py-1">{{ t('detail.bitrate') }}</td><td>{{ trackDetail.metadata.quality.bitrate_kbps }} kbps</td></tr>
+                            <tr><td class="text-gray-500 pr-4 py-1">{{ t('detail.sample_rate') }}</td><td>{{ trackDetail.metadata.quality.sample_rate }} Hz<span v-if="trackDetail.metadata.quality.bit_depth"> / {{ trackDetail.metadata.quality.bit_depth }} bit</span></td></tr>
+                            <tr><td class="text-gray-500 pr-4 py-1">{{ t('detail.channels') }}</td><td>{{ trackDetail.metadata.quality.channels }}</td></tr>
+                            <tr v-if="trackDetail.metadata.quality.encoder_delay || trackDetail.metadata.quality.encoder_padding"><td class="text-gray-500 pr-4 py-1">{{ t('detail.gapless') }}</td><td>{{ t('detail.gapless_value', { delay: trackDetail.metadata.quality.encoder_delay || 0, padding: trackDetail.metadata.quality.encoder_padding || 0 }) }}</td></tr>
+                            <tr v-if="trackDetail.metadata.quality.spectral_cutoff_hz"><td class="text-gray-500 pr-4 py-1">{{ t('detail.cutoff') }}</td><td>{{ (trackDetail.metadata.quality.spectral_cutoff_hz / 1000).toFixed(1) }} kHz</td></tr>
+                            <tr><td class="text-gray-500 pr-4 py-1">{{ t('detail.peak_rms') }}</td><td>{{ trackDetail.metadata.quality.peak_dbfs.toFixed(1) }} / {{ trackDetail.metadata.quality.rms_dbfs.toFixed(1) }} dBFS</td></tr>
+                            <tr v-if="trackDetail.metadata.quality.integrated_lufs != null"><td class="text-gray-500 pr-4 py-1">{{ t('detail.loudness') }}</td><td>{{ trackDetail.metadata.quality.integrated_lufs.toFixed(1) }} LUFS (ReplayGain {{ trackDetail.replay_gain_db > 0 ? '+' : '' }}{{ trackDetail.replay_gain_db.toFixed(1) }} dB)</td></tr>
+                            <tr><td class="text-gray-500 pr-4 py-1">{{ t('detail.loudness_war') }}</td><td>{{ trackDetail.metadata.quality.loudness_war_score.toFixed(0) }} / 100</td></tr>
+                        </table>
+                        <p v-else class="text-sm text-gray-500">{{ t('detail.not_measured') }}</p>
+                    </div>
+                </div>
+
+                <div class="bg-white p-6 rounded-lg shadow">
+                    <h3 class="font-semibold mb-3">{{ t('detail.features') }}</h3>
+                    <div v-show="trackDetail.analysis" class="max-w-xl mx-auto"><canvas ref="featureCanvas"></canvas></div>
+                    <p v-if="!trackDetail.analysis" class="text-sm text-gray-500">{{ t('detail.not_analyzed') }}</p>
+                </div>
+            </div>
+        </div>
+
         <!-- Recommendations Modal -->
-        <div v-if="showRecommendModal" class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50" @click.self="showRecommendModal = false">
-            <div class="bg-white rounded-lg shadow-xl w-full max-w-2xl max-h-[80vh] overflow-hidden">
+        <div v-if="showRecommendModal" class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50 p-3" @click.self="showRecommendModal = false">
+            <div class="bg-white rounded-lg shadow-xl w-full max-w-2xl max-h-[90vh] md:max-h-[80vh] overflow-hidden">
                 <div class="bg-purple-600 text-white px-6 py-4 flex justify-between items-center">
-                    <h3 class="text-lg font-bold">🎵 Similar Songs</h3>
+                    <h3 class="text-lg font-bold">🎵 {{ t('rec.title') }}</h3>
                     <button @click="showRecommendModal = false" class="text-white hover:text-gray-200 text-2xl">&times;</button>
                 </div>
                 <div class="p-4">
                     <div v-if="recommendLoading" class="text-center py-8">
                         <span class="animate-spin text-3xl">⟳</span>
-                        <p class="mt-2 text-gray-500">Finding similar songs...</p>
+                        <p class="mt-2 text-gray-500">{{ t('rec.finding') }}</p>
                     </div>
                     <div v-else-if="recommendations.length === 0" class="text-center py-8 text-gray-500">
-                        <p>No similar songs found. Try scanning with analysis enabled.</p>
+                        <p>{{ t('rec.none') }}</p>
                     </div>
                     <div v-else class="overflow-y-auto max-h-96">
                         <div class="mb-4 text-sm text-gray-600">
-                            Based on: <strong>{{ recommendSourceTrack?.metadata?.title }}</strong> by {{ recommendSourceTrack?.metadata?.artist }}
+                            {{ t('rec.based_on') }} <strong>{{ recommendSourceTrack?.metadata?.title }}</strong> {{ t('rec.by', { artist: recommendSourceTrack?.metadata?.artist }) }}
                         </div>
                         <table class="w-full">
                             <thead class="bg-gray-50">
                                 <tr>
                                     <th class="px-4 py-2 text-left text-xs font-semibold text-gray-600">#</th>
-                                    <th class="px-4 py-2 text-left text-xs font-semibold text-gray-600">Title</th>
-                                    <th class="px-4 py-2 text-left text-xs font-semibold text-gray-600">Artist</th>
-                                    <th class="px-4 py-2 text-right text-xs font-semibold text-gray-600">Similarity</th>
+                                    <th class="px-4 py-2 text-left text-xs font-semibold text-gray-600">{{ t('field.title') }}</th>
+                                    <th class="px-4 py-2 text-left text-xs font-semibold text-gray-600">{{ t('field.artist') }}</th>
+                                    <th class="px-4 py-2 text-right text-xs font-semibold text-gray-600">{{ t('rec.similarity') }}</th>
                                 </tr>
                             </thead>
                             <tbody>
@@ -245,18 +648,107 @@ pub const HTML_CONTENT: &str = r#"
             </div>
         </div>
 
+        <!-- Event Log -->
+        <div class="fixed bottom-0 inset-x-0 z-20 md:left-auto md:right-4 md:w-[36rem]">
+            <button @click="logOpen = !logOpen" class="ml-auto block bg-gray-800 text-white text-sm px-4 py-2 rounded-t-lg shadow">
+                {{ t('log.title') }}
+                <span v-if="logErrorCount" class="ml-2 px-2 rounded-full bg-red-500 text-xs">{{ logErrorCount }}</span>
+                {{ logOpen ? '▾' : '▴' }}
+            </button>
+            <div v-show="logOpen" class="bg-gray-900 text-gray-100 shadow-lg">
+                <div class="flex flex-wrap items-center gap-3 px-3 py-2 border-b border-gray-700 text-xs">
+                    <label class="flex items-center gap-1"><input type="checkbox" v-model="logFollow"> {{ t('log.follow') }}</label>
+                    <select v-model="logLevel" class="bg-gray-800 border border-gray-700 rounded px-1">
+                        <option value="">{{ t('log.all_levels') }}</option>
+                        <option value="warn">{{ t('log.warnings') }}</option>
+                        <option value="error">{{ t('log.errors') }}</option>
+                    </select>
+                    <button @click="clearLog" class="hover:underline">{{ t('log.clear') }}</button>
+                    <span class="ml-auto" :class="logConnected ? 'text-green-400' : 'text-gray-500'">● {{ logConnected ? t('log.live') : t('log.offline') }}</span>
+                </div>
+                <div ref="logBox" class="h-64 overflow-y-auto font-mono text-xs p-2 space-y-1">
+                    <div v-if="visibleLog.length === 0" class="text-gray-500">{{ t('log.empty') }}</div>
+                    <div v-for="e in visibleLog" :key="e.seq" :class="e.level === 'error' ? 'text-red-400' : e.level === 'warn' ? 'text-yellow-300' : 'text-gray-200'">
+                        <span class="text-gray-500">{{ new Date(e.at * 1000).toLocaleTimeString(locale) }}</span>
+                        <span class="text-indigo-300">[{{ e.kind }}]</span>
+                        {{ e.message }}
+                        <span v-if="e.path" class="text-gray-500 break-all">{{ e.path }}</span>
+                    </div>
+                </div>
+            </div>
+        </div>
+
     </div>
 
     <script>
         const { createApp, ref, computed, onMounted, watch } = Vue;
+        // Filled in by the server from --base-path (e.g. "/music" behind a reverse proxy)
+        const BASE_PATH = '__BASE_PATH__';
+        // Shown in the language switcher in their own language
+        const LANGUAGE_NAMES = { 'en': 'English', 'zh-CN': '简体中文', 'ja': '日本語' };
+        const COLUMNS = ['artist', 'album', 'original_artist', 'genre', 'year', 'duration', 'bitrate', 'bpm', 'size', 'path'];
+        const DEFAULT_COLUMNS = ['artist', 'album', 'original_artist', 'size'];
 
         createApp({
             setup() {
                 const tracks = ref([]);
                 const duplicateGroups = ref([]);
+                const works = ref([]);
+                const selectedWorkKey = ref(null);
                 const searchQuery = ref('');
+                const decadeFilter = ref('');
+                const recentTracks = ref([]);
+                const recentDays = ref(7);
+                const decades = ref([]);
                 const activeTab = ref('library');
 
+                // Strings come from /api/locales/{lang}; English fills in missing keys
+                const locales = ref([]);
+                const locale = ref('en');
+                const messages = ref({});
+                const fallbackMessages = ref({});
+
+                const t = (key, params = {}) => {
+                    const text = messages.value[key] ?? fallbackMessages.value[key] ?? key;
+                    return text.replace(/\{(\w+)\}/g, (m, name) => name in params ? params[name] : m);
+                };
+
+                const loadCatalog = async (lang) => {
+                    const res = await fetch(`${BASE_PATH}/api/locales/${lang}`);
+                    if (!res.ok) throw new Error(await res.text());
+                    return res.json();
+                };
+
+                const setLocale = async (lang) => {
+                    try {
+                        messages.value = await loadCatalog(lang);
+                        locale.value = lang;
+                        localStorage.setItem('locale', lang);
+                        document.documentElement.lang = lang;
+                        document.title = t('app.title');
+                    } catch (e) {
+                        console.error("Failed to load locale", e);
+                    }
+                };
+
+                // The saved choice, then the browser's languages, then the server's
+                // pick from Accept-Language
+                const initLocale = async () => {
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/locales`);
+                        const info = await res.json();
+                        locales.value = info.available;
+                        fallbackMessages.value = await loadCatalog('en');
+                        const primary = (tag) => tag.split('-')[0].toLowerCase();
+                        const match = (tag) => info.available.find(l => l.toLowerCase() === tag.toLowerCase())
+                            || info.available.find(l => primary(l) === primary(tag));
+                        const wanted = [localStorage.getItem('locale'), ...(navigator.languages || [navigator.language])];
+                        await setLocale(wanted.filter(Boolean).map(match).find(Boolean) || info.preferred);
+                    } catch (e) {
+                        console.error("Failed to load locales", e);
+                    }
+                };
+
                 // Scan State
                 const isScanning = ref(false);
                 const scanStatus = ref({
@@ -275,9 +767,62 @@ pub const HTML_CONTENT: &str = r#"
                 const recommendations = ref([]);
                 const recommendSourceTrack = ref(null);
 
+                // Optional library table columns in display order; title and actions
+                // are always shown. Choices and sorting persist in this browser.
+                const columns = ref(JSON.parse(localStorage.getItem('columns') || 'null') || DEFAULT_COLUMNS);
+                const sortKey = ref(localStorage.getItem('sortKey') || '');
+                const sortOrder = ref(localStorage.getItem('sortOrder') || 'asc');
+
+                const showColumn = (c) => columns.value.includes(c);
+
+                const toggleColumn = (c) => {
+                    const on = !showColumn(c);
+                    columns.value = COLUMNS.filter(x => x === c ? on : showColumn(x));
+                    localStorage.setItem('columns', JSON.stringify(columns.value));
+                };
+
+                // Ascending, then descending, then back to the default album order
+                const sortBy = (key) => {
+                    if (sortKey.value !== key) {
+                        sortKey.value = key;
+                        sortOrder.value = 'asc';
+                    } else if (sortOrder.value === 'asc') {
+                        sortOrder.value = 'desc';
+                    } else {
+                        sortKey.value = '';
+                    }
+                    localStorage.setItem('sortKey', sortKey.value);
+                    localStorage.setItem('sortOrder', sortOrder.value);
+                    fetchTracks();
+                };
+
+                const sortIndicator = (key) => sortKey.value === key ? (sortOrder.value === 'asc' ? ' ▲' : ' ▼') : '';
+
+                const cellText = (track, c) => {
+                    const m = track.metadata;
+                    switch (c) {
+                        case 'artist': return m.artist || t('track.unknown_artist');
+                        case 'year': return m.year || '-';
+                        case 'duration': return formatTime(Math.round(m.duration));
+                        case 'bitrate': return m.quality && m.quality.bitrate_kbps ? `${m.quality.bitrate_kbps} kbps` : '-';
+                        case 'bpm': return track.bpm != null ? Math.round(track.bpm) : '-';
+                        case 'size': return formatBytes(track.file_size);
+                        default: return '-';
+                    }
+                };
+
+                // Server-side genre filter; a parent genre includes its sub-genres
+                const genreFilter = ref('');
+
                 const fetchTracks = async () => {
                     try {
-                        const res = await fetch('/api/tracks');
+                        const query = new URLSearchParams();
+                        if (sortKey.value) {
+                            query.set('sort', sortKey.value);
+                            query.set('order', sortOrder.value);
+                        }
+                        if (genreFilter.value) query.set('genre', genreFilter.value);
+                        const res = await fetch(`${BASE_PATH}/api/tracks?${query}`);
                         const data = await res.json();
                         tracks.value = data;
                     } catch (e) {
@@ -287,7 +832,7 @@ pub const HTML_CONTENT: &str = r#"
                 
                 const fetchDuplicates = async () => {
                      try {
-                        const res = await fetch('/api/duplicates');
+                        const res = await fetch(`${BASE_PATH}/api/duplicates`);
                         const data = await res.json();
                         duplicateGroups.value = data;
                     } catch (e) {
@@ -295,38 +840,82 @@ pub const HTML_CONTENT: &str = r#"
                     }
                 }
 
+                const fetchRecent = async () => {
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/tracks/recent?days=${recentDays.value}`);
+                        recentTracks.value = await res.json();
+                    } catch (e) {
+                        console.error("Failed to load recent tracks", e);
+                    }
+                };
+
+                const recentAdded = computed(() => recentTracks.value.filter(t => t.change === 'added').length);
+
+                const fetchStats = async () => {
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/stats`);
+                        const data = await res.json();
+                        decades.value = data.decades || [];
+                    } catch (e) {
+                        console.error("Failed to load stats", e);
+                    }
+                };
+
+                const fetchWorks = async () => {
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/works`);
+                        works.value = await res.json();
+                    } catch (e) {
+                        console.error("Failed to load works", e);
+                    }
+                };
+
+                const showWork = async (track) => {
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/works?path=${encodeURIComponent(track.path)}`);
+                        const work = await res.json();
+                        if (work.error) return;
+                        await fetchWorks();
+                        selectedWorkKey.value = work.key;
+                        activeTab.value = 'works';
+                        setTimeout(() => {
+                            const el = document.getElementById('work-' + work.key);
+                            if (el) el.scrollIntoView({ behavior: 'smooth' });
+                        }, 50);
+                    } catch (e) {
+                        console.error("Failed to load work", e);
+                    }
+                };
+
                 const startScan = async () => {
                     try {
-                        const res = await fetch('/api/scan/start', { method: 'POST' });
+                        const res = await fetch(`${BASE_PATH}/api/scan/start`, { method: 'POST' });
                         const data = await res.json();
                         if (data.status === 'started') {
                             isScanning.value = true;
-                            pollStatus();
                         } else {
-                            alert('Failed to start scan: ' + (data.error || 'Unknown error'));
+                            alert(t('scan.start_failed', { error: data.error || t('common.unknown_error') }));
                         }
                     } catch (e) {
-                        alert('Error starting scan: ' + e);
+                        alert(t('scan.start_error', { error: e }));
                     }
                 };
 
-                const pollStatus = async () => {
-                    const timer = setInterval(async () => {
-                        try {
-                            const res = await fetch('/api/scan/status');
-                            const status = await res.json();
-                            scanStatus.value = status;
-                            isScanning.value = status.is_scanning;
-
-                            if (!status.is_scanning) {
-                                clearInterval(timer);
-                                fetchTracks(); // Reload data
-                                fetchDuplicates();
-                            }
-                        } catch (e) {
-                            console.error("Polling error", e);
+                // Scan progress pushed over SSE: the current state on connect,
+                // then live updates; data is reloaded once when a scan ends
+                const watchScan = () => {
+                    const source = new EventSource(`${BASE_PATH}/api/scan/stream`);
+                    source.addEventListener('progress', (msg) => {
+                        const status = JSON.parse(msg.data);
+                        const finished = isScanning.value && !status.is_scanning;
+                        scanStatus.value = status;
+                        isScanning.value = status.is_scanning;
+                        if (finished) {
+                            fetchTracks();
+                            fetchDuplicates();
+                            fetchStats();
                         }
-                    }, 1000);
+                    });
                 };
 
                 const findSimilar = async (track) => {
@@ -336,7 +925,7 @@ pub const HTML_CONTENT: &str = r#"
                     recommendations.value = [];
 
                     try {
-                        const res = await fetch(`/api/recommend?path=${encodeURIComponent(track.path)}`);
+                        const res = await fetch(`${BASE_PATH}/api/recommend?path=${encodeURIComponent(track.path)}`);
                         const data = await res.json();
                         if (data.error) {
                             console.error('Recommendation error:', data.error);
@@ -364,11 +953,459 @@ pub const HTML_CONTENT: &str = r#"
                     return 'text-gray-500';
                 };
 
+                const trackDetail = ref(null);
+                const trackDetailError = ref('');
+                const waveformError = ref('');
+                const waveformCanvas = ref(null);
+                const featureCanvas = ref(null);
+                let featureChart = null;
+
+                const detailRows = computed(() => {
+                    const m = trackDetail.value ? trackDetail.value.metadata : null;
+                    if (!m) return [];
+                    return [
+                        [t('field.album'), m.album || '-'],
+                        [t('field.year'), m.year || '-'],
+                        [t('detail.disc_track'), `${m.disc_number || '-'} / ${m.track_number || '-'}`],
+                        [t('detail.duration'), formatTime(Math.round(m.duration))],
+                        [t('detail.original'), m.original_artist ? `${m.original_title || ''} — ${m.original_artist}${m.original_year ? ` (${m.original_year})` : ''}` : '-'],
+                        [t('detail.language'), m.vocal_language || '-'],
+                        [t('detail.content'), t('content.' + m.content_kind)],
+                        [t('field.size'), formatBytes(trackDetail.value.file_size)],
+                    ];
+                });
+
+                const drawWaveform = (peaks) => {
+                    const canvas = waveformCanvas.value;
+                    if (!canvas) return;
+                    canvas.width = canvas.clientWidth;
+                    const ctx = canvas.getContext('2d');
+                    const mid = canvas.height / 2;
+                    const step = canvas.width / peaks.length;
+                    ctx.clearRect(0, 0, canvas.width, canvas.height);
+                    ctx.fillStyle = '#6366f1';
+                    peaks.forEach((p, i) => {
+                        const h = Math.max(1, p * mid);
+                        ctx.fillRect(i * step, mid - h, Math.max(1, step - 0.5), h * 2);
+                    });
+                };
+
+                const drawFeatures = (detail) => {
+                    if (featureChart) {
+                        featureChart.destroy();
+                        featureChart = null;
+                    }
+                    if (!detail.analysis || !featureCanvas.value) return;
+                    featureChart = new Chart(featureCanvas.value, {
+                        type: 'radar',
+                        data: {
+                            labels: detail.feature_names,
+                            datasets: [{
+                                label: detail.metadata.title || t('detail.track'),
+                                data: detail.analysis,
+                                backgroundColor: 'rgba(99, 102, 241, 0.2)',
+                                borderColor: '#6366f1',
+                            }],
+                        },
+                        options: { scales: { r: { suggestedMin: -1, suggestedMax: 1 } } },
+                    });
+                };
+
+                // Server event log over SSE; EventSource reconnects on its own
+                // and resumes from the last seen event
+                const LOG_LIMIT = 1000;
+                const logEvents = ref([]);
+                const logOpen = ref(false);
+                const logFollow = ref(true);
+                const logLevel = ref('');
+                const logConnected = ref(false);
+                const logBox = ref(null);
+
+                const visibleLog = computed(() => {
+                    if (logLevel.value === 'error') return logEvents.value.filter(e => e.level === 'error');
+                    if (logLevel.value === 'warn') return logEvents.value.filter(e => e.level !== 'info');
+                    return logEvents.value;
+                });
+
+                const logErrorCount = computed(() => logEvents.value.filter(e => e.level === 'error').length);
+
+                const clearLog = () => { logEvents.value = []; };
+
+                const connectLog = () => {
+                    const source = new EventSource(`${BASE_PATH}/api/events`);
+                    source.onopen = () => { logConnected.value = true; };
+                    source.onerror = () => { logConnected.value = false; };
+                    source.addEventListener('log', async (msg) => {
+                        logEvents.value.push(JSON.parse(msg.data));
+                        if (logEvents.value.length > LOG_LIMIT) logEvents.value.splice(0, logEvents.value.length - LOG_LIMIT);
+                        if (logFollow.value && logOpen.value && logBox.value) {
+                            await Vue.nextTick();
+                            logBox.value.scrollTop = logBox.value.scrollHeight;
+                        }
+                    });
+                };
+
+                const shareLink = ref(null);
+                const shareError = ref('');
+
+                // Expiring link that plays the track without the dashboard
+                const shareTrack = async () => {
+                    shareError.value = '';
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/shares`, {
+                            method: 'POST',
+                            headers: { 'Content-Type': 'application/json' },
+                            body: JSON.stringify({ track_id: trackDetail.value.id }),
+                        });
+                        if (!res.ok) throw new Error(await res.text());
+                        const link = await res.json();
+                        shareLink.value = { ...link, url: `${location.origin}${link.url}` };
+                        navigator.clipboard?.writeText(shareLink.value.url).catch(() => {});
+                    } catch (e) {
+                        shareError.value = t('detail.share_failed', { error: e.message });
+                    }
+                };
+
+                const loadTrack = async (id) => {
+                    activeTab.value = 'track';
+                    shareLink.value = null;
+                    shareError.value = '';
+                    trackDetail.value = null;
+                    trackDetailError.value = '';
+                    waveformError.value = '';
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/track/${id}`);
+                        if (!res.ok) {
+                            trackDetailError.value = t('detail.not_found');
+                            return;
+                        }
+                        trackDetail.value = await res.json();
+                    } catch (e) {
+                        trackDetailError.value = t('detail.load_failed');
+                        return;
+                    }
+                    await Vue.nextTick();
+                    drawFeatures(trackDetail.value);
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/track/${id}/waveform`);
+                        if (!res.ok) throw new Error(await res.text());
+                        drawWaveform(await res.json());
+                    } catch (e) {
+                        waveformError.value = t('detail.waveform_unavailable', { error: e.message });
+                    }
+                };
+
+                const openTrack = (track) => {
+                    history.pushState({ trackId: track.id }, '', `${BASE_PATH}/track/${track.id}`);
+                    loadTrack(track.id);
+                };
+
+                // Named filter combinations, stored in filter_presets.json on the server
+                const presets = ref([]);
+                const minMinutes = ref('');
+                const maxMinutes = ref('');
+                const formatFilter = ref([]);
+
+                const fetchPresets = async () => {
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/presets`);
+                        presets.value = await res.json();
+                    } catch (e) {
+                        console.error("Failed to load presets", e);
+                    }
+                };
+
+                const savePreset = async () => {
+                    const name = (window.prompt(t('library.preset_name')) || '').trim();
+                    if (!name) return;
+                    const minutes = (m) => m === '' ? null : Math.round(m * 60);
+                    const preset = {
+                        query: searchQuery.value,
+                        genre: genreFilter.value || null,
+                        decade: decadeFilter.value === '' ? null : decadeFilter.value,
+                        min_duration_secs: minutes(minMinutes.value),
+                        max_duration_secs: minutes(maxMinutes.value),
+                        formats: formatFilter.value,
+                    };
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/presets/${encodeURIComponent(name)}`, {
+                            method: 'PUT',
+                            headers: { 'Content-Type': 'application/json' },
+                            body: JSON.stringify(preset),
+                        });
+                        if (!res.ok) throw new Error(await res.text());
+                        presets.value = await res.json();
+                    } catch (e) {
+                        alert(t('library.preset_failed', { error: e.message }));
+                    }
+                };
+
+                const applyPreset = (preset) => {
+                    searchQuery.value = preset.query || '';
+                    decadeFilter.value = preset.decade ?? '';
+                    minMinutes.value = preset.min_duration_secs != null ? preset.min_duration_secs / 60 : '';
+                    maxMinutes.value = preset.max_duration_secs != null ? preset.max_duration_secs / 60 : '';
+                    formatFilter.value = [...(preset.formats || [])];
+                    if ((preset.genre || '') !== genreFilter.value) filterGenre(preset.genre || '');
+                };
+
+                const deletePreset = async (preset) => {
+                    try {
+                        await fetch(`${BASE_PATH}/api/presets/${encodeURIComponent(preset.name)}`, { method: 'DELETE' });
+                        presets.value = presets.value.filter(p => p.name !== preset.name);
+                    } catch (e) {
+                        console.error("Failed to delete preset", e);
+                    }
+                };
+
+                const filterGenre = (genre) => {
+                    genreFilter.value = genre;
+                    if (activeTab.value !== 'library') closeTrack();
+                    fetchTracks();
+                };
+
+                const closeTrack = () => {
+                    history.pushState({}, '', `${BASE_PATH}/`);
+                    activeTab.value = 'library';
+                };
+
+                // Deep links (/track/{id}) and back/forward navigation
+                const routeFromLocation = () => {
+                    const match = location.pathname.slice(BASE_PATH.length).match(/^\/track\/([0-9a-f]+)$/);
+                    if (match) {
+                        loadTrack(match[1]);
+                    } else if (activeTab.value === 'track') {
+                        activeTab.value = 'library';
+                    }
+                };
+                window.addEventListener('popstate', routeFromLocation);
+
+                const selectedIds = ref([]);
+                const bulkFields = ref({ artist: '', album: '', genre: '', year: '' });
+                const bulkPreview = ref(null);
+                const bulkMessage = ref('');
+                const bulkMessageError = ref(false);
+                const lastEditUndoable = ref(false);
+
+                const allVisibleSelected = computed(() =>
+                    filteredTracks.value.length > 0 && filteredTracks.value.every(t => selectedIds.value.includes(t.id)));
+
+                const toggleAllVisible = () => {
+                    if (allVisibleSelected.value) {
+                        const visible = new Set(filteredTracks.value.map(t => t.id));
+                        selectedIds.value = selectedIds.value.filter(id => !visible.has(id));
+                    } else {
+                        selectedIds.value = [...new Set([...selectedIds.value, ...filteredTracks.value.map(t => t.id)])];
+                    }
+                };
+
+                const clearSelection = () => {
+                    selectedIds.value = [];
+                    bulkPreview.value = null;
+                    bulkMessage.value = '';
+                };
+
+                // Changing the selection or the fields invalidates the preview
+                watch([selectedIds, bulkFields], () => { bulkPreview.value = null; }, { deep: true });
+
+                const bulkChanges = () => {
+                    const set = {};
+                    for (const field of ['artist', 'album', 'genre']) {
+                        if (bulkFields.value[field].trim() !== '') set[field] = bulkFields.value[field];
+                    }
+                    if (bulkFields.value.year !== '') set.year = bulkFields.value.year;
+                    return set;
+                };
+
+                const postBulk = async (url, body) => {
+                    bulkMessage.value = '';
+                    bulkMessageError.value = false;
+                    const res = await fetch(url, {
+                        method: 'POST',
+                        headers: { 'Content-Type': 'application/json' },
+                        body: body ? JSON.stringify(body) : undefined,
+                    });
+                    if (!res.ok) {
+                        bulkMessageError.value = true;
+                        bulkMessage.value = await res.text();
+                        return null;
+                    }
+                    return res.json();
+                };
+
+                const downloading = ref(false);
+
+                // Zip of the selected files, saved through a temporary object URL
+                const downloadSelected = async () => {
+                    downloading.value = true;
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/download`, {
+                            method: 'POST',
+                            headers: { 'Content-Type': 'application/json' },
+                            body: JSON.stringify({ ids: selectedIds.value }),
+                        });
+                        if (!res.ok) throw new Error(await res.text());
+                        const url = URL.createObjectURL(await res.blob());
+                        const link = document.createElement('a');
+                        link.href = url;
+                        link.download = 'tracks.zip';
+                        link.click();
+                        URL.revokeObjectURL(url);
+                    } catch (e) {
+                        alert(t('bulk.download_failed', { error: e.message }));
+                    } finally {
+                        downloading.value = false;
+                    }
+                };
+
+                const runBulkEdit = async (preview) => {
+                    const set = bulkChanges();
+                    if (Object.keys(set).length === 0) {
+                        bulkMessageError.value = true;
+                        bulkMessage.value = t('bulk.fill_one');
+                        return;
+                    }
+                    const result = await postBulk(`${BASE_PATH}/api/tracks/bulk`, { ids: selectedIds.value, set, preview });
+                    if (!result) return;
+                    if (preview) {
+                        bulkPreview.value = result.changes;
+                        bulkMessage.value = result.changes.length ? '' : t('bulk.nothing');
+                        return;
+                    }
+                    lastEditUndoable.value = result.applied;
+                    bulkMessageError.value = result.errors.length > 0;
+                    bulkMessage.value = result.errors.length
+                        ? t('bulk.updated_failed', { n: result.changes.length, errors: result.errors.join('; ') })
+                        : t('bulk.updated', { n: result.changes.length });
+                    bulkFields.value = { artist: '', album: '', genre: '', year: '' };
+                    fetchTracks();
+                };
+
+                const undoBulkEdit = async () => {
+                    const result = await postBulk(`${BASE_PATH}/api/tracks/bulk/undo`);
+                    if (!result) return;
+                    lastEditUndoable.value = false;
+                    bulkMessage.value = result.applied ? t('bulk.restored', { n: result.changes.length }) : t('bulk.nothing_to_undo');
+                    fetchTracks();
+                };
+
+                const consistency = ref({ folders: [], scattered: [] });
+
+                const fetchConsistency = async () => {
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/albums/consistency`);
+                        consistency.value = await res.json();
+                    } catch (e) {
+                        console.error("Failed to load album consistency", e);
+                    }
+                };
+
+                const applyMajority = async (d) => {
+                    const value = d.field === 'year' ? Number(d.majority) : d.majority;
+                    const result = await postBulk(`${BASE_PATH}/api/tracks/bulk`, { ids: d.dissenting_ids, set: { [d.field]: value } });
+                    if (!result) return;
+                    lastEditUndoable.value = result.applied;
+                    bulkMessage.value = t('albums.set', { field: t('field.' + d.field), value: d.majority, n: d.dissenting_ids.length });
+                    fetchConsistency();
+                    fetchTracks();
+                };
+
+                const issueReport = ref({ counts: {}, issues: [] });
+                const issueKind = ref('');
+                const issueTotal = computed(() => issueReport.value.issues.length);
+                // Long queues (e.g. missing genre on an untagged library) are capped for rendering
+                const visibleIssues = computed(() => issueReport.value.issues
+                    .filter(i => issueKind.value === '' || i.kind === issueKind.value)
+                    .slice(0, 500));
+
+                const fetchIssues = async () => {
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/issues`);
+                        issueReport.value = await res.json();
+                    } catch (e) {
+                        console.error("Failed to load issues", e);
+                    }
+                };
+
+                const applyIssueFix = async (issue) => {
+                    const result = await postBulk(`${BASE_PATH}/api/tracks/bulk`, { ids: [issue.track_id], set: issue.fix });
+                    if (!result) return;
+                    lastEditUndoable.value = result.applied;
+                    bulkMessage.value = t('issues.fixed', { n: result.changes.length, file: fileName(issue.path) });
+                    fetchIssues();
+                    fetchTracks();
+                };
+
+                // Hand the track to the library tab's bulk edit panel
+                const editIssueTrack = (issue) => {
+                    selectedIds.value = [issue.track_id];
+                    activeTab.value = 'library';
+                };
+
+                const storage = ref({ snapshots: [], formats: [], top_artists: [], top_albums: [] });
+                const growthCanvas = ref(null);
+                const formatCanvas = ref(null);
+                let growthChart = null;
+                let formatChart = null;
+
+                const drawStorage = () => {
+                    if (growthChart) growthChart.destroy();
+                    if (formatChart) formatChart.destroy();
+                    const snaps = storage.value.snapshots;
+                    growthChart = new Chart(growthCanvas.value, {
+                        type: 'line',
+                        data: {
+                            labels: snaps.map(s => new Date(s.timestamp * 1000).toLocaleDateString()),
+                            datasets: [
+                                { label: t('storage.size_gb'), data: snaps.map(s => s.total_size / 1024 ** 3), borderColor: '#6366f1', yAxisID: 'size' },
+                                { label: t('storage.tracks'), data: snaps.map(s => s.total_tracks), borderColor: '#10b981', yAxisID: 'tracks' },
+                            ],
+                        },
+                        options: { scales: { size: { position: 'left' }, tracks: { position: 'right', grid: { drawOnChartArea: false } } } },
+                    });
+                    formatChart = new Chart(formatCanvas.value, {
+                        type: 'doughnut',
+                        data: {
+                            labels: storage.value.formats.map(f => f.name),
+                            datasets: [{ data: storage.value.formats.map(f => f.bytes) }],
+                        },
+                        options: { plugins: { tooltip: { callbacks: { label: (c) => `${c.label}: ${formatBytes(c.raw)}` } } } },
+                    });
+                };
+
+                const fetchTimeline = async () => {
+                    try {
+                        const res = await fetch(`${BASE_PATH}/api/stats/timeline`);
+                        storage.value = await res.json();
+                    } catch (e) {
+                        console.error("Failed to load storage timeline", e);
+                        return;
+                    }
+                    await Vue.nextTick();
+                    drawStorage();
+                };
+
+                // Chart labels are drawn once, so redraw them in the new language
+                watch(locale, () => {
+                    if (growthChart) drawStorage();
+                    if (featureChart && trackDetail.value) drawFeatures(trackDetail.value);
+                });
+
                 onMounted(() => {
+                    initLocale();
+                    routeFromLocation();
                     fetchTracks();
+                    fetchPresets();
+                    connectLog();
+                    // Installable app with an offline shell (needs HTTPS or localhost)
+                    if ('serviceWorker' in navigator) {
+                        navigator.serviceWorker.register(`${BASE_PATH}/sw.js`).catch(e => console.error("Service worker registration failed", e));
+                    }
                     fetchDuplicates();
-                    // Check if scan is already running on load
-                    pollStatus();
+                    fetchWorks();
+                    fetchStats();
+                    // Also shows a scan that is already running on load
+                    watchScan();
                 });
 
                 const totalSize = computed(() => {
@@ -380,11 +1417,26 @@ pub const HTML_CONTENT: &str = r#"
                     return artists.size;
                 });
 
+                const fileFormat = (path) => {
+                    const name = fileName(path);
+                    const dot = name.lastIndexOf('.');
+                    return dot > 0 ? name.slice(dot + 1).toLowerCase() : '';
+                };
+
+                const formats = computed(() => [...new Set(tracks.value.map(t => fileFormat(t.path)).filter(f => f))].sort());
+
                 const filteredTracks = computed(() => {
-                    const q = searchQuery.value.toLowerCase();
-                    if (!q) return tracks.value.slice(0, 100);
+                    const q = searchQuery.value.normalize('NFC').toLowerCase();
+                    const decade = decadeFilter.value;
+                    const inDecade = (t) => decade === '' || (t.metadata.year && Math.floor(t.metadata.year / 10) * 10 === decade);
+                    const min = minMinutes.value === '' ? null : minMinutes.value * 60;
+                    const max = maxMinutes.value === '' ? null : maxMinutes.value * 60;
+                    const inRange = (t) => (min === null || t.metadata.duration >= min) && (max === null || t.metadata.duration <= max);
+                    const inFormat = (t) => formatFilter.value.length === 0 || formatFilter.value.includes(fileFormat(t.path));
+                    const shown = tracks.value.filter(t => inDecade(t) && inRange(t) && inFormat(t));
+                    if (!q) return shown.slice(0, 100);
                     
-                    return tracks.value.filter(t => {
+                    return shown.filter(t => {
                         const title = (t.metadata.title || '').toLowerCase();
                         const artist = (t.metadata.artist || '').toLowerCase();
                         const album = (t.metadata.album || '').toLowerCase();
@@ -406,6 +1458,8 @@ pub const HTML_CONTENT: &str = r#"
                     return `${parseFloat((bytes / Math.pow(k, i)).toFixed(dm))} ${sizes[i]}`;
                 }
                 
+                const fileName = (path) => path.split(/[\\/]/).pop();
+
                 const formatTime = (secs) => {
                     if (!secs) return '0s';
                     const m = Math.floor(secs / 60);
@@ -414,8 +1468,53 @@ pub const HTML_CONTENT: &str = r#"
                 }
 
                 return {
+                    t,
+                    locale,
+                    locales,
+                    setLocale,
+                    LANGUAGE_NAMES,
+                    COLUMNS,
+                    columns,
+                    showColumn,
+                    toggleColumn,
+                    sortBy,
+                    sortIndicator,
+                    cellText,
                     tracks,
                     duplicateGroups,
+                    works,
+                    selectedWorkKey,
+                    decadeFilter,
+                    genreFilter,
+                    filterGenre,
+                    downloading,
+                    downloadSelected,
+                    shareLink,
+                    shareError,
+                    shareTrack,
+                    logOpen,
+                    logFollow,
+                    logLevel,
+                    logConnected,
+                    logBox,
+                    visibleLog,
+                    logErrorCount,
+                    clearLog,
+                    presets,
+                    minMinutes,
+                    maxMinutes,
+                    formatFilter,
+                    formats,
+                    savePreset,
+                    applyPreset,
+                    deletePreset,
+                    decades,
+                    recentTracks,
+                    recentDays,
+                    recentAdded,
+                    fetchRecent,
+                    fetchWorks,
+                    showWork,
                     searchQuery,
                     activeTab,
                     isScanning,
@@ -425,6 +1524,7 @@ pub const HTML_CONTENT: &str = r#"
                     uniqueArtists,
                     formatBytes,
                     formatTime,
+                    fileName,
                     startScan,
                     findSimilar,
                     showRecommendModal,
@@ -433,11 +1533,108 @@ pub const HTML_CONTENT: &str = r#"
                     recommendSourceTrack,
                     formatSimilarity,
                     getSimilarityClass,
-                    percentComplete
+                    percentComplete,
+                    BASE_PATH,
+                    trackDetail,
+                    trackDetailError,
+                    waveformError,
+                    waveformCanvas,
+                    featureCanvas,
+                    detailRows,
+                    openTrack,
+                    closeTrack,
+                    selectedIds,
+                    bulkFields,
+                    bulkPreview,
+                    bulkMessage,
+                    bulkMessageError,
+                    lastEditUndoable,
+                    allVisibleSelected,
+                    toggleAllVisible,
+                    clearSelection,
+                    runBulkEdit,
+                    undoBulkEdit,
+                    consistency,
+                    fetchConsistency,
+                    applyMajority,
+                    issueReport,
+                    issueKind,
+                    issueTotal,
+                    visibleIssues,
+                    fetchIssues,
+                    applyIssueFix,
+                    editIssueTrack,
+                    storage,
+                    growthCanvas,
+                    formatCanvas,
+                    fetchTimeline
                 };
             }
         }).mount('#app');
     </script>
 </body>
 </html>
+"##;
+
+/// Web app manifest, so phones can add the dashboard to the home screen.
+pub const MANIFEST: &str = r##"{
+  "name": "Audio Sorter",
+  "short_name": "Audio Sorter",
+  "start_url": "__BASE_PATH__/",
+  "scope": "__BASE_PATH__/",
+  "display": "standalone",
+  "background_color": "#f3f4f6",
+  "theme_color": "#4f46e5",
+  "icons": [
+    { "src": "__BASE_PATH__/icon.svg", "sizes": "any", "type": "image/svg+xml", "purpose": "any maskable" }
+  ]
+}
+"##;
+
+pub const ICON_SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 512 512">
+  <rect width="512" height="512" rx="96" fill="#4f46e5"/>
+  <path d="M208 128v196a56 56 0 1 0 32 50V208l128-32v116a56 56 0 1 0 32 50V96z" fill="#fff"/>
+</svg>
+"##;
+
+/// Service worker caching the page, its CDN scripts and the locale catalogs.
+/// Requests go to the network first so a restarted server is picked up at
+/// once; the cache only answers while the server is unreachable. Other API
+/// responses are never cached.
+pub const SERVICE_WORKER: &str = r#"
+const CACHE = 'audio-sorter-v1';
+const BASE_PATH = '__BASE_PATH__';
+const SHELL = [`${BASE_PATH}/`, `${BASE_PATH}/manifest.webmanifest`, `${BASE_PATH}/icon.svg`];
+
+self.addEventListener('install', (event) => {
+    event.waitUntil(caches.open(CACHE).then(cache => cache.addAll(SHELL)).then(() => self.skipWaiting()));
+});
+
+self.addEventListener('activate', (event) => {
+    event.waitUntil(caches.keys()
+        .then(keys => Promise.all(keys.filter(k => k !== CACHE).map(k => caches.delete(k))))
+        .then(() => self.clients.claim()));
+});
+
+self.addEventListener('fetch', (event) => {
+    const req = event.request;
+    if (req.method !== 'GET') return;
+    const url = new URL(req.url);
+    const cacheable = req.mode === 'navigate'
+        || url.origin !== location.origin
+        || SHELL.includes(url.pathname)
+        || url.pathname.startsWith(`${BASE_PATH}/api/locales`);
+    if (!cacheable) return;
+    event.respondWith(fetch(req)
+        .then(res => {
+            if (res.ok || res.type === 'opaque') {
+                const copy = res.clone();
+                caches.open(CACHE).then(cache => cache.put(req, copy));
+            }
+            return res;
+        })
+        .catch(() => caches.match(req).then(hit =>
+            // Deep links such as /track/{id} fall back to the cached page
+            hit || (req.mode === 'navigate' ? caches.match(`${BASE_PATH}/`) : Response.error()))));
+});
 "#;
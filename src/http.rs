@@ -0,0 +1,55 @@
+use anyhow::{Context, Result};
+use reqwest::blocking::{Client, ClientBuilder};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Network settings applied to every outgoing HTTP client (AcoustID,
+/// MusicBrainz), read from the environment (and `.env`):
+/// - `AUDIO_SORTER_PROXY`: `http://`, `https://` or `socks5://` proxy URL
+/// - `AUDIO_SORTER_CA_BUNDLE`: PEM file with extra root certificates, for
+///   TLS-intercepting corporate proxies
+pub struct HttpConfig {
+    pub proxy: Option<String>,
+    pub ca_bundle: Option<PathBuf>,
+}
+
+impl HttpConfig {
+    fn from_env() -> Self {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Self {
+            proxy: var("AUDIO_SORTER_PROXY"),
+            ca_bundle: var("AUDIO_SORTER_CA_BUNDLE").map(PathBuf::from),
+        }
+    }
+}
+
+pub fn config() -> &'static HttpConfig {
+    static CONFIG: OnceLock<HttpConfig> = OnceLock::new();
+    CONFIG.get_or_init(HttpConfig::from_env)
+}
+
+/// A client builder with the configured proxy and CA bundle applied. Without
+/// `AUDIO_SORTER_PROXY`, reqwest still honours `HTTPS_PROXY` / `ALL_PROXY`.
+pub fn builder() -> Result<ClientBuilder> {
+    let config = config();
+    let mut builder = Client::builder();
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy).context("Invalid AUDIO_SORTER_PROXY")?);
+    }
+    if let Some(path) = &config.ca_bundle {
+        let pem =
+            std::fs::read(path).with_context(|| format!("Failed to read CA bundle {:?}", path))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid CA bundle {:?}", path))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    Ok(builder)
+}
+
+/// A client for general requests (MusicBrainz). Cheap to clone and safe to
+/// share across rayon workers.
+pub fn client() -> Result<Client> {
+    builder()?.build().context("Failed to build HTTP client")
+}
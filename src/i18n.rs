@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// Message catalogs shared by the dashboard (`GET /api/locales/{lang}`) and
+/// the CLI scan summary. Keys missing from a translation fall back to English.
+pub const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("locales/en.json")),
+    ("zh-CN", include_str!("locales/zh-CN.json")),
+    ("ja", include_str!("locales/ja.json")),
+];
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Supported locale for a language tag such as `zh-Hans-CN`, `ja_JP.UTF-8`
+/// or `EN`, matched on the primary language.
+pub fn match_tag(tag: &str) -> Option<&'static str> {
+    let primary = tag
+        .split(['-', '_', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    LOCALES.iter().map(|(name, _)| *name).find(|name| {
+        name.split('-')
+            .next()
+            .unwrap_or_default()
+            .eq_ignore_ascii_case(&primary)
+    })
+}
+
+/// First supported language of an `Accept-Language` header, highest weight first.
+pub fn negotiate(accept_language: &str) -> &'static str {
+    let mut ranges: Vec<(&str, f32)> = accept_language
+        .split(',')
+        .filter_map(|part| {
+            let mut fields = part.trim().split(';');
+            let tag = fields.next()?.trim();
+            let weight = fields
+                .find_map(|f| f.trim().strip_prefix("q="))
+                .and_then(|q| q.parse().ok())
+                .unwrap_or(1.0);
+            Some((tag, weight))
+        })
+        .collect();
+    // Stable sort keeps the browser's order between equal weights
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranges
+        .into_iter()
+        .filter(|&(_, weight)| weight > 0.0)
+        .find_map(|(tag, _)| match_tag(tag))
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+/// Raw JSON catalog of `locale`.
+pub fn catalog(locale: &str) -> Option<&'static str> {
+    LOCALES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(locale))
+        .map(|(_, json)| *json)
+}
+
+fn parse(json: &str) -> HashMap<String, String> {
+    serde_json::from_str(json).unwrap_or_default()
+}
+
+/// Locale for CLI output: `AUDIO_SORTER_LANG`, else the POSIX locale
+/// variables, else English.
+pub fn cli_locale() -> &'static str {
+    ["AUDIO_SORTER_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| match_tag(&value))
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+fn cli_messages() -> &'static (HashMap<String, String>, HashMap<String, String>) {
+    static MESSAGES: OnceLock<(HashMap<String, String>, HashMap<String, String>)> = OnceLock::new();
+    MESSAGES.get_or_init(|| {
+        let fallback = parse(catalog(DEFAULT_LOCALE).unwrap_or("{}"));
+        let messages = parse(catalog(cli_locale()).unwrap_or("{}"));
+        (messages, fallback)
+    })
+}
+
+/// CLI message `key` in the user's locale with `{name}` placeholders filled in.
+pub fn t(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let (messages, fallback) = cli_messages();
+    let mut text = messages
+        .get(key)
+        .or_else(|| fallback.get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_string());
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    text
+}
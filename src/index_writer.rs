@@ -0,0 +1,252 @@
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::storage::{AudioLibrary, SharedLibrary};
+
+/// Edits queued before senders wait for the writer
+const QUEUE_DEPTH: usize = 64;
+
+type Job = Box<dyn FnOnce(&mut Writer) + Send>;
+
+/// Where index changes are applied: `index.json` directly (CLI commands own
+/// the file for their run) or the server's `IndexWriter`.
+pub trait IndexAccess {
+    /// The index as last saved.
+    fn read(&self) -> Result<Arc<AudioLibrary>>;
+
+    /// Apply `edit` and save the index if it succeeds.
+    fn update<T, F>(&self, edit: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut AudioLibrary) -> Result<T> + Send + 'static;
+}
+
+/// `index.json` loaded, edited and saved in place.
+pub struct IndexFile(pub PathBuf);
+
+impl IndexAccess for IndexFile {
+    fn read(&self) -> Result<Arc<AudioLibrary>> {
+        Ok(Arc::new(AudioLibrary::load(&self.0)?))
+    }
+
+    fn update<T, F>(&self, edit: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut AudioLibrary) -> Result<T> + Send + 'static,
+    {
+        let mut library = AudioLibrary::load(&self.0)?;
+        let value = edit(&mut library)?;
+        library.save(&self.0)?;
+        Ok(value)
+    }
+}
+
+/// State owned by the writer thread.
+struct Writer {
+    index_path: PathBuf,
+    shared: Arc<SharedLibrary>,
+    library: AudioLibrary,
+    /// Generation of `shared` that `library` was last in sync with
+    generation: u64,
+    /// Staged edits not saved yet
+    dirty: bool,
+}
+
+impl Writer {
+    /// Adopt index changes made outside the writer (a CLI run in another
+    /// process, `POST /api/library/reload`) unless staged edits are pending.
+    fn sync(&mut self) {
+        if self.dirty {
+            return;
+        }
+        let snapshot = self.shared.snapshot_now();
+        if snapshot.generation != self.generation {
+            self.library = (*snapshot.library).clone();
+            self.generation = snapshot.generation;
+        }
+    }
+
+    /// Save staged edits and publish them to the web handlers.
+    fn commit(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.library.save(&self.index_path)?;
+        self.generation = self.shared.replace(self.library.clone());
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Drop whatever a failed edit left behind, back to the last commit.
+    fn discard(&mut self) {
+        let snapshot = self.shared.snapshot_now();
+        self.library = (*snapshot.library).clone();
+        self.generation = snapshot.generation;
+        self.dirty = false;
+    }
+
+    /// Run one queued edit. Edits that save commit earlier staged ones first,
+    /// so a failure only rolls back the edit itself; a failed staged edit drops
+    /// everything staged since the last commit.
+    fn run<T>(
+        &mut self,
+        edit: impl FnOnce(&mut AudioLibrary) -> Result<T>,
+        save: bool,
+    ) -> Result<T> {
+        self.sync();
+        if save {
+            self.commit()?;
+        }
+        let result = edit(&mut self.library).and_then(|value| {
+            self.dirty = true;
+            if save {
+                self.commit()?;
+            }
+            Ok(value)
+        });
+        if result.is_err() {
+            self.discard();
+        }
+        result
+    }
+}
+
+/// Handle to the thread that owns every change the server makes to
+/// `index.json`: scan merges, bulk edits and lookup retries are queued here
+/// and applied one at a time to a single in-memory library, instead of each
+/// loading, modifying and saving the file on its own.
+#[derive(Clone)]
+pub struct IndexWriter {
+    tx: mpsc::Sender<Job>,
+    shared: Arc<SharedLibrary>,
+}
+
+impl IndexWriter {
+    pub fn spawn(index_path: PathBuf, shared: Arc<SharedLibrary>) -> Self {
+        let (tx, mut rx) = mpsc::channel::<Job>(QUEUE_DEPTH);
+        let snapshot = shared.snapshot();
+        let mut writer = Writer {
+            index_path,
+            shared: shared.clone(),
+            library: (*snapshot.library).clone(),
+            generation: snapshot.generation,
+            dirty: false,
+        };
+        // Edits write tags and the index, so they run off the async runtime
+        std::thread::spawn(move || {
+            while let Some(job) = rx.blocking_recv() {
+                job(&mut writer);
+            }
+            if let Err(e) = writer.commit() {
+                eprintln!("Could not save staged index changes: {:#}", e);
+            }
+        });
+        Self { tx, shared }
+    }
+
+    /// Library the web handlers read, as of the last commit.
+    pub fn shared(&self) -> &Arc<SharedLibrary> {
+        &self.shared
+    }
+
+    fn job<T, F>(edit: F, save: bool) -> (Job, oneshot::Receiver<Result<T>>)
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut AudioLibrary) -> Result<T> + Send + 'static,
+    {
+        let (reply, rx) = oneshot::channel();
+        let job: Job = Box::new(move |writer| {
+            // The caller may have gone away; the edit still stands
+            let _ = reply.send(writer.run(edit, save));
+        });
+        (job, rx)
+    }
+
+    fn stopped() -> anyhow::Error {
+        anyhow!("Index writer has stopped")
+    }
+
+    /// Apply `edit`, then save and publish the index. A failed edit changes nothing.
+    pub async fn apply<T, F>(&self, edit: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut AudioLibrary) -> Result<T> + Send + 'static,
+    {
+        let (job, rx) = Self::job(edit, true);
+        self.tx.send(job).await.map_err(|_| Self::stopped())?;
+        rx.await.map_err(|_| Self::stopped())?
+    }
+
+    /// `apply` for edits with effects outside the index, such as tags written
+    /// to files: `edit` also returns how to take those back, which runs when
+    /// the index can't be saved afterwards.
+    pub async fn apply_or_revert<T, R, F>(&self, edit: F) -> Result<T>
+    where
+        T: Send + 'static,
+        R: FnOnce() + Send + 'static,
+        F: FnOnce(&mut AudioLibrary) -> Result<(T, R)> + Send + 'static,
+    {
+        let (reply, rx) = oneshot::channel();
+        let job: Job = Box::new(move |writer| {
+            let mut revert = None;
+            let result = writer.run(
+                |library| {
+                    let (value, undo) = edit(library)?;
+                    revert = Some(undo);
+                    Ok(value)
+                },
+                true,
+            );
+            if let (Err(_), Some(revert)) = (&result, revert) {
+                revert();
+            }
+            let _ = reply.send(result);
+        });
+        self.tx.send(job).await.map_err(|_| Self::stopped())?;
+        rx.await.map_err(|_| Self::stopped())?
+    }
+
+    /// `apply` for blocking threads (scans, lookup retries).
+    pub fn apply_blocking<T, F>(&self, edit: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut AudioLibrary) -> Result<T> + Send + 'static,
+    {
+        let (job, rx) = Self::job(edit, true);
+        self.tx.blocking_send(job).map_err(|_| Self::stopped())?;
+        rx.blocking_recv().map_err(|_| Self::stopped())?
+    }
+
+    /// Apply `edit` in memory only; it is saved by the next `apply` or
+    /// `flush_blocking`. Used for scan batches, which are saved periodically.
+    pub fn stage_blocking<T, F>(&self, edit: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut AudioLibrary) -> Result<T> + Send + 'static,
+    {
+        let (job, rx) = Self::job(edit, false);
+        self.tx.blocking_send(job).map_err(|_| Self::stopped())?;
+        rx.blocking_recv().map_err(|_| Self::stopped())?
+    }
+
+    /// Save and publish staged edits.
+    pub fn flush_blocking(&self) -> Result<()> {
+        self.apply_blocking(|_| Ok(()))
+    }
+}
+
+impl IndexAccess for IndexWriter {
+    fn read(&self) -> Result<Arc<AudioLibrary>> {
+        Ok(self.shared.get())
+    }
+
+    fn update<T, F>(&self, edit: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut AudioLibrary) -> Result<T> + Send + 'static,
+    {
+        self.apply_blocking(edit)
+    }
+}
@@ -0,0 +1,313 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+use crate::bulk_edit::FieldChanges;
+use crate::scanner::{QuarantineReason, QuarantinedFile};
+use crate::spoken_word::ContentKind;
+use crate::storage::{AudioLibrary, IndexedTrack};
+
+/// AcoustID matches below this score are queued for review.
+const LOW_CONFIDENCE_SCORE: f64 = 0.8;
+
+/// Lossless files whose spectrum stops below this were likely transcoded from lossy.
+const TRANSCODE_CUTOFF_HZ: f32 = 19_000.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// The last scan could not decode or read the file
+    DecodeError,
+    /// The last scan skipped an empty, partially downloaded or still growing file
+    Quarantined,
+    LowConfidence,
+    MissingAlbum,
+    MissingGenre,
+    SuspectedTranscode,
+    /// Track numbers of an album have gaps
+    IncompleteAlbum,
+    /// Tags that look like UTF-8 decoded as Windows-1252/Latin-1
+    Mojibake,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Issue {
+    pub kind: IssueKind,
+    /// Absent for files that never made it into the index and for album-level issues
+    pub track_id: Option<String>,
+    /// File, or album folder for `incomplete_album`
+    pub path: String,
+    pub message: String,
+    /// Tag changes that would fix the issue, for `POST /api/tracks/bulk`
+    pub fix: Option<FieldChanges>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct IssueReport {
+    /// Issue count per kind, including kinds filtered out of `issues`
+    pub counts: BTreeMap<IssueKind, usize>,
+    pub issues: Vec<Issue>,
+}
+
+/// Load `scan_errors.json` (path -> error message from the last scan).
+pub fn load_scan_errors(path: &Path) -> Result<BTreeMap<String, String>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(path).context("Failed to read scan errors")?;
+    serde_json::from_str(&content).context("Failed to parse scan errors JSON")
+}
+
+/// Replace `scan_errors.json` with the failures of the scan that just finished.
+/// Failed files are never indexed, so every scan retries them.
+pub fn save_scan_errors(path: &Path, errors: &BTreeMap<PathBuf, String>) -> Result<()> {
+    let map: BTreeMap<String, &String> = errors
+        .iter()
+        .map(|(p, e)| (p.to_string_lossy().into_owned(), e))
+        .collect();
+    let content = serde_json::to_string_pretty(&map).context("Failed to serialize scan errors")?;
+    fs::write(path, content).context("Failed to write scan errors")?;
+    Ok(())
+}
+
+/// Load `quarantine.json` (path -> why the last scan skipped it).
+pub fn load_quarantine(path: &Path) -> Result<BTreeMap<String, QuarantineReason>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(path).context("Failed to read quarantine list")?;
+    serde_json::from_str(&content).context("Failed to parse quarantine JSON")
+}
+
+/// Replace `quarantine.json` with the files skipped by the scan that just ran.
+pub fn save_quarantine(path: &Path, files: &[QuarantinedFile]) -> Result<()> {
+    let map: BTreeMap<String, QuarantineReason> = files
+        .iter()
+        .map(|f| (f.path.to_string_lossy().into_owned(), f.reason))
+        .collect();
+    let content =
+        serde_json::to_string_pretty(&map).context("Failed to serialize quarantine list")?;
+    fs::write(path, content).context("Failed to write quarantine list")?;
+    Ok(())
+}
+
+/// Windows-1252 characters for bytes 0x80..=0x9F (undefined bytes map to C1 controls).
+const CP1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+fn cp1252_byte(c: char) -> Option<u8> {
+    let n = c as u32;
+    if n < 0x80 || (0xA0..=0xFF).contains(&n) {
+        return Some(n as u8);
+    }
+    CP1252_HIGH
+        .iter()
+        .position(|&h| h == c)
+        .map(|i| 0x80 + i as u8)
+}
+
+/// Undo UTF-8 that was decoded as Windows-1252 ("BjÃ¶rk" -> "Björk"). Genuine
+/// Latin-1 text ("Café") does not re-encode to valid UTF-8 and is left alone.
+pub fn repair_mojibake(s: &str) -> Option<String> {
+    if s.is_ascii() {
+        return None;
+    }
+    let bytes: Vec<u8> = s.chars().map(cp1252_byte).collect::<Option<_>>()?;
+    let fixed = String::from_utf8(bytes).ok()?;
+    (fixed != s).then_some(fixed)
+}
+
+fn track_issue(track: &IndexedTrack, kind: IssueKind, message: String) -> Issue {
+    Issue {
+        kind,
+        track_id: Some(track.id.clone()),
+        path: track.path.to_string_lossy().into_owned(),
+        message,
+        fix: None,
+    }
+}
+
+fn mojibake_issue(track: &IndexedTrack) -> Option<Issue> {
+    let meta = &track.metadata;
+    let fix = FieldChanges {
+        title: repair_mojibake(&meta.title),
+        artist: repair_mojibake(&meta.artist),
+        album: meta.album.as_deref().and_then(repair_mojibake),
+        ..Default::default()
+    };
+    let replacement_char = [Some(&meta.title), Some(&meta.artist), meta.album.as_ref()]
+        .into_iter()
+        .flatten()
+        .any(|v| v.contains('\u{FFFD}'));
+
+    if !fix.is_empty() {
+        let fixed: Vec<&str> = [&fix.title, &fix.artist, &fix.album]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        let mut issue = track_issue(
+            track,
+            IssueKind::Mojibake,
+            format!("Garbled tags, probably: {}", fixed.join(" / ")),
+        );
+        issue.fix = Some(fix);
+        Some(issue)
+    } else if replacement_char {
+        Some(track_issue(
+            track,
+            IssueKind::Mojibake,
+            "Tags contain U+FFFD replacement characters".to_string(),
+        ))
+    } else {
+        None
+    }
+}
+
+/// Track-number gaps per album, e.g. tracks 1, 2 and 4 present -> 3 missing.
+fn incomplete_albums(library: &AudioLibrary) -> Vec<Issue> {
+    type AlbumKey = (String, String, u32);
+    let mut albums: BTreeMap<AlbumKey, (String, String, BTreeSet<u32>)> = BTreeMap::new();
+    for track in library.sorted_tracks() {
+        let meta = &track.metadata;
+        let (Some(album), Some(number)) = (meta.album.as_deref(), meta.track_number) else {
+            continue;
+        };
+        let artist = meta.album_artist.as_deref().unwrap_or(&meta.artist);
+        let folder = track
+            .path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_string_lossy()
+            .into_owned();
+        albums
+            .entry((
+                artist.to_lowercase(),
+                album.to_lowercase(),
+                meta.disc_number.unwrap_or(1),
+            ))
+            .or_insert_with(|| (album.to_string(), folder, BTreeSet::new()))
+            .2
+            .insert(number);
+    }
+
+    albums
+        .into_iter()
+        .filter_map(|((_, _, disc), (album, folder, numbers))| {
+            let last = *numbers.iter().next_back()?;
+            // Absurd numbers are tagging errors, not 500-track albums
+            if numbers.len() < 2 || last > 99 {
+                return None;
+            }
+            let missing: Vec<String> = (1..last)
+                .filter(|n| !numbers.contains(n))
+                .map(|n| n.to_string())
+                .collect();
+            if missing.is_empty() {
+                return None;
+            }
+            Some(Issue {
+                kind: IssueKind::IncompleteAlbum,
+                track_id: None,
+                path: folder,
+                message: format!(
+                    "{} (disc {}): missing track {} (highest present: {})",
+                    album,
+                    disc,
+                    missing.join(", "),
+                    last
+                ),
+                fix: None,
+            })
+        })
+        .collect()
+}
+
+/// Collect every problem queue. `kinds` limits the returned issues (empty = all);
+/// counts always cover every kind.
+pub fn collect(
+    library: &AudioLibrary,
+    scan_errors: &BTreeMap<String, String>,
+    quarantine: &BTreeMap<String, QuarantineReason>,
+    kinds: &[IssueKind],
+) -> IssueReport {
+    let mut issues: Vec<Issue> = scan_errors
+        .iter()
+        .map(|(path, error)| Issue {
+            kind: IssueKind::DecodeError,
+            track_id: None,
+            path: path.clone(),
+            message: error.clone(),
+            fix: None,
+        })
+        .collect();
+    issues.extend(quarantine.iter().map(|(path, reason)| Issue {
+        kind: IssueKind::Quarantined,
+        track_id: library.files.get(Path::new(path)).map(|t| t.id.clone()),
+        path: path.clone(),
+        message: format!(
+            "Skipped: {}; checked again by the next scan",
+            reason.label()
+        ),
+        fix: None,
+    }));
+
+    for track in library.sorted_tracks() {
+        let meta = &track.metadata;
+        if let Some(score) = meta.acoustid_score.filter(|s| *s < LOW_CONFIDENCE_SCORE) {
+            issues.push(track_issue(
+                track,
+                IssueKind::LowConfidence,
+                format!("AcoustID match score {:.2}", score),
+            ));
+        }
+        if meta.content_kind == ContentKind::Music {
+            if meta.album.is_none() {
+                issues.push(track_issue(
+                    track,
+                    IssueKind::MissingAlbum,
+                    "No album tag".to_string(),
+                ));
+            }
+            if meta.genre.is_none() {
+                issues.push(track_issue(
+                    track,
+                    IssueKind::MissingGenre,
+                    "No genre".to_string(),
+                ));
+            }
+        }
+        if let Some(q) = &meta.quality {
+            if let Some(cutoff) = q
+                .spectral_cutoff_hz
+                .filter(|c| q.lossless && q.sample_rate >= 44_100 && *c < TRANSCODE_CUTOFF_HZ)
+            {
+                issues.push(track_issue(
+                    track,
+                    IssueKind::SuspectedTranscode,
+                    format!(
+                        "Lossless {} with spectrum cut at {:.1} kHz",
+                        q.codec.to_uppercase(),
+                        cutoff / 1000.0
+                    ),
+                ));
+            }
+        }
+        issues.extend(mojibake_issue(track));
+    }
+    issues.extend(incomplete_albums(library));
+
+    let mut counts: BTreeMap<IssueKind, usize> = BTreeMap::new();
+    for issue in &issues {
+        *counts.entry(issue.kind).or_default() += 1;
+    }
+    if !kinds.is_empty() {
+        issues.retain(|i| kinds.contains(&i.kind));
+    }
+    IssueReport { counts, issues }
+}
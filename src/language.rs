@@ -0,0 +1,107 @@
+use crate::organizer::TrackMetadata;
+
+/// ISO 639-2 code for "no linguistic content", used for instrumentals.
+pub const INSTRUMENTAL: &str = "zxx";
+
+const INSTRUMENTAL_MARKERS: &[&str] = &[
+    "instrumental",
+    "off vocal",
+    "off-vocal",
+    "(inst.)",
+    "(inst)",
+    "karaoke",
+    "伴奏",
+    "オフボーカル",
+    "インスト",
+];
+
+/// Best-effort vocal language for a track.
+///
+/// Uses the language tag when present, then instrumental markers in the title,
+/// then the script the title/artist are written in. Latin-script titles are left
+/// undetermined since they can't be told apart without listening.
+pub fn detect_vocal_language(meta: &TrackMetadata) -> Option<String> {
+    if let Some(lang) = meta.vocal_language.as_deref().and_then(normalize_code) {
+        return Some(lang);
+    }
+
+    if is_instrumental_title(&meta.title) {
+        return Some(INSTRUMENTAL.to_string());
+    }
+
+    detect_script(&format!("{} {}", meta.title, meta.artist)).map(|s| s.to_string())
+}
+
+pub fn is_instrumental_title(title: &str) -> bool {
+    let title = title.to_lowercase();
+    INSTRUMENTAL_MARKERS.iter().any(|m| title.contains(m))
+}
+
+/// Title with instrumental markers and bracket noise removed, for matching a
+/// "Song (Off Vocal)" to its "Song" counterpart.
+pub fn base_title(title: &str) -> String {
+    let mut t = title.to_lowercase();
+    for m in INSTRUMENTAL_MARKERS {
+        t = t.replace(m, " ");
+    }
+    t.replace("inst.", " ")
+        .chars()
+        .map(|c| match c {
+            '(' | ')' | '[' | ']' | '（' | '）' | '【' | '】' | '-' | '~' | '～' => ' ',
+            c => c,
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Map ISO 639-2 tag values (e.g. ID3 `TLAN`) to the two-letter codes used in the index.
+pub fn normalize_code(code: &str) -> Option<String> {
+    let code = code.trim().to_lowercase();
+    let mapped = match code.as_str() {
+        "" | "und" => return None,
+        "jpn" => "ja",
+        "eng" => "en",
+        "zho" | "chi" => "zh",
+        "kor" => "ko",
+        "fra" | "fre" => "fr",
+        "deu" | "ger" => "de",
+        "spa" => "es",
+        "ita" => "it",
+        "por" => "pt",
+        "rus" => "ru",
+        other => other,
+    };
+    Some(mapped.to_string())
+}
+
+fn detect_script(text: &str) -> Option<&'static str> {
+    let mut han = 0;
+    let mut kana = 0;
+    let mut hangul = 0;
+    let mut cyrillic = 0;
+
+    for c in text.chars() {
+        match c as u32 {
+            0x3040..=0x30FF | 0x31F0..=0x31FF => kana += 1,
+            0xAC00..=0xD7AF | 0x1100..=0x11FF | 0x3130..=0x318F => hangul += 1,
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF => han += 1,
+            0x0400..=0x04FF => cyrillic += 1,
+            _ => {}
+        }
+    }
+
+    // Kana is the only reliable way to tell Japanese from Chinese
+    if kana > 0 {
+        Some("ja")
+    } else if hangul > 0 {
+        Some("ko")
+    } else if han > 0 {
+        Some("zh")
+    } else if cyrillic > 0 {
+        Some("ru")
+    } else {
+        None
+    }
+}
@@ -0,0 +1,123 @@
+use clap::Parser;
+use std::path::PathBuf;
+
+pub mod acoustid;
+pub mod analysis_store;
+pub mod analyzers;
+pub mod artists;
+pub mod artwork;
+#[cfg(feature = "sqlite")]
+pub mod beets;
+pub mod bulk_edit;
+pub mod bundle;
+pub mod cleanup;
+pub mod columns;
+pub mod consistency;
+pub mod decode;
+pub mod diff;
+pub mod doctor;
+pub mod download;
+pub mod events;
+pub mod exit_status;
+pub mod fingerprint;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod hooks;
+pub mod html_template;
+pub mod http;
+pub mod i18n;
+pub mod index_writer;
+pub mod issues;
+pub mod language;
+pub mod lookup_cache;
+pub mod lookup_queue;
+pub mod loudness;
+pub mod matching;
+pub mod memory_budget;
+pub mod merge_policy;
+pub mod musicbrainz;
+pub mod near_duplicates;
+pub mod neighbors;
+pub mod nfo;
+pub mod notify;
+pub mod organize;
+pub mod organizer;
+pub mod pairing;
+pub mod playlist;
+pub mod playlist_import;
+pub mod presets;
+pub mod priority;
+pub mod profile;
+pub mod progress;
+pub mod prompt;
+pub mod quality;
+pub mod radio;
+pub mod rate_limit;
+pub mod reanalyze;
+pub mod reports;
+pub mod scan_manager;
+pub mod scanner;
+pub mod server;
+pub mod service;
+pub mod share;
+pub mod similarity;
+pub mod splitter;
+pub mod spoken_word;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_index;
+pub mod storage;
+pub mod timeline;
+pub mod unicode;
+pub mod upload;
+pub mod worker;
+pub mod works;
+
+pub use organizer::TrackMetadata;
+
+#[derive(Parser, Debug)]
+pub struct ScanArgs {
+    /// Input directory to scan
+    #[arg(short, long)]
+    pub input_dir: PathBuf,
+
+    /// Directory to store index data (index.json)
+    #[arg(short, long)]
+    pub output_dir: PathBuf,
+
+    /// Offline mode (no network; only local tags and responses cached by earlier online scans)
+    #[arg(long, default_value_t = false)]
+    pub offline: bool,
+
+    /// AcoustID Client ID (Optional in offline mode)
+    #[arg(long, env = "ACOUSTID_CLIENT_ID")]
+    pub client_id: Option<String>,
+
+    /// Only report what would be added, updated and removed; decode nothing
+    #[arg(long, default_value_t = false)]
+    pub dry_run: bool,
+
+    /// Process at most N new/modified files (repeat the command to continue)
+    #[arg(long)]
+    pub limit: Option<usize>,
+
+    /// Process only a deterministic sample of the library, e.g. "5%"
+    #[arg(long, value_parser = scanner::parse_percent)]
+    pub sample: Option<f64>,
+
+    /// Also scan hidden and system files/folders (dotfiles, `@eaDir`, `System Volume Information`)
+    #[arg(long, default_value_t = false)]
+    pub include_hidden: bool,
+
+    /// Combined size (MiB) of files decoded at once (default: a quarter of RAM)
+    #[arg(long)]
+    pub memory_budget_mb: Option<u64>,
+
+    /// Run at idle CPU and I/O priority so the machine stays responsive
+    #[arg(long, default_value_t = false)]
+    pub background: bool,
+
+    /// Write phase and stage timings, worker utilization and I/O counters to
+    /// this JSON file for performance reports (no paths or tags)
+    #[arg(long)]
+    pub profile: Option<PathBuf>,
+}
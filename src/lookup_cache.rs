@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Persistent cache of raw AcoustID/MusicBrainz response bodies.
+///
+/// Shared by all scan workers. In replay-only mode (`scan --offline`) misses fail
+/// instead of going to the network, so previously seen fingerprints still resolve
+/// to full online metadata on air-gapped machines.
+#[derive(Default)]
+pub struct LookupCache {
+    entries: DashMap<String, String>,
+    replay_only: bool,
+}
+
+impl LookupCache {
+    pub fn new(replay_only: bool) -> Self {
+        Self {
+            entries: DashMap::new(),
+            replay_only,
+        }
+    }
+
+    /// Load from a JSON file. Returns an empty cache if the file doesn't exist.
+    pub fn load(path: &Path, replay_only: bool) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new(replay_only));
+        }
+        let content = fs::read_to_string(path).context("Failed to read lookup cache")?;
+        let map: HashMap<String, String> =
+            serde_json::from_str(&content).context("Failed to parse lookup cache")?;
+        Ok(Self {
+            entries: map.into_iter().collect(),
+            replay_only,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let map: HashMap<String, String> = self
+            .entries
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect();
+        let content = serde_json::to_string(&map).context("Failed to serialize lookup cache")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create lookup cache directory")?;
+        }
+        fs::write(path, content).context("Failed to write lookup cache")?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn is_replay_only(&self) -> bool {
+        self.replay_only
+    }
+
+    /// Return the cached body for `key`, or run `fetch` and remember its result.
+    pub fn get_or_fetch<F>(&self, key: String, fetch: F) -> Result<String>
+    where
+        F: FnOnce() -> Result<String>,
+    {
+        if let Some(body) = self.entries.get(&key) {
+            return Ok(body.clone());
+        }
+        if self.replay_only {
+            return Err(anyhow::anyhow!("Not in lookup cache (offline): {}", key));
+        }
+        let body = fetch()?;
+        self.entries.insert(key, body.clone());
+        Ok(body)
+    }
+}
+
+/// Run `fetch` through the cache when one is given.
+pub fn cached<F>(cache: Option<&LookupCache>, key: String, fetch: F) -> Result<String>
+where
+    F: FnOnce() -> Result<String>,
+{
+    match cache {
+        Some(c) => c.get_or_fetch(key, fetch),
+        None => fetch(),
+    }
+}
+
+pub fn acoustid_key(fingerprint: &str, duration: f64) -> String {
+    // Fingerprints are a few KB; a stable hash keeps the cache file small
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in fingerprint.as_bytes() {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("acoustid:{:016x}:{}", hash, duration.round() as u64)
+}
+
+pub fn mb_recording_key(recording_id: &str) -> String {
+    format!("mb:recording:{}", recording_id)
+}
+
+pub fn mb_work_key(work_id: &str) -> String {
+    format!("mb:work:{}", work_id)
+}
@@ -0,0 +1,210 @@
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::acoustid;
+use crate::analysis_store::AnalysisStore;
+use crate::cleanup::Cleanup;
+use crate::http;
+use crate::index_writer::IndexAccess;
+use crate::lookup_cache::LookupCache;
+use crate::merge_policy::MergePolicy;
+use crate::pairing;
+use crate::worker;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingLookup {
+    /// UNIX timestamp (seconds) of the first failed attempt
+    pub queued_at: u64,
+    pub attempts: u32,
+    pub last_error: String,
+}
+
+/// Tracks whose online lookup failed for a transient reason (network, server
+/// error, quota), persisted as `pending_lookups.json` so they get online
+/// metadata later without a rescan. Shared by scan workers like `LookupCache`.
+#[derive(Default)]
+pub struct LookupQueue {
+    entries: DashMap<PathBuf, PendingLookup>,
+}
+
+#[derive(Debug, Default)]
+pub struct FlushSummary {
+    pub resolved: usize,
+    /// Lookups that got through but found no match; no longer queued
+    pub no_match: usize,
+    pub still_pending: usize,
+    /// Queued files that left the index or have no fingerprint
+    pub dropped: usize,
+}
+
+impl LookupQueue {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).context("Failed to read lookup queue")?;
+        let map: BTreeMap<PathBuf, PendingLookup> =
+            serde_json::from_str(&content).context("Failed to parse lookup queue")?;
+        Ok(Self {
+            entries: map.into_iter().collect(),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let map: BTreeMap<PathBuf, PendingLookup> = self
+            .entries
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect();
+        let content =
+            serde_json::to_string_pretty(&map).context("Failed to serialize lookup queue")?;
+        fs::write(path, content).context("Failed to write lookup queue")?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Queue `path`, or count another failed attempt if it is already queued.
+    pub fn enqueue(&self, path: &Path, error: &anyhow::Error) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut entry = self
+            .entries
+            .entry(path.to_path_buf())
+            .or_insert_with(|| PendingLookup {
+                queued_at: now,
+                attempts: 0,
+                last_error: String::new(),
+            });
+        entry.attempts += 1;
+        entry.last_error = format!("{:#}", error);
+    }
+
+    pub fn remove(&self, path: &Path) {
+        self.entries.remove(path);
+    }
+
+    /// Queued paths with their entries, sorted by path.
+    pub fn entries(&self) -> Vec<(PathBuf, PendingLookup)> {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+}
+
+/// Retry up to `limit` queued lookups for the index in `index_dir`, reusing the
+/// stored fingerprints. Resolved tracks are merged like `enrich` does; lookups
+/// that fail transiently again stay queued. Lookups run against `index` as
+/// read at the start; only the merge holds it, so the server's writer stays
+/// free for other edits in between.
+pub fn flush(
+    index: &impl IndexAccess,
+    index_dir: &Path,
+    client_id: &str,
+    limit: Option<usize>,
+) -> Result<FlushSummary> {
+    let queue_path = index_dir.join("pending_lookups.json");
+    let cache_path = index_dir.join("lookup_cache.json");
+    let queue = LookupQueue::load(&queue_path)?;
+    let mut summary = FlushSummary::default();
+    if queue.is_empty() {
+        return Ok(summary);
+    }
+
+    let library = index.read()?;
+    let lookup_cache = LookupCache::load(&cache_path, false)?;
+    let cleanup = Cleanup::load(&index_dir.join("cleanup.json"))?;
+    let policy = MergePolicy::load(&index_dir.join("merge_policy.json"))?;
+
+    let mut pending = Vec::new();
+    for (path, _) in queue.entries() {
+        match library.files.get(&path) {
+            Some(track) if track.metadata.fingerprint.is_some() => pending.push(path),
+            _ => {
+                queue.remove(&path);
+                summary.dropped += 1;
+            }
+        }
+    }
+    if let Some(limit) = limit {
+        pending.truncate(limit);
+    }
+
+    // The last run's key or quota problem may have been fixed since
+    acoustid::resume();
+    let http_client = http::client()?;
+    let results: Vec<_> = pending
+        .par_iter()
+        .map_init(
+            || http_client.clone(),
+            |client, path| {
+                let mut meta = library.files[path].metadata.clone();
+                let result = worker::enrich_track(
+                    path,
+                    &mut meta,
+                    client_id,
+                    client,
+                    &lookup_cache,
+                    &policy,
+                )
+                .map(|_| meta);
+                (path, result)
+            },
+        )
+        .collect();
+
+    let mut resolved = Vec::new();
+    for (path, result) in results {
+        match result {
+            Ok(mut meta) => {
+                cleanup.apply(&mut meta);
+                resolved.push((path.clone(), meta));
+                queue.remove(path);
+                summary.resolved += 1;
+            }
+            Err(e) if acoustid::is_unavailable(&e) => queue.enqueue(path, &e),
+            Err(_) => {
+                queue.remove(path);
+                summary.no_match += 1;
+            }
+        }
+    }
+    summary.still_pending = queue.len();
+
+    if summary.resolved > 0 {
+        // Titles may have changed, so karaoke pairs are re-linked
+        let analysis_store =
+            AnalysisStore::load(&index_dir.join("analysis.bin")).unwrap_or_default();
+        index.update(move |library| {
+            for (path, meta) in resolved {
+                // Files removed while the lookups ran stay removed
+                if let Some(track) = library.files.get_mut(&path) {
+                    track.metadata = meta;
+                }
+            }
+            pairing::link_vocal_pairs(library, &analysis_store);
+            Ok(())
+        })?;
+    }
+    lookup_cache.save(&cache_path)?;
+    queue.save(&queue_path)?;
+    Ok(summary)
+}
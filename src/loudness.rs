@@ -0,0 +1,147 @@
+/// ReplayGain 2.0 reference level.
+pub const REFERENCE_LUFS: f32 = -18.0;
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+#[derive(Clone, Copy)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+/// The two K-weighting stages (high shelf, then high pass) for `sample_rate`.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let fs = sample_rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b0: (vh + vb * k / q + k * k) / a0,
+        b1: 2.0 * (k * k - vh) / a0,
+        b2: (vh - vb * k / q + k * k) / a0,
+        a1: 2.0 * (k * k - 1.0) / a0,
+        a2: (1.0 - k / q + k * k) / a0,
+        z1: 0.0,
+        z2: 0.0,
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b0: 1.0,
+        b1: -2.0,
+        b2: 1.0,
+        a1: 2.0 * (k * k - 1.0) / a0,
+        a2: (1.0 - k / q + k * k) / a0,
+        z1: 0.0,
+        z2: 0.0,
+    };
+
+    [shelf, high_pass]
+}
+
+/// Streaming integrated loudness meter per ITU-R BS.1770 / EBU R128
+/// (K-weighting, 400 ms blocks with 75% overlap, absolute and relative gating).
+pub struct LoudnessMeter {
+    channels: usize,
+    filters: Vec<[Biquad; 2]>,
+    /// Frames per 100 ms step (blocks are four steps long)
+    step_frames: usize,
+    frames_in_step: usize,
+    step_energy: f64,
+    /// Weighted mean square of the most recent steps
+    recent_steps: Vec<f64>,
+    block_energies: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+        Self {
+            channels,
+            filters: vec![k_weighting(sample_rate); channels],
+            step_frames: (sample_rate as usize / 10).max(1),
+            frames_in_step: 0,
+            step_energy: 0.0,
+            recent_steps: Vec::with_capacity(4),
+            block_energies: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, interleaved: &[f32]) {
+        for frame in interleaved.chunks_exact(self.channels) {
+            for (ch, &s) in frame.iter().enumerate() {
+                let [shelf, high_pass] = &mut self.filters[ch];
+                let y = high_pass.process(shelf.process(s as f64));
+                // Surround channels (beyond L, R, C) are weighted +1.5 dB
+                let weight = if ch >= 3 { 1.41 } else { 1.0 };
+                self.step_energy += weight * y * y;
+            }
+
+            self.frames_in_step += 1;
+            if self.frames_in_step == self.step_frames {
+                if self.recent_steps.len() == 4 {
+                    self.recent_steps.remove(0);
+                }
+                self.recent_steps
+                    .push(self.step_energy / self.step_frames as f64);
+                if self.recent_steps.len() == 4 {
+                    self.block_energies
+                        .push(self.recent_steps.iter().sum::<f64>() / 4.0);
+                }
+                self.frames_in_step = 0;
+                self.step_energy = 0.0;
+            }
+        }
+    }
+
+    /// Gated integrated loudness; `None` for silence or audio shorter than 400 ms.
+    pub fn integrated_lufs(&self) -> Option<f32> {
+        let lufs = |energy: f64| -0.691 + 10.0 * energy.log10();
+        let mean = |blocks: &[f64]| blocks.iter().sum::<f64>() / blocks.len() as f64;
+
+        let audible: Vec<f64> = self
+            .block_energies
+            .iter()
+            .copied()
+            .filter(|&e| e > 0.0 && lufs(e) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if audible.is_empty() {
+            return None;
+        }
+
+        let relative_gate = lufs(mean(&audible)) + RELATIVE_GATE_LU;
+        let gated: Vec<f64> = audible
+            .into_iter()
+            .filter(|&e| lufs(e) > relative_gate)
+            .collect();
+        if gated.is_empty() {
+            return None;
+        }
+        Some(lufs(mean(&gated)) as f32)
+    }
+}
+
+/// Gain (dB) that brings a track to the ReplayGain 2.0 reference level.
+pub fn replay_gain_db(integrated_lufs: f32) -> f32 {
+    REFERENCE_LUFS - integrated_lufs
+}
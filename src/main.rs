@@ -1,23 +1,25 @@
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use rayon::prelude::*;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-pub mod acoustid;
-pub mod analysis_store;
-pub mod fingerprint;
-pub mod html_template;
-pub mod musicbrainz;
-pub mod organizer;
-pub mod scan_manager;
-pub mod scanner;
-pub mod server;
-pub mod storage;
-pub mod worker;
+#[cfg(feature = "sqlite")]
+use audio_sorter::beets;
+use audio_sorter::{
+    acoustid, analysis_store, analyzers, artwork, bundle, cleanup, diff, doctor, exit_status,
+    hooks, http, i18n, index_writer, issues, lookup_cache, lookup_queue, memory_budget,
+    merge_policy, neighbors, nfo, notify, organize, pairing, playlist, playlist_import, priority,
+    profile, progress, reanalyze, reports, scanner, server, service, splitter,
+    storage::{self, AudioLibrary},
+    timeline, worker, ScanArgs, TrackMetadata,
+};
 
-use organizer::TrackMetadata;
-use storage::{AudioLibrary, IndexedTrack};
+/// Files processed between interrupt checks in a CLI scan
+const SCAN_BATCH_SIZE: usize = 200;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -32,25 +34,230 @@ enum Commands {
     Scan(ScanArgs),
     /// Start web dashboard
     Serve(ServeArgs),
+    /// Propose track splits for a long continuous file (live set, vinyl side)
+    Split(SplitArgs),
+    /// Fill in online metadata for indexed tracks without re-decoding audio
+    Enrich(EnrichArgs),
+    /// Recompute selected analysis products for indexed tracks, keeping their metadata
+    Reanalyze(ReanalyzeArgs),
+    /// Re-apply cleanup.json (find/replace rules, normalization) to the whole index
+    Cleanup(CleanupArgs),
+    /// Copy indexed tracks into a folder layout rendered from a path template
+    Organize(OrganizeArgs),
+    /// Find folders mixing embedded and folder cover art, and embed or extract it
+    Artwork(ArtworkArgs),
+    /// List the largest, lowest-bitrate or otherwise odd files with reclaimable space
+    Report(ReportArgs),
+    /// Compare two index snapshots: added, removed, retagged and moved tracks
+    Diff(DiffArgs),
+    /// Export or import the index as a portable bundle
+    #[command(subcommand)]
+    Index(IndexCommand),
+    /// Turn M3U/M3U8, PLS and XSPF files exported by other players into stored playlists
+    #[command(subcommand)]
+    Playlist(PlaylistCommand),
+    /// List or retry online lookups queued after network or quota failures
+    #[command(subcommand)]
+    Lookups(LookupsCommand),
+    /// Check settings, index consistency, external tools, the AcoustID key and permissions
+    Doctor(DoctorArgs),
 }
 
 #[derive(Parser, Debug)]
-pub struct ScanArgs {
-    /// Input directory to scan
+struct DoctorArgs {
+    /// Directory containing index data (index.json)
     #[arg(short, long)]
-    input_dir: PathBuf,
+    output_dir: PathBuf,
 
-    /// Directory to store index data (index.json)
+    /// Music directory that scans read
     #[arg(short, long)]
-    output_dir: PathBuf,
+    input_dir: Option<PathBuf>,
+
+    /// AcoustID Client ID
+    #[arg(long, env = "ACOUSTID_CLIENT_ID")]
+    client_id: Option<String>,
 
-    /// Offline mode (skip AcoustID/MusicBrainz and only use local tags)
+    /// Skip the AcoustID key check (no network access)
     #[arg(long, default_value_t = false)]
     offline: bool,
 
-    /// AcoustID Client ID (Optional in offline mode)
+    /// Also list checks that passed
+    #[arg(short, long, default_value_t = false)]
+    verbose: bool,
+}
+
+#[derive(Parser, Debug)]
+struct DiffArgs {
+    /// Earlier snapshot: index directory, index.json copy or export bundle
+    #[arg(long)]
+    old: PathBuf,
+
+    /// Later snapshot, in any of the same forms
+    #[arg(long)]
+    new: PathBuf,
+
+    #[arg(long, value_enum, default_value_t = diff::DiffFormat::Json)]
+    format: diff::DiffFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum IndexCommand {
+    /// Write index, analysis, playlists and settings into one bundle file
+    Export(IndexExportArgs),
+    /// Restore a bundle into an index directory, optionally under a new library root
+    Import(IndexImportArgs),
+    /// Rewrite analysis.bin with f32, f16 or 8-bit quantized vectors
+    EncodeVectors(IndexEncodeVectorsArgs),
+    /// Move index.json into the SQLite index.db now instead of at the next save
+    #[cfg(feature = "sqlite")]
+    Migrate(IndexMigrateArgs),
+    /// Bring tags, MusicBrainz IDs and files of a beets library.db into the index
+    #[cfg(feature = "sqlite")]
+    ImportBeets(IndexImportBeetsArgs),
+}
+
+#[derive(Subcommand, Debug)]
+enum PlaylistCommand {
+    /// Match playlist entries to indexed tracks and save them to playlists.json
+    Import(PlaylistImportArgs),
+}
+
+#[derive(Parser, Debug)]
+struct PlaylistImportArgs {
+    /// Directory containing index data (index.json)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// Playlist files (.m3u, .m3u8, .pls, .xspf)
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Stored playlist name (default: the file name without extension; one file only)
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Replace a path prefix of the entries, e.g. 'D:\Music=/mnt/music' (repeatable)
+    #[arg(long = "map", value_name = "FROM=TO", value_parser = playlist_import::parse_path_map)]
+    maps: Vec<playlist_import::PathMap>,
+
+    /// Only match by path, not by title, artist and length
+    #[arg(long, default_value_t = false)]
+    no_fuzzy: bool,
+
+    /// Replace stored playlists of the same name
+    #[arg(long, default_value_t = false)]
+    force: bool,
+
+    /// Report matches without writing playlists.json
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum LookupsCommand {
+    /// Show tracks waiting for an online lookup
+    List(LookupsListArgs),
+    /// Retry queued lookups and merge the results into the index
+    Flush(LookupsFlushArgs),
+}
+
+#[derive(Parser, Debug)]
+struct LookupsListArgs {
+    /// Directory containing index data (index.json)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+struct LookupsFlushArgs {
+    /// Directory containing index data (index.json)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// AcoustID Client ID
     #[arg(long, env = "ACOUSTID_CLIENT_ID")]
-    client_id: Option<String>,
+    client_id: String,
+
+    /// Retry at most N queued tracks
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+#[derive(Parser, Debug)]
+struct IndexExportArgs {
+    /// Directory containing index data (index.json)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// Bundle file to write, e.g. lib.asbundle
+    #[arg(long)]
+    bundle: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+struct IndexEncodeVectorsArgs {
+    /// Directory containing index data (analysis.bin)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// Storage encoding; later scans keep writing the store this way
+    #[arg(long, value_enum)]
+    encoding: analysis_store::VectorEncoding,
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Parser, Debug)]
+struct IndexMigrateArgs {
+    /// Directory containing index data (index.json)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Parser, Debug)]
+struct IndexImportBeetsArgs {
+    /// Directory containing index data (index.json)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// beets library database, e.g. ~/.config/beets/library.db
+    #[arg(long)]
+    db: PathBuf,
+
+    /// Replace a path prefix of the beets library, e.g. '/home/me/Music=D:\Music' (repeatable)
+    #[arg(long = "map", value_name = "FROM=TO", value_parser = playlist_import::parse_path_map)]
+    maps: Vec<playlist_import::PathMap>,
+
+    /// Keep the index's tags and only fill the fields it lacks
+    #[arg(long, default_value_t = false)]
+    fill_missing_only: bool,
+
+    /// Report what would change without saving the index
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+}
+
+#[derive(Parser, Debug)]
+struct IndexImportArgs {
+    /// Directory to write index data into
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// Bundle file to read
+    #[arg(long)]
+    bundle: PathBuf,
+
+    /// Library root on this machine (default: the root recorded at export)
+    #[arg(long)]
+    root: Option<PathBuf>,
+
+    /// Overwrite an existing index in the output directory
+    #[arg(long, default_value_t = false)]
+    force: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -66,25 +273,1030 @@ struct ServeArgs {
     /// Input directory to scan (required for web-based scanning)
     #[arg(long)]
     input_dir: Option<PathBuf>,
+
+    /// Serve under a path prefix, e.g. "/music" behind an nginx subpath proxy
+    #[arg(long, default_value = "")]
+    base_path: String,
+
+    /// External URL of the app, e.g. "https://example.com/music", for stream URLs in playlists
+    #[arg(long)]
+    public_url: Option<String>,
+
+    /// Trust X-Forwarded-* headers from a reverse proxy in front of the server
+    #[arg(long, default_value_t = false)]
+    trust_proxy: bool,
+
+    /// Allowed CORS origin (repeatable); "*" allows any origin
+    #[arg(long = "cors-origin")]
+    cors_origins: Vec<String>,
+
+    /// Requests per minute per client IP on mutation endpoints (0 = unlimited)
+    #[arg(long, default_value_t = 10)]
+    rate_limit: u32,
+
+    /// Maximum request body size in bytes on mutation endpoints
+    #[arg(long, default_value_t = 64 * 1024)]
+    max_body_bytes: usize,
+
+    /// Retry queued online lookups every N minutes (0 = never; needs ACOUSTID_CLIENT_ID)
+    #[arg(long, default_value_t = 30)]
+    retry_lookups_mins: u64,
+
+    /// Directory `POST /api/upload` writes to (default: <input-dir>/incoming)
+    #[arg(long)]
+    incoming_dir: Option<PathBuf>,
+
+    /// Bearer token for `POST /api/upload`; uploads are disabled without one
+    #[arg(long, env = "AUDIO_SORTER_UPLOAD_TOKEN", hide_env_values = true)]
+    upload_token: Option<String>,
+
+    /// Maximum size in bytes of one `POST /api/upload` request
+    #[arg(long, default_value_t = 1024 * 1024 * 1024)]
+    max_upload_bytes: usize,
+
+    /// Also serve the gRPC control API on this port
+    #[cfg(feature = "grpc")]
+    #[arg(long)]
+    grpc_port: Option<u16>,
+
+    /// Register these serve options as a systemd unit (Linux) or Windows service, then exit
+    #[arg(long, default_value_t = false)]
+    install_service: bool,
+
+    /// Run under the Windows service control manager (set by --install-service)
+    #[cfg(windows)]
+    #[arg(long, default_value_t = false, hide = true)]
+    service_mode: bool,
+}
+
+impl ServeArgs {
+    /// The `serve` command line reproducing these options, with absolute
+    /// paths since services don't start in the current directory.
+    fn service_command_line(&self) -> Result<Vec<String>> {
+        let absolute = |p: &Path| -> Result<String> {
+            Ok(std::path::absolute(p)
+                .with_context(|| format!("Failed to resolve {:?}", p))?
+                .display()
+                .to_string())
+        };
+        let mut args = vec![
+            "serve".to_string(),
+            "--index-dir".to_string(),
+            absolute(&self.index_dir)?,
+            "--port".to_string(),
+            self.port.to_string(),
+        ];
+        if let Some(input_dir) = &self.input_dir {
+            args.extend(["--input-dir".to_string(), absolute(input_dir)?]);
+        }
+        if let Some(incoming_dir) = &self.incoming_dir {
+            args.extend(["--incoming-dir".to_string(), absolute(incoming_dir)?]);
+        }
+        if !self.base_path.is_empty() {
+            args.extend(["--base-path".to_string(), self.base_path.clone()]);
+        }
+        if let Some(url) = &self.public_url {
+            args.extend(["--public-url".to_string(), url.clone()]);
+        }
+        if self.trust_proxy {
+            args.push("--trust-proxy".to_string());
+        }
+        for origin in &self.cors_origins {
+            args.extend(["--cors-origin".to_string(), origin.clone()]);
+        }
+        args.extend([
+            "--rate-limit".to_string(),
+            self.rate_limit.to_string(),
+            "--max-body-bytes".to_string(),
+            self.max_body_bytes.to_string(),
+            "--retry-lookups-mins".to_string(),
+            self.retry_lookups_mins.to_string(),
+            "--max-upload-bytes".to_string(),
+            self.max_upload_bytes.to_string(),
+        ]);
+        // The upload token is left out so it doesn't end up in the unit file;
+        // set AUDIO_SORTER_UPLOAD_TOKEN in the service environment instead
+        #[cfg(feature = "grpc")]
+        if let Some(port) = self.grpc_port {
+            args.extend(["--grpc-port".to_string(), port.to_string()]);
+        }
+        Ok(args)
+    }
+}
+
+#[derive(Parser, Debug)]
+struct EnrichArgs {
+    /// Directory containing index data (index.json)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// AcoustID Client ID
+    #[arg(long, env = "ACOUSTID_CLIENT_ID")]
+    client_id: String,
+
+    /// Also re-query tracks that already have online metadata
+    #[arg(long, default_value_t = false)]
+    force: bool,
+
+    /// Enrich at most N tracks
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+#[derive(Parser, Debug)]
+struct ReanalyzeArgs {
+    /// Directory containing index data (index.json, analysis.bin)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// Analysis products to recompute (repeatable or comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',', required = true)]
+    scope: Vec<reanalyze::Scope>,
+
+    /// Only tracks matching field=value (artist, album, genre, year, decade,
+    /// vocal_language); repeat to combine
+    #[arg(long)]
+    filter: Vec<String>,
+
+    /// Reanalyze at most N tracks
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Only list the tracks that would be reanalyzed
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+}
+
+#[derive(Parser, Debug)]
+struct CleanupArgs {
+    /// Directory containing index data (index.json, cleanup.json)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// Save the changes; without this only the affected tracks are listed
+    #[arg(long, default_value_t = false)]
+    apply: bool,
+}
+
+#[derive(Parser, Debug)]
+struct OrganizeArgs {
+    /// Directory containing index data (index.json)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// Destination root for the organized copy
+    #[arg(long)]
+    dest: PathBuf,
+
+    /// Relative path template; fields: artist, album, title, genre, genre_path, year,
+    /// decade, original_artist, original_year [default: {artist}/{album}/{title}]
+    #[arg(long)]
+    template: Option<String>,
+
+    /// Group by genre (template {genre_path}/{artist}/{album}/{title}), typically with --link
+    #[arg(long, default_value_t = false)]
+    by_genre: bool,
+
+    /// Copy files, or build a parallel tree of symlinks/hardlinks
+    #[arg(long, value_enum, default_value_t = organize::LinkMode::Copy)]
+    link: organize::LinkMode,
+
+    /// How to handle tracks that render to the same target path
+    #[arg(long, value_enum, default_value_t = organize::CollisionStrategy::Hash)]
+    on_collision: organize::CollisionStrategy,
+
+    /// Also write Kodi/Jellyfin album.nfo and artist.nfo files and folder art
+    #[arg(long, default_value_t = false)]
+    nfo: bool,
+
+    /// Only report the planned copies and all collisions
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+}
+
+#[derive(Parser, Debug)]
+struct ArtworkArgs {
+    /// Directory containing index data (index.json)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// Report only, embed folder images into tracks, or extract embedded art to folders
+    #[arg(long, value_enum, default_value_t = artwork::ArtworkMode::Report)]
+    mode: artwork::ArtworkMode,
+
+    /// List the files that would be written without writing them
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+}
+
+#[derive(Parser, Debug)]
+struct ReportArgs {
+    /// Directory containing index data (index.json)
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    #[arg(long, value_enum, default_value_t = reports::ReportKind::Largest)]
+    kind: reports::ReportKind,
+
+    /// Number of files to list
+    #[arg(long, default_value_t = 50)]
+    limit: usize,
+
+    /// Lossy files below this bitrate count as low quality
+    #[arg(long, default_value_t = 160)]
+    min_kbps: u32,
+
+    /// Bitrate assumed when estimating the savings of re-encoding large files
+    #[arg(long, default_value_t = 320)]
+    target_kbps: u32,
+}
+
+#[derive(Parser, Debug)]
+struct SplitArgs {
+    /// Audio file to split
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Minimum silence length (seconds) treated as a track boundary
+    #[arg(long, default_value_t = 2.0)]
+    min_silence: f64,
+
+    /// Level (dBFS) below which audio counts as silence
+    #[arg(long, default_value_t = -50.0, allow_hyphen_values = true)]
+    threshold_db: f32,
+
+    /// Shorter segments are merged into their neighbour
+    #[arg(long, default_value_t = 30.0)]
+    min_track: f64,
+
+    /// Fingerprint each segment and look it up on AcoustID
+    #[arg(long, default_value_t = false)]
+    identify: bool,
+
+    /// Write a CUE sheet next to the input file
+    #[arg(long, default_value_t = false)]
+    cue: bool,
+
+    /// Export each segment as a WAV file into this directory
+    #[arg(long)]
+    export_dir: Option<PathBuf>,
+
+    /// AcoustID Client ID (used with --identify)
+    #[arg(long, env = "ACOUSTID_CLIENT_ID")]
+    client_id: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv::dotenv().ok();
+    analyzers::register_builtins();
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Scan(args) => run_scan(args).await,
+        Commands::Scan(args) => {
+            let index_dir = args.output_dir.clone();
+            let quiet = args.dry_run;
+            let started = Instant::now();
+            let result = run_scan(args).await;
+            let elapsed = started.elapsed().as_secs();
+            if !quiet {
+                notify::job_finished(&index_dir, "Scan", elapsed, &result);
+            }
+            if let Err(e) = &result {
+                eprintln!("Error: {:#}", e);
+            }
+            std::process::exit(exit_status::report("scan", &result, elapsed));
+        }
         Commands::Serve(args) => run_serve(args).await,
+        // Blocking HTTP client must not run on the async runtime thread
+        Commands::Split(args) => tokio::task::spawn_blocking(move || run_split(args)).await?,
+        Commands::Enrich(args) => tokio::task::spawn_blocking(move || run_enrich(args)).await?,
+        Commands::Reanalyze(args) => run_reanalyze(args),
+        Commands::Cleanup(args) => run_cleanup(args),
+        Commands::Organize(args) => {
+            let index_dir = args.output_dir.clone();
+            let quiet = args.dry_run;
+            let started = Instant::now();
+            let result = run_organize(args);
+            if !quiet {
+                notify::job_finished(&index_dir, "Organize", started.elapsed().as_secs(), &result);
+            }
+            result
+        }
+        Commands::Artwork(args) => run_artwork(args),
+        Commands::Report(args) => run_report(args),
+        Commands::Diff(args) => run_diff(args),
+        Commands::Index(IndexCommand::Export(args)) => run_index_export(args),
+        Commands::Index(IndexCommand::Import(args)) => run_index_import(args),
+        Commands::Index(IndexCommand::EncodeVectors(args)) => run_index_encode_vectors(args),
+        #[cfg(feature = "sqlite")]
+        Commands::Index(IndexCommand::Migrate(args)) => run_index_migrate(args),
+        #[cfg(feature = "sqlite")]
+        Commands::Index(IndexCommand::ImportBeets(args)) => run_index_import_beets(args),
+        Commands::Playlist(PlaylistCommand::Import(args)) => run_playlist_import(args),
+        Commands::Lookups(LookupsCommand::List(args)) => run_lookups_list(args),
+        Commands::Lookups(LookupsCommand::Flush(args)) => {
+            tokio::task::spawn_blocking(move || run_lookups_flush(args)).await?
+        }
+        Commands::Doctor(args) => tokio::task::spawn_blocking(move || run_doctor(args)).await?,
     }
 }
 
 async fn run_serve(args: ServeArgs) -> Result<()> {
-    server::start_server(args.index_dir, args.input_dir, args.port).await;
+    if args.install_service {
+        return service::install(&args.service_command_line()?);
+    }
+    // Reports the service stopped once the server has returned
+    #[cfg(windows)]
+    let _service = if args.service_mode {
+        Some(service::windows::attach()?)
+    } else {
+        None
+    };
+
+    let options = server::ServerOptions {
+        port: args.port,
+        base_path: args.base_path,
+        public_url: args.public_url,
+        trust_proxy: args.trust_proxy,
+        cors_origins: args.cors_origins,
+        mutation_rate_per_minute: args.rate_limit,
+        max_body_bytes: args.max_body_bytes,
+        lookup_retry_mins: args.retry_lookups_mins,
+        incoming_dir: args.incoming_dir,
+        upload_token: args.upload_token,
+        max_upload_bytes: args.max_upload_bytes,
+        #[cfg(feature = "grpc")]
+        grpc_port: args.grpc_port,
+    };
+    // Problems that would otherwise only surface when a request touches them
+    let checks = doctor::quick_checks(&args.index_dir);
+    if checks.iter().any(|c| c.status != doctor::Status::Ok) {
+        doctor::print(&checks, false);
+        println!("Run `audio_sorter doctor` for a full check.");
+    }
+    server::start_server(args.index_dir, args.input_dir, options).await;
     Ok(())
 }
 
-async fn run_scan(args: ScanArgs) -> Result<()> {
+fn run_enrich(args: EnrichArgs) -> Result<()> {
+    let index_path = args.output_dir.join("index.json");
+    let analysis_path = args.output_dir.join("analysis.bin");
+    let cache_path = args.output_dir.join("lookup_cache.json");
+    let mut library = AudioLibrary::load(&index_path)?;
+    let lookup_cache = lookup_cache::LookupCache::load(&cache_path, false)?;
+    let queue_path = args.output_dir.join("pending_lookups.json");
+    let lookup_queue = lookup_queue::LookupQueue::load(&queue_path)?;
+    let cleanup = cleanup::Cleanup::load(&args.output_dir.join("cleanup.json"))?;
+    let merge_policy = merge_policy::MergePolicy::load(&args.output_dir.join("merge_policy.json"))?;
+    acoustid::validate_key(&args.client_id)?;
+
+    // Tracks without a MusicBrainz recording ID never got an online match
+    let mut pending: Vec<PathBuf> = library
+        .files
+        .values()
+        .filter(|t| args.force || t.metadata.recording_mbid.is_none())
+        .filter(|t| t.metadata.fingerprint.is_some())
+        .map(|t| t.path.clone())
+        .collect();
+    pending.sort();
+    if let Some(limit) = args.limit {
+        pending.truncate(limit);
+    }
+
+    println!("Enriching {} tracks online...", pending.len());
+    let http_client = http::client()?;
+    let results: Vec<(PathBuf, Result<TrackMetadata>)> = pending
+        .par_iter()
+        .map_init(
+            || http_client.clone(),
+            |client, path| {
+                let mut meta = library.files[path].metadata.clone();
+                let result = worker::enrich_track(
+                    path,
+                    &mut meta,
+                    &args.client_id,
+                    client,
+                    &lookup_cache,
+                    &merge_policy,
+                )
+                .map(|_| meta);
+                (path.clone(), result)
+            },
+        )
+        .collect();
+
+    let mut enriched = 0;
+    let mut failed = 0;
+    for (path, result) in results {
+        match result {
+            Ok(mut meta) => {
+                cleanup.apply(&mut meta);
+                if let Some(track) = library.files.get_mut(&path) {
+                    track.metadata = meta;
+                }
+                lookup_queue.remove(&path);
+                enriched += 1;
+            }
+            Err(e) => {
+                eprintln!("No online match for {:?}: {}", path, e);
+                failed += 1;
+            }
+        }
+    }
+
+    // Titles may have changed, so karaoke pairs are re-linked
+    let analysis_store = analysis_store::AnalysisStore::load(&analysis_path).unwrap_or_default();
+    pairing::link_vocal_pairs(&mut library, &analysis_store);
+
+    println!("Enriched: {}, No match/errors: {}", enriched, failed);
+    if let Some(reason) = acoustid::suspended() {
+        println!("Online lookups were suspended: {}", reason);
+    }
+    library.save(&index_path)?;
+    lookup_cache.save(&cache_path)?;
+    lookup_queue.save(&queue_path)?;
+    println!("Done!");
+    Ok(())
+}
+
+fn run_reanalyze(args: ReanalyzeArgs) -> Result<()> {
+    let index_path = args.output_dir.join("index.json");
+    let analysis_path = args.output_dir.join("analysis.bin");
+    let mut library = AudioLibrary::load(&index_path)?;
+    let mut query = reanalyze::parse_filters(&args.filter)?;
+    query.limit = args.limit;
+    if args.scope.contains(&reanalyze::Scope::Features) {
+        let settings = scanner::ScanSettings::load(&args.output_dir.join("scan.json"))?;
+        analyzers::configure(&settings.analyzers)?;
+    }
+
+    let pending: Vec<PathBuf> = playlist::select(&library, &query)
+        .into_iter()
+        .map(|t| t.path.clone())
+        .collect();
+    if args.dry_run {
+        for path in &pending {
+            println!("{}", path.display());
+        }
+        println!(
+            "\n{} tracks would be reanalyzed ({:?}).",
+            pending.len(),
+            args.scope
+        );
+        return Ok(());
+    }
+
+    println!("Reanalyzing {} tracks ({:?})...", pending.len(), args.scope);
+    let results: Vec<_> = pending
+        .par_iter()
+        .map(|path| {
+            let mut meta = library.files[path].metadata.clone();
+            let result = reanalyze::reanalyze_file(path, &args.scope, &mut meta)
+                .map(|analysis| (meta, analysis));
+            (path.clone(), result)
+        })
+        .collect();
+
+    let mut analysis_store = analysis_store::AnalysisStore::load(&analysis_path)?;
+    let mut updated = 0;
+    let mut failed = 0;
+    for (path, result) in results {
+        match result {
+            Ok((meta, analysis)) => {
+                if let Some(track) = library.files.get_mut(&path) {
+                    track.metadata = meta;
+                }
+                if let Some(analysis) = analysis {
+                    analysis_store.insert(path, analysis);
+                }
+                updated += 1;
+            }
+            Err(e) => {
+                eprintln!("Error reanalyzing {:?}: {:#}", path, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!("Updated: {}, Errors: {}", updated, failed);
+    library.save(&index_path)?;
+    if args.scope.contains(&reanalyze::Scope::Bliss) {
+        analysis_store.save(&analysis_path)?;
+        let count = neighbors::rebuild(&args.output_dir, &analysis_store)?;
+        println!("Precomputed neighbors for {} tracks.", count);
+    }
+    println!("Done!");
+    Ok(())
+}
+
+fn run_cleanup(args: CleanupArgs) -> Result<()> {
+    let index_path = args.output_dir.join("index.json");
+    let cleanup_path = args.output_dir.join("cleanup.json");
+    let cleanup = cleanup::Cleanup::load(&cleanup_path)?;
+    if cleanup.is_empty() {
+        println!("No cleanup rules or policies in {:?}.", cleanup_path);
+        return Ok(());
+    }
+
+    let mut library = AudioLibrary::load(&index_path)?;
+    let mut affected = 0;
+    for track in library.files.values_mut() {
+        let changes = cleanup.apply(&mut track.metadata);
+        if changes.is_empty() {
+            continue;
+        }
+        affected += 1;
+        println!("{:?}", track.path);
+        for (field, old, new) in changes {
+            println!("  {}: {:?} -> {:?}", field, old, new);
+        }
+    }
+
+    if !args.apply {
+        println!(
+            "\n{} tracks would change. Re-run with --apply to save.",
+            affected
+        );
+        return Ok(());
+    }
+    library.save(&index_path)?;
+    println!("\nUpdated {} tracks.", affected);
+    Ok(())
+}
+
+fn run_organize(args: OrganizeArgs) -> Result<()> {
+    let library = AudioLibrary::load(&args.output_dir.join("index.json"))?;
+    let template = args.template.clone().unwrap_or_else(|| {
+        if args.by_genre {
+            "{genre_path}/{artist}/{album}/{title}".to_string()
+        } else {
+            "{artist}/{album}/{title}".to_string()
+        }
+    });
+    let plan = organize::plan(
+        &library,
+        &args.dest,
+        &template,
+        args.on_collision,
+        args.dry_run,
+    );
+    organize::print_plan(&plan);
+    let nfo_files = if args.nfo {
+        let files = nfo::plan(&library, &plan, &args.dest);
+        nfo::print_plan(&files);
+        files
+    } else {
+        Vec::new()
+    };
+
+    // Refuse up front rather than failing halfway through the copy
+    let space = organize::preflight_space(&plan, &args.dest, args.link);
+    if args.dry_run {
+        if let Err(e) = space {
+            eprintln!("Warning: {}", e);
+        }
+        println!("Dry run: nothing was copied.");
+        return Ok(());
+    }
+    space?;
+    let written = organize::execute(&plan, args.link)?;
+    println!("Wrote {} files to {:?}.", written, args.dest);
+    if args.nfo {
+        let written = nfo::execute(&nfo_files)?;
+        println!("Wrote {} NFO and artwork files.", written);
+    }
+    Ok(())
+}
+
+fn run_artwork(args: ArtworkArgs) -> Result<()> {
+    let library = AudioLibrary::load(&args.output_dir.join("index.json"))?;
+    println!("Reading embedded art of {} tracks...", library.files.len());
+    let folders = artwork::survey(&library);
+
+    let inconsistent: Vec<&artwork::FolderArt> =
+        folders.iter().filter(|f| f.is_inconsistent()).collect();
+    for f in &inconsistent {
+        println!(
+            "{:?}: {} with embedded art, {} without; folder image: {}",
+            f.folder,
+            f.with_embedded.len(),
+            f.without_embedded.len(),
+            f.folder_image
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "none".to_string())
+        );
+    }
+    println!(
+        "{} of {} folders mix embedded and external art.",
+        inconsistent.len(),
+        folders.len()
+    );
+
+    let written = artwork::reconcile(&folders, args.mode, args.dry_run)?;
+    if args.mode != artwork::ArtworkMode::Report {
+        if args.dry_run {
+            println!("Dry run: {} files would be written.", written);
+        } else {
+            println!("Wrote {} files.", written);
+        }
+    }
+    Ok(())
+}
+
+fn run_report(args: ReportArgs) -> Result<()> {
+    let library = AudioLibrary::load(&args.output_dir.join("index.json"))?;
+    let opts = reports::ReportOptions {
+        limit: args.limit,
+        min_kbps: args.min_kbps,
+        target_kbps: args.target_kbps,
+    };
+    let report = reports::build(&library, args.kind, &opts);
+
+    for entry in &report.entries {
+        println!(
+            "{:>10} MiB  {:<32} {}",
+            entry.file_size / (1024 * 1024),
+            entry.detail,
+            entry.path
+        );
+    }
+    let reclaimable = report.reclaimable_bytes / (1024 * 1024);
+    println!(
+        "\n{} matching files (showing {}).",
+        report.total_matches,
+        report.entries.len()
+    );
+    match args.kind {
+        reports::ReportKind::Largest => println!(
+            "Re-encoding all of them at {} kbps would free about {} MiB.",
+            args.target_kbps, reclaimable
+        ),
+        _ => println!(
+            "Replacing or removing them would free up to {} MiB.",
+            reclaimable
+        ),
+    }
+    Ok(())
+}
+
+fn run_diff(args: DiffArgs) -> Result<()> {
+    let old = diff::load_snapshot(&args.old)?;
+    let new = diff::load_snapshot(&args.new)?;
+    let report = diff::diff(&old, &new);
+    let output = match args.format {
+        diff::DiffFormat::Json => {
+            serde_json::to_string_pretty(&report).context("Failed to serialize diff")?
+        }
+        diff::DiffFormat::Csv => diff::to_csv(&report),
+    };
+
+    match &args.out {
+        Some(path) => {
+            std::fs::write(path, output).context("Failed to write diff report")?;
+            println!(
+                "{} added, {} removed, {} retagged, {} moved. Report written to {:?}.",
+                report.added, report.removed, report.retagged, report.moved, path
+            );
+        }
+        None => println!("{}", output),
+    }
+    Ok(())
+}
+
+fn run_playlist_import(args: PlaylistImportArgs) -> Result<()> {
+    anyhow::ensure!(
+        args.name.is_none() || args.files.len() == 1,
+        "--name needs exactly one playlist file"
+    );
+    let library = AudioLibrary::load(&args.output_dir.join("index.json"))?;
+    let playlists_path = args.output_dir.join("playlists.json");
+    let mut stored = playlist::load_stored(&playlists_path)?;
+
+    for file in &args.files {
+        let name = match &args.name {
+            Some(name) => name.clone(),
+            None => file
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .with_context(|| format!("No playlist name in {:?}", file))?,
+        };
+        anyhow::ensure!(
+            args.force || !stored.contains_key(&name),
+            "A playlist named '{}' already exists (use --force to replace it)",
+            name
+        );
+        let entries = playlist_import::read(file)?;
+        // Relative entries are relative to the playlist's folder
+        let absolute = std::env::current_dir()?.join(file);
+        let base_dir = absolute.parent().unwrap_or(Path::new("/"));
+        let resolved =
+            playlist_import::resolve(&library, entries, base_dir, &args.maps, !args.no_fuzzy);
+
+        let count = |kind| {
+            resolved
+                .iter()
+                .filter(|r| r.track.is_some_and(|(_, k)| k == kind))
+                .count()
+        };
+        let tracks: Vec<String> = resolved
+            .iter()
+            .filter_map(|r| r.track)
+            .map(|(t, _)| t.path.to_string_lossy().into_owned())
+            .collect();
+        println!(
+            "{}: {} of {} entries matched ({} by path, {} by file name, {} by tags).",
+            name,
+            tracks.len(),
+            resolved.len(),
+            count(playlist_import::MatchKind::Path),
+            count(playlist_import::MatchKind::FileName),
+            count(playlist_import::MatchKind::Metadata),
+        );
+        for r in resolved.iter().filter(|r| r.track.is_none()) {
+            println!("  not found: {}", r.entry.location);
+        }
+
+        if tracks.is_empty() {
+            println!("  Nothing matched; {:?} was not saved.", name);
+            continue;
+        }
+        stored.insert(
+            name,
+            playlist::PlaylistQuery {
+                tracks,
+                ..Default::default()
+            },
+        );
+    }
+
+    if args.dry_run {
+        println!("Dry run: playlists.json was not changed.");
+        return Ok(());
+    }
+    playlist::save_stored(&playlists_path, &stored)?;
+    println!("Saved to {:?}.", playlists_path);
+    Ok(())
+}
+
+fn run_index_export(args: IndexExportArgs) -> Result<()> {
+    let bundle = bundle::export(&args.output_dir)?;
+    bundle::write(&bundle, &args.bundle)?;
+    println!(
+        "Exported {} tracks (root {:?}) to {:?}.",
+        bundle.tracks.len(),
+        bundle.library_root,
+        args.bundle
+    );
+    Ok(())
+}
+
+fn run_index_encode_vectors(args: IndexEncodeVectorsArgs) -> Result<()> {
+    let analysis_path = args.output_dir.join("analysis.bin");
+    let before = std::fs::metadata(&analysis_path)
+        .map(|m| m.len())
+        .unwrap_or(0);
+    let mut store = analysis_store::AnalysisStore::load(&analysis_path)?;
+    println!(
+        "Re-encoding {} vectors from {:?} to {:?}...",
+        store.data.len(),
+        store.encoding,
+        args.encoding
+    );
+    // Going back to f32 doesn't restore the precision lost to quantization
+    store.encoding = args.encoding;
+    store.save(&analysis_path)?;
+    let after = std::fs::metadata(&analysis_path)
+        .map(|m| m.len())
+        .unwrap_or(0);
+    println!(
+        "analysis.bin: {:.1} MiB -> {:.1} MiB.",
+        before as f64 / (1024.0 * 1024.0),
+        after as f64 / (1024.0 * 1024.0)
+    );
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+fn run_index_migrate(args: IndexMigrateArgs) -> Result<()> {
+    let index_path = args.output_dir.join("index.json");
+    if storage::stored_path(&index_path) != index_path {
+        println!("{:?} already uses index.db.", args.output_dir);
+        return Ok(());
+    }
+    anyhow::ensure!(
+        index_path.exists(),
+        "No index.json in {:?}",
+        args.output_dir
+    );
+    let library = AudioLibrary::load(&index_path)?;
+    library.save(&index_path)?;
+    println!("{} tracks moved to index.db.", library.files.len());
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+fn run_index_import_beets(args: IndexImportBeetsArgs) -> Result<()> {
+    let index_path = args.output_dir.join("index.json");
+    let mut library = AudioLibrary::load(&index_path)?;
+    let items = beets::read(&args.db, &args.maps)?;
+    println!("Read {} items from {:?}.", items.len(), args.db);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let result = beets::merge(&mut library, items, args.fill_missing_only, now);
+    for path in &result.missing {
+        println!("[missing] {:?}", path);
+    }
+    println!(
+        "{} tracks added, {} indexed tracks updated, {} files not found (use --map if the library moved).",
+        result.added,
+        result.updated,
+        result.missing.len()
+    );
+    if args.dry_run {
+        println!("Dry run: the index was not changed.");
+        return Ok(());
+    }
+    std::fs::create_dir_all(&args.output_dir).context("Failed to create index directory")?;
+    library.save(&index_path)?;
+    if result.added > 0 {
+        println!(
+            "Run `reanalyze -o {:?} --scope bliss,loudness,fingerprint` to analyze the added tracks while keeping their beets tags.",
+            args.output_dir
+        );
+    }
+    Ok(())
+}
+
+fn run_index_import(args: IndexImportArgs) -> Result<()> {
+    let index_path = args.output_dir.join("index.json");
+    if storage::stored_path(&index_path).exists() && !args.force {
+        anyhow::bail!(
+            "{:?} already contains an index; use --force to replace it",
+            args.output_dir
+        );
+    }
+    let bundle = bundle::read(&args.bundle)?;
+    println!(
+        "Bundle from {:?} with {} tracks.",
+        bundle.library_root,
+        bundle.tracks.len()
+    );
+    let count = bundle::import(bundle, &args.output_dir, args.root.as_deref())?;
+    println!("Imported {} tracks into {:?}.", count, args.output_dir);
+    Ok(())
+}
+
+fn run_lookups_list(args: LookupsListArgs) -> Result<()> {
+    let queue = lookup_queue::LookupQueue::load(&args.output_dir.join("pending_lookups.json"))?;
+    for (path, entry) in queue.entries() {
+        println!(
+            "{:?}  attempts: {}  last error: {}",
+            path, entry.attempts, entry.last_error
+        );
+    }
+    println!("{} lookups pending.", queue.len());
+    Ok(())
+}
+
+fn run_lookups_flush(args: LookupsFlushArgs) -> Result<()> {
+    acoustid::validate_key(&args.client_id)?;
+    let index = index_writer::IndexFile(args.output_dir.join("index.json"));
+    let summary = lookup_queue::flush(&index, &args.output_dir, &args.client_id, args.limit)?;
+    println!(
+        "Resolved: {}, No match: {}, Still pending: {}, Dropped (no longer indexed): {}",
+        summary.resolved, summary.no_match, summary.still_pending, summary.dropped
+    );
+    if let Some(reason) = acoustid::suspended() {
+        println!("Online lookups were suspended: {}", reason);
+    }
+    Ok(())
+}
+
+fn run_doctor(args: DoctorArgs) -> Result<()> {
+    let checks = doctor::run(
+        &args.output_dir,
+        args.input_dir.as_deref(),
+        args.client_id.as_deref(),
+        args.offline,
+    );
+    doctor::print(&checks, args.verbose);
+    let count = |status: doctor::Status| checks.iter().filter(|c| c.status == status).count();
+    let (failed, warned) = (count(doctor::Status::Fail), count(doctor::Status::Warn));
+    println!(
+        "{} checks: {} failed, {} warnings.",
+        checks.len(),
+        failed,
+        warned
+    );
+    if failed > 0 {
+        std::process::exit(exit_status::FAILED);
+    }
+    Ok(())
+}
+
+fn run_split(args: SplitArgs) -> Result<()> {
+    let opts = splitter::SplitOptions {
+        min_silence_secs: args.min_silence,
+        threshold_db: args.threshold_db,
+        min_track_secs: args.min_track,
+    };
+
+    println!("Detecting silence in {:?}...", args.input);
+    let mut segments = splitter::detect_segments(&args.input, &opts)?;
+
+    if args.identify {
+        println!("Identifying {} segments...", segments.len());
+        splitter::identify_segments(&args.input, &mut segments, args.client_id.as_deref())?;
+    }
+
+    for s in &segments {
+        println!(
+            "{:02}. {:>8.1}s - {:>8.1}s  {} - {}",
+            s.index,
+            s.start_secs,
+            s.end_secs,
+            s.artist.as_deref().unwrap_or("?"),
+            s.title.as_deref().unwrap_or("?")
+        );
+    }
+
+    if args.cue {
+        let cue_path = args.input.with_extension("cue");
+        splitter::write_cue(&args.input, &segments, &cue_path)?;
+        println!("CUE sheet written to {:?}", cue_path);
+    }
+
+    if let Some(dir) = &args.export_dir {
+        std::fs::create_dir_all(dir).context("Failed to create export directory")?;
+        let stem = args
+            .input
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("track");
+        let out_paths: Vec<PathBuf> = segments
+            .iter()
+            .map(|s| dir.join(format!("{} - {:02}.wav", stem, s.index)))
+            .collect();
+        splitter::write_segment_wavs(&args.input, &segments, &out_paths, None)?;
+        println!("Exported {} segments to {:?}", out_paths.len(), dir);
+    }
+
+    Ok(())
+}
+
+fn print_scan_plan(plan: &scanner::ScanPlan) {
+    let count = |reason| {
+        plan.to_process
+            .iter()
+            .filter(|f| f.reason == reason)
+            .count()
+    };
+
+    println!("\nDry run (nothing was decoded or saved):");
+    for f in &plan.to_process {
+        println!("  [{}] {:?}", f.reason.label(), f.path);
+    }
+    for path in &plan.removed {
+        println!("  [removed] {:?}", path);
+    }
+    for f in &plan.quarantined {
+        println!("  [skipped: {}] {:?}", f.reason.label(), f.path);
+    }
+
+    println!(
+        "\nWould add {}, update {} ({} changed on disk, {} missing analysis), remove {}; {} unchanged, {} skipped until a later scan.",
+        count(scanner::ChangeReason::NewFile),
+        count(scanner::ChangeReason::Modified) + count(scanner::ChangeReason::MissingAnalysis),
+        count(scanner::ChangeReason::Modified),
+        count(scanner::ChangeReason::MissingAnalysis),
+        plan.removed.len(),
+        plan.unchanged,
+        plan.quarantined.len()
+    );
+}
+
+/// Per top-level directory summary, slowest first; omitted for a flat library.
+fn print_directory_stats(directories: &BTreeMap<String, scanner::DirStats>) {
+    if directories.len() < 2 {
+        return;
+    }
+    let mut rows: Vec<(&String, &scanner::DirStats)> = directories.iter().collect();
+    rows.sort_by(|a, b| b.1.busy_secs.total_cmp(&a.1.busy_secs));
+    println!("{}", i18n::t("cli.by_directory", &[]));
+    for (name, s) in rows {
+        println!(
+            "  {:<32} processed {:>6}  skipped {:>6}  quarantined {:>4}  errors {:>4}  {:>8.1}s",
+            name, s.processed, s.skipped, s.quarantined, s.errors, s.busy_secs
+        );
+    }
+}
+
+async fn run_scan(args: ScanArgs) -> Result<exit_status::JobCounts> {
     // Note: Scanning is CPU heavy, but we are running inside tokio main now.
     // Ideally we should use spawn_blocking for Rayon, but for a simplified CLI tool it's okay-ish
     // provided we don't block the async runtime too badly if we had other web tasks (which we don't during scan).
@@ -93,10 +1305,32 @@ async fn run_scan(args: ScanArgs) -> Result<()> {
     println!("Starting Audio Sorter - Multi-threaded Indexer");
     println!("Input: {:?}", args.input_dir);
     println!("Index Dir: {:?}", args.output_dir);
+    if !args.input_dir.is_dir() {
+        return Err(
+            anyhow::anyhow!("Input directory {:?} does not exist", args.input_dir)
+                .context(exit_status::ConfigError),
+        );
+    }
     if args.offline {
         println!("Mode: OFFLINE");
     } else {
         println!("Mode: ONLINE");
+        if let Some(client_id) = args.client_id.clone() {
+            tokio::task::spawn_blocking(move || acoustid::validate_key(&client_id))
+                .await
+                .context("Key validation task failed")?
+                .context(exit_status::ConfigError)?;
+        }
+    }
+    let profile = args
+        .profile
+        .is_some()
+        .then(|| Arc::new(profile::ScanProfile::new()));
+    if args.background {
+        match priority::enter_background() {
+            Ok(()) => println!("Priority: background (idle CPU and I/O)"),
+            Err(e) => eprintln!("Could not lower scan priority: {}", e),
+        }
     }
 
     // 1. Load Index
@@ -128,10 +1362,59 @@ async fn run_scan(args: ScanArgs) -> Result<()> {
         }
     };
 
+    let cache_path = args.output_dir.join("lookup_cache.json");
+    let lookup_cache = match lookup_cache::LookupCache::load(&cache_path, args.offline) {
+        Ok(cache) => {
+            if args.offline && !cache.is_empty() {
+                println!(
+                    "Replaying {} cached online responses while offline.",
+                    cache.len()
+                );
+            }
+            cache
+        }
+        Err(e) => {
+            eprintln!("Could not load lookup cache: {}. Starting fresh.", e);
+            lookup_cache::LookupCache::new(args.offline)
+        }
+    };
+
+    let cleanup = cleanup::Cleanup::load(&args.output_dir.join("cleanup.json"))
+        .context(exit_status::ConfigError)?;
+    let merge_policy = merge_policy::MergePolicy::load(&args.output_dir.join("merge_policy.json"))
+        .context(exit_status::ConfigError)?;
+    let queue_path = args.output_dir.join("pending_lookups.json");
+    let lookup_queue = lookup_queue::LookupQueue::load(&queue_path)?;
+
+    let merged = library.remove_key_duplicates();
+    for path in &merged {
+        analysis_store.remove(path);
+    }
+    if !merged.is_empty() {
+        println!(
+            "Merged {} index entries that named the same file with different casing.",
+            merged.len()
+        );
+    }
+
+    if let Some(profile) = &profile {
+        profile.end_phase("load");
+    }
+
     // 2. Scan Directory
     println!("Scanning directory...");
-    let files = scanner::scan_directory(&args.input_dir)?;
+    let mut scan_settings = scanner::ScanSettings::load(&args.output_dir.join("scan.json"))
+        .context(exit_status::ConfigError)?;
+    scan_settings.include_hidden |= args.include_hidden;
+    memory_budget::configure(args.memory_budget_mb.or(scan_settings.memory_budget_mb));
+    analyzers::configure(&scan_settings.analyzers).context(exit_status::ConfigError)?;
+    let spinner = progress::spinner("enumerating files");
+    let files = scanner::scan_directory(&args.input_dir, &scan_settings)?;
+    spinner.finish_and_clear();
     println!("Found {} candidate files.", files.len());
+    if let Some(profile) = &profile {
+        profile.end_phase("enumerate");
+    }
 
     let current_time = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -140,41 +1423,62 @@ async fn run_scan(args: ScanArgs) -> Result<()> {
 
     // 3. Diff Phase (Serial)
     println!("Identifying changed files...");
-    let mut files_to_process = Vec::new();
-    let mut skipped_count = 0;
-
-    for path in &files {
-        if let Ok(metadata) = std::fs::metadata(path) {
-            let mtime = metadata
-                .modified()
-                .unwrap_or(SystemTime::UNIX_EPOCH)
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
-            let size = metadata.len();
-
-            let needs_update = if let Some(indexed) = library.files.get(path) {
-                if indexed.modified_time != mtime || indexed.file_size != size {
-                    true
-                } else {
-                    // Check if analysis is missing (e.g. added later)
-                    if analysis_store.get(path).is_none() {
-                        true
-                    } else {
-                        false
-                    }
-                }
-            } else {
-                true
-            };
-
-            if needs_update {
-                files_to_process.push((path.clone(), size, mtime));
-            } else {
-                skipped_count += 1;
-            }
-        }
+    let mut plan = scanner::plan_scan(
+        &args.input_dir,
+        &files,
+        &library,
+        &analysis_store,
+        &scan_settings,
+    );
+    scanner::apply_renames(&plan, &mut library, &mut analysis_store);
+    let skipped_count = plan.unchanged;
+
+    if args.limit.is_some() || args.sample.is_some() {
+        let pending = plan.to_process.len();
+        scanner::select_subset(
+            &mut plan,
+            &args.input_dir,
+            files.len(),
+            args.limit,
+            args.sample,
+        );
+        println!(
+            "Subset selected: {} of {} pending files (remaining {} left for later runs).",
+            plan.to_process.len(),
+            pending,
+            pending - plan.to_process.len()
+        );
+    }
+
+    if args.dry_run {
+        print_scan_plan(&plan);
+        return Ok(exit_status::JobCounts {
+            skipped: skipped_count,
+            quarantined: plan.quarantined.len(),
+            ..Default::default()
+        });
+    }
+
+    if !plan.quarantined.is_empty() {
+        println!(
+            "Skipping {} empty, partially downloaded or still growing files until the next scan.",
+            plan.quarantined.len()
+        );
     }
+    issues::save_quarantine(&args.output_dir.join("quarantine.json"), &plan.quarantined)?;
+    let removed_count = scanner::remove_missing(&plan, &mut library, &mut analysis_store);
+    if removed_count > 0 {
+        println!("{}", i18n::t("cli.removed", &[("n", &removed_count)]));
+    }
+
+    let quarantined_count = plan.quarantined.len();
+    let hooks = hooks::HookRunner::load(&args.output_dir);
+    let mut directories = std::mem::take(&mut plan.directories);
+    let files_to_process: Vec<(PathBuf, u64, u64)> = plan
+        .to_process
+        .into_iter()
+        .map(|f| (f.path, f.size, f.mtime))
+        .collect();
 
     let to_process_count = files_to_process.len();
     println!(
@@ -182,62 +1486,215 @@ async fn run_scan(args: ScanArgs) -> Result<()> {
         skipped_count, to_process_count
     );
 
+    if let Some(profile) = &profile {
+        profile.end_phase("plan");
+    }
+
     if to_process_count == 0 {
         println!("Nothing to do.");
-        return Ok(());
+        if removed_count > 0 {
+            library.save(&index_path)?;
+            analysis_store.save(&analysis_path)?;
+            neighbors::rebuild(&args.output_dir, &analysis_store)?;
+        }
+        let counts = exit_status::JobCounts {
+            skipped: skipped_count,
+            quarantined: quarantined_count,
+            removed: removed_count,
+            ..Default::default()
+        };
+        write_profile(&args, profile.as_deref(), &counts)?;
+        hooks.scan_complete(&counts);
+        return Ok(counts);
+    }
+
+    // Ctrl-C / SIGTERM: finish the batch in flight, then save what is done.
+    // A second Ctrl-C quits at once.
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let interrupted = interrupted.clone();
+        tokio::spawn(async move {
+            service::shutdown_signal().await;
+            interrupted.store(true, Ordering::SeqCst);
+            eprintln!(
+                "\nInterrupted: finishing the current batch and saving (Ctrl-C again to quit now)..."
+            );
+            let _ = tokio::signal::ctrl_c().await;
+            std::process::exit(130);
+        });
     }
 
-    // 4. Process Phase (Parallel)
+    // 4. Process Phase (Parallel, in batches so an interrupt loses little work)
     // Rayon uses its own thread pool, safe to call from here.
-    let processed_results: Vec<(PathBuf, u64, u64, Result<(TrackMetadata, Option<Vec<f32>>)>)> =
-        files_to_process
+    let http_client = http::client()?;
+    let bars = progress::ScanBars::new(to_process_count, !args.offline && args.client_id.is_some())
+        .with_profile(profile.clone());
+    let mut success_count = 0;
+    let mut error_count = 0;
+    let mut scan_errors = BTreeMap::new();
+    let mut attempted = 0;
+
+    let ctx = worker::ScanContext {
+        args: &args,
+        cache: &lookup_cache,
+        queue: &lookup_queue,
+        policy: &merge_policy,
+        bars: Some(&bars),
+    };
+
+    for batch in files_to_process.chunks(SCAN_BATCH_SIZE) {
+        if interrupted.load(Ordering::SeqCst) {
+            break;
+        }
+        let processed_results: Vec<(PathBuf, u64, u64, f64, worker::Processed)> = batch
             .par_iter()
             .map_init(
-                || reqwest::blocking::Client::new(),
+                || http_client.clone(),
                 |client, (path, size, mtime)| {
-                    let result = worker::process_file(path, &args, client);
-                    (path.clone(), *size, *mtime, result)
+                    let started = Instant::now();
+                    let result =
+                        worker::process_file(path, &ctx, client, scan_settings.pipeline(path));
+                    bars.file_done();
+                    let busy = started.elapsed().as_secs_f64();
+                    (path.clone(), *size, *mtime, busy, result)
                 },
             )
             .collect();
+        attempted += batch.len();
 
-    // 5. Merge Phase
-    let mut success_count = 0;
-    let mut error_count = 0;
+        // 5. Merge Phase
+        for (path, size, mtime, busy, result) in processed_results {
+            let dir = directories
+                .entry(scanner::top_level_dir(&args.input_dir, &path))
+                .or_default();
+            dir.busy_secs += busy;
+            match result {
+                Ok((mut meta, analysis_opt)) => {
+                    cleanup.apply(&mut meta);
+                    hooks.track_identified(&path, &meta);
+                    library.upsert(path.clone(), size, mtime, current_time, meta);
 
-    for (path, size, mtime, result) in processed_results {
-        match result {
-            Ok((meta, analysis_opt)) => {
-                let entry = IndexedTrack {
-                    path: path.clone(),
-                    file_size: size,
-                    modified_time: mtime,
-                    scanned_at: current_time,
-                    metadata: meta,
-                };
-                library.files.insert(path.clone(), entry);
-
-                if let Some(analysis) = analysis_opt {
-                    analysis_store.insert(path, analysis);
-                }
+                    if let Some(analysis) = analysis_opt {
+                        analysis_store.insert(path, analysis);
+                    }
 
-                success_count += 1;
-            }
-            Err(e) => {
-                eprintln!("Error processing {:?}: {}", path, e);
-                error_count += 1;
+                    dir.processed += 1;
+                    success_count += 1;
+                }
+                Err(e) => {
+                    bars.println(format!("Error processing {:?}: {}", path, e));
+                    scan_errors.insert(path, format!("{:#}", e));
+                    dir.errors += 1;
+                    error_count += 1;
+                }
             }
         }
     }
+    bars.finish();
+    if let Some(profile) = &profile {
+        profile.end_phase(profile::PROCESS_PHASE);
+    }
+    let remaining = to_process_count - attempted;
+
+    // 6. Link karaoke (vocal/off-vocal) pairs across the whole library
+    let pair_count = pairing::link_vocal_pairs(&mut library, &analysis_store);
+    if let Some(profile) = &profile {
+        profile.end_phase("pair");
+    }
 
-    // 6. Save Index
-    println!("\nScan complete.");
-    println!("Processed: {}, Errors: {}", success_count, error_count);
+    // 7. Save Index
+    if remaining > 0 {
+        println!("\n{}", i18n::t("cli.scan_interrupted", &[]));
+    } else {
+        println!("\n{}", i18n::t("cli.scan_complete", &[]));
+    }
+    println!(
+        "{}",
+        i18n::t(
+            "cli.processed",
+            &[("processed", &success_count), ("errors", &error_count)]
+        )
+    );
+    print_directory_stats(&directories);
+    println!("{}", i18n::t("cli.pairs_linked", &[("n", &pair_count)]));
+    if let Some(reason) = acoustid::suspended() {
+        let skipped = acoustid::skipped_lookups();
+        println!(
+            "{}",
+            i18n::t(
+                "cli.lookups_suspended",
+                &[("reason", &reason), ("n", &skipped)]
+            )
+        );
+    }
+    if !lookup_queue.is_empty() {
+        println!(
+            "{}",
+            i18n::t("cli.lookups_queued", &[("n", &lookup_queue.len())])
+        );
+    }
     println!("Saving index to {:?}...", index_path);
     library.save(&index_path)?;
     println!("Saving analysis store to {:?}...", analysis_path);
     analysis_store.save(&analysis_path)?;
-    println!("Done!");
+    // An interrupted scan leaves this to the next one (or `serve`, which
+    // rebuilds stale neighbor lists at startup)
+    if remaining == 0 {
+        println!("Precomputing nearest neighbors...");
+        neighbors::rebuild(&args.output_dir, &analysis_store)?;
+    }
+    if !args.offline {
+        lookup_cache.save(&cache_path)?;
+        lookup_queue.save(&queue_path)?;
+    }
+    timeline::record(&args.output_dir.join("scan_history.json"), &library)?;
+    issues::save_scan_errors(&args.output_dir.join("scan_errors.json"), &scan_errors)?;
+    if let Some(profile) = &profile {
+        profile.end_phase("save");
+    }
+    if remaining > 0 {
+        println!(
+            "{}",
+            i18n::t(
+                "cli.saved_partial",
+                &[("attempted", &attempted), ("remaining", &remaining)]
+            )
+        );
+    } else {
+        println!("{}", i18n::t("cli.done", &[]));
+    }
 
+    let counts = exit_status::JobCounts {
+        processed: success_count,
+        errors: error_count,
+        skipped: skipped_count,
+        quarantined: quarantined_count,
+        removed: removed_count,
+        remaining,
+    };
+    write_profile(&args, profile.as_deref(), &counts)?;
+    hooks.scan_complete(&counts);
+    Ok(counts)
+}
+
+/// Write the `scan --profile` report, if one was requested.
+fn write_profile(
+    args: &ScanArgs,
+    profile: Option<&profile::ScanProfile>,
+    counts: &exit_status::JobCounts,
+) -> Result<()> {
+    let (Some(path), Some(profile)) = (&args.profile, profile) else {
+        return Ok(());
+    };
+    let files = BTreeMap::from([
+        ("processed", counts.processed),
+        ("errors", counts.errors),
+        ("skipped", counts.skipped),
+        ("quarantined", counts.quarantined),
+        ("remaining", counts.remaining),
+    ]);
+    let online = !args.offline && args.client_id.is_some();
+    profile.report(online, files).save(path)?;
+    println!("Profile written to {:?}.", path);
     Ok(())
 }
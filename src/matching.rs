@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use utoipa::ToSchema;
+
+use crate::acoustid::AcoustIdResponse;
+use crate::organizer::TrackMetadata;
+
+/// Candidates kept per track for later review.
+pub const MAX_CANDIDATES: usize = 5;
+
+/// Weights of the combined rank; the AcoustID score dominates, duration and
+/// tag agreement break ties between recordings sharing a fingerprint.
+const SCORE_WEIGHT: f64 = 0.6;
+const DURATION_WEIGHT: f64 = 0.2;
+const TAG_WEIGHT: f64 = 0.2;
+
+/// Duration difference (seconds) at which the duration factor reaches zero.
+const DURATION_TOLERANCE_SECS: f64 = 30.0;
+
+/// Default for `ACOUSTID_MAX_DURATION_DELTA`.
+const DEFAULT_MAX_DURATION_DELTA_SECS: f64 = 20.0;
+
+/// Candidates whose recording length differs from the file by more than this
+/// many seconds are rejected, e.g. a 12-minute live version matched to a
+/// 3-minute single (`ACOUSTID_MAX_DURATION_DELTA`; 0 disables the check).
+pub fn max_duration_delta() -> f64 {
+    static DELTA: OnceLock<f64> = OnceLock::new();
+    *DELTA.get_or_init(|| {
+        std::env::var("ACOUSTID_MAX_DURATION_DELTA")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(DEFAULT_MAX_DURATION_DELTA_SECS)
+    })
+}
+
+/// Whether the candidate's length is plausible for a file of `duration` seconds.
+/// Unknown lengths pass.
+fn duration_plausible(candidate: &MatchCandidate, duration: f64) -> bool {
+    let max_delta = max_duration_delta();
+    match candidate.duration {
+        Some(d) if max_delta > 0.0 && duration > 0.0 => (d - duration).abs() <= max_delta,
+        _ => true,
+    }
+}
+
+/// One AcoustID recording candidate for a fingerprint.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MatchCandidate {
+    pub recording_mbid: String,
+    /// Absent when the server only returns IDs (self-hosted AcoustID)
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// Recording length known to AcoustID, in seconds
+    pub duration: Option<f64>,
+    /// AcoustID fingerprint match score (0..1)
+    pub score: f64,
+    /// Combined rank of score, duration delta and tag similarity (0..1)
+    pub rank: f64,
+}
+
+fn words(s: &str) -> Vec<String> {
+    s.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Word overlap (Dice coefficient, 0..1) of the distinct words of two
+/// strings; `None` when either side is empty.
+pub fn similarity(a: &str, b: &str) -> Option<f64> {
+    let a: HashSet<String> = words(a).into_iter().collect();
+    let b: HashSet<String> = words(b).into_iter().collect();
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let shared = a.intersection(&b).count() as f64;
+    Some(shared / (a.len() + b.len()) as f64 * 2.0)
+}
+
+/// How well the candidate agrees with local tags; 0.5 when nothing can be compared.
+fn tag_factor(candidate: &MatchCandidate, local: Option<&TrackMetadata>) -> f64 {
+    let Some(local) = local else { return 0.5 };
+    let scores: Vec<f64> = [
+        candidate
+            .title
+            .as_deref()
+            .and_then(|t| similarity(t, &local.title)),
+        candidate
+            .artist
+            .as_deref()
+            .and_then(|a| similarity(a, &local.artist)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if scores.is_empty() {
+        0.5
+    } else {
+        scores.iter().sum::<f64>() / scores.len() as f64
+    }
+}
+
+fn duration_factor(candidate: &MatchCandidate, duration: f64) -> f64 {
+    match candidate.duration {
+        Some(d) if duration > 0.0 => {
+            (1.0 - (d - duration).abs() / DURATION_TOLERANCE_SECS).max(0.0)
+        }
+        _ => 0.5,
+    }
+}
+
+/// Every plausible recording of every result, best first, limited to
+/// `MAX_CANDIDATES`. `duration` is the decoded length of the file; `local` its
+/// own tags, if any.
+pub fn rank(
+    response: &AcoustIdResponse,
+    duration: f64,
+    local: Option<&TrackMetadata>,
+) -> Vec<MatchCandidate> {
+    let mut candidates: Vec<MatchCandidate> = Vec::new();
+    for result in response.results.iter().flatten() {
+        for recording in result.recordings.iter().flatten() {
+            let mut candidate = MatchCandidate {
+                recording_mbid: recording.id.clone(),
+                title: recording.title.clone(),
+                artist: recording
+                    .artists
+                    .as_ref()
+                    .and_then(|a| a.first())
+                    .map(|a| a.name.clone()),
+                duration: recording.duration,
+                score: result.score,
+                rank: 0.0,
+            };
+            if !duration_plausible(&candidate, duration) {
+                continue;
+            }
+            candidate.rank = SCORE_WEIGHT * result.score
+                + DURATION_WEIGHT * duration_factor(&candidate, duration)
+                + TAG_WEIGHT * tag_factor(&candidate, local);
+            candidates.push(candidate);
+        }
+    }
+    candidates.sort_by(|a, b| b.rank.partial_cmp(&a.rank).unwrap_or(Ordering::Equal));
+    // A recording can be linked to several AcoustID tracks; keep its best rank
+    let mut seen = HashSet::new();
+    candidates.retain(|c| seen.insert(c.recording_mbid.clone()));
+    candidates.truncate(MAX_CANDIDATES);
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similarity_counts_repeated_words_once() {
+        assert_eq!(similarity("la la la", "la"), Some(1.0));
+        assert_eq!(similarity("Na Na Hey Hey", "hey na"), Some(1.0));
+        assert_eq!(similarity("Blue Moon", "Moon River"), Some(0.5));
+        assert_eq!(similarity("", "x"), None);
+    }
+}
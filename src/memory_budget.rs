@@ -0,0 +1,73 @@
+use std::sync::{Condvar, Mutex, OnceLock};
+use sysinfo::System;
+
+/// Smallest default budget, for machines reporting very little RAM
+const MIN_DEFAULT_BYTES: u64 = 512 * 1024 * 1024;
+
+struct State {
+    capacity: u64,
+    used: u64,
+}
+
+/// Process-wide cap on the combined size of files being decoded at once.
+/// Workers wait in `acquire` until enough of the budget is free, so a batch
+/// of huge WAV stems is decoded a few at a time instead of all together.
+struct MemoryBudget {
+    state: Mutex<State>,
+    freed: Condvar,
+}
+
+fn budget() -> &'static MemoryBudget {
+    static BUDGET: OnceLock<MemoryBudget> = OnceLock::new();
+    BUDGET.get_or_init(|| MemoryBudget {
+        state: Mutex::new(State {
+            capacity: default_bytes(),
+            used: 0,
+        }),
+        freed: Condvar::new(),
+    })
+}
+
+/// A quarter of physical RAM, at least 512 MiB.
+pub fn default_bytes() -> u64 {
+    let mut sys = System::new();
+    sys.refresh_memory();
+    (sys.total_memory() / 4).max(MIN_DEFAULT_BYTES)
+}
+
+/// Change the budget for later `acquire` calls; `None` restores the default.
+pub fn configure(megabytes: Option<u64>) {
+    let capacity = match megabytes {
+        Some(mb) => mb.max(1) * 1024 * 1024,
+        None => default_bytes(),
+    };
+    budget().state.lock().unwrap().capacity = capacity;
+    budget().freed.notify_all();
+}
+
+/// Share of the budget held while a file is decoded; released on drop.
+pub struct Permit {
+    weight: u64,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        budget().state.lock().unwrap().used -= self.weight;
+        budget().freed.notify_all();
+    }
+}
+
+/// Block until `file_size` bytes fit into the budget. A file larger than the
+/// whole budget waits until nothing else is decoding and then runs alone.
+pub fn acquire(file_size: u64) -> Permit {
+    let budget = budget();
+    let mut state = budget.state.lock().unwrap();
+    loop {
+        let weight = file_size.min(state.capacity);
+        if state.used + weight <= state.capacity {
+            state.used += weight;
+            return Permit { weight };
+        }
+        state = budget.freed.wait(state).unwrap();
+    }
+}
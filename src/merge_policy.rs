@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::organizer::TrackMetadata;
+
+/// How a tag field is resolved when an online match disagrees with the file.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeMode {
+    /// Online value wins; local tags fill fields the match doesn't know
+    #[default]
+    PreferOnline,
+    /// Local tags are kept as they are, even when empty
+    PreferLocal,
+    /// Local tags are kept; only empty ones are filled from the match
+    FillMissingOnly,
+}
+
+/// Modes for the fields an online match provides. `all` applies to fields
+/// without their own entry.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FieldModes {
+    pub all: Option<MergeMode>,
+    pub title: Option<MergeMode>,
+    pub artist: Option<MergeMode>,
+    pub album: Option<MergeMode>,
+    pub year: Option<MergeMode>,
+}
+
+/// Contents of `merge_policy.json` in the index directory, e.g.
+/// `{"default": {"all": "prefer-online"}, "folders": {"/music/Curated": {"all": "prefer-local"}}}`.
+/// Folder keys are matched against track paths as they appear in the index;
+/// the deepest matching folder wins, field by field, over `default`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct MergePolicy {
+    pub default: FieldModes,
+    pub folders: BTreeMap<PathBuf, FieldModes>,
+}
+
+fn pick<T>(mode: MergeMode, local: Option<T>, online: Option<T>) -> Option<T> {
+    match mode {
+        MergeMode::PreferOnline => online.or(local),
+        MergeMode::PreferLocal => local,
+        MergeMode::FillMissingOnly => local.or(online),
+    }
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    (!s.trim().is_empty()).then(|| s.to_string())
+}
+
+impl MergePolicy {
+    /// Load `merge_policy.json`. A missing file means online values win.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).context("Failed to read merge policy")?;
+        serde_json::from_str(&content).context("Failed to parse merge policy JSON")
+    }
+
+    /// The most specific folder entry containing `path`.
+    fn folder_modes(&self, path: &Path) -> Option<&FieldModes> {
+        self.folders
+            .iter()
+            .filter(|(folder, _)| path.starts_with(folder))
+            .max_by_key(|(folder, _)| folder.components().count())
+            .map(|(_, modes)| modes)
+    }
+
+    fn mode(&self, path: &Path, field: fn(&FieldModes) -> Option<MergeMode>) -> MergeMode {
+        let folder = self.folder_modes(path);
+        folder
+            .and_then(field)
+            .or_else(|| folder.and_then(|m| m.all))
+            .or_else(|| field(&self.default))
+            .or(self.default.all)
+            .unwrap_or_default()
+    }
+
+    /// Resolve title, artist, album and year of the online match `online`
+    /// against the file's own tags `local`, in place.
+    pub fn apply(&self, path: &Path, local: &TrackMetadata, online: &mut TrackMetadata) {
+        online.title = pick(
+            self.mode(path, |m| m.title),
+            non_empty(&local.title),
+            non_empty(&online.title),
+        )
+        .unwrap_or_default();
+        online.artist = pick(
+            self.mode(path, |m| m.artist),
+            non_empty(&local.artist),
+            non_empty(&online.artist),
+        )
+        .unwrap_or_default();
+        online.album = pick(
+            self.mode(path, |m| m.album),
+            local.album.clone(),
+            online.album.take(),
+        );
+        online.year = pick(self.mode(path, |m| m.year), local.year, online.year);
+    }
+}
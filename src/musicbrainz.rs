@@ -1,6 +1,26 @@
 use anyhow::{Context, Result};
 use reqwest::blocking::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::lookup_cache::{self, LookupCache};
+
+/// Work lookups allowed per track during cover detection when
+/// `MUSICBRAINZ_WORK_LOOKUPS` is unset. Each uncached lookup costs a
+/// rate-limited request (about a second).
+const DEFAULT_WORK_LOOKUPS: usize = 2;
+
+/// Per-track budget of work lookups (`MUSICBRAINZ_WORK_LOOKUPS`; 0 disables
+/// cover detection).
+pub fn work_lookup_budget() -> usize {
+    static BUDGET: OnceLock<usize> = OnceLock::new();
+    *BUDGET.get_or_init(|| {
+        std::env::var("MUSICBRAINZ_WORK_LOOKUPS")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(DEFAULT_WORK_LOOKUPS)
+    })
+}
 
 #[derive(Debug, Deserialize)]
 pub struct MBRecordingResponse {
@@ -9,15 +29,17 @@ pub struct MBRecordingResponse {
     #[serde(rename = "artist-credit")]
     pub artist_credit: Option<Vec<ArtistCredit>>,
     pub relations: Option<Vec<Relation>>,
+    #[serde(rename = "first-release-date")]
+    pub first_release_date: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ArtistCredit {
     pub name: String,
     pub artist: Option<MBArtist>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MBArtist {
     pub id: String,
     pub name: String,
@@ -38,14 +60,14 @@ pub struct MBWork {
 }
 
 // Struct for Work lookup response which contains recordings
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MBWorkResponse {
     pub id: String,
     pub title: String,
     pub relations: Option<Vec<WorkRelation>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct WorkRelation {
     #[serde(rename = "type")]
     pub rel_type: String,
@@ -53,52 +75,87 @@ pub struct WorkRelation {
     pub begin: Option<String>, // Date, e.g. "1988-01-01"
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MBRecordingMinimal {
     pub id: String,
     pub title: String,
     #[serde(rename = "artist-credit")]
     pub artist_credit: Option<Vec<ArtistCredit>>,
+    #[serde(rename = "first-release-date", default)]
+    pub first_release_date: Option<String>,
 }
 
-pub fn fetch_recording_details(client: &Client, recording_id: &str) -> Result<MBRecordingResponse> {
+pub fn fetch_recording_details(
+    client: &Client,
+    cache: Option<&LookupCache>,
+    recording_id: &str,
+) -> Result<MBRecordingResponse> {
     let url = format!(
         "https://musicbrainz.org/ws/2/recording/{}?inc=work-rels+artist-credits&fmt=json",
         recording_id
     );
 
-    let resp = client
-        .get(&url)
-        .header("User-Agent", "AudioSorter/0.1.0 ( myemail@example.com )") // Replace with real info or arg
-        .send()
-        .context("Failed to query MusicBrainz")?;
+    let body = lookup_cache::cached(cache, lookup_cache::mb_recording_key(recording_id), || {
+        let resp = client
+            .get(&url)
+            .header("User-Agent", "AudioSorter/0.1.0 ( myemail@example.com )") // Replace with real info or arg
+            .send()
+            .context("Failed to query MusicBrainz")?;
 
-    // Sleep to respect rate limits (1 req/sec)
-    std::thread::sleep(std::time::Duration::from_secs(1));
+        // Sleep to respect rate limits (1 req/sec)
+        std::thread::sleep(std::time::Duration::from_secs(1));
 
-    if !resp.status().is_success() {
-        return Err(anyhow::anyhow!("MusicBrainz API error: {}", resp.status()));
-    }
+        if !resp.status().is_success() {
+            return Err(anyhow::anyhow!("MusicBrainz API error: {}", resp.status()));
+        }
 
-    let data: MBRecordingResponse = resp.json()?;
+        resp.text().context("Failed to read MusicBrainz response")
+    })?;
+
+    let data: MBRecordingResponse = serde_json::from_str(&body)?;
     Ok(data)
 }
 
-pub fn fetch_work_recordings(client: &Client, work_id: &str) -> Result<MBWorkResponse> {
+/// Reduce a work response to its work -> recordings graph before it is cached.
+/// Popular works link thousands of recordings with dates, attributes and full
+/// artist objects; only titles and credits are kept.
+fn compact_work(body: &str) -> Result<String> {
+    let mut work: MBWorkResponse =
+        serde_json::from_str(body).context("Failed to parse MusicBrainz work")?;
+    if let Some(relations) = &mut work.relations {
+        relations.retain(|r| r.recording.is_some());
+    }
+    serde_json::to_string(&work).context("Failed to serialize MusicBrainz work")
+}
+
+pub fn fetch_work_recordings(
+    client: &Client,
+    cache: Option<&LookupCache>,
+    work_id: &str,
+) -> Result<MBWorkResponse> {
     // Get work and linked recordings
     let url = format!(
         "https://musicbrainz.org/ws/2/work/{}?inc=recording-rels+artist-credits&fmt=json",
         work_id
     );
 
-    let resp = client
-        .get(&url)
-        .header("User-Agent", "AudioSorter/0.1.0 ( myemail@example.com )")
-        .send()
-        .context("Failed to query MusicBrainz Work")?;
+    let body = lookup_cache::cached(cache, lookup_cache::mb_work_key(work_id), || {
+        let resp = client
+            .get(&url)
+            .header("User-Agent", "AudioSorter/0.1.0 ( myemail@example.com )")
+            .send()
+            .context("Failed to query MusicBrainz Work")?;
+
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        if !resp.status().is_success() {
+            return Err(anyhow::anyhow!("MusicBrainz API error: {}", resp.status()));
+        }
 
-    std::thread::sleep(std::time::Duration::from_secs(1));
+        let text = resp.text().context("Failed to read MusicBrainz response")?;
+        compact_work(&text)
+    })?;
 
-    let data: MBWorkResponse = resp.json()?;
+    let data: MBWorkResponse = serde_json::from_str(&body)?;
     Ok(data)
 }
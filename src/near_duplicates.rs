@@ -0,0 +1,161 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
+
+use crate::fingerprint::{self, ITEM_SECS};
+use crate::spoken_word::ContentKind;
+use crate::storage::{AudioLibrary, IndexedTrack, TrackOrder};
+
+/// Largest shift between two copies that is searched for (30 seconds)
+const MAX_OFFSET_ITEMS: usize = 242;
+/// Raw items are bucketed by their top 20 bits to find candidate pairs
+const KEY_SHIFT: u32 = 12;
+/// Buckets shared by more tracks than this (silence, noise) are ignored
+const MAX_BUCKET_TRACKS: usize = 50;
+/// Distinct buckets two tracks must share before they are aligned
+const MIN_SHARED_KEYS: usize = 8;
+/// Differences shorter than this are reported as none
+const MIN_REPORTED_SECS: f64 = 0.5;
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct PairTrack {
+    pub id: String,
+    pub path: String,
+    pub duration: f64,
+}
+
+impl From<&IndexedTrack> for PairTrack {
+    fn from(track: &IndexedTrack) -> Self {
+        Self {
+            id: track.id.clone(),
+            path: track.path.to_string_lossy().into_owned(),
+            duration: track.metadata.duration,
+        }
+    }
+}
+
+/// Two files of the same recording whose fingerprints differ, lined up.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct NearDuplicate {
+    pub a: PairTrack,
+    pub b: PairTrack,
+    /// Fingerprint similarity over the overlapping part (0-1)
+    pub similarity: f32,
+    /// Extra audio at the start of B compared to A; negative when B's intro is trimmed
+    pub start_offset_secs: f64,
+    /// Extra audio at the end of B compared to A; negative when B ends early
+    pub end_offset_secs: f64,
+    /// B's duration minus A's
+    pub length_diff_secs: f64,
+    /// e.g. "B is A with 4.0 s extra at the start"
+    pub summary: String,
+}
+
+fn describe_edge(secs: f64, edge: &str) -> Option<String> {
+    if secs >= MIN_REPORTED_SECS {
+        Some(format!("{:.1} s extra at the {}", secs, edge))
+    } else if secs <= -MIN_REPORTED_SECS {
+        Some(format!("{:.1} s trimmed from the {}", -secs, edge))
+    } else {
+        None
+    }
+}
+
+fn summary(start: f64, end: f64) -> String {
+    let parts: Vec<String> = [describe_edge(start, "start"), describe_edge(end, "end")]
+        .into_iter()
+        .flatten()
+        .collect();
+    if parts.is_empty() {
+        "B lines up with A; the encodings differ".to_string()
+    } else {
+        format!("B is A with {}", parts.join(" and "))
+    }
+}
+
+/// Pairs of tracks with different fingerprints that align at or above
+/// `min_similarity`, most similar first. Exact fingerprint matches are left
+/// to `AudioLibrary::find_duplicates`.
+pub fn find(
+    library: &AudioLibrary,
+    min_similarity: f32,
+    include_spoken_word: bool,
+) -> Vec<NearDuplicate> {
+    let mut tracks: Vec<&IndexedTrack> = library
+        .files
+        .values()
+        .filter(|t| include_spoken_word || t.metadata.content_kind != ContentKind::SpokenWord)
+        .filter(|t| t.metadata.fingerprint.is_some())
+        .collect();
+    tracks.sort_by_cached_key(|t| TrackOrder::of(t));
+
+    let raw: Vec<Option<Vec<u32>>> = tracks
+        .par_iter()
+        .map(|t| fingerprint::decode(t.metadata.fingerprint.as_deref()?))
+        .collect();
+
+    let mut buckets: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, items) in raw.iter().enumerate() {
+        let Some(items) = items else { continue };
+        let keys: HashSet<u32> = items.iter().map(|v| v >> KEY_SHIFT).collect();
+        for key in keys {
+            buckets.entry(key).or_default().push(i);
+        }
+    }
+
+    let mut shared: HashMap<(usize, usize), usize> = HashMap::new();
+    for members in buckets.values() {
+        if members.len() > MAX_BUCKET_TRACKS {
+            continue;
+        }
+        for (n, &i) in members.iter().enumerate() {
+            for &j in &members[n + 1..] {
+                *shared.entry((i, j)).or_default() += 1;
+            }
+        }
+    }
+
+    let max_length_diff = 2.0 * MAX_OFFSET_ITEMS as f64 * ITEM_SECS;
+    let candidates: Vec<(usize, usize)> = shared
+        .into_iter()
+        .filter(|&(_, n)| n >= MIN_SHARED_KEYS)
+        .map(|(pair, _)| pair)
+        .filter(|&(i, j)| {
+            let (a, b) = (&tracks[i].metadata, &tracks[j].metadata);
+            a.fingerprint != b.fingerprint && (a.duration - b.duration).abs() <= max_length_diff
+        })
+        .collect();
+
+    let mut pairs: Vec<NearDuplicate> = candidates
+        .par_iter()
+        .filter_map(|&(i, j)| {
+            let (a, b) = (raw[i].as_ref()?, raw[j].as_ref()?);
+            let alignment = fingerprint::align(a, b, MAX_OFFSET_ITEMS)?;
+            if alignment.similarity < min_similarity {
+                return None;
+            }
+            let (ta, tb) = (tracks[i], tracks[j]);
+            let start = alignment.offset as f64 * ITEM_SECS;
+            let length_diff = tb.metadata.duration - ta.metadata.duration;
+            let end = length_diff - start;
+            Some(NearDuplicate {
+                a: ta.into(),
+                b: tb.into(),
+                similarity: alignment.similarity,
+                start_offset_secs: start,
+                end_offset_secs: end,
+                length_diff_secs: length_diff,
+                summary: summary(start, end),
+            })
+        })
+        .collect();
+
+    pairs.sort_by(|x, y| {
+        y.similarity
+            .total_cmp(&x.similarity)
+            .then_with(|| x.a.path.cmp(&y.a.path))
+            .then_with(|| x.b.path.cmp(&y.b.path))
+    });
+    pairs
+}
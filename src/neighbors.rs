@@ -0,0 +1,195 @@
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use crate::analysis_store::{euclidean_distance, AnalysisStore, SharedAnalysis};
+
+/// Neighbors kept per track; `/api/recommend` returns the closest 20 of them
+/// after filtering.
+pub const NEIGHBOR_COUNT: usize = 50;
+
+/// Each track's nearest neighbors by bliss distance, precomputed after scans
+/// and stored as `neighbors.bin` next to `analysis.bin`. Recommendations read
+/// this instead of comparing against the whole analysis store, so they stay
+/// fast and consistent while a scan rewrites the store.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct NeighborIndex {
+    paths: Vec<PathBuf>,
+    /// Per entry of `paths`: (index into `paths`, distance), closest first
+    neighbors: Vec<Vec<(u32, f32)>>,
+    #[serde(skip)]
+    positions: HashMap<PathBuf, usize>,
+}
+
+impl NeighborIndex {
+    /// Compare every vector against every other, in parallel.
+    pub fn build(store: &AnalysisStore, k: usize) -> Self {
+        let mut entries: Vec<(&PathBuf, &Vec<f32>)> = store.data.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        let neighbors = entries
+            .par_iter()
+            .enumerate()
+            .map(|(i, (_, target))| {
+                let distances = entries
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(j, (_, v))| (j as u32, euclidean_distance(target, v)))
+                    .collect();
+                closest(distances, k)
+            })
+            .collect();
+
+        let mut index = Self {
+            paths: entries.into_iter().map(|(p, _)| p.clone()).collect(),
+            neighbors,
+            positions: HashMap::new(),
+        };
+        index.index_positions();
+        index
+    }
+
+    fn index_positions(&mut self) {
+        self.positions = self
+            .paths
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.clone(), i))
+            .collect();
+    }
+
+    /// Load `neighbors.bin`. Returns an empty index if it doesn't exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let bytes = fs::read(path).context("Failed to read neighbor index file")?;
+        let mut index: Self =
+            bincode::deserialize(&bytes).context("Failed to deserialize neighbor index")?;
+        index.index_positions();
+        Ok(index)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let bytes = bincode::serialize(self).context("Failed to serialize neighbor index")?;
+        let tmp = path.with_extension("bin.tmp");
+        fs::write(&tmp, bytes).context("Failed to write neighbor index file")?;
+        fs::rename(&tmp, path).context("Failed to replace neighbor index file")?;
+        Ok(())
+    }
+
+    /// Precomputed neighbors of `path`, closest first; `None` for tracks
+    /// analyzed after the last rebuild.
+    pub fn get(&self, path: &Path) -> Option<impl Iterator<Item = (&PathBuf, f32)>> {
+        let i = *self.positions.get(path)?;
+        Some(
+            self.neighbors[i]
+                .iter()
+                .map(|&(j, d)| (&self.paths[j as usize], d)),
+        )
+    }
+}
+
+/// The `k` smallest distances, closest first; NaN (dimension mismatch) dropped.
+fn closest<T>(mut distances: Vec<(T, f32)>, k: usize) -> Vec<(T, f32)> {
+    distances.retain(|(_, d)| !d.is_nan());
+    let by_distance = |a: &(T, f32), b: &(T, f32)| a.1.total_cmp(&b.1);
+    if distances.len() > k {
+        distances.select_nth_unstable_by(k, by_distance);
+        distances.truncate(k);
+    }
+    distances.sort_by(by_distance);
+    distances
+}
+
+/// Up to `NEIGHBOR_COUNT` neighbors of `path`, closest first: the precomputed
+/// list, or for tracks analyzed after the last rebuild a pass over the store.
+/// `None` when `path` has no analysis at all.
+pub fn lookup(
+    index: &NeighborIndex,
+    analysis: &SharedAnalysis,
+    path: &Path,
+) -> Option<Vec<(PathBuf, f32)>> {
+    if let Some(list) = index.get(path) {
+        return Some(list.map(|(p, d)| (p.clone(), d)).collect());
+    }
+    let store = analysis.get();
+    let target = store.get(path)?;
+    let distances = store
+        .data
+        .iter()
+        .filter(|(p, _)| p.as_path() != path)
+        .map(|(p, v)| (p.clone(), euclidean_distance(target, v)))
+        .collect();
+    Some(closest(distances, NEIGHBOR_COUNT))
+}
+
+fn file_mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Whether `neighbors.bin` in `index_dir` is missing or older than `analysis.bin`.
+pub fn is_stale(index_dir: &Path) -> bool {
+    match (
+        file_mtime(&index_dir.join("analysis.bin")),
+        file_mtime(&index_dir.join("neighbors.bin")),
+    ) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some(analysis), Some(neighbors)) => neighbors < analysis,
+    }
+}
+
+/// Recompute `neighbors.bin` from `store`. Returns the number of tracks indexed.
+pub fn rebuild(index_dir: &Path, store: &AnalysisStore) -> Result<usize> {
+    let index = NeighborIndex::build(store, NEIGHBOR_COUNT);
+    index.save(&index_dir.join("neighbors.bin"))?;
+    Ok(index.paths.len())
+}
+
+/// Neighbor index kept resident by the web server, reloaded when
+/// `neighbors.bin` is rewritten.
+pub struct SharedNeighbors {
+    path: PathBuf,
+    inner: RwLock<(Option<SystemTime>, Arc<NeighborIndex>)>,
+}
+
+impl SharedNeighbors {
+    pub fn new(path: PathBuf) -> Self {
+        let mtime = file_mtime(&path);
+        let index = NeighborIndex::load(&path).unwrap_or_else(|e| {
+            eprintln!("Could not load neighbor index: {}. Starting empty.", e);
+            NeighborIndex::default()
+        });
+        Self {
+            path,
+            inner: RwLock::new((mtime, Arc::new(index))),
+        }
+    }
+
+    /// Current index, reloading first if the file was modified.
+    pub fn get(&self) -> Arc<NeighborIndex> {
+        let mtime = file_mtime(&self.path);
+        {
+            let inner = self.inner.read().unwrap();
+            if inner.0 == mtime {
+                return inner.1.clone();
+            }
+        }
+
+        let mut inner = self.inner.write().unwrap();
+        if inner.0 != mtime {
+            match NeighborIndex::load(&self.path) {
+                Ok(index) => *inner = (mtime, Arc::new(index)),
+                Err(e) => eprintln!("Could not reload neighbor index: {}", e),
+            }
+        }
+        inner.1.clone()
+    }
+}
@@ -0,0 +1,329 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::artwork;
+use crate::organize::OrganizePlan;
+use crate::storage::{AudioLibrary, IndexedTrack};
+
+/// Marks files written here, so later runs refresh them but leave
+/// hand-written NFOs alone.
+const GENERATOR_COMMENT: &str = "<!-- Written by audio_sorter organize --nfo -->";
+
+/// What goes into one file next to the organized tracks.
+#[derive(Debug)]
+pub enum NfoContent {
+    /// `album.nfo` / `artist.nfo` XML
+    Xml(String),
+    /// Folder image of the source album, copied as is
+    CopyImage(PathBuf),
+    /// Front cover embedded in this track, written as `cover.jpg`/`cover.png`
+    ExtractCover(PathBuf),
+}
+
+#[derive(Debug)]
+pub struct NfoFile {
+    /// File to write, or for `ExtractCover` the folder to write into
+    pub target: PathBuf,
+    pub content: NfoContent,
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn element(out: &mut String, indent: usize, name: &str, value: &str) {
+    if !value.trim().is_empty() {
+        out.push_str(&format!(
+            "{:indent$}<{name}>{}</{name}>\n",
+            "",
+            escape(value.trim())
+        ));
+    }
+}
+
+fn document(root: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n{}\n<{root}>\n{}</{root}>\n",
+        GENERATOR_COMMENT, body
+    )
+}
+
+/// Most frequent value, the first seen among equals.
+fn most_common<'a>(values: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for v in values.filter(|v| !v.trim().is_empty()) {
+        match counts.iter_mut().find(|(c, _)| c.eq_ignore_ascii_case(v)) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((v, 1)),
+        }
+    }
+    counts.iter().rev().max_by_key(|(_, n)| *n).map(|(v, _)| *v)
+}
+
+/// Album artist tag, else the only track artist, else "Various Artists".
+fn album_artist(tracks: &[&IndexedTrack]) -> String {
+    if let Some(a) = most_common(
+        tracks
+            .iter()
+            .filter_map(|t| t.metadata.album_artist.as_deref()),
+    ) {
+        return a.to_string();
+    }
+    let first = &tracks[0].metadata.artist;
+    if tracks
+        .iter()
+        .all(|t| t.metadata.artist.eq_ignore_ascii_case(first))
+    {
+        first.clone()
+    } else {
+        "Various Artists".to_string()
+    }
+}
+
+fn genres<'a>(tracks: &[&'a IndexedTrack]) -> Vec<&'a str> {
+    let mut genres: Vec<&str> = Vec::new();
+    for genre in tracks
+        .iter()
+        .copied()
+        .filter_map(|t| t.metadata.genre.as_deref())
+        .flat_map(|g| g.split(';'))
+        .map(str::trim)
+        .filter(|g| !g.is_empty())
+    {
+        if !genres.iter().any(|g| g.eq_ignore_ascii_case(genre)) {
+            genres.push(genre);
+        }
+    }
+    genres
+}
+
+/// Kodi/Jellyfin `album.nfo` for the tracks of one album folder, listed in
+/// the given order. Covers are listed in the review with their original artist,
+/// since neither player has a field for them.
+pub fn album_nfo(album: &str, tracks: &[&IndexedTrack]) -> String {
+    let mut body = String::new();
+    element(&mut body, 2, "title", album);
+    let artist = album_artist(tracks);
+    element(&mut body, 2, "artist", &artist);
+    body.push_str("  <albumArtistCredits>\n");
+    element(&mut body, 4, "artist", &artist);
+    body.push_str("  </albumArtistCredits>\n");
+    for genre in genres(tracks) {
+        element(&mut body, 2, "genre", genre);
+    }
+    if let Some(year) = tracks.iter().filter_map(|t| t.metadata.year).min() {
+        element(&mut body, 2, "year", &year.to_string());
+    }
+
+    let covers: Vec<String> = tracks
+        .iter()
+        .filter_map(|t| {
+            let meta = &t.metadata;
+            let original = meta.original_artist.as_deref()?;
+            let mut line = format!(
+                "\"{}\" is a cover of \"{}\" by {}",
+                meta.title,
+                meta.original_title.as_deref().unwrap_or(&meta.title),
+                original
+            );
+            if let Some(year) = meta.original_year {
+                line.push_str(&format!(" ({})", year));
+            }
+            line.push('.');
+            Some(line)
+        })
+        .collect();
+    element(&mut body, 2, "review", &covers.join("\n"));
+
+    for (i, t) in tracks.iter().enumerate() {
+        let meta = &t.metadata;
+        let seconds = meta.duration.round() as u64;
+        body.push_str("  <track>\n");
+        let position = meta.track_number.unwrap_or(i as u32 + 1);
+        element(&mut body, 4, "position", &position.to_string());
+        element(&mut body, 4, "title", &meta.title);
+        element(
+            &mut body,
+            4,
+            "duration",
+            &format!("{}:{:02}", seconds / 60, seconds % 60),
+        );
+        body.push_str("  </track>\n");
+    }
+    document("album", &body)
+}
+
+/// Kodi/Jellyfin `artist.nfo` listing the artist's albums by year.
+pub fn artist_nfo(name: &str, albums: &[(String, Option<u32>)], genres: &[&str]) -> String {
+    let mut body = String::new();
+    element(&mut body, 2, "name", name);
+    for genre in genres {
+        element(&mut body, 2, "genre", genre);
+    }
+    for (title, year) in albums {
+        body.push_str("  <album>\n");
+        element(&mut body, 4, "title", title);
+        if let Some(year) = year {
+            element(&mut body, 4, "year", &year.to_string());
+        }
+        body.push_str("  </album>\n");
+    }
+    document("artist", &body)
+}
+
+/// An existing NFO that this module did not write, which must be kept.
+fn hand_written(path: &Path) -> bool {
+    path.exists()
+        && !fs::read_to_string(path).is_ok_and(|content| content.contains(GENERATOR_COMMENT))
+}
+
+/// NFOs and folder art for the organized tree: `album.nfo` and a cover image
+/// in every destination folder whose tracks share one album, `artist.nfo` in
+/// the folder above when that is below `dest`. Covers come from the source
+/// folder's image or else a track's embedded art, and are only added where
+/// the destination has none.
+pub fn plan(library: &AudioLibrary, organize_plan: &OrganizePlan, dest: &Path) -> Vec<NfoFile> {
+    let mut folders: BTreeMap<PathBuf, Vec<&IndexedTrack>> = BTreeMap::new();
+    for copy in organize_plan.copies.iter().chain(&organize_plan.existing) {
+        let (Some(parent), Some(track)) = (copy.target.parent(), library.files.get(&copy.source))
+        else {
+            continue;
+        };
+        folders.entry(parent.to_path_buf()).or_default().push(track);
+    }
+
+    let mut files = Vec::new();
+    let mut artists: BTreeMap<PathBuf, Vec<(&str, Vec<&IndexedTrack>)>> = BTreeMap::new();
+    for (folder, mut tracks) in folders {
+        let Some(album) = most_common(
+            tracks
+                .iter()
+                .copied()
+                .filter_map(|t| t.metadata.album.as_deref()),
+        ) else {
+            continue;
+        };
+        let same_album = tracks.iter().all(|t| {
+            t.metadata
+                .album
+                .as_deref()
+                .is_some_and(|a| a.eq_ignore_ascii_case(album))
+        });
+        if !same_album {
+            continue;
+        }
+        tracks.sort_by_key(|t| (t.metadata.disc_number, t.metadata.track_number));
+
+        files.push(NfoFile {
+            target: folder.join("album.nfo"),
+            content: NfoContent::Xml(album_nfo(album, &tracks)),
+        });
+        if !folder.exists() || artwork::find_folder_image(&folder).is_none() {
+            let source_image = tracks
+                .iter()
+                .filter_map(|t| t.path.parent())
+                .find_map(artwork::find_folder_image);
+            files.push(match source_image {
+                Some(image) => NfoFile {
+                    target: folder.join(image.file_name().unwrap_or_default()),
+                    content: NfoContent::CopyImage(image),
+                },
+                None => NfoFile {
+                    target: folder.clone(),
+                    content: NfoContent::ExtractCover(tracks[0].path.clone()),
+                },
+            });
+        }
+        if let Some(parent) = folder
+            .parent()
+            .filter(|p| p.starts_with(dest) && *p != dest)
+        {
+            artists
+                .entry(parent.to_path_buf())
+                .or_default()
+                .push((album, tracks));
+        }
+    }
+
+    for (folder, albums) in artists {
+        let all: Vec<&IndexedTrack> = albums.iter().flat_map(|(_, t)| t.iter().copied()).collect();
+        let mut listed: Vec<(String, Option<u32>)> = albums
+            .iter()
+            .map(|(album, tracks)| {
+                let year = tracks.iter().filter_map(|t| t.metadata.year).min();
+                (album.to_string(), year)
+            })
+            .collect();
+        listed.sort_by_key(|(title, year)| (*year, title.to_lowercase()));
+        let name = album_artist(&all);
+        files.push(NfoFile {
+            target: folder.join("artist.nfo"),
+            content: NfoContent::Xml(artist_nfo(&name, &listed, &genres(&all))),
+        });
+    }
+
+    files.retain(|f| !matches!(f.content, NfoContent::Xml(_)) || !hand_written(&f.target));
+    files
+}
+
+pub fn print_plan(files: &[NfoFile]) {
+    let mut nfos = 0;
+    for f in files {
+        match &f.content {
+            NfoContent::Xml(_) => {
+                nfos += 1;
+                println!("[nfo] {:?}", f.target);
+            }
+            NfoContent::CopyImage(source) => println!("[art] {:?} <- {:?}", f.target, source),
+            NfoContent::ExtractCover(source) => {
+                println!("[art] {:?} <- embedded in {:?}", f.target, source)
+            }
+        }
+    }
+    println!(
+        "\n{} NFO files, {} folder images.",
+        nfos,
+        files.len() - nfos
+    );
+}
+
+/// Write the planned files. Tracks without embedded art yield no cover.
+/// Returns the number of files written.
+pub fn execute(files: &[NfoFile]) -> Result<usize> {
+    let mut written = 0;
+    for f in files {
+        let dir = match &f.content {
+            NfoContent::ExtractCover(_) => Some(f.target.as_path()),
+            _ => f.target.parent(),
+        };
+        if let Some(dir) = dir {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory {:?}", dir))?;
+        }
+        match &f.content {
+            NfoContent::Xml(xml) => {
+                fs::write(&f.target, xml)
+                    .with_context(|| format!("Failed to write {:?}", f.target))?;
+            }
+            NfoContent::CopyImage(source) => {
+                fs::copy(source, &f.target)
+                    .with_context(|| format!("Failed to copy {:?} to {:?}", source, f.target))?;
+            }
+            NfoContent::ExtractCover(source) => {
+                let Some(picture) = artwork::front_cover(source)? else {
+                    continue;
+                };
+                let target = f.target.join(artwork::cover_file_name(picture.data()));
+                fs::write(&target, picture.data())
+                    .with_context(|| format!("Failed to write {:?}", target))?;
+            }
+        }
+        written += 1;
+    }
+    Ok(written)
+}
@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Contents of `notify.json` in the index directory, e.g.
+/// `{"on_success": true, "on_failure": true, "min_secs": 60}`.
+/// A missing file means no notifications.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct NotifySettings {
+    /// Notify when a scan or organize job finishes
+    pub on_success: bool,
+    /// Notify when a job fails
+    pub on_failure: bool,
+    /// Stay quiet for jobs shorter than this, so quick runs don't pop up
+    pub min_secs: u64,
+}
+
+impl NotifySettings {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).context("Failed to read notify settings")?;
+        serde_json::from_str(&content).context("Failed to parse notify settings JSON")
+    }
+}
+
+/// Show a desktop notification for the end of `job` ("Scan", "Organize")
+/// if `notify.json` in `index_dir` asks for it. Never fails the job itself:
+/// problems are only printed.
+pub fn job_finished<T>(index_dir: &Path, job: &str, elapsed_secs: u64, result: &Result<T>) {
+    let settings = match NotifySettings::load(&index_dir.join("notify.json")) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Notification skipped: {:#}", e);
+            return;
+        }
+    };
+    if elapsed_secs < settings.min_secs {
+        return;
+    }
+    let (summary, body) = match result {
+        Ok(_) if settings.on_success => (
+            format!("{} finished", job),
+            format!(
+                "{} in {}",
+                index_dir.display(),
+                format_elapsed(elapsed_secs)
+            ),
+        ),
+        Err(e) if settings.on_failure => (format!("{} failed", job), format!("{:#}", e)),
+        _ => return,
+    };
+    if let Err(e) = show(&summary, &body) {
+        eprintln!("Could not show desktop notification: {:#}", e);
+    }
+}
+
+fn format_elapsed(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(feature = "notify")]
+fn show(summary: &str, body: &str) -> Result<()> {
+    notify_rust::Notification::new()
+        .appname("Audio Sorter")
+        .summary(summary)
+        .body(body)
+        .show()
+        .context("Notification server rejected the notification")?;
+    Ok(())
+}
+
+#[cfg(not(feature = "notify"))]
+fn show(_summary: &str, _body: &str) -> Result<()> {
+    anyhow::bail!("built without the `notify` feature")
+}
@@ -0,0 +1,382 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use sysinfo::Disks;
+
+use crate::organizer;
+use crate::quality;
+use crate::scanner;
+use crate::storage::{AudioLibrary, IndexedTrack};
+
+/// What to do when the template maps several tracks to the same target path.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionStrategy {
+    /// Keep every file, appending a short ID to each colliding name
+    Hash,
+    /// Copy none of the colliding files
+    Skip,
+    /// Ask which file to keep for each collision
+    Interactive,
+    /// Keep only the best copy by measured audio quality
+    PreferQuality,
+}
+
+/// How each planned file is materialized in the destination.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMode {
+    Copy,
+    /// Symbolic links to the indexed files (no extra space)
+    Symlink,
+    /// Hard links (no extra space; destination must be on the same volume)
+    Hardlink,
+}
+
+#[derive(Debug)]
+pub struct PlannedCopy {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub size: u64,
+}
+
+/// Several tracks rendered to one target path, and how that was resolved.
+#[derive(Debug)]
+pub struct Collision {
+    pub target: PathBuf,
+    pub sources: Vec<PathBuf>,
+    pub resolution: String,
+}
+
+#[derive(Debug, Default)]
+pub struct OrganizePlan {
+    pub copies: Vec<PlannedCopy>,
+    pub collisions: Vec<Collision>,
+    /// Targets that already exist in the destination (left untouched)
+    pub existing: Vec<PlannedCopy>,
+}
+
+impl OrganizePlan {
+    fn push(&mut self, track: &IndexedTrack, target: PathBuf) {
+        let copy = PlannedCopy {
+            source: track.path.clone(),
+            target,
+            size: track.file_size,
+        };
+        if copy.target.exists() {
+            self.existing.push(copy);
+        } else {
+            self.copies.push(copy);
+        }
+    }
+
+    /// Bytes the planned copies will add to the destination (links take none).
+    pub fn required_bytes(&self, mode: LinkMode) -> u64 {
+        match mode {
+            LinkMode::Copy => self.copies.iter().map(|c| c.size).sum(),
+            LinkMode::Symlink | LinkMode::Hardlink => 0,
+        }
+    }
+}
+
+/// Free headroom below which a copy that fits still gets a warning.
+const SPACE_WARNING_MARGIN: u64 = 1024 * 1024 * 1024;
+
+/// Free space on the volume holding `path`, or its nearest existing ancestor
+/// when the destination has not been created yet.
+pub fn available_space(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|p| p.exists())?.canonicalize().ok()?;
+    let disks = Disks::new_with_refreshed_list();
+    // Most specific mount point wins (e.g. /mnt/music over /)
+    disks
+        .iter()
+        .filter(|d| existing.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| d.available_space())
+}
+
+/// Check that the destination volume can hold the plan. Errors when it cannot,
+/// prints a warning when little space would be left or free space is unknown.
+pub fn preflight_space(plan: &OrganizePlan, dest: &Path, mode: LinkMode) -> Result<()> {
+    let required = plan.required_bytes(mode);
+    if required == 0 {
+        return Ok(());
+    }
+    let Some(available) = available_space(dest) else {
+        eprintln!(
+            "Warning: could not determine free space at {:?}; {} MiB will be written.",
+            dest,
+            required / (1024 * 1024)
+        );
+        return Ok(());
+    };
+
+    println!(
+        "Space: {} MiB required, {} MiB available at {:?}.",
+        required / (1024 * 1024),
+        available / (1024 * 1024),
+        dest
+    );
+    if required > available {
+        anyhow::bail!(
+            "Not enough space at {:?}: need {} MiB more",
+            dest,
+            (required - available) / (1024 * 1024) + 1
+        );
+    }
+    if available - required < SPACE_WARNING_MARGIN {
+        eprintln!(
+            "Warning: less than {} MiB would remain free at {:?}.",
+            SPACE_WARNING_MARGIN / (1024 * 1024),
+            dest
+        );
+    }
+    Ok(())
+}
+
+/// Target path for a track: rendered template plus the extension of the
+/// format sniffed from its content, or else the source's extension.
+fn render_target(dest: &Path, template: &str, track: &IndexedTrack) -> PathBuf {
+    let mut relative = organizer::render_path_template(template, &track.metadata);
+    let ext = scanner::sniff(&track.path).map(str::to_string).or_else(|| {
+        track
+            .path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase)
+    });
+    if let Some(ext) = ext {
+        relative.push('.');
+        relative.push_str(&ext);
+    }
+    dest.join(relative)
+}
+
+/// `Song.flac` -> `Song [1a2b3c4d].flac`
+fn disambiguate(target: &Path, track: &IndexedTrack) -> PathBuf {
+    let stem = target
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut name = format!("{} [{}]", stem, &track.id[..8.min(track.id.len())]);
+    if let Some(ext) = target.extension() {
+        name.push('.');
+        name.push_str(&ext.to_string_lossy());
+    }
+    target.with_file_name(name)
+}
+
+fn ask_which(target: &Path, group: &[&IndexedTrack]) -> Option<usize> {
+    println!("\nCollision at {:?}:", target);
+    for (i, t) in group.iter().enumerate() {
+        println!("  {}) {:?} ({} bytes)", i + 1, t.path, t.file_size);
+    }
+    print!("Keep which file? [1-{}, s = skip all]: ", group.len());
+    io::stdout().flush().ok();
+
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line).ok()?;
+    line.trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|n| (1..=group.len()).contains(n))
+        .map(|n| n - 1)
+}
+
+/// Render every track's target path and resolve collisions. Targets are compared
+/// case-insensitively, since Windows and macOS file systems would merge them.
+/// `Interactive` only prompts when `dry_run` is false.
+pub fn plan(
+    library: &AudioLibrary,
+    dest: &Path,
+    template: &str,
+    strategy: CollisionStrategy,
+    dry_run: bool,
+) -> OrganizePlan {
+    let mut groups: BTreeMap<String, (PathBuf, Vec<&IndexedTrack>)> = BTreeMap::new();
+    for track in library.sorted_tracks() {
+        let target = render_target(dest, template, track);
+        let key = target.to_string_lossy().to_lowercase();
+        groups
+            .entry(key)
+            .or_insert_with(|| (target, Vec::new()))
+            .1
+            .push(track);
+    }
+
+    let mut plan = OrganizePlan::default();
+
+    for (_, (target, group)) in groups {
+        if group.len() == 1 {
+            plan.push(group[0], target);
+            continue;
+        }
+
+        let keep: Option<usize> = match strategy {
+            CollisionStrategy::Hash => {
+                for t in &group {
+                    plan.push(t, disambiguate(&target, t));
+                }
+                plan.collisions.push(Collision {
+                    target,
+                    sources: group.iter().map(|t| t.path.clone()).collect(),
+                    resolution: "kept all with ID suffixes".to_string(),
+                });
+                continue;
+            }
+            CollisionStrategy::Skip => None,
+            CollisionStrategy::Interactive if dry_run => {
+                plan.collisions.push(Collision {
+                    target,
+                    sources: group.iter().map(|t| t.path.clone()).collect(),
+                    resolution: "will ask".to_string(),
+                });
+                continue;
+            }
+            CollisionStrategy::Interactive => ask_which(&target, &group),
+            CollisionStrategy::PreferQuality => {
+                let ranked = quality::rank_group(group.iter().map(|t| (*t).clone()).collect());
+                let best = &ranked[0].track.path;
+                group.iter().position(|t| &t.path == best)
+            }
+        };
+
+        let resolution = match keep {
+            Some(i) => {
+                plan.push(group[i], target.clone());
+                format!("kept {:?}", group[i].path)
+            }
+            None => "skipped all".to_string(),
+        };
+        plan.collisions.push(Collision {
+            target,
+            sources: group.iter().map(|t| t.path.clone()).collect(),
+            resolution,
+        });
+    }
+    plan
+}
+
+pub fn print_plan(plan: &OrganizePlan) {
+    for c in &plan.collisions {
+        println!(
+            "[collision] {:?} <- {} files, {}",
+            c.target,
+            c.sources.len(),
+            c.resolution
+        );
+        for source in &c.sources {
+            println!("    {:?}", source);
+        }
+    }
+    for copy in &plan.existing {
+        println!("[exists] {:?}", copy.target);
+    }
+    println!(
+        "\n{} files to copy, {} collisions, {} already in place.",
+        plan.copies.len(),
+        plan.collisions.len(),
+        plan.existing.len()
+    );
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(source, target)
+}
+
+/// Copy or link the planned files; the source library is never modified.
+pub fn execute(plan: &OrganizePlan, mode: LinkMode) -> Result<usize> {
+    let mut written = 0;
+    for copy in &plan.copies {
+        if let Some(parent) = copy.target.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+        let result = match mode {
+            LinkMode::Copy => fs::copy(&copy.source, &copy.target).map(|_| ()),
+            LinkMode::Symlink => symlink(&copy.source, &copy.target),
+            LinkMode::Hardlink => fs::hard_link(&copy.source, &copy.target),
+        };
+        result.with_context(|| {
+            format!(
+                "Failed to {:?} {:?} to {:?}",
+                mode, copy.source, copy.target
+            )
+        })?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::organizer::TrackMetadata;
+
+    fn track(library: &mut AudioLibrary, path: &str, title: &str) {
+        let meta = TrackMetadata {
+            artist: "Artist".to_string(),
+            title: title.to_string(),
+            ..Default::default()
+        };
+        library.upsert(PathBuf::from(path), 1, 1, 1, meta);
+    }
+
+    #[test]
+    fn colliding_targets_get_id_suffixes() {
+        let mut library = AudioLibrary::default();
+        track(&mut library, "/in/a/song.mp3", "Song");
+        track(&mut library, "/in/b/SONG.mp3", "SONG");
+        track(&mut library, "/in/c/other.mp3", "Other");
+        let dest = Path::new("/nonexistent/out");
+
+        let plan = plan(
+            &library,
+            dest,
+            "{artist}/{title}",
+            CollisionStrategy::Hash,
+            true,
+        );
+
+        assert_eq!(plan.collisions.len(), 1);
+        assert_eq!(plan.collisions[0].sources.len(), 2);
+        assert_eq!(plan.copies.len(), 3);
+        let mut targets: Vec<_> = plan.copies.iter().map(|c| c.target.clone()).collect();
+        targets.sort();
+        targets.dedup();
+        assert_eq!(targets.len(), 3);
+        for copy in plan
+            .copies
+            .iter()
+            .filter(|c| !c.source.ends_with("other.mp3"))
+        {
+            let id = &library.files[&copy.source].id[..8];
+            let name = copy.target.file_name().unwrap().to_string_lossy();
+            assert!(name.ends_with(&format!(" [{}].mp3", id)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn skip_drops_every_colliding_track() {
+        let mut library = AudioLibrary::default();
+        track(&mut library, "/in/a/song.mp3", "Song");
+        track(&mut library, "/in/b/song.mp3", "Song");
+
+        let plan = plan(
+            &library,
+            Path::new("/nonexistent/out"),
+            "{artist}/{title}",
+            CollisionStrategy::Skip,
+            true,
+        );
+
+        assert!(plan.copies.is_empty());
+        assert_eq!(plan.collisions[0].resolution, "skipped all");
+    }
+}
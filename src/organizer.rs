@@ -1,44 +1,366 @@
 use anyhow::{Context, Result};
-use lofty::{Accessor, TaggedFileExt};
+use lofty::{Accessor, ItemKey, Tag, TaggedFileExt};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+use crate::analyzers::Features;
+use crate::matching::MatchCandidate;
+use crate::quality::AudioQuality;
+use crate::spoken_word::ContentKind;
+use crate::unicode;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
 pub struct TrackMetadata {
     pub title: String,
     pub artist: String,
     pub album: Option<String>,
+    #[serde(default)]
+    pub album_artist: Option<String>, // From local tags
+    #[serde(default)]
+    pub genre: Option<String>, // From local tags
+    #[serde(default)]
+    pub year: Option<u32>, // Release year (tags or MusicBrainz first release)
+    #[serde(default)]
+    pub disc_number: Option<u32>, // From local tags
+    #[serde(default)]
+    pub track_number: Option<u32>, // From local tags
+    #[serde(default)]
+    pub rating: Option<u8>, // 1-5 stars from RATING / FMPS_RATING tags
     pub original_artist: Option<String>, // For covers
     pub original_title: Option<String>,  // For covers
+    #[serde(default)]
+    pub original_year: Option<u32>, // Earliest known recording/release of the work
     pub duration: f64,                   // Duration in seconds
     pub fingerprint: Option<String>,     // Chromaprint fingerprint
+    #[serde(default)]
+    pub quality: Option<AudioQuality>, // Measured from decoded audio
+    #[serde(default)]
+    pub content_kind: ContentKind, // Music vs. audiobook/podcast
+    #[serde(default)]
+    pub vocal_language: Option<String>, // ISO 639-1, "zxx" for instrumentals
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub vocal_pair: Option<PathBuf>, // Off-vocal <-> vocal counterpart
+    #[serde(default)]
+    pub acoustid_score: Option<f64>, // Confidence of the AcoustID match (0..1)
+    #[serde(default)]
+    pub recording_mbid: Option<String>, // MusicBrainz recording ID
+    #[serde(default)]
+    pub work_mbid: Option<String>, // MusicBrainz work ID (shared by covers)
+    #[serde(default)]
+    pub match_candidates: Vec<MatchCandidate>, // Top AcoustID candidates, best first
+    #[serde(default)]
+    pub source: MetadataSource,
+    #[serde(default)]
+    pub overwritten_fields: Vec<String>, // Local tag fields replaced by online data
+    #[serde(default, skip_serializing_if = "Features::is_empty")]
+    pub features: Features, // From the analyzers enabled in scan.json
+}
+
+impl TrackMetadata {
+    /// Store every text field in Unicode NFC, so the same name typed or
+    /// tagged in decomposed form compares, groups and searches as equal.
+    /// Returns whether any field changed.
+    pub fn normalize_unicode(&mut self) -> bool {
+        let mut changed = unicode::nfc_in_place(&mut self.title);
+        changed |= unicode::nfc_in_place(&mut self.artist);
+        for field in [
+            &mut self.album,
+            &mut self.album_artist,
+            &mut self.genre,
+            &mut self.original_artist,
+            &mut self.original_title,
+        ] {
+            if let Some(value) = field.as_mut() {
+                changed |= unicode::nfc_in_place(value);
+            }
+        }
+        changed
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataSource {
+    #[default]
+    Tags,
+    Online,
+}
+
+/// Star rating from a `FMPS_RATING` value (0.0-1.0) or else a `RATING` value
+/// (1-5 stars, or 0-100 as some players write it). 0 means unrated.
+pub fn stars(fmps_rating: Option<f64>, rating: Option<f64>) -> Option<u8> {
+    let stars = match (fmps_rating, rating) {
+        (Some(fmps), _) => fmps * 5.0,
+        (None, Some(r)) if r <= 5.0 => r,
+        (None, Some(r)) => r / 20.0,
+        (None, None) => return None,
+    };
+    let stars = stars.round();
+    (1.0..=5.0).contains(&stars).then_some(stars as u8)
+}
+
+/// Rating tags are free-form (Vorbis comments, APE items, ID3 `TXXX`), so
+/// they are matched by name.
+fn read_rating(tag: &Tag) -> Option<u8> {
+    let number = |name: &str| {
+        tag.items()
+            .find(|item| matches!(item.key(), ItemKey::Unknown(k) if k.eq_ignore_ascii_case(name)))
+            .and_then(|item| item.value().text())
+            .and_then(|v| v.trim().parse::<f64>().ok())
+    };
+    stars(number("FMPS_RATING"), number("RATING"))
 }
 
 pub fn read_tags(path: &Path) -> Result<TrackMetadata> {
     let probed = lofty::Probe::open(path)
         .context("Failed to open file for probing")?
+        // Trust the content over the extension (misnamed files)
+        .guess_file_type()
+        .context("Failed to detect file type")?
         .read()
         .context("Failed to read file tags")?;
 
     let tag = probed.primary_tag().or_else(|| probed.first_tag());
 
-    let (title, artist, album) = if let Some(t) = tag {
+    let (title, artist, album, genre, year, language) = if let Some(t) = tag {
         (
             t.title().map(|s| s.into_owned()).unwrap_or_default(),
             t.artist().map(|s| s.into_owned()).unwrap_or_default(),
             t.album().map(|s| s.into_owned()),
+            t.genre().map(|s| s.into_owned()),
+            t.year().filter(|y| *y > 0),
+            t.get_string(&ItemKey::Language).map(|s| s.to_string()),
         )
     } else {
-        (String::new(), String::new(), None)
+        (String::new(), String::new(), None, None, None, None)
     };
+    let album_artist = tag
+        .and_then(|t| t.get_string(&ItemKey::AlbumArtist))
+        .map(|s| s.to_string());
+    let disc_number = tag.and_then(|t| t.disk()).filter(|n| *n > 0);
+    let track_number = tag.and_then(|t| t.track()).filter(|n| *n > 0);
+    let rating = tag.and_then(read_rating);
 
     Ok(TrackMetadata {
         title,
         artist,
         album,
+        album_artist,
+        genre,
+        year,
+        disc_number,
+        track_number,
+        rating,
         original_artist: None, // Cannot know from local tags alone usually
         original_title: None,
         duration: 0.0, // Will be filled by scanner/fingerprinter
         fingerprint: None,
+        vocal_language: language,
+        ..Default::default()
     })
 }
+
+/// Names of the user-visible fields whose (non-empty) local value differs in `new`.
+pub fn changed_fields(local: &TrackMetadata, new: &TrackMetadata) -> Vec<String> {
+    let mut fields = Vec::new();
+    if !local.title.is_empty() && local.title != new.title {
+        fields.push("title".to_string());
+    }
+    if !local.artist.is_empty() && local.artist != new.artist {
+        fields.push("artist".to_string());
+    }
+    if local.album.is_some() && local.album != new.album {
+        fields.push("album".to_string());
+    }
+    if local.year.is_some() && local.year != new.year {
+        fields.push("year".to_string());
+    }
+    fields
+}
+
+/// Apply an online match on top of existing metadata, keeping locally measured
+/// fields (quality, content kind, analysis links) intact.
+pub fn merge_online(meta: &mut TrackMetadata, online: TrackMetadata) {
+    meta.overwritten_fields = changed_fields(meta, &online);
+    meta.title = online.title;
+    meta.artist = online.artist;
+    if online.album.is_some() {
+        meta.album = online.album;
+    }
+    if online.year.is_some() {
+        meta.year = online.year;
+    }
+    meta.original_artist = online.original_artist;
+    meta.original_title = online.original_title;
+    meta.original_year = online.original_year;
+    meta.acoustid_score = online.acoustid_score;
+    meta.recording_mbid = online.recording_mbid;
+    meta.work_mbid = online.work_mbid;
+    meta.match_candidates = online.match_candidates;
+    meta.source = MetadataSource::Online;
+}
+
+/// First year of the decade, e.g. 1994 -> 1990.
+pub fn decade(year: u32) -> u32 {
+    year / 10 * 10
+}
+
+/// Parse the year from a MusicBrainz date ("1988", "1988-04", "1988-04-01").
+pub fn parse_year(date: &str) -> Option<u32> {
+    date.get(..4)?.parse().ok().filter(|y| *y > 0)
+}
+
+/// Render a relative path template such as `{artist}/{decade}s/{year} - {album}/{title}`.
+/// Supported fields: artist, album, title, genre, genre_path, year, decade, original_artist,
+/// original_year (the work's first year, falling back to the track's own year).
+/// `genre_path` nests hierarchical genres ("Electronic/House" or "Electronic > House")
+/// and uses only the first of several genres ("Rock; Pop").
+/// Missing values render as "Unknown ..." so the layout stays predictable.
+pub fn render_path_template(template: &str, meta: &TrackMetadata) -> String {
+    let or_unknown = |v: &str, fallback: &str| {
+        if v.trim().is_empty() {
+            fallback.to_string()
+        } else {
+            sanitize_component(v)
+        }
+    };
+
+    template
+        .replace("{artist}", &or_unknown(&meta.artist, "Unknown Artist"))
+        .replace("{title}", &or_unknown(&meta.title, "Unknown Title"))
+        .replace(
+            "{album}",
+            &or_unknown(meta.album.as_deref().unwrap_or(""), "Unknown Album"),
+        )
+        .replace("{genre_path}", &genre_path(meta.genre.as_deref()))
+        .replace(
+            "{genre}",
+            &or_unknown(meta.genre.as_deref().unwrap_or(""), "Unknown Genre"),
+        )
+        .replace(
+            "{original_artist}",
+            &or_unknown(
+                meta.original_artist.as_deref().unwrap_or(&meta.artist),
+                "Unknown Artist",
+            ),
+        )
+        .replace(
+            "{original_year}",
+            &meta
+                .original_year
+                .or(meta.year)
+                .map(|y| y.to_string())
+                .unwrap_or_else(|| "Unknown Year".to_string()),
+        )
+        .replace(
+            "{year}",
+            &meta
+                .year
+                .map(|y| y.to_string())
+                .unwrap_or_else(|| "Unknown Year".to_string()),
+        )
+        .replace(
+            "{decade}",
+            &meta
+                .year
+                .map(|y| decade(y).to_string())
+                .unwrap_or_else(|| "Unknown".to_string()),
+        )
+}
+
+fn genre_path(genre: Option<&str>) -> String {
+    let first = genre
+        .and_then(|g| {
+            g.split([';', ',', '\0'])
+                .map(str::trim)
+                .find(|g| !g.is_empty())
+        })
+        .unwrap_or("");
+    let parts: Vec<String> = first
+        .split(['/', '>', '\\'])
+        .map(sanitize_component)
+        .filter(|p| !p.is_empty())
+        .collect();
+    if parts.is_empty() {
+        "Unknown Genre".to_string()
+    } else {
+        parts.join("/")
+    }
+}
+
+/// Every genre of a tag ("Rock; Electronic/House") with the parents of
+/// hierarchical ones, lowercased: {"rock", "electronic", "electronic/house"}.
+pub fn genre_terms(genre: Option<&str>) -> BTreeSet<String> {
+    let mut terms = BTreeSet::new();
+    for g in genre.unwrap_or("").split([';', ',', '\0']) {
+        let mut path = String::new();
+        for part in g.split(['/', '>', '\\']).map(str::trim) {
+            if part.is_empty() {
+                continue;
+            }
+            if !path.is_empty() {
+                path.push('/');
+            }
+            path.push_str(&part.to_lowercase());
+            terms.insert(path.clone());
+        }
+    }
+    terms
+}
+
+/// Each genre of a tag as written, hierarchy levels joined by '/':
+/// "Rock; Electronic > House" gives ["Rock", "Electronic/House"].
+pub fn genre_list(genre: Option<&str>) -> Vec<String> {
+    let mut genres: Vec<String> = Vec::new();
+    for g in genre.unwrap_or("").split([';', ',', '\0']) {
+        let parts: Vec<&str> = g
+            .split(['/', '>', '\\'])
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .collect();
+        let joined = parts.join("/");
+        if !joined.is_empty() && !genres.iter().any(|x| x.eq_ignore_ascii_case(&joined)) {
+            genres.push(joined);
+        }
+    }
+    genres
+}
+
+/// Strip characters that are invalid in Windows/macOS/Linux file names.
+pub fn sanitize_component(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stars_from_rating_scales() {
+        assert_eq!(stars(Some(0.8), None), Some(4));
+        assert_eq!(stars(Some(1.0), Some(20.0)), Some(5));
+        assert_eq!(stars(None, Some(3.0)), Some(3));
+        assert_eq!(stars(None, Some(60.0)), Some(3));
+        assert_eq!(stars(None, Some(100.0)), Some(5));
+    }
+
+    #[test]
+    fn unrated_and_out_of_range_give_none() {
+        assert_eq!(stars(None, None), None);
+        assert_eq!(stars(Some(0.0), None), None);
+        assert_eq!(stars(None, Some(0.0)), None);
+        assert_eq!(stars(None, Some(255.0)), None);
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::analysis_store::{euclidean_distance, AnalysisStore};
+use crate::language::{self, INSTRUMENTAL};
+use crate::storage::AudioLibrary;
+
+// Off-vocal versions share the backing track, so their bliss features sit close together
+const MAX_PAIR_DISTANCE: f32 = 1.5;
+const MAX_DURATION_DELTA_SECS: f64 = 10.0;
+
+fn is_instrumental(vocal_language: Option<&str>, title: &str) -> bool {
+    vocal_language == Some(INSTRUMENTAL) || language::is_instrumental_title(title)
+}
+
+/// Link instrumental/off-vocal tracks to their vocal counterparts.
+/// Candidates must share a base title; the closest by feature distance, then
+/// length, are paired first, and each track is in at most one pair.
+/// Returns the number of pairs linked.
+pub fn link_vocal_pairs(library: &mut AudioLibrary, store: &AnalysisStore) -> usize {
+    for track in library.files.values_mut() {
+        track.metadata.vocal_pair = None;
+    }
+
+    let mut vocals_by_title: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let mut instrumentals = Vec::new();
+
+    for (path, track) in &library.files {
+        let meta = &track.metadata;
+        if meta.title.is_empty() {
+            continue;
+        }
+        if is_instrumental(meta.vocal_language.as_deref(), &meta.title) {
+            instrumentals.push(path.clone());
+        } else {
+            vocals_by_title
+                .entry(language::base_title(&meta.title))
+                .or_default()
+                .push(path.clone());
+        }
+    }
+
+    // Every acceptable (instrumental, vocal) match with its distance and
+    // length difference
+    let mut matches = Vec::new();
+    for inst_path in &instrumentals {
+        let inst = &library.files[inst_path].metadata;
+        let Some(candidates) = vocals_by_title.get(&language::base_title(&inst.title)) else {
+            continue;
+        };
+        for vocal_path in candidates {
+            let vocal = &library.files[vocal_path].metadata;
+            let delta = (vocal.duration - inst.duration).abs();
+            if delta > MAX_DURATION_DELTA_SECS {
+                continue;
+            }
+            let distance = match (store.get(inst_path), store.get(vocal_path)) {
+                (Some(a), Some(b)) => euclidean_distance(a, b),
+                // Without analysis, only trust same-artist or same-album matches
+                _ if vocal.artist == inst.artist
+                    || (vocal.album.is_some() && vocal.album == inst.album) =>
+                {
+                    MAX_PAIR_DISTANCE
+                }
+                _ => continue,
+            };
+            if distance.is_nan() || distance > MAX_PAIR_DISTANCE {
+                continue;
+            }
+            matches.push((distance, delta, inst_path, vocal_path));
+        }
+    }
+
+    // Closest matches first, then by path so equal ones always pair the same
+    // way; each track joins at most one pair
+    matches.sort_by(|a, b| {
+        a.0.total_cmp(&b.0)
+            .then(a.1.total_cmp(&b.1))
+            .then_with(|| a.2.cmp(b.2))
+            .then_with(|| a.3.cmp(b.3))
+    });
+    let mut taken = HashSet::new();
+    let mut pairs = Vec::new();
+    for (_, _, inst_path, vocal_path) in matches {
+        if taken.contains(inst_path) || taken.contains(vocal_path) {
+            continue;
+        }
+        taken.insert(inst_path);
+        taken.insert(vocal_path);
+        pairs.push((inst_path.clone(), vocal_path.clone()));
+    }
+
+    let count = pairs.len();
+    for (inst_path, vocal_path) in pairs {
+        if let Some(t) = library.files.get_mut(&inst_path) {
+            t.metadata.vocal_pair = Some(vocal_path.clone());
+        }
+        if let Some(t) = library.files.get_mut(&vocal_path) {
+            t.metadata.vocal_pair = Some(inst_path);
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::organizer::TrackMetadata;
+
+    fn add(library: &mut AudioLibrary, path: &str, title: &str, duration: f64) -> PathBuf {
+        let meta = TrackMetadata {
+            title: title.into(),
+            artist: "Singer".into(),
+            duration,
+            ..Default::default()
+        };
+        library.upsert(PathBuf::from(path), 1, 1, 1, meta);
+        PathBuf::from(path)
+    }
+
+    #[test]
+    fn each_vocal_pairs_with_the_closest_instrumental_only() {
+        let mut library = AudioLibrary::default();
+        let vocal = add(&mut library, "song.mp3", "Song", 200.0);
+        let near = add(&mut library, "song_inst.mp3", "Song (Instrumental)", 201.0);
+        let far = add(&mut library, "song_karaoke.mp3", "Song (Off Vocal)", 205.0);
+
+        let count = link_vocal_pairs(&mut library, &AnalysisStore::default());
+        let pair = |p: &PathBuf| library.files[p].metadata.vocal_pair.clone();
+        assert_eq!(count, 1);
+        assert_eq!(pair(&vocal), Some(near.clone()));
+        assert_eq!(pair(&near), Some(vocal));
+        assert_eq!(pair(&far), None);
+    }
+}
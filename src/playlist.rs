@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use utoipa::IntoParams;
+
+use crate::organizer;
+use crate::storage::{self, AudioLibrary, IndexedTrack};
+use crate::unicode;
+
+/// Track filters for generated playlists. Also the format of stored playlists
+/// in `playlists.json` (name -> query), which may also list fixed tracks.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct PlaylistQuery {
+    /// Case-insensitive genre match
+    pub genre: Option<String>,
+    /// Case-insensitive artist match
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub year: Option<u32>,
+    /// First year of the decade, e.g. `1990`
+    pub decade: Option<u32>,
+    /// Comma-separated vocal languages, e.g. `ja` or `zxx,en`
+    pub vocal_language: Option<String>,
+    /// Only tracks rated at least this many stars (1-5); unrated tracks are left out
+    pub min_rating: Option<u8>,
+    pub shuffle: bool,
+    /// Makes `shuffle` reproducible
+    pub seed: Option<u64>,
+    pub limit: Option<usize>,
+    /// Fixed track paths, in order (stored playlists only, e.g. from
+    /// `playlist import`); the filters then narrow this list instead of the
+    /// whole library
+    #[param(ignore)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tracks: Vec<String>,
+}
+
+fn matches(value: Option<&str>, wanted: &Option<String>) -> bool {
+    match wanted {
+        Some(w) => value.is_some_and(|v| v.eq_ignore_ascii_case(&unicode::nfc(w.trim()))),
+        None => true,
+    }
+}
+
+/// Indexed tracks for the listed paths, in list order. Paths that are not
+/// indexed (anymore) are skipped.
+fn listed_tracks<'a>(library: &'a AudioLibrary, paths: &[String]) -> Vec<&'a IndexedTrack> {
+    let by_key: HashMap<PathBuf, &IndexedTrack> = library
+        .files
+        .values()
+        .map(|t| (storage::path_key(&t.path), t))
+        .collect();
+    paths
+        .iter()
+        .filter_map(|p| {
+            let path = Path::new(p);
+            library
+                .files
+                .get(path)
+                .or_else(|| by_key.get(&storage::path_key(path)).copied())
+        })
+        .collect()
+}
+
+/// Apply the query's filters, ordering, shuffle and limit.
+pub fn select<'a>(library: &'a AudioLibrary, query: &PlaylistQuery) -> Vec<&'a IndexedTrack> {
+    let languages: Option<Vec<String>> = query.vocal_language.as_ref().map(|l| {
+        l.split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect()
+    });
+
+    let candidates = if query.tracks.is_empty() {
+        library.sorted_tracks()
+    } else {
+        listed_tracks(library, &query.tracks)
+    };
+    let mut tracks: Vec<&IndexedTrack> = candidates
+        .into_iter()
+        .filter(|t| matches(t.metadata.genre.as_deref(), &query.genre))
+        .filter(|t| matches(Some(&t.metadata.artist), &query.artist))
+        .filter(|t| matches(t.metadata.album.as_deref(), &query.album))
+        .filter(|t| query.year.is_none() || t.metadata.year == query.year)
+        .filter(|t| match query.decade {
+            Some(d) => t.metadata.year.map(organizer::decade) == Some(d),
+            None => true,
+        })
+        .filter(|t| match &languages {
+            Some(langs) => t
+                .metadata
+                .vocal_language
+                .as_ref()
+                .is_some_and(|l| langs.contains(l)),
+            None => true,
+        })
+        .filter(|t| match query.min_rating {
+            Some(min) => t.metadata.rating.is_some_and(|r| r >= min),
+            None => true,
+        })
+        .collect();
+
+    if query.shuffle {
+        match query.seed {
+            Some(seed) => tracks.shuffle(&mut StdRng::seed_from_u64(seed)),
+            None => tracks.shuffle(&mut rand::thread_rng()),
+        }
+    }
+    if let Some(limit) = query.limit {
+        tracks.truncate(limit);
+    }
+    tracks
+}
+
+/// Render an extended M3U playlist of local file paths.
+pub fn render_m3u(tracks: &[&IndexedTrack]) -> String {
+    render_m3u_with(tracks, |t| t.path.display().to_string())
+}
+
+/// Extended M3U with each entry's location given by `location`, e.g. a stream URL.
+pub fn render_m3u_with(
+    tracks: &[&IndexedTrack],
+    location: impl Fn(&IndexedTrack) -> String,
+) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    for t in tracks {
+        let meta = &t.metadata;
+        out.push_str(&format!(
+            "#EXTINF:{},{} - {}\n{}\n",
+            meta.duration.round() as i64,
+            meta.artist,
+            meta.title,
+            location(t)
+        ));
+    }
+    out
+}
+
+/// Load stored playlists from `playlists.json`. Missing file means no stored playlists.
+pub fn load_stored(path: &Path) -> Result<HashMap<String, PlaylistQuery>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = fs::read_to_string(path).context("Failed to read playlists file")?;
+    serde_json::from_str(&content).context("Failed to parse playlists JSON")
+}
+
+/// Write stored playlists back to `playlists.json`.
+pub fn save_stored(path: &Path, playlists: &HashMap<String, PlaylistQuery>) -> Result<()> {
+    let content =
+        serde_json::to_string_pretty(playlists).context("Failed to serialize playlists")?;
+    fs::write(path, content).context("Failed to write playlists file")
+}
@@ -0,0 +1,553 @@
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::matching;
+use crate::storage::{self, AudioLibrary, IndexedTrack};
+
+/// Combined title/artist similarity a metadata match needs.
+const MIN_FUZZY_SCORE: f64 = 0.75;
+
+/// Length difference (seconds) above which a metadata match is rejected,
+/// when both the playlist and the index know the length.
+const MAX_DURATION_DELTA_SECS: f64 = 10.0;
+
+/// One playlist entry as written by the exporting player.
+#[derive(Debug, Clone, Default)]
+pub struct Entry {
+    /// Path or `file://` URL, as in the file
+    pub location: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// Seconds
+    pub duration: Option<f64>,
+}
+
+/// `FROM=TO` path prefix replacement, e.g. `D:\Music=/mnt/music` for
+/// playlists or a beets library from another machine.
+#[derive(Debug, Clone)]
+pub struct PathMap {
+    from: String,
+    to: String,
+}
+
+/// Parse a `--map FROM=TO` argument.
+pub fn parse_path_map(s: &str) -> Result<PathMap, String> {
+    let (from, to) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected FROM=TO, got '{}'", s))?;
+    if from.trim().is_empty() {
+        return Err("FROM must not be empty".to_string());
+    }
+    Ok(PathMap {
+        from: slashes(from.trim()).trim_end_matches('/').to_string(),
+        to: slashes(to.trim()).trim_end_matches('/').to_string(),
+    })
+}
+
+/// How an entry was matched to an indexed track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    /// Same path after remapping
+    Path,
+    /// Same folder and file name under a different root
+    FileName,
+    /// Title, artist and length from the playlist or the file name
+    Metadata,
+}
+
+#[derive(Debug)]
+pub struct Resolved<'a> {
+    pub entry: Entry,
+    pub track: Option<(&'a IndexedTrack, MatchKind)>,
+}
+
+fn slashes(s: &str) -> String {
+    s.replace('\\', "/")
+}
+
+/// Text of a playlist file: UTF-8 (with or without BOM), UTF-16 with BOM as
+/// written by some Windows players, or Latin-1 for old `.m3u` files.
+fn decode(bytes: &[u8]) -> String {
+    let utf16 = |be: bool| {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|c| {
+                if be {
+                    u16::from_be_bytes([c[0], c[1]])
+                } else {
+                    u16::from_le_bytes([c[0], c[1]])
+                }
+            })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        [0xFF, 0xFE, ..] => utf16(false),
+        [0xFE, 0xFF, ..] => utf16(true),
+        _ => match std::str::from_utf8(bytes) {
+            Ok(s) => s.to_string(),
+            Err(_) => bytes.iter().map(|&b| b as char).collect(),
+        },
+    }
+}
+
+/// Read the entries of an M3U/M3U8, PLS or XSPF playlist, chosen by
+/// extension or, failing that, by content.
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read playlist {:?}", path))?;
+    let content = decode(&bytes);
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+    Ok(match ext.as_deref() {
+        Some("pls") => parse_pls(&content),
+        Some("xspf") => parse_xspf(&content),
+        Some("m3u" | "m3u8") => parse_m3u(&content),
+        _ if content.trim_start().starts_with("<?xml") || content.contains("<playlist") => {
+            parse_xspf(&content)
+        }
+        _ if content.trim_start().starts_with("[playlist]") => parse_pls(&content),
+        _ => parse_m3u(&content),
+    })
+}
+
+/// Split "Artist - Title" display text; a text without separator is a title.
+fn split_display(text: &str) -> (Option<String>, Option<String>) {
+    let text = text.trim();
+    match text.split_once(" - ") {
+        Some((artist, title)) => (
+            Some(artist.trim().to_string()),
+            Some(title.trim().to_string()),
+        ),
+        None if !text.is_empty() => (None, Some(text.to_string())),
+        None => (None, None),
+    }
+}
+
+fn seconds(value: &str) -> Option<f64> {
+    value.trim().parse::<f64>().ok().filter(|d| *d > 0.0)
+}
+
+pub fn parse_m3u(content: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut info: Option<(Option<f64>, String)> = None;
+    for line in content.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("#EXTINF:") {
+            // #EXTINF:<seconds>[ attributes],<display text>
+            let (head, text) = rest.split_once(',').unwrap_or((rest, ""));
+            let length = head.split_whitespace().next().and_then(seconds);
+            info = Some((length, text.to_string()));
+        } else if !line.is_empty() && !line.starts_with('#') {
+            let (duration, text) = info.take().unwrap_or_default();
+            let (artist, title) = split_display(&text);
+            entries.push(Entry {
+                location: line.to_string(),
+                title,
+                artist,
+                duration,
+            });
+        }
+    }
+    entries
+}
+
+pub fn parse_pls(content: &str) -> Vec<Entry> {
+    let mut entries: BTreeMap<u32, Entry> = BTreeMap::new();
+    for line in content.lines().map(str::trim) {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let (field, n) = key.split_at(key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len()));
+        let Ok(n) = n.parse::<u32>() else { continue };
+        let entry = entries.entry(n).or_default();
+        match field {
+            "file" => entry.location = value.trim().to_string(),
+            "title" => (entry.artist, entry.title) = split_display(value),
+            // -1 marks streams of unknown length
+            "length" => entry.duration = seconds(value),
+            _ => {}
+        }
+    }
+    entries
+        .into_values()
+        .filter(|e| !e.location.is_empty())
+        .collect()
+}
+
+fn unescape_xml(s: &str) -> String {
+    static ENTITY: OnceLock<Regex> = OnceLock::new();
+    let entity = ENTITY.get_or_init(|| Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|[a-z]+);").unwrap());
+    entity
+        .replace_all(s, |caps: &regex::Captures| {
+            let name = &caps[1];
+            let c = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => match name.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => name.strip_prefix('#').and_then(|d| d.parse().ok()),
+                }
+                .and_then(char::from_u32),
+            };
+            c.map(String::from).unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+pub fn parse_xspf(content: &str) -> Vec<Entry> {
+    static TRACK: OnceLock<Regex> = OnceLock::new();
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+    let track = TRACK.get_or_init(|| Regex::new(r"(?s)<track\b[^>]*>(.*?)</track>").unwrap());
+    let field = FIELD.get_or_init(|| {
+        Regex::new(r"(?s)<(location|title|creator|duration)\b[^>]*>(.*?)</").unwrap()
+    });
+    track
+        .captures_iter(content)
+        .filter_map(|t| {
+            let mut entry = Entry::default();
+            for f in field.captures_iter(&t[1]) {
+                let value = unescape_xml(f[2].trim());
+                match &f[1] {
+                    // The first location is the one players use
+                    "location" if entry.location.is_empty() => entry.location = value,
+                    "title" => entry.title = Some(value),
+                    "creator" => entry.artist = Some(value),
+                    // Milliseconds
+                    "duration" => entry.duration = seconds(&value).map(|ms| ms / 1000.0),
+                    _ => {}
+                }
+            }
+            (!entry.location.is_empty()).then_some(entry)
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn has_drive(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() >= 2 && b[0].is_ascii_alphabetic() && b[1] == b':'
+}
+
+/// Drop `.` and resolve `..` without touching the filesystem, so relative
+/// entries line up with indexed paths.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `path` with the first matching prefix replaced, in this platform's
+/// separators.
+pub fn remap(path: &str, maps: &[PathMap]) -> String {
+    let mut path = slashes(path);
+    for map in maps {
+        let prefixed = path
+            .get(..map.from.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&map.from))
+            && matches!(path.as_bytes().get(map.from.len()), None | Some(b'/'));
+        if prefixed {
+            path = format!("{}{}", map.to, &path[map.from.len()..]);
+            break;
+        }
+    }
+    if cfg!(windows) {
+        path = path.replace('/', "\\");
+    }
+    path
+}
+
+/// The local path an entry points to: `file://` URLs decoded, path prefixes
+/// remapped and relative paths resolved against the playlist's folder.
+/// `None` for streams and other non-file URLs.
+pub fn local_path(location: &str, base_dir: &Path, maps: &[PathMap]) -> Option<PathBuf> {
+    let mut location = location.trim().to_string();
+    if let Some(rest) = location
+        .get(..7)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("file://"))
+        .map(|_| &location[7..])
+    {
+        let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+        let mut decoded = percent_decode(rest);
+        // file:///C:/Music/... names a Windows drive
+        if decoded.starts_with('/') && has_drive(&decoded[1..]) {
+            decoded.remove(0);
+        }
+        location = decoded;
+    } else if location.contains("://") {
+        return None;
+    }
+
+    let location = remap(&location, maps);
+    let path = PathBuf::from(&location);
+    if path.is_absolute() || has_drive(&location) {
+        Some(normalize(&path))
+    } else {
+        Some(normalize(&base_dir.join(path)))
+    }
+}
+
+/// Folder and file name of a path, compared like `storage::path_key`.
+fn tail_key(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?;
+    let parent = path.parent().and_then(Path::file_name)?;
+    Some(storage::path_key(&Path::new(parent).join(name)))
+}
+
+/// Title and artist to search by: the playlist's own text, or else an
+/// "Artist - Title" or "01 - Title" file name.
+fn search_terms(entry: &Entry, path: Option<&Path>) -> (Option<String>, Option<String>) {
+    if entry.title.is_some() {
+        return (entry.title.clone(), entry.artist.clone());
+    }
+    let stem = path
+        .and_then(Path::file_stem)
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let (artist, title) = split_display(stem);
+    // A leading track number is not an artist
+    match artist {
+        Some(a) if a.chars().all(|c| c.is_ascii_digit()) => (title, None),
+        artist => (title, artist),
+    }
+}
+
+struct Index<'a> {
+    by_key: HashMap<PathBuf, &'a IndexedTrack>,
+    by_tail: HashMap<PathBuf, Vec<&'a IndexedTrack>>,
+    by_word: HashMap<String, Vec<&'a IndexedTrack>>,
+}
+
+impl<'a> Index<'a> {
+    fn new(library: &'a AudioLibrary) -> Self {
+        let mut by_key = HashMap::new();
+        let mut by_tail: HashMap<PathBuf, Vec<&IndexedTrack>> = HashMap::new();
+        let mut by_word: HashMap<String, Vec<&IndexedTrack>> = HashMap::new();
+        for track in library.files.values() {
+            by_key.insert(storage::path_key(&track.path), track);
+            if let Some(tail) = tail_key(&track.path) {
+                by_tail.entry(tail).or_default().push(track);
+            }
+            let words: HashSet<String> = title_words(&track.metadata.title).collect();
+            for word in words {
+                by_word.entry(word).or_default().push(track);
+            }
+        }
+        Self {
+            by_key,
+            by_tail,
+            by_word,
+        }
+    }
+
+    fn by_path(&self, path: &Path) -> Option<(&'a IndexedTrack, MatchKind)> {
+        if let Some(track) = self.by_key.get(&storage::path_key(path)) {
+            return Some((*track, MatchKind::Path));
+        }
+        match self.by_tail.get(&tail_key(path)?)?.as_slice() {
+            [track] => Some((*track, MatchKind::FileName)),
+            _ => None,
+        }
+    }
+
+    /// Best indexed track by title and artist similarity, nearest length
+    /// first among equals.
+    fn by_metadata(&self, entry: &Entry, path: Option<&Path>) -> Option<&'a IndexedTrack> {
+        let (title, artist) = search_terms(entry, path);
+        let title = title?;
+        let mut seen = HashSet::new();
+        let candidates = title_words(&title)
+            .filter_map(|w| self.by_word.get(&w))
+            .flatten()
+            .copied()
+            .filter(|t| seen.insert(t.id.as_str()));
+        let mut best: Option<(f64, f64, &IndexedTrack)> = None;
+        for track in candidates {
+            let meta = &track.metadata;
+            let delta = match entry.duration {
+                Some(d) if meta.duration > 0.0 => (d - meta.duration).abs(),
+                _ => 0.0,
+            };
+            if delta > MAX_DURATION_DELTA_SECS {
+                continue;
+            }
+            let Some(title_score) = matching::similarity(&title, &meta.title) else {
+                continue;
+            };
+            let score = match artist
+                .as_deref()
+                .and_then(|a| matching::similarity(a, &meta.artist))
+            {
+                Some(artist_score) => (title_score + artist_score) / 2.0,
+                None => title_score,
+            };
+            if score < MIN_FUZZY_SCORE {
+                continue;
+            }
+            let better = match best {
+                Some((s, d, _)) => score > s || (score == s && delta < d),
+                None => true,
+            };
+            if better {
+                best = Some((score, delta, track));
+            }
+        }
+        best.map(|(_, _, track)| track)
+    }
+}
+
+fn title_words(s: &str) -> impl Iterator<Item = String> + '_ {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Match playlist entries to indexed tracks: by remapped path, then by
+/// folder and file name, then (with `fuzzy`) by title, artist and length.
+pub fn resolve<'a>(
+    library: &'a AudioLibrary,
+    entries: Vec<Entry>,
+    base_dir: &Path,
+    maps: &[PathMap],
+    fuzzy: bool,
+) -> Vec<Resolved<'a>> {
+    let index = Index::new(library);
+    entries
+        .into_par_iter()
+        .map(|entry| {
+            let path = local_path(&entry.location, base_dir, maps);
+            let track = path.as_deref().and_then(|p| index.by_path(p)).or_else(|| {
+                fuzzy
+                    .then(|| index.by_metadata(&entry, path.as_deref()))
+                    .flatten()
+                    .map(|t| (t, MatchKind::Metadata))
+            });
+            Resolved { entry, track }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn m3u_entries_take_the_preceding_extinf() {
+        let entries = parse_m3u(
+            "#EXTM3U\n#EXTINF:215 tvg-id=\"x\",Artist - Title\nsongs/a.mp3\n\n# comment\nb.flac\n",
+        );
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].location, "songs/a.mp3");
+        assert_eq!(entries[0].artist.as_deref(), Some("Artist"));
+        assert_eq!(entries[0].title.as_deref(), Some("Title"));
+        assert_eq!(entries[0].duration, Some(215.0));
+        assert_eq!(entries[1].location, "b.flac");
+        assert!(entries[1].title.is_none() && entries[1].duration.is_none());
+    }
+
+    #[test]
+    fn pls_entries_are_ordered_by_number() {
+        let entries = parse_pls(
+            "[playlist]\nFile2=b.mp3\nTitle2=Only Title\nLength2=-1\nFile1=a.mp3\nLength1=60\nTitle3=no file\nNumberOfEntries=3\n",
+        );
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].location, "a.mp3");
+        assert_eq!(entries[0].duration, Some(60.0));
+        assert_eq!(entries[1].location, "b.mp3");
+        assert_eq!(entries[1].title.as_deref(), Some("Only Title"));
+        assert!(entries[1].artist.is_none() && entries[1].duration.is_none());
+    }
+
+    #[test]
+    fn xspf_fields_are_unescaped() {
+        let entries = parse_xspf(
+            r#"<?xml version="1.0"?><playlist><trackList>
+                <track><location>file:///music/a%20b.mp3</location><location>http://x</location>
+                <title>Rock &amp; Roll &#x263A;</title><creator>A</creator><duration>90500</duration></track>
+                <track><title>no location</title></track>
+            </trackList></playlist>"#,
+        );
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].location, "file:///music/a%20b.mp3");
+        assert_eq!(entries[0].title.as_deref(), Some("Rock & Roll \u{263A}"));
+        assert_eq!(entries[0].artist.as_deref(), Some("A"));
+        assert_eq!(entries[0].duration, Some(90.5));
+    }
+
+    #[test]
+    fn path_maps_replace_whole_prefixes() {
+        let maps = [parse_path_map(r"D:\Music\=/mnt/music").unwrap()];
+        let local = |p: &str| {
+            if cfg!(windows) {
+                p.replace('/', "\\")
+            } else {
+                p.to_string()
+            }
+        };
+        assert_eq!(
+            remap(r"d:\music\a\b.mp3", &maps),
+            local("/mnt/music/a/b.mp3")
+        );
+        assert_eq!(
+            remap(r"D:\Musicals\b.mp3", &maps),
+            local("D:/Musicals/b.mp3")
+        );
+        assert!(parse_path_map("no-separator").is_err());
+        assert!(parse_path_map("=/mnt").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn local_paths_resolve_urls_and_relative_entries() {
+        let base = Path::new("/lists");
+        assert_eq!(
+            local_path("file://localhost/music/a%20b.mp3", base, &[]),
+            Some(PathBuf::from("/music/a b.mp3"))
+        );
+        assert_eq!(
+            local_path("../music/./c.mp3", base, &[]),
+            Some(PathBuf::from("/music/c.mp3"))
+        );
+        assert_eq!(local_path("http://radio/stream", base, &[]), None);
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use utoipa::ToSchema;
+
+/// A named combination of library table filters, shown as a chip above the
+/// dashboard's library table.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+#[serde(default)]
+pub struct FilterPreset {
+    pub name: String,
+    /// Search text matched against title, artist and album
+    pub query: String,
+    /// Genre passed to `/api/tracks?genre=`
+    pub genre: Option<String>,
+    /// First year of a decade, e.g. `1990`
+    pub decade: Option<u32>,
+    pub min_duration_secs: Option<u64>,
+    pub max_duration_secs: Option<u64>,
+    /// File extensions, lowercase without the dot (e.g. `flac`); empty = any
+    pub formats: Vec<String>,
+}
+
+/// Presets saved through `PUT /api/presets/{name}`, in creation order.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PresetStore {
+    pub presets: Vec<FilterPreset>,
+}
+
+impl PresetStore {
+    /// Load `filter_presets.json`. A missing file means no presets yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).context("Failed to read filter presets")?;
+        serde_json::from_str(&content).context("Failed to parse filter presets JSON")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content =
+            serde_json::to_string_pretty(self).context("Failed to serialize filter presets")?;
+        fs::write(path, content).context("Failed to write filter presets")
+    }
+
+    /// Replace the preset with the same name, or append a new one.
+    pub fn upsert(&mut self, preset: FilterPreset) {
+        match self.presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+    }
+
+    /// Whether a preset called `name` existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.presets.len();
+        self.presets.retain(|p| p.name != name);
+        self.presets.len() != before
+    }
+}
@@ -0,0 +1,65 @@
+use std::io;
+
+/// Lower the CPU and I/O priority of the calling thread as far as the
+/// platform allows without special privileges. Threads and child processes
+/// (fpcalc) started from it afterwards inherit the setting on Unix.
+pub fn lower_current_thread() -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        // Linux applies both to the calling thread only
+        const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+        const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+        const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+        // SAFETY: plain syscalls on the current thread, no pointers involved
+        unsafe {
+            if libc::setpriority(libc::PRIO_PROCESS, 0, 19) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                0,
+                IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+            ) != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        // The Darwin background band throttles CPU and disk I/O together
+        // SAFETY: plain syscall on the current thread, no pointers involved
+        if unsafe { libc::setpriority(libc::PRIO_DARWIN_THREAD, 0, libc::PRIO_DARWIN_BG) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    #[cfg(all(unix, not(any(target_os = "linux", target_os = "macos"))))]
+    {
+        // SAFETY: plain syscall, no pointers involved
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 19) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Threading::{
+            GetCurrentThread, SetThreadPriority, THREAD_MODE_BACKGROUND_BEGIN,
+        };
+        // Background mode lowers CPU, I/O and memory priority of the thread
+        // SAFETY: GetCurrentThread returns a pseudo handle that needs no closing
+        if unsafe { SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_BEGIN) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Put the calling thread and every worker of rayon's global pool (which
+/// runs the scan) into background priority.
+pub fn enter_background() -> io::Result<()> {
+    lower_current_thread()?;
+    rayon::broadcast(|_| lower_current_thread())
+        .into_iter()
+        .collect()
+}
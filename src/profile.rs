@@ -0,0 +1,289 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::progress::Stage;
+
+/// Name of the parallel phase, which worker utilization is measured against
+pub const PROCESS_PHASE: &str = "process";
+
+/// Timings of one `worker::process_file` stage across all files.
+#[derive(Serialize, Debug, Default)]
+pub struct StageTimings {
+    pub count: usize,
+    pub total_secs: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+impl StageTimings {
+    fn from_secs(mut secs: Vec<f64>) -> Self {
+        if secs.is_empty() {
+            return Self::default();
+        }
+        secs.sort_by(|a, b| a.total_cmp(b));
+        let at = |q: f64| secs[((secs.len() - 1) as f64 * q).round() as usize] * 1000.0;
+        let total: f64 = secs.iter().sum();
+        Self {
+            count: secs.len(),
+            total_secs: total,
+            mean_ms: total / secs.len() as f64 * 1000.0,
+            p50_ms: at(0.5),
+            p95_ms: at(0.95),
+            max_ms: at(1.0),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PhaseTiming {
+    pub name: &'static str,
+    pub secs: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WorkerTimings {
+    pub files: usize,
+    /// Time spent inside a stage
+    pub busy_secs: f64,
+    /// `busy_secs` over the wall time of the `PROCESS_PHASE` phase
+    pub utilization: f64,
+}
+
+/// CPU and disk counters over the whole run. Disk counters and I/O wait are
+/// only available on Linux; CPU times on Unix.
+#[derive(Serialize, Debug, Default, Clone, Copy)]
+pub struct ResourceUsage {
+    /// CPU time of this process and of its finished children (fpcalc)
+    pub user_cpu_secs: Option<f64>,
+    pub system_cpu_secs: Option<f64>,
+    /// Bytes this process caused to be read from / written to storage
+    pub read_bytes: Option<u64>,
+    pub write_bytes: Option<u64>,
+    /// Time all CPUs of the machine sat idle waiting for I/O (system-wide,
+    /// so other programs count too)
+    pub system_iowait_secs: Option<f64>,
+}
+
+impl ResourceUsage {
+    fn now() -> Self {
+        #[cfg_attr(not(unix), allow(unused_mut))]
+        let mut usage = Self::default();
+        #[cfg(unix)]
+        {
+            let mut total = (0.0, 0.0);
+            for who in [libc::RUSAGE_SELF, libc::RUSAGE_CHILDREN] {
+                // SAFETY: getrusage only writes into the zeroed struct we pass
+                let mut ru: libc::rusage = unsafe { std::mem::zeroed() };
+                if unsafe { libc::getrusage(who, &mut ru) } == 0 {
+                    let secs = |t: libc::timeval| t.tv_sec as f64 + t.tv_usec as f64 / 1e6;
+                    total.0 += secs(ru.ru_utime);
+                    total.1 += secs(ru.ru_stime);
+                }
+            }
+            usage.user_cpu_secs = Some(total.0);
+            usage.system_cpu_secs = Some(total.1);
+        }
+        #[cfg(target_os = "linux")]
+        {
+            if let Ok(io) = fs::read_to_string("/proc/self/io") {
+                let field = |name: &str| {
+                    io.lines()
+                        .find_map(|l| l.strip_prefix(name))
+                        .and_then(|v| v.trim().parse().ok())
+                };
+                usage.read_bytes = field("read_bytes:");
+                usage.write_bytes = field("write_bytes:");
+            }
+            // First line: "cpu user nice system idle iowait ..." in clock ticks
+            if let Ok(stat) = fs::read_to_string("/proc/stat") {
+                // SAFETY: sysconf has no preconditions
+                let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as f64;
+                usage.system_iowait_secs = stat
+                    .lines()
+                    .next()
+                    .and_then(|l| l.split_whitespace().nth(5))
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .map(|t| t / ticks);
+            }
+        }
+        usage
+    }
+
+    fn since(self, start: Self) -> Self {
+        fn diff<T: std::ops::Sub<Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
+            Some(a? - b?)
+        }
+        Self {
+            user_cpu_secs: diff(self.user_cpu_secs, start.user_cpu_secs),
+            system_cpu_secs: diff(self.system_cpu_secs, start.system_cpu_secs),
+            read_bytes: diff(self.read_bytes, start.read_bytes),
+            write_bytes: diff(self.write_bytes, start.write_bytes),
+            system_iowait_secs: diff(self.system_iowait_secs, start.system_iowait_secs),
+        }
+    }
+}
+
+/// Contents of `scan --profile out.json`. Holds counts and timings only —
+/// no paths, file names or tags — so it can be attached to a bug report.
+#[derive(Serialize, Debug)]
+pub struct ProfileReport {
+    pub version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub cpus: usize,
+    pub worker_threads: usize,
+    pub online: bool,
+    pub files: BTreeMap<&'static str, usize>,
+    /// Wall time of the scan's phases, in order
+    pub phases: Vec<PhaseTiming>,
+    pub total_secs: f64,
+    pub stages: BTreeMap<&'static str, StageTimings>,
+    pub workers: Vec<WorkerTimings>,
+    pub resources: ResourceUsage,
+}
+
+#[derive(Default)]
+struct WorkerSlot {
+    current: Option<(Stage, Instant)>,
+    busy_secs: f64,
+    files: usize,
+}
+
+/// Recorder behind `scan --profile`, fed by `ScanBars` as workers enter and
+/// leave the stages of `worker::process_file`.
+pub struct ScanProfile {
+    started: Instant,
+    start_usage: ResourceUsage,
+    phases: Mutex<Vec<PhaseTiming>>,
+    /// Start of the phase in progress
+    phase_started: Mutex<Instant>,
+    stages: Mutex<BTreeMap<&'static str, Vec<f64>>>,
+    workers: Vec<Mutex<WorkerSlot>>,
+}
+
+impl Default for ScanProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScanProfile {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            start_usage: ResourceUsage::now(),
+            phases: Mutex::new(Vec::new()),
+            phase_started: Mutex::new(now),
+            stages: Mutex::new(BTreeMap::new()),
+            workers: (0..rayon::current_num_threads())
+                .map(|_| Mutex::default())
+                .collect(),
+        }
+    }
+
+    /// Close the phase that started where the previous one ended.
+    pub fn end_phase(&self, name: &'static str) {
+        let now = Instant::now();
+        let mut started = self.phase_started.lock().unwrap();
+        self.phases.lock().unwrap().push(PhaseTiming {
+            name,
+            secs: now.duration_since(*started).as_secs_f64(),
+        });
+        *started = now;
+    }
+
+    fn slot(&self) -> Option<&Mutex<WorkerSlot>> {
+        rayon::current_thread_index().and_then(|i| self.workers.get(i))
+    }
+
+    /// The calling rayon worker started `stage` of a file.
+    pub fn enter(&self, stage: Stage) {
+        if let Some(slot) = self.slot() {
+            let mut slot = slot.lock().unwrap();
+            if stage == Stage::Decode {
+                slot.files += 1;
+            }
+            slot.current = Some((stage, Instant::now()));
+        }
+    }
+
+    /// The calling rayon worker finished `stage`.
+    pub fn complete(&self, stage: Stage) {
+        let Some(slot) = self.slot() else {
+            return;
+        };
+        let mut slot = slot.lock().unwrap();
+        if let Some((current, started)) = slot.current.take() {
+            if current == stage {
+                let secs = started.elapsed().as_secs_f64();
+                slot.busy_secs += secs;
+                self.stages
+                    .lock()
+                    .unwrap()
+                    .entry(stage.label())
+                    .or_default()
+                    .push(secs);
+            }
+        }
+    }
+
+    /// Summarize the run; `files` holds counts such as processed and errors.
+    pub fn report(&self, online: bool, files: BTreeMap<&'static str, usize>) -> ProfileReport {
+        let phases = self.phases.lock().unwrap().clone();
+        let window = phases
+            .iter()
+            .find(|p| p.name == PROCESS_PHASE)
+            .map_or(0.0, |p| p.secs);
+        let workers = self
+            .workers
+            .iter()
+            .map(|slot| {
+                let slot = slot.lock().unwrap();
+                WorkerTimings {
+                    files: slot.files,
+                    busy_secs: slot.busy_secs,
+                    utilization: if window > 0.0 {
+                        slot.busy_secs / window
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect();
+        let stages = std::mem::take(&mut *self.stages.lock().unwrap())
+            .into_iter()
+            .map(|(name, secs)| (name, StageTimings::from_secs(secs)))
+            .collect();
+        ProfileReport {
+            version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            cpus: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            worker_threads: self.workers.len(),
+            online,
+            files,
+            phases,
+            total_secs: self.started.elapsed().as_secs_f64(),
+            stages,
+            workers,
+            resources: ResourceUsage::now().since(self.start_usage),
+        }
+    }
+}
+
+impl ProfileReport {
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self).context("Failed to serialize profile")?;
+        fs::write(path, content).with_context(|| format!("Failed to write profile to {:?}", path))
+    }
+}
@@ -0,0 +1,181 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::profile::ScanProfile;
+
+/// Steps of `worker::process_file`, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Decoding and fingerprinting
+    Decode,
+    /// AcoustID / MusicBrainz lookup
+    Lookup,
+    /// Quality, loudness and bliss analysis
+    Analyze,
+}
+
+impl Stage {
+    pub fn label(self) -> &'static str {
+        match self {
+            Stage::Decode => "decode",
+            Stage::Lookup => "lookup",
+            Stage::Analyze => "analyze",
+        }
+    }
+}
+
+fn is_tty() -> bool {
+    std::io::stdout().is_terminal()
+}
+
+fn bar_style() -> ProgressStyle {
+    ProgressStyle::with_template(
+        "{prefix:>8} [{bar:40.cyan/blue}] {pos}/{len} ({per_sec}, ETA {eta})",
+    )
+    .expect("valid progress template")
+    .progress_chars("=> ")
+}
+
+fn line_style() -> ProgressStyle {
+    ProgressStyle::with_template("{prefix:>8} {spinner} {wide_msg}")
+        .expect("valid progress template")
+}
+
+/// A spinner for a step of unknown length (directory enumeration, diffing).
+/// Hidden when stdout isn't a terminal; callers print their own summary line.
+pub fn spinner(message: &str) -> ProgressBar {
+    if !is_tty() {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::with_draw_target(None, ProgressDrawTarget::stdout())
+        .with_style(line_style())
+        .with_message(message.to_string());
+    bar.enable_steady_tick(Duration::from_millis(120));
+    bar
+}
+
+/// Progress of the CLI scan's process phase: one bar per stage, an overall
+/// bar with ETA and one line per rayon worker showing its current file.
+/// When stdout isn't a terminal nothing is drawn; a plain line is printed
+/// every 5% instead so logs stay readable.
+pub struct ScanBars {
+    multi: MultiProgress,
+    decode: ProgressBar,
+    /// Absent for offline scans
+    lookup: Option<ProgressBar>,
+    files: ProgressBar,
+    workers: Vec<ProgressBar>,
+    plain: bool,
+    total: usize,
+    done: AtomicUsize,
+    /// Stage timings for `scan --profile`
+    profile: Option<Arc<ScanProfile>>,
+}
+
+impl ScanBars {
+    pub fn new(total: usize, online: bool) -> Self {
+        let plain = !is_tty();
+        let multi = MultiProgress::with_draw_target(if plain {
+            ProgressDrawTarget::hidden()
+        } else {
+            ProgressDrawTarget::stdout()
+        });
+        let stage_bar = |prefix: &'static str| {
+            multi.add(
+                ProgressBar::new(total as u64)
+                    .with_style(bar_style())
+                    .with_prefix(prefix),
+            )
+        };
+        let decode = stage_bar("decode");
+        let lookup = online.then(|| stage_bar("lookup"));
+        let files = stage_bar("files");
+        let workers = (0..rayon::current_num_threads())
+            .map(|i| {
+                multi.add(
+                    ProgressBar::new_spinner()
+                        .with_style(line_style())
+                        .with_prefix(format!("#{}", i + 1)),
+                )
+            })
+            .collect();
+        Self {
+            multi,
+            decode,
+            lookup,
+            files,
+            workers,
+            plain,
+            total,
+            done: AtomicUsize::new(0),
+            profile: None,
+        }
+    }
+
+    /// Also record stage timings into `profile`.
+    pub fn with_profile(mut self, profile: Option<Arc<ScanProfile>>) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// The calling rayon worker started `stage` of `path`.
+    pub fn enter(&self, stage: Stage, path: &Path) {
+        if let Some(profile) = &self.profile {
+            profile.enter(stage);
+        }
+        let worker = rayon::current_thread_index().and_then(|i| self.workers.get(i));
+        if let Some(bar) = worker {
+            let name = path.file_name().unwrap_or(path.as_os_str());
+            bar.set_message(format!("{} {}", stage.label(), name.to_string_lossy()));
+            bar.tick();
+        }
+    }
+
+    /// The calling worker finished `stage` of its current file.
+    pub fn complete(&self, stage: Stage) {
+        if let Some(profile) = &self.profile {
+            profile.complete(stage);
+        }
+        match stage {
+            Stage::Decode => self.decode.inc(1),
+            Stage::Lookup => {
+                if let Some(bar) = &self.lookup {
+                    bar.inc(1);
+                }
+            }
+            Stage::Analyze => {}
+        }
+    }
+
+    /// A file left the process phase, successfully or not.
+    pub fn file_done(&self) {
+        self.files.inc(1);
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        let step = (self.total / 20).max(1);
+        if self.plain && (done.is_multiple_of(step) || done == self.total) {
+            println!("Processed {}/{} files...", done, self.total);
+        }
+    }
+
+    /// Print a line (e.g. a file's error) above the bars without tearing them.
+    pub fn println(&self, line: impl AsRef<str>) {
+        self.multi.suspend(|| eprintln!("{}", line.as_ref()));
+    }
+
+    /// Remove the bars so the summary prints below a clean terminal.
+    pub fn finish(self) {
+        for bar in &self.workers {
+            bar.finish_and_clear();
+        }
+        self.decode.finish_and_clear();
+        if let Some(bar) = &self.lookup {
+            bar.finish_and_clear();
+        }
+        self.files.finish_and_clear();
+        let _ = self.multi.clear();
+    }
+}
@@ -0,0 +1,308 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+use utoipa::ToSchema;
+
+use crate::analysis_store::AnalysisStore;
+use crate::language;
+use crate::organizer;
+use crate::spoken_word::ContentKind;
+use crate::storage::{AudioLibrary, IndexedTrack};
+
+/// Tracks returned when the prompt doesn't give a length
+const DEFAULT_TRACKS: usize = 20;
+
+/// Rough energy asked for by mood words, judged from the bliss tempo and
+/// loudness features until a mood classifier exists.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Energy {
+    Low,
+    High,
+}
+
+const LOW_ENERGY_WORDS: &[&str] = &[
+    "mellow",
+    "calm",
+    "chill",
+    "relaxing",
+    "relaxed",
+    "late-night",
+    "late night",
+    "sleepy",
+    "soft",
+    "quiet",
+    "ambient",
+    "slow",
+    "peaceful",
+];
+const HIGH_ENERGY_WORDS: &[&str] = &[
+    "energetic",
+    "upbeat",
+    "party",
+    "workout",
+    "intense",
+    "fast",
+    "loud",
+    "driving",
+    "hype",
+];
+
+/// What a prompt such as "late-night mellow jazz, no vocals, 1 hour" asks for.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct PromptConstraints {
+    /// Library genres named in the prompt (lowercase genre terms)
+    pub genres: Vec<String>,
+    /// `Some(true)` for "instrumental" / "no vocals", `Some(false)` for "with vocals"
+    pub instrumental: Option<bool>,
+    pub energy: Option<Energy>,
+    /// First year of a decade such as "80s" or "1990s"
+    pub decade: Option<u32>,
+    /// Target playlist length in seconds
+    pub duration_secs: Option<u64>,
+}
+
+fn regexes() -> &'static (Regex, Regex) {
+    static RE: OnceLock<(Regex, Regex)> = OnceLock::new();
+    RE.get_or_init(|| {
+        (
+            Regex::new(r"(\d+(?:\.\d+)?)\s*(hours?|hrs?|h|minutes?|mins?|m)\b").unwrap(),
+            Regex::new(r"\b(?:(19|20)?([0-9])0)'?s\b").unwrap(),
+        )
+    })
+}
+
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase).any(|(i, _)| {
+        let before = text[..i].chars().next_back();
+        let after = text[i + phrase.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Parse a free-text prompt. Genres are only recognized if some track in
+/// `library` carries them, so unknown words are ignored rather than
+/// filtering everything out.
+pub fn parse(prompt: &str, library: &AudioLibrary) -> PromptConstraints {
+    let text = prompt.to_lowercase();
+    let (duration_re, decade_re) = regexes();
+
+    let duration_secs = duration_re.captures(&text).and_then(|c| {
+        let value: f64 = c[1].parse().ok()?;
+        let unit = if c[2].starts_with('h') { 3600.0 } else { 60.0 };
+        Some((value * unit).round() as u64)
+    });
+
+    let decade = decade_re.captures(&text).and_then(|c| {
+        let digit: u32 = c[2].parse().ok()?;
+        let century = match c.get(1).map(|m| m.as_str()) {
+            Some("20") => 2000,
+            Some(_) => 1900,
+            // "80s" means the 1980s, "00s" / "10s" / "20s" this century
+            None if digit <= 2 => 2000,
+            None => 1900,
+        };
+        Some(century + digit * 10)
+    });
+
+    let instrumental = if ["no vocals", "no vocal", "instrumental", "without vocals"]
+        .iter()
+        .any(|p| contains_phrase(&text, p))
+    {
+        Some(true)
+    } else if ["with vocals", "vocals", "vocal", "singing"]
+        .iter()
+        .any(|p| contains_phrase(&text, p))
+    {
+        Some(false)
+    } else {
+        None
+    };
+
+    let energy = if LOW_ENERGY_WORDS.iter().any(|w| contains_phrase(&text, w)) {
+        Some(Energy::Low)
+    } else if HIGH_ENERGY_WORDS.iter().any(|w| contains_phrase(&text, w)) {
+        Some(Energy::High)
+    } else {
+        None
+    };
+
+    let known: BTreeSet<String> = library
+        .files
+        .values()
+        .flat_map(|t| organizer::genre_terms(t.metadata.genre.as_deref()))
+        .collect();
+    // Match the last segment of hierarchical terms ("electronic/house" by "house")
+    let genres = known
+        .into_iter()
+        .filter(|term| {
+            let leaf = term.rsplit('/').next().unwrap_or(term);
+            contains_phrase(&text, leaf)
+        })
+        .collect();
+
+    PromptConstraints {
+        genres,
+        instrumental,
+        energy,
+        decade,
+        duration_secs,
+    }
+}
+
+/// Mean of the normalized bliss tempo and loudness features.
+fn energy_of(analysis: &[f32]) -> Option<f32> {
+    Some((analysis.first()? + analysis.get(8)?) / 2.0)
+}
+
+/// Tracks satisfying `constraints`: the better-matching half for an energy
+/// request, shuffled, then cut to the requested duration (or 20 tracks).
+pub fn select<'a>(
+    library: &'a AudioLibrary,
+    store: &AnalysisStore,
+    constraints: &PromptConstraints,
+    seed: Option<u64>,
+) -> Vec<&'a IndexedTrack> {
+    let wanted_genres: BTreeSet<&String> = constraints.genres.iter().collect();
+    let mut tracks: Vec<&IndexedTrack> = library
+        .sorted_tracks()
+        .into_iter()
+        .filter(|t| t.metadata.content_kind != ContentKind::SpokenWord)
+        .filter(|t| {
+            wanted_genres.is_empty()
+                || organizer::genre_terms(t.metadata.genre.as_deref())
+                    .iter()
+                    .any(|g| wanted_genres.contains(g))
+        })
+        .filter(|t| match constraints.instrumental {
+            Some(instrumental) => {
+                let is_instrumental =
+                    t.metadata.vocal_language.as_deref() == Some(language::INSTRUMENTAL);
+                is_instrumental == instrumental
+            }
+            None => true,
+        })
+        .filter(|t| match constraints.decade {
+            Some(d) => t.metadata.year.map(organizer::decade) == Some(d),
+            None => true,
+        })
+        .collect();
+
+    if let Some(energy) = constraints.energy {
+        let mut scored: Vec<(&IndexedTrack, f32)> = tracks
+            .into_iter()
+            .filter_map(|t| Some((t, energy_of(store.get(&t.path)?)?)))
+            .collect();
+        scored.sort_by(|a, b| match energy {
+            Energy::Low => a.1.total_cmp(&b.1),
+            Energy::High => b.1.total_cmp(&a.1),
+        });
+        scored.truncate(scored.len().div_ceil(2));
+        tracks = scored.into_iter().map(|(t, _)| t).collect();
+    }
+
+    match seed {
+        Some(seed) => tracks.shuffle(&mut StdRng::seed_from_u64(seed)),
+        None => tracks.shuffle(&mut rand::thread_rng()),
+    }
+
+    match constraints.duration_secs {
+        Some(target) => {
+            let mut total = 0.0;
+            tracks
+                .into_iter()
+                .take_while(|t| {
+                    let keep = total < target as f64;
+                    total += t.metadata.duration;
+                    keep
+                })
+                .collect()
+        }
+        None => {
+            tracks.truncate(DEFAULT_TRACKS);
+            tracks
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn library(genres: &[&str]) -> AudioLibrary {
+        let mut library = AudioLibrary::default();
+        for (i, genre) in genres.iter().enumerate() {
+            let meta = organizer::TrackMetadata {
+                genre: Some(genre.to_string()),
+                ..Default::default()
+            };
+            library.upsert(PathBuf::from(format!("{}.mp3", i)), 1, 1, 1, meta);
+        }
+        library
+    }
+
+    #[test]
+    fn parses_genre_keywords_known_to_the_library() {
+        let library = library(&["Jazz", "Electronic/House", "Rock"]);
+        let cases: &[(&str, &[&str])] = &[
+            ("mellow jazz", &["jazz"]),
+            ("some house and rock", &["electronic/house", "rock"]),
+            ("jazzy rockabilly", &[]),
+            ("polka", &[]),
+        ];
+        for (prompt, expected) in cases {
+            assert_eq!(parse(prompt, &library).genres, *expected, "{}", prompt);
+        }
+    }
+
+    #[test]
+    fn parses_decades() {
+        let library = AudioLibrary::default();
+        let cases = [
+            ("80s synthpop", Some(1980)),
+            ("1990s", Some(1990)),
+            ("hits of the 2010s", Some(2010)),
+            ("00s", Some(2000)),
+            ("'70's rock", Some(1970)),
+            ("20s jazz", Some(2020)),
+            ("1925", None),
+            ("no decade here", None),
+        ];
+        for (prompt, expected) in cases {
+            assert_eq!(parse(prompt, &library).decade, expected, "{}", prompt);
+        }
+    }
+
+    #[test]
+    fn parses_mood_vocals_and_duration() {
+        let library = AudioLibrary::default();
+        let cases = [
+            (
+                "late-night mellow, no vocals, 1 hour",
+                Some(Energy::Low),
+                Some(true),
+                Some(3600),
+            ),
+            (
+                "upbeat workout with vocals 45 min",
+                Some(Energy::High),
+                Some(false),
+                Some(2700),
+            ),
+            ("calm party", Some(Energy::Low), None, None),
+            ("instrumental 1.5h", None, Some(true), Some(5400)),
+            ("softly spoken", None, None, None),
+        ];
+        for (prompt, energy, instrumental, duration) in cases {
+            let parsed = parse(prompt, &library);
+            assert_eq!(parsed.energy, energy, "{}", prompt);
+            assert_eq!(parsed.instrumental, instrumental, "{}", prompt);
+            assert_eq!(parsed.duration_secs, duration, "{}", prompt);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use anyhow::Result;
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::decode::{downmix_into, AudioStream, DecodedAudio, StreamInfo};
+use crate::loudness::{self, LoudnessMeter};
+use crate::storage::IndexedTrack;
+
+const FFT_SIZE: usize = 4096;
+// Analyze one FFT window every ~2 seconds of audio; enough for a stable average spectrum
+const SPECTRUM_HOP_SECS: f32 = 2.0;
+const CLIP_THRESHOLD: f32 = 0.999;
+
+/// Objective quality signals measured from the decoded audio.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct AudioQuality {
+    pub codec: String,
+    pub lossless: bool,
+    pub bitrate_kbps: Option<u32>, // Average bitrate (file size / duration)
+    pub sample_rate: u32,
+    pub bit_depth: Option<u32>,
+    pub channels: usize,
+    pub spectral_cutoff_hz: Option<f32>, // Highest frequency with real content
+    pub clipping_ratio: f32,             // Fraction of samples at full scale
+    pub peak_dbfs: f32,
+    pub rms_dbfs: f32,
+    pub crest_factor_db: f32,
+    pub loudness_war_score: f32, // 0 (dynamic) .. 100 (brickwalled)
+    #[serde(default)]
+    pub integrated_lufs: Option<f32>, // EBU R128; absent in indexes from before it was measured
+    #[serde(default)]
+    pub encoder_delay: Option<u32>, // Gapless: frames to drop at the start
+    #[serde(default)]
+    pub encoder_padding: Option<u32>, // Gapless: frames to drop at the end
+}
+
+impl AudioQuality {
+    /// ReplayGain 2.0 track gain in dB, when loudness was measured.
+    pub fn replay_gain_db(&self) -> Option<f32> {
+        self.integrated_lufs.map(loudness::replay_gain_db)
+    }
+}
+
+/// Quality measurements over the decoded chunks of one file, so they can
+/// share a decode with other consumers of the samples.
+pub struct QualityMeter {
+    info: StreamInfo,
+    hop: usize,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    spectrum_sum: Vec<f64>,
+    spectrum_count: usize,
+    mono: Vec<f32>,
+    scratch: Vec<Complex<f32>>,
+    peak: f32,
+    sum_sq: f64,
+    clipped: u64,
+    total_samples: u64,
+    total_frames: u64,
+    loudness: LoudnessMeter,
+}
+
+impl QualityMeter {
+    pub fn new(info: StreamInfo) -> Self {
+        let hop = ((info.sample_rate as f32 * SPECTRUM_HOP_SECS) as usize).max(FFT_SIZE);
+        let window = (0..FFT_SIZE)
+            .map(|i| {
+                0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / (FFT_SIZE - 1) as f32).cos()
+            })
+            .collect();
+        Self {
+            hop,
+            fft: FftPlanner::<f32>::new().plan_fft_forward(FFT_SIZE),
+            window,
+            spectrum_sum: vec![0f64; FFT_SIZE / 2],
+            spectrum_count: 0,
+            mono: Vec::with_capacity(hop + FFT_SIZE),
+            scratch: vec![Complex::new(0f32, 0f32); FFT_SIZE],
+            peak: 0.0,
+            sum_sq: 0.0,
+            clipped: 0,
+            total_samples: 0,
+            total_frames: 0,
+            loudness: LoudnessMeter::new(info.sample_rate, info.channels),
+            info,
+        }
+    }
+
+    /// Feed interleaved samples; chunks must hold whole frames.
+    pub fn push(&mut self, chunk: &[f32]) {
+        for &s in chunk {
+            let a = s.abs();
+            self.peak = self.peak.max(a);
+            self.sum_sq += (s as f64) * (s as f64);
+            if a >= CLIP_THRESHOLD {
+                self.clipped += 1;
+            }
+        }
+        self.loudness.push(chunk);
+        self.total_samples += chunk.len() as u64;
+        self.total_frames += (chunk.len() / self.info.channels) as u64;
+
+        downmix_into(chunk, self.info.channels, &mut self.mono);
+        while self.mono.len() >= self.hop {
+            // Skip fully silent windows so intros/outros don't drag the average down
+            if self.mono[..FFT_SIZE].iter().any(|s| s.abs() > 1e-4) {
+                for (i, c) in self.scratch.iter_mut().enumerate() {
+                    *c = Complex::new(self.mono[i] * self.window[i], 0.0);
+                }
+                self.fft.process(&mut self.scratch);
+                for (acc, c) in self.spectrum_sum.iter_mut().zip(self.scratch.iter()) {
+                    *acc += c.norm() as f64;
+                }
+                self.spectrum_count += 1;
+            }
+            self.mono.drain(..self.hop);
+        }
+    }
+
+    pub fn finish(self, file_size: u64) -> Result<AudioQuality> {
+        let info = self.info;
+        if self.total_samples == 0 {
+            return Err(anyhow::anyhow!("No audio samples decoded"));
+        }
+
+        let duration = self.total_frames as f64 / info.sample_rate as f64;
+        let bitrate_kbps = if duration > 0.0 {
+            Some(((file_size as f64 * 8.0) / duration / 1000.0).round() as u32)
+        } else {
+            None
+        };
+
+        let spectral_cutoff_hz = if self.spectrum_count > 0 {
+            estimate_cutoff(&self.spectrum_sum, self.spectrum_count, info.sample_rate)
+        } else {
+            None
+        };
+
+        let rms = (self.sum_sq / self.total_samples as f64).sqrt() as f32;
+        let peak_dbfs = to_db(self.peak);
+        let rms_dbfs = to_db(rms);
+        let crest_factor_db = (peak_dbfs - rms_dbfs).max(0.0);
+        let clipping_ratio = self.clipped as f32 / self.total_samples as f32;
+
+        Ok(AudioQuality {
+            lossless: is_lossless_codec(&info.codec),
+            codec: info.codec,
+            bitrate_kbps,
+            sample_rate: info.sample_rate,
+            bit_depth: info.bits_per_sample,
+            encoder_delay: info.encoder_delay,
+            encoder_padding: info.encoder_padding,
+            channels: info.channels,
+            spectral_cutoff_hz,
+            clipping_ratio,
+            peak_dbfs,
+            rms_dbfs,
+            crest_factor_db,
+            loudness_war_score: loudness_war_score(crest_factor_db, clipping_ratio),
+            integrated_lufs: self.loudness.integrated_lufs(),
+        })
+    }
+}
+
+/// Measure a file by streaming it, without holding the decoded audio.
+pub fn analyze_file(path: &Path, file_size: u64) -> Result<AudioQuality> {
+    let mut stream = AudioStream::open(path)?;
+    let mut meter = QualityMeter::new(stream.info.clone());
+    while let Some(chunk) = stream.next_chunk()? {
+        meter.push(chunk);
+    }
+    meter.finish(file_size)
+}
+
+/// Measure audio that was already decoded for the registered analyzers.
+pub fn analyze_decoded(audio: &DecodedAudio, file_size: u64) -> Result<AudioQuality> {
+    let mut meter = QualityMeter::new(audio.info.clone());
+    // One second per chunk; any whole number of frames gives the same result
+    let chunk = (audio.info.sample_rate as usize).max(1) * audio.info.channels;
+    for samples in audio.samples.chunks(chunk) {
+        meter.push(samples);
+    }
+    meter.finish(file_size)
+}
+
+fn to_db(amplitude: f32) -> f32 {
+    20.0 * amplitude.max(1e-9).log10()
+}
+
+fn is_lossless_codec(codec: &str) -> bool {
+    codec == "flac" || codec == "alac" || codec == "wavpack" || codec.starts_with("pcm")
+}
+
+/// Find the highest frequency whose average energy is still within 60 dB of the loudest bin.
+/// Lossy encoders low-pass aggressively (e.g. ~16 kHz for 128 kbps MP3), which shows up as a
+/// cliff in the averaged spectrum.
+fn estimate_cutoff(spectrum_sum: &[f64], count: usize, sample_rate: u32) -> Option<f32> {
+    let db: Vec<f32> = spectrum_sum
+        .iter()
+        .map(|s| to_db((*s / count as f64) as f32))
+        .collect();
+    let max_db = db.iter().cloned().fold(f32::MIN, f32::max);
+    let floor = max_db - 60.0;
+
+    // Smooth over a few bins so single noisy bins above the cliff don't count
+    let smooth = 8;
+    let bin_hz = sample_rate as f32 / FFT_SIZE as f32;
+    (smooth..db.len())
+        .rev()
+        .find(|&i| db[i + 1 - smooth..=i].iter().sum::<f32>() / smooth as f32 > floor)
+        .map(|i| i as f32 * bin_hz)
+}
+
+/// Map crest factor (peak-to-RMS) to a 0-100 "brickwall" score.
+/// ~20 dB crest is typical for dynamic masters, ~6 dB for heavily limited ones.
+fn loudness_war_score(crest_factor_db: f32, clipping_ratio: f32) -> f32 {
+    let crest = ((20.0 - crest_factor_db) / 14.0).clamp(0.0, 1.0) * 90.0;
+    let clip = (clipping_ratio * 1000.0).clamp(0.0, 10.0);
+    crest + clip
+}
+
+/// Combined score used to rank copies of the same recording (higher is better).
+pub fn quality_score(q: &AudioQuality) -> f32 {
+    let mut score = 0.0;
+
+    if q.lossless {
+        score += 50.0;
+    } else if let Some(kbps) = q.bitrate_kbps {
+        score += (kbps.min(320) as f32 / 320.0) * 35.0;
+    }
+
+    if let Some(cutoff) = q.spectral_cutoff_hz {
+        let nyquist = (q.sample_rate as f32 / 2.0).min(22050.0);
+        score += (cutoff / nyquist).clamp(0.0, 1.0) * 30.0;
+    }
+
+    score -= (q.clipping_ratio * 1000.0).clamp(0.0, 10.0);
+    score -= q.loudness_war_score * 0.1;
+    score
+}
+
+/// Human-readable justification for a copy's ranking.
+pub fn describe(q: &AudioQuality) -> Vec<String> {
+    let mut reasons = Vec::new();
+
+    if q.lossless {
+        reasons.push(format!("Lossless {}", q.codec.to_uppercase()));
+    } else if let Some(kbps) = q.bitrate_kbps {
+        reasons.push(format!("{} ~{} kbps", q.codec.to_uppercase(), kbps));
+    }
+
+    if let Some(cutoff) = q.spectral_cutoff_hz {
+        if cutoff < 19000.0 && q.sample_rate >= 44100 {
+            if q.lossless {
+                reasons.push(format!(
+                    "Spectrum cut at {:.1} kHz (likely transcoded from lossy)",
+                    cutoff / 1000.0
+                ));
+            } else {
+                reasons.push(format!("Spectrum cut at {:.1} kHz", cutoff / 1000.0));
+            }
+        } else {
+            reasons.push(format!("Full-band spectrum ({:.1} kHz)", cutoff / 1000.0));
+        }
+    }
+
+    if q.clipping_ratio > 0.0001 {
+        reasons.push(format!("{:.2}% samples clipped", q.clipping_ratio * 100.0));
+    }
+
+    if q.loudness_war_score >= 70.0 {
+        reasons.push(format!(
+            "Heavily compressed master (crest {:.1} dB)",
+            q.crest_factor_db
+        ));
+    }
+
+    reasons
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct RankedCopy {
+    #[serde(flatten)]
+    pub track: IndexedTrack,
+    pub quality_score: Option<f32>,
+    pub reasons: Vec<String>,
+    pub is_best: bool,
+}
+
+/// Rank the copies inside a duplicate group, best first.
+/// Copies without quality data fall back to file size (the old heuristic).
+pub fn rank_group(group: Vec<IndexedTrack>) -> Vec<RankedCopy> {
+    let mut ranked: Vec<RankedCopy> = group
+        .into_iter()
+        .map(|track| {
+            let (quality_score, reasons) = match &track.metadata.quality {
+                Some(q) => (Some(quality_score(q)), describe(q)),
+                None => (
+                    None,
+                    vec!["No quality data (rescan to measure)".to_string()],
+                ),
+            };
+            RankedCopy {
+                track,
+                quality_score,
+                reasons,
+                is_best: false,
+            }
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| {
+        let sa = a.quality_score.unwrap_or(f32::MIN);
+        let sb = b.quality_score.unwrap_or(f32::MIN);
+        sb.partial_cmp(&sa)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.track.file_size.cmp(&a.track.file_size))
+    });
+
+    if let Some(first) = ranked.first_mut() {
+        first.is_best = true;
+    }
+    ranked
+}
@@ -0,0 +1,216 @@
+use anyhow::{Context, Result};
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::spoken_word::ContentKind;
+use crate::storage::AudioLibrary;
+
+/// Plays kept in `play_history.json`; older ones are dropped as new ones come in
+const MAX_PLAYS: usize = 10_000;
+
+/// Longest queue `generate` builds, whatever `count` asks for
+pub const MAX_RADIO_TRACKS: usize = 500;
+
+/// One entry of `play_history.json`.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct PlayEvent {
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+    /// UNIX timestamp (seconds)
+    pub played_at: u64,
+}
+
+/// Tracks reported as played through `POST /api/plays`, oldest first.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PlayHistory {
+    pub plays: Vec<PlayEvent>,
+}
+
+impl PlayHistory {
+    /// Load `play_history.json`. A missing file means nothing was played yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).context("Failed to read play history")?;
+        serde_json::from_str(&content).context("Failed to parse play history JSON")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content =
+            serde_json::to_string_pretty(self).context("Failed to serialize play history")?;
+        fs::write(path, content).context("Failed to write play history")
+    }
+
+    /// Append a play, dropping the oldest ones beyond `MAX_PLAYS`.
+    pub fn record(&mut self, path: PathBuf, played_at: u64) {
+        self.plays.push(PlayEvent { path, played_at });
+        let excess = self.plays.len().saturating_sub(MAX_PLAYS);
+        self.plays.drain(..excess);
+    }
+
+    /// Tracks played at or after `since`.
+    pub fn played_since(&self, since: u64) -> HashSet<&Path> {
+        self.plays
+            .iter()
+            .filter(|p| p.played_at >= since)
+            .map(|p| p.path.as_path())
+            .collect()
+    }
+
+    /// Tracks played within `days` days before `now`.
+    pub fn played_within_days(&self, days: u64, now: u64) -> HashSet<&Path> {
+        self.played_since(now.saturating_sub(days.saturating_mul(24 * 60 * 60)))
+    }
+}
+
+/// Constraints for a radio queue grown from a seed track.
+#[derive(Deserialize, Debug, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct RadioOptions {
+    /// Queue length, seed included (at most 500)
+    pub count: usize,
+    /// No artist repeats within this many consecutive tracks (0 = no limit)
+    pub artist_gap: usize,
+    /// Skip tracks played within this many days (needs `POST /api/plays`)
+    pub exclude_played_days: Option<u64>,
+    /// 0 always takes the closest allowed neighbor; higher values pick more
+    /// freely among farther ones (in units of feature distance)
+    pub temperature: f32,
+    pub include_spoken_word: bool,
+    /// Makes a non-zero `temperature` reproducible
+    pub seed: Option<u64>,
+}
+
+impl Default for RadioOptions {
+    fn default() -> Self {
+        Self {
+            count: 25,
+            artist_gap: 3,
+            exclude_played_days: None,
+            temperature: 0.0,
+            include_spoken_word: false,
+            seed: None,
+        }
+    }
+}
+
+/// A queued track and its feature distance to the track it was picked from.
+pub struct RadioPick {
+    pub path: PathBuf,
+    pub distance: f32,
+}
+
+/// Grow a queue from `seed` by repeatedly stepping to a neighbor of the last
+/// track, subject to `options` (`count` capped at `MAX_RADIO_TRACKS`). When
+/// the last track has no allowed neighbor the walk backs up to earlier
+/// tracks; the queue ends early if none has one.
+pub fn generate(
+    seed: &Path,
+    library: &AudioLibrary,
+    history: &PlayHistory,
+    options: &RadioOptions,
+    now: u64,
+    neighbors_of: impl Fn(&Path) -> Option<Vec<(PathBuf, f32)>>,
+) -> Vec<RadioPick> {
+    let recently_played = match options.exclude_played_days {
+        Some(days) => history.played_within_days(days, now),
+        None => HashSet::new(),
+    };
+    let mut rng = match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let artist = |path: &Path| {
+        library
+            .files
+            .get(path)
+            .map(|t| t.metadata.artist.trim().to_lowercase())
+            .filter(|a| !a.is_empty())
+    };
+
+    let mut queue = vec![RadioPick {
+        path: seed.to_path_buf(),
+        distance: 0.0,
+    }];
+    let mut queued: HashSet<PathBuf> = HashSet::from([seed.to_path_buf()]);
+
+    while queue.len() < options.count.min(MAX_RADIO_TRACKS) {
+        let gap_start = queue.len().saturating_sub(options.artist_gap);
+        let blocked_artists: HashSet<String> = queue[gap_start..]
+            .iter()
+            .filter_map(|p| artist(&p.path))
+            .collect();
+        let allowed = |path: &Path| {
+            !queued.contains(path)
+                && !recently_played.contains(path)
+                && artist(path).is_none_or(|a| !blocked_artists.contains(&a))
+                && (options.include_spoken_word
+                    || library
+                        .files
+                        .get(path)
+                        .is_none_or(|t| t.metadata.content_kind != ContentKind::SpokenWord))
+        };
+
+        let candidates = queue.iter().rev().find_map(|anchor| {
+            let list: Vec<(PathBuf, f32)> = neighbors_of(&anchor.path)?
+                .into_iter()
+                .filter(|(p, _)| allowed(p))
+                .collect();
+            (!list.is_empty()).then_some(list)
+        });
+        let Some(candidates) = candidates else {
+            break;
+        };
+
+        let (path, distance) = pick(candidates, options.temperature, &mut rng);
+        queued.insert(path.clone());
+        queue.push(RadioPick { path, distance });
+    }
+    queue
+}
+
+/// Closest candidate at temperature 0, otherwise a draw weighted by
+/// exp(-(distance - closest) / temperature). `candidates` is sorted, non-empty.
+fn pick(mut candidates: Vec<(PathBuf, f32)>, temperature: f32, rng: &mut StdRng) -> (PathBuf, f32) {
+    if temperature > 0.0 {
+        let closest = candidates[0].1;
+        let weights = candidates
+            .iter()
+            .map(|(_, d)| (-(d - closest) / temperature).exp());
+        if let Ok(dist) = WeightedIndex::new(weights) {
+            return candidates.swap_remove(dist.sample(rng));
+        }
+    }
+    candidates.swap_remove(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_keeps_only_the_latest_plays() {
+        let mut history = PlayHistory::default();
+        for i in 0..MAX_PLAYS as u64 + 5 {
+            history.record(PathBuf::from(format!("{}.mp3", i)), i);
+        }
+        assert_eq!(history.plays.len(), MAX_PLAYS);
+        assert_eq!(history.plays[0].played_at, 5);
+    }
+
+    #[test]
+    fn huge_day_windows_cover_all_plays() {
+        let mut history = PlayHistory::default();
+        history.record(PathBuf::from("a.mp3"), 0);
+        assert_eq!(history.played_within_days(u64::MAX, 100).len(), 1);
+        assert!(history.played_within_days(0, 100).is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Per-IP token bucket for the mutation endpoints.
+/// Each client may burst up to `per_minute` requests, refilled continuously.
+pub struct RateLimiter {
+    per_minute: u32,
+    /// Key clients by the address a reverse proxy put in `X-Forwarded-For`
+    trust_proxy: bool,
+    buckets: DashMap<IpAddr, (f64, Instant)>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32, trust_proxy: bool) -> Self {
+        Self {
+            per_minute,
+            trust_proxy,
+            buckets: DashMap::new(),
+        }
+    }
+
+    /// Take one token for `ip`. Returns the seconds to wait when the bucket is empty.
+    pub fn check(&self, ip: IpAddr) -> Result<(), u64> {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> Result<(), u64> {
+        if self.per_minute == 0 {
+            return Ok(());
+        }
+
+        let capacity = self.per_minute as f64;
+        let refill_per_sec = capacity / 60.0;
+
+        let mut bucket = self.buckets.entry(ip).or_insert((capacity, now));
+        let (tokens, last) = *bucket;
+        let tokens =
+            (tokens + now.duration_since(last).as_secs_f64() * refill_per_sec).min(capacity);
+
+        if tokens >= 1.0 {
+            *bucket = (tokens - 1.0, now);
+            Ok(())
+        } else {
+            *bucket = (tokens, now);
+            Err(((1.0 - tokens) / refill_per_sec).ceil() as u64)
+        }
+    }
+
+    /// Drop the buckets of clients idle long enough to be full again; a new
+    /// bucket for them starts full, so nothing changes but the memory held.
+    pub fn sweep(&self) {
+        self.sweep_at(Instant::now());
+    }
+
+    fn sweep_at(&self, now: Instant) {
+        let capacity = self.per_minute as f64;
+        let refill_per_sec = capacity / 60.0;
+        self.buckets.retain(|_, (tokens, last)| {
+            *tokens + now.duration_since(*last).as_secs_f64() * refill_per_sec < capacity
+        });
+    }
+
+    /// The client a request is counted against: the peer address, or with
+    /// `trust_proxy` the last `X-Forwarded-For` entry, which the proxy in
+    /// front of us appended (earlier entries come from the client).
+    fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.trust_proxy {
+            return peer;
+        }
+        headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|ip| ip.trim().parse().ok())
+            .next_back()
+            .unwrap_or(peer)
+    }
+}
+
+/// Sweep idle buckets every `interval` for as long as the server runs.
+pub async fn sweep_periodically(limiter: Arc<RateLimiter>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        limiter.sweep();
+    }
+}
+
+/// Middleware rejecting clients that exceed the limit with `429 Too Many Requests`.
+pub async fn limit_by_ip(
+    State(limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = limiter.client_ip(addr.ip(), request.headers());
+    match limiter.check(ip) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            let mut response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests, slow down",
+            )
+                .into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after.max(1)));
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 1));
+
+    #[test]
+    fn bucket_allows_burst_then_refills() {
+        let limiter = RateLimiter::new(6, false);
+        let start = Instant::now();
+        for _ in 0..6 {
+            assert_eq!(limiter.check_at(CLIENT, start), Ok(()));
+        }
+        // One token per 10 seconds at 6 per minute
+        assert_eq!(limiter.check_at(CLIENT, start), Err(10));
+        assert_eq!(
+            limiter.check_at(CLIENT, start + Duration::from_secs(4)),
+            Err(6)
+        );
+        assert_eq!(
+            limiter.check_at(CLIENT, start + Duration::from_secs(10)),
+            Ok(())
+        );
+        // Refill stops at capacity
+        let later = start + Duration::from_secs(3600);
+        for _ in 0..6 {
+            assert_eq!(limiter.check_at(CLIENT, later), Ok(()));
+        }
+        assert!(limiter.check_at(CLIENT, later).is_err());
+    }
+
+    #[test]
+    fn zero_disables_the_limit() {
+        let limiter = RateLimiter::new(0, false);
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert_eq!(limiter.check_at(CLIENT, now), Ok(()));
+        }
+        assert!(limiter.buckets.is_empty());
+    }
+
+    #[test]
+    fn sweep_drops_only_refilled_buckets() {
+        let limiter = RateLimiter::new(60, false);
+        let other: IpAddr = "198.51.100.7".parse().unwrap();
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.check_at(CLIENT, start).unwrap();
+        }
+        let later = start + Duration::from_secs(30);
+        limiter.check_at(other, later).unwrap();
+
+        // One token per second: the first client is full again, the other not yet
+        limiter.sweep_at(later + Duration::from_millis(500));
+        assert_eq!(limiter.buckets.len(), 1);
+        assert!(limiter.buckets.contains_key(&other));
+        limiter.sweep_at(later + Duration::from_secs(2));
+        assert!(limiter.buckets.is_empty());
+    }
+
+    #[test]
+    fn forwarded_for_only_with_trust_proxy() {
+        let peer: IpAddr = "10.0.0.2".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("203.0.113.9, 192.0.2.1"),
+        );
+
+        assert_eq!(RateLimiter::new(10, false).client_ip(peer, &headers), peer);
+        let trusted = RateLimiter::new(10, true);
+        assert_eq!(trusted.client_ip(peer, &headers), CLIENT);
+        assert_eq!(trusted.client_ip(peer, &HeaderMap::new()), peer);
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static("unknown"));
+        assert_eq!(trusted.client_ip(peer, &headers), peer);
+    }
+}
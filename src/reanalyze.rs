@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::analyzers;
+use crate::decode::DecodedAudio;
+use crate::fingerprint;
+use crate::memory_budget;
+use crate::organizer::TrackMetadata;
+use crate::playlist::PlaylistQuery;
+use crate::quality;
+
+// Import decoder trait and implementation
+use bliss_audio::decoder::symphonia::SymphoniaDecoder;
+use bliss_audio::decoder::Decoder as DecoderTrait;
+
+/// An analysis product that can be recomputed without touching tags or online metadata.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// bliss feature vector in analysis.bin (similarity, recommendations)
+    Bliss,
+    /// Measured audio quality: EBU R128 loudness, spectrum, clipping, gapless info
+    Loudness,
+    /// Chromaprint fingerprint and duration
+    Fingerprint,
+    /// Output of the analyzers enabled in scan.json (`analyzers`)
+    Features,
+}
+
+/// Parse `--filter` values such as `artist=Queen` or `decade=1980` into a track
+/// query; the same filters as generated playlists, combined with AND.
+pub fn parse_filters(filters: &[String]) -> Result<PlaylistQuery> {
+    let mut query = PlaylistQuery::default();
+    for filter in filters {
+        let (key, value) = filter
+            .split_once('=')
+            .with_context(|| format!("Filter must look like field=value: {}", filter))?;
+        let value = value.trim().to_string();
+        let number = || {
+            value
+                .parse::<u32>()
+                .with_context(|| format!("Filter {} needs a number", key))
+        };
+        match key.trim() {
+            "artist" => query.artist = Some(value),
+            "album" => query.album = Some(value),
+            "genre" => query.genre = Some(value),
+            "vocal_language" => query.vocal_language = Some(value),
+            "year" => query.year = Some(number()?),
+            "decade" => query.decade = Some(number()?),
+            other => anyhow::bail!(
+                "Unknown filter field '{}' (artist, album, genre, year, decade, vocal_language)",
+                other
+            ),
+        }
+    }
+    Ok(query)
+}
+
+/// Recompute the requested products for one file, updating `meta` in place.
+/// Returns the new bliss vector when `Scope::Bliss` was requested.
+pub fn reanalyze_file(
+    path: &Path,
+    scopes: &[Scope],
+    meta: &mut TrackMetadata,
+) -> Result<Option<Vec<f32>>> {
+    let mut analysis = None;
+    let file_size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let _permit = memory_budget::acquire(file_size);
+    // Loudness and features together share one decode
+    let decoded = if scopes.contains(&Scope::Loudness)
+        && scopes.contains(&Scope::Features)
+        && analyzers::any_active()
+    {
+        Some(DecodedAudio::load(path).context("Failed to decode audio")?)
+    } else {
+        None
+    };
+    for scope in scopes {
+        match scope {
+            Scope::Bliss => {
+                let song = SymphoniaDecoder::song_from_path(path)
+                    .map_err(|e| anyhow::anyhow!("bliss analysis failed: {}", e))?;
+                analysis = Some(song.analysis.as_vec());
+            }
+            Scope::Loudness => {
+                let measured = match &decoded {
+                    Some(audio) => quality::analyze_decoded(audio, file_size),
+                    None => quality::analyze_file(path, file_size),
+                };
+                meta.quality = Some(measured.context("Quality analysis failed")?);
+            }
+            Scope::Fingerprint => {
+                let (duration, fp) = fingerprint::compute_fingerprint(path)
+                    .context("Fingerprint generation failed")?;
+                meta.duration = duration;
+                meta.fingerprint = Some(fp);
+            }
+            Scope::Features => {
+                meta.features = match &decoded {
+                    Some(audio) => analyzers::analyze(audio),
+                    None => analyzers::analyze_file(path).context("Analyzers failed")?,
+                };
+            }
+        }
+    }
+    Ok(analysis)
+}
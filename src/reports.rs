@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use utoipa::ToSchema;
+
+use crate::storage::{AudioLibrary, IndexedTrack};
+
+/// Which storage cleanup report to build.
+#[derive(clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReportKind {
+    /// Largest files first
+    Largest,
+    /// Lossy files below the minimum bitrate, lowest first
+    LowBitrate,
+    /// Mono files and sample rates below 44.1 kHz
+    Oddities,
+}
+
+/// Thresholds shared by the CLI and the API.
+#[derive(Debug, Clone, Copy)]
+pub struct ReportOptions {
+    pub limit: usize,
+    /// Lossy files below this are listed by `LowBitrate`
+    pub min_kbps: u32,
+    /// Bitrate a large file is assumed to be re-encoded at when estimating savings
+    pub target_kbps: u32,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self {
+            limit: 50,
+            min_kbps: 160,
+            target_kbps: 320,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ReportEntry {
+    pub id: String,
+    pub path: String,
+    pub file_size: u64,
+    /// Why the file is listed, e.g. "FLAC, 1024 kbps" or "mono, 22050 Hz"
+    pub detail: String,
+    /// Space freed if this file were replaced (see `FileReport::reclaimable_bytes`)
+    pub reclaimable_bytes: u64,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct FileReport {
+    pub kind: ReportKind,
+    pub entries: Vec<ReportEntry>,
+    /// All matching files, before `limit`
+    pub total_matches: usize,
+    /// Sum over all matches. For `Largest`, the saving from re-encoding at the
+    /// target bitrate; otherwise the size of the files themselves, which a
+    /// better copy would replace.
+    pub reclaimable_bytes: u64,
+}
+
+fn detail(track: &IndexedTrack) -> String {
+    let Some(q) = &track.metadata.quality else {
+        return "not measured".to_string();
+    };
+    let mut parts = vec![q.codec.clone()];
+    if let Some(kbps) = q.bitrate_kbps {
+        parts.push(format!("{} kbps", kbps));
+    }
+    parts.push(format!("{} Hz", q.sample_rate));
+    if q.channels == 1 {
+        parts.push("mono".to_string());
+    }
+    parts.join(", ")
+}
+
+/// Bytes saved by re-encoding at `target_kbps`; 0 when the file is already smaller.
+fn reencode_saving(track: &IndexedTrack, target_kbps: u32) -> u64 {
+    let target_size = (track.metadata.duration * target_kbps as f64 * 1000.0 / 8.0) as u64;
+    track.file_size.saturating_sub(target_size)
+}
+
+/// Build a report. Tracks without a quality measurement are only
+/// considered by `Largest`.
+pub fn build(library: &AudioLibrary, kind: ReportKind, opts: &ReportOptions) -> FileReport {
+    let mut matches: Vec<(&IndexedTrack, u64)> = Vec::new();
+    for track in library.files.values() {
+        let quality = track.metadata.quality.as_ref();
+        let reclaimable = match kind {
+            ReportKind::Largest => reencode_saving(track, opts.target_kbps),
+            ReportKind::LowBitrate => {
+                let Some(q) = quality else { continue };
+                if q.lossless || q.bitrate_kbps.is_none_or(|b| b >= opts.min_kbps) {
+                    continue;
+                }
+                track.file_size
+            }
+            ReportKind::Oddities => {
+                let Some(q) = quality else { continue };
+                if q.channels != 1 && q.sample_rate >= 44_100 {
+                    continue;
+                }
+                track.file_size
+            }
+        };
+        matches.push((track, reclaimable));
+    }
+
+    match kind {
+        ReportKind::Largest => matches.sort_by_key(|(t, _)| Reverse(t.file_size)),
+        ReportKind::LowBitrate => matches.sort_by_key(|(t, _)| {
+            t.metadata
+                .quality
+                .as_ref()
+                .and_then(|q| q.bitrate_kbps)
+                .unwrap_or(0)
+        }),
+        ReportKind::Oddities => matches.sort_by_key(|(t, _)| {
+            t.metadata
+                .quality
+                .as_ref()
+                .map(|q| q.sample_rate)
+                .unwrap_or(0)
+        }),
+    }
+
+    let total_matches = matches.len();
+    let reclaimable_bytes = matches.iter().map(|(_, r)| r).sum();
+    let entries = matches
+        .into_iter()
+        .take(opts.limit)
+        .map(|(track, reclaimable_bytes)| ReportEntry {
+            id: track.id.clone(),
+            path: track.path.to_string_lossy().into_owned(),
+            file_size: track.file_size,
+            detail: detail(track),
+            reclaimable_bytes,
+        })
+        .collect();
+
+    FileReport {
+        kind,
+        entries,
+        total_matches,
+        reclaimable_bytes,
+    }
+}
@@ -1,13 +1,20 @@
-use crate::storage::{AudioLibrary, IndexedTrack};
-use crate::TrackMetadata;
-use anyhow::{Context, Result};
+use crate::events::{self, Level};
+use crate::index_writer::IndexWriter;
+use crate::organizer::MetadataSource;
+use crate::scanner::DirStats;
+use crate::worker::Processed;
+use anyhow::Result;
 use rayon::prelude::*;
-use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use sysinfo::{Disks, System};
+use tokio::sync::watch;
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, ToSchema)]
 pub struct ResourceStats {
     pub cpu_usage: f32,
     pub memory_usage: u64, // in bytes
@@ -15,7 +22,7 @@ pub struct ResourceStats {
     pub disk_total: u64,   // in bytes (total space on target drive)
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, ToSchema)]
 pub struct ScanProgress {
     pub is_scanning: bool,
     pub files_total: usize,
@@ -24,6 +31,10 @@ pub struct ScanProgress {
     pub elapsed_secs: u64,
     pub resources: ResourceStats,
     pub errors: usize,
+    /// Set when AcoustID rejected the key or quota ran out and lookups were suspended
+    pub online_suspended: Option<String>,
+    /// Counts and worker time per top-level directory of the input folder
+    pub directories: BTreeMap<String, DirStats>,
 }
 
 impl Default for ScanProgress {
@@ -41,18 +52,36 @@ impl Default for ScanProgress {
                 disk_total: 0,
             },
             errors: 0,
+            online_suspended: None,
+            directories: BTreeMap::new(),
         }
     }
 }
 
 pub struct ScanManager {
     progress: Arc<RwLock<ScanProgress>>,
+    /// Latest progress for `/api/scan/stream`, pushed on start, every
+    /// monitor tick and at the end of a scan
+    updates: Arc<watch::Sender<ScanProgress>>,
+    /// Set on shutdown: the running scan stops after its current batch
+    stop_requested: Arc<AtomicBool>,
+    /// Files waiting for `scan_files` (e.g. uploads); taken by the next scan
+    queued: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl Default for ScanManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ScanManager {
     pub fn new() -> Self {
         Self {
             progress: Arc::new(RwLock::new(ScanProgress::default())),
+            updates: Arc::new(watch::channel(ScanProgress::default()).0),
+            stop_requested: Arc::new(AtomicBool::new(false)),
+            queued: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -60,14 +89,73 @@ impl ScanManager {
         self.progress.read().unwrap().clone()
     }
 
+    /// Receiver that sees the current progress and every update pushed after it.
+    pub fn subscribe(&self) -> watch::Receiver<ScanProgress> {
+        self.updates.subscribe()
+    }
+
+    fn publish(progress: &RwLock<ScanProgress>, updates: &watch::Sender<ScanProgress>) {
+        if let Ok(p) = progress.read() {
+            // Keeps the value even while no dashboard is connected
+            updates.send_replace(p.clone());
+        }
+    }
+
+    /// Refuse new scans, let a running one finish its current batch and save
+    /// the index, and return once it has.
+    pub async fn stop_and_wait(&self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        while self.get_progress().is_scanning {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    }
+
     pub fn start_scan(
         &self,
         input_dir: PathBuf,
         index_dir: PathBuf,
         offline: bool,
         client_id: Option<String>,
+        writer: IndexWriter,
+    ) -> Result<()> {
+        self.start(input_dir, index_dir, offline, client_id, writer, false)
+    }
+
+    /// Index just `files` (below `input_dir`), without enumerating the rest
+    /// of the library. While a scan runs the files stay queued, and the next
+    /// scan of either kind takes them; the error says why none started now.
+    pub fn scan_files(
+        &self,
+        files: Vec<PathBuf>,
+        input_dir: PathBuf,
+        index_dir: PathBuf,
+        offline: bool,
+        client_id: Option<String>,
+        writer: IndexWriter,
+    ) -> Result<()> {
+        self.queued.lock().unwrap().extend(files);
+        self.start(input_dir, index_dir, offline, client_id, writer, true)
+    }
+
+    /// Start a scan of the whole input directory, or with `targeted` of the
+    /// queued files only.
+    fn start(
+        &self,
+        input_dir: PathBuf,
+        index_dir: PathBuf,
+        offline: bool,
+        client_id: Option<String>,
+        writer: IndexWriter,
+        targeted: bool,
     ) -> Result<()> {
         let progress = self.progress.clone();
+        let updates = self.updates.clone();
+        let stop_requested = self.stop_requested.clone();
+        let queued = self.queued.clone();
+
+        if stop_requested.load(Ordering::SeqCst) {
+            return Err(anyhow::anyhow!("Server is shutting down"));
+        }
 
         // Check if already scanning
         if progress.read().unwrap().is_scanning {
@@ -80,11 +168,20 @@ impl ScanManager {
             *p = ScanProgress::default();
             p.is_scanning = true;
         }
-
+        Self::publish(&progress, &updates);
+
+        let started = if targeted {
+            format!("Scan of new files started: {}", input_dir.display())
+        } else {
+            format!("Scan started: {}", input_dir.display())
+        };
+        events::emit(Level::Info, "scan", started, None);
         let index_dir_clone = index_dir.clone();
+        let notify_dir = index_dir.clone();
         tokio::spawn(async move {
             let start_time = Instant::now();
             let progress_for_monitor = progress.clone();
+            let monitor_updates = updates.clone();
             let monitor_index_dir = index_dir_clone.clone();
 
             // Start resource monitoring in a separate OS thread (not tokio task)
@@ -138,13 +235,24 @@ impl ScanManager {
                         p.resources.disk_usage = disk_usage;
                         p.resources.disk_total = disk_total;
                     }
+                    Self::publish(&progress_for_monitor, &monitor_updates);
                 }
             });
 
             // Run actual scan in a blocking thread
             let scan_progress = progress.clone();
             let scan_result = tokio::task::spawn_blocking(move || {
-                Self::run_scan_logic(input_dir, index_dir, offline, client_id, scan_progress)
+                Self::run_scan_logic(
+                    input_dir,
+                    index_dir,
+                    offline,
+                    client_id,
+                    scan_progress,
+                    stop_requested,
+                    writer,
+                    queued,
+                    targeted,
+                )
             })
             .await;
 
@@ -154,37 +262,99 @@ impl ScanManager {
                 p.is_scanning = false;
                 p.elapsed_secs = start_time.elapsed().as_secs();
             }
+            Self::publish(&progress, &updates);
 
             // Wait for monitor thread to finish
             let _ = monitor_handle.join();
 
-            if let Err(e) = scan_result {
-                eprintln!("Scan task failed: {:?}", e);
-            } else if let Ok(Err(e)) = scan_result {
-                eprintln!("Scan failed: {}", e);
+            let scan_result = match scan_result {
+                Ok(result) => result,
+                Err(e) => Err(anyhow::anyhow!("Scan task failed: {:?}", e)),
+            };
+            match &scan_result {
+                Ok(()) => {
+                    let p = progress.read().unwrap().clone();
+                    events::emit(
+                        Level::Info,
+                        "scan",
+                        format!(
+                            "Scan finished: {} files, {} errors in {} s",
+                            p.files_processed, p.errors, p.elapsed_secs
+                        ),
+                        None,
+                    );
+                }
+                Err(e) => {
+                    eprintln!("Scan failed: {}", e);
+                    events::emit(Level::Error, "scan", format!("Scan failed: {:#}", e), None);
+                }
             }
+            crate::notify::job_finished(
+                &notify_dir,
+                "Scan",
+                start_time.elapsed().as_secs(),
+                &scan_result,
+            );
         });
 
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn run_scan_logic(
         input_dir: PathBuf,
         index_dir: PathBuf,
         offline: bool,
         client_id: Option<String>,
         progress: Arc<RwLock<ScanProgress>>,
+        stop_requested: Arc<AtomicBool>,
+        writer: IndexWriter,
+        queued: Arc<Mutex<Vec<PathBuf>>>,
+        targeted: bool,
     ) -> Result<()> {
-        let index_path = index_dir.join("index.json");
         let analysis_path = index_dir.join("analysis.bin");
+        let cache_path = index_dir.join("lookup_cache.json");
 
-        // 1. Load Index
-        let mut library = AudioLibrary::load(&index_path).unwrap_or_default();
+        // 1. Load Index (the writer holds the library; edits below are staged
+        // there and saved with each periodic save)
         let mut analysis_store =
             crate::analysis_store::AnalysisStore::load(&analysis_path).unwrap_or_default();
+        for path in writer.stage_blocking(|library| Ok(library.remove_key_duplicates()))? {
+            analysis_store.remove(&path);
+        }
+        let lookup_cache = crate::lookup_cache::LookupCache::load(&cache_path, offline)
+            .unwrap_or_else(|_| crate::lookup_cache::LookupCache::new(offline));
+        let cleanup = crate::cleanup::Cleanup::load(&index_dir.join("cleanup.json"))?;
+        let queue_path = index_dir.join("pending_lookups.json");
+        let lookup_queue = crate::lookup_queue::LookupQueue::load(&queue_path)?;
+        let merge_policy =
+            crate::merge_policy::MergePolicy::load(&index_dir.join("merge_policy.json"))?;
+        let http_client = crate::http::client()?;
+
+        // A suspension from an earlier scan may have been fixed since
+        crate::acoustid::resume();
+        if let (false, Some(id)) = (offline, client_id.as_deref()) {
+            if let Err(e) = crate::acoustid::validate_key(id) {
+                crate::acoustid::suspend(e.to_string());
+            }
+        }
 
         // 2. Scan Directory
-        let files = crate::scanner::scan_directory(&input_dir)?;
+        let scan_settings = crate::scanner::ScanSettings::load(&index_dir.join("scan.json"))?;
+        crate::memory_budget::configure(scan_settings.memory_budget_mb);
+        crate::analyzers::configure(&scan_settings.analyzers)?;
+        let files = if targeted {
+            let mut files = std::mem::take(&mut *queued.lock().unwrap());
+            files.sort();
+            files.dedup();
+            files.retain(|f| f.starts_with(&input_dir) && f.is_file());
+            files
+        } else {
+            let files = crate::scanner::scan_directory(&input_dir, &scan_settings)?;
+            // Files queued before the enumeration are covered by it
+            queued.lock().unwrap().retain(|f| !files.contains(f));
+            files
+        };
 
         {
             let mut p = progress.write().unwrap();
@@ -197,45 +367,63 @@ impl ScanManager {
             .as_secs();
 
         // 3. Diff Phase
-        let mut files_to_process = Vec::new();
-        let mut skipped_count = 0;
-
-        for path in &files {
-            if let Ok(metadata) = std::fs::metadata(path) {
-                let mtime = metadata
-                    .modified()
-                    .unwrap_or(SystemTime::UNIX_EPOCH)
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs();
-                let size = metadata.len();
-
-                let needs_update = if let Some(indexed) = library.files.get(path) {
-                    if indexed.modified_time != mtime || indexed.file_size != size {
-                        true
-                    } else {
-                        // Check if analysis is missing
-                        analysis_store.get(path).is_none()
-                    }
-                } else {
-                    true
-                };
-
-                if needs_update {
-                    files_to_process.push((path.clone(), size, mtime));
-                } else {
-                    skipped_count += 1;
-                }
-            }
+        let mut plan = crate::scanner::plan_scan(
+            &input_dir,
+            &files,
+            &writer.shared().get(),
+            &analysis_store,
+            &scan_settings,
+        );
+        // Every other indexed file is missing from a targeted file list
+        if targeted {
+            plan.removed.clear();
+        }
+        let (plan, mut analysis_store, removed) = writer.stage_blocking(move |library| {
+            crate::scanner::apply_renames(&plan, library, &mut analysis_store);
+            let removed = crate::scanner::remove_missing(&plan, library, &mut analysis_store);
+            Ok((plan, analysis_store, removed))
+        })?;
+        if removed > 0 {
+            events::emit(
+                Level::Info,
+                "scan",
+                format!(
+                    "Removed {} index entries for files deleted from disk",
+                    removed
+                ),
+                None,
+            );
         }
+        crate::issues::save_quarantine(&index_dir.join("quarantine.json"), &plan.quarantined)?;
+        // Quarantined files count as done so the progress bar can reach the total
+        let skipped_count = plan.unchanged + plan.quarantined.len();
+        let mut counts = crate::exit_status::JobCounts {
+            skipped: plan.unchanged,
+            quarantined: plan.quarantined.len(),
+            removed,
+            ..Default::default()
+        };
+        let hooks = crate::hooks::HookRunner::load(&index_dir);
+        let mut directories = plan.directories;
+        let files_to_process: Vec<(PathBuf, u64, u64)> = plan
+            .to_process
+            .into_iter()
+            .map(|f| (f.path, f.size, f.mtime))
+            .collect();
 
         // Auto-fill processed count for skipped files
         {
             let mut p = progress.write().unwrap();
             p.files_processed = skipped_count;
+            p.directories = directories.clone();
         }
 
         if files_to_process.is_empty() {
+            writer.flush_blocking()?;
+            if removed > 0 {
+                analysis_store.save(&analysis_path)?;
+            }
+            hooks.scan_complete(&counts);
             return Ok(());
         }
 
@@ -244,6 +432,7 @@ impl ScanManager {
         let batch_size = 50;
         let mut processed_c = skipped_count;
         let mut error_c = 0;
+        let mut scan_errors = BTreeMap::new();
 
         // Configure Rayon thread pool to limit concurrency
         // Use logical cores - 1, minimum 1 to prevent UI freeze
@@ -263,60 +452,103 @@ impl ScanManager {
 
         pool.install(|| {
             for chunk in files_to_process.chunks(batch_size) {
+                // Unprocessed files are picked up by the next scan
+                if stop_requested.load(Ordering::SeqCst) {
+                    break;
+                }
                 // Process chunk in parallel
-                let chunk_results: Vec<(
-                    PathBuf,
-                    u64,
-                    u64,
-                    Result<(TrackMetadata, Option<Vec<f32>>)>,
-                )> = chunk
+                let chunk_results: Vec<(PathBuf, u64, u64, f64, Processed)> = chunk
                     .par_iter()
                     .map_init(
-                        || reqwest::blocking::Client::new(),
+                        || http_client.clone(),
                         |client, (path, size, mtime)| {
                             let args = crate::ScanArgs {
                                 input_dir: input_dir.clone(),
                                 output_dir: index_dir.clone(),
                                 offline,
                                 client_id: client_id.clone(),
+                                dry_run: false,
+                                limit: None,
+                                sample: None,
+                                include_hidden: false,
+                                memory_budget_mb: None,
+                                background: false,
+                                profile: None,
                             };
 
-                            let result = crate::worker::process_file(path, &args, client);
-                            (path.clone(), *size, *mtime, result)
+                            let started = Instant::now();
+                            let ctx = crate::worker::ScanContext {
+                                args: &args,
+                                cache: &lookup_cache,
+                                queue: &lookup_queue,
+                                policy: &merge_policy,
+                                bars: None,
+                            };
+                            let result = crate::worker::process_file(
+                                path,
+                                &ctx,
+                                client,
+                                scan_settings.pipeline(path),
+                            );
+                            let busy = started.elapsed().as_secs_f64();
+                            (path.clone(), *size, *mtime, busy, result)
                         },
                     )
                     .collect();
 
                 // Merge results (Single-threaded to avoid lock contention on library/store)
-                for (path, size, mtime, result) in chunk_results {
+                let mut upserts = Vec::with_capacity(chunk_results.len());
+                for (path, size, mtime, busy, result) in chunk_results {
                     processed_c += 1;
+                    let dir = directories
+                        .entry(crate::scanner::top_level_dir(&input_dir, &path))
+                        .or_default();
+                    dir.busy_secs += busy;
                     match result {
-                        Ok((meta, analysis_opt)) => {
-                            let entry = IndexedTrack {
-                                path: path.clone(),
-                                file_size: size,
-                                modified_time: mtime,
-                                scanned_at: current_time,
-                                metadata: meta,
-                            };
-                            library.files.insert(path.clone(), entry);
+                        Ok((mut meta, analysis_opt)) => {
+                            if meta.source == MetadataSource::Online {
+                                events::emit(
+                                    Level::Info,
+                                    "lookup",
+                                    format!("Matched online: {} - {}", meta.artist, meta.title),
+                                    Some(&path),
+                                );
+                            }
+                            cleanup.apply(&mut meta);
+                            hooks.track_identified(&path, &meta);
+                            upserts.push((path.clone(), size, mtime, meta));
 
                             if let Some(analysis) = analysis_opt {
                                 analysis_store.insert(path, analysis);
                             }
+                            dir.processed += 1;
                         }
-                        Err(_) => {
-                            // Only log error, don't stop scan
-                            // eprintln!("Error: {}", e);
+                        Err(e) => {
+                            // Only record the error, don't stop scan
+                            events::emit(Level::Error, "file", format!("{:#}", e), Some(&path));
+                            scan_errors.insert(path, format!("{:#}", e));
+                            dir.errors += 1;
                             error_c += 1;
                         }
                     }
                 }
 
+                let staged = writer.stage_blocking(move |library| {
+                    for (path, size, mtime, meta) in upserts {
+                        library.upsert(path, size, mtime, current_time, meta);
+                    }
+                    Ok(())
+                });
+                if let Err(e) = staged {
+                    events::emit(Level::Error, "save", format!("{:#}", e), None);
+                }
+
                 // Update Progress (Once per batch)
                 if let Ok(mut p) = progress.write() {
                     p.files_processed = processed_c;
                     p.errors = error_c;
+                    p.online_suspended = crate::acoustid::suspended();
+                    p.directories = directories.clone();
                     // Update current file to show activity (using last file of the batch)
                     if let Some(last) = chunk.last() {
                         if let Some(name) = last.0.file_name().and_then(|s| s.to_str()) {
@@ -327,15 +559,72 @@ impl ScanManager {
 
                 // Periodic Save (Every 4 batches = 200 files)
                 if processed_c % 200 == 0 {
-                    let _ = library.save(&index_path);
+                    match writer.flush_blocking() {
+                        Ok(()) => events::emit(
+                            Level::Info,
+                            "save",
+                            format!("Saved index after {} files", processed_c),
+                            None,
+                        ),
+                        Err(e) => events::emit(
+                            Level::Warn,
+                            "save",
+                            format!("Periodic save failed: {:#}", e),
+                            None,
+                        ),
+                    }
                     let _ = analysis_store.save(&analysis_path);
+                    if !offline {
+                        let _ = lookup_cache.save(&cache_path);
+                        let _ = lookup_queue.save(&queue_path);
+                    }
                 }
             }
         });
 
-        // 6. Save Index
-        library.save(&index_path)?;
+        // 5. Link karaoke (vocal/off-vocal) pairs across the whole library
+        let analysis_store = Arc::new(analysis_store);
+        let pairing_store = analysis_store.clone();
+        writer.stage_blocking(move |library| {
+            crate::pairing::link_vocal_pairs(library, &pairing_store);
+            Ok(())
+        })?;
+
+        // 6. Save Index; the writer hands the result straight to the web
+        // handlers instead of re-parsing index.json
+        writer.flush_blocking()?;
         analysis_store.save(&analysis_path)?;
+        if !offline {
+            lookup_cache.save(&cache_path)?;
+            lookup_queue.save(&queue_path)?;
+        }
+        let library = writer.shared().get();
+        crate::timeline::record(&index_dir.join("scan_history.json"), &library)?;
+        crate::issues::save_scan_errors(&index_dir.join("scan_errors.json"), &scan_errors)?;
+        events::emit(
+            Level::Info,
+            "save",
+            format!("Saved index with {} tracks", library.files.len()),
+            None,
+        );
+        let attempted = processed_c - skipped_count;
+        counts.processed = attempted - error_c;
+        counts.errors = error_c;
+        counts.remaining = files_to_process.len() - attempted;
+        hooks.scan_complete(&counts);
+
+        // Recommendations keep using the previous neighbor lists until this finishes
+        std::thread::spawn(move || {
+            if let Err(e) = crate::neighbors::rebuild(&index_dir, &analysis_store) {
+                eprintln!("Neighbor precomputation failed: {:#}", e);
+                events::emit(
+                    Level::Error,
+                    "neighbors",
+                    format!("Neighbor precomputation failed: {:#}", e),
+                    None,
+                );
+            }
+        });
 
         Ok(())
     }
@@ -1,22 +1,654 @@
-use anyhow::Result;
-use std::collections::HashSet;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
 use walkdir::WalkDir;
 
-pub fn scan_directory(path: &Path) -> Result<Vec<PathBuf>> {
+use crate::analysis_store::AnalysisStore;
+use crate::storage::{self, AudioLibrary};
+use crate::unicode;
+
+/// Contents of `scan.json` in the index directory, e.g.
+/// `{"extensions": ["mp3", "flac", "opus"], "sniff": true, "include_hidden": false}`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ScanSettings {
+    /// File extensions scanned without looking inside (case-insensitive)
+    pub extensions: Vec<String>,
+    /// Also read the first bytes of every other file and index it when they
+    /// look like audio (misnamed or extensionless files from phone backups)
+    pub sniff: bool,
+    /// Descend into hidden and system entries (see `is_hidden`), which are
+    /// skipped by default
+    pub include_hidden: bool,
+    /// Combined size (MiB) of files decoded at once; a quarter of RAM when unset
+    pub memory_budget_mb: Option<u64>,
+    /// Steps per lowercase extension, e.g. `{"wav": {"lookup": false}}`;
+    /// unlisted extensions get every step
+    pub pipelines: HashMap<String, Pipeline>,
+    /// Registered analyzers (`analyzers::Analyzer`) to run besides bliss, by name
+    pub analyzers: Vec<String>,
+}
+
+/// Processing steps for files of one extension. The fingerprint is always
+/// computed, since duplicates and online matching depend on it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct Pipeline {
+    /// AcoustID / MusicBrainz lookup (when the scan is online)
+    pub lookup: bool,
+    /// Codec, spectrum, clipping and loudness measurement
+    pub quality: bool,
+    /// Bliss feature vector for recommendations and radio, and the analyzers
+    /// enabled in `analyzers`
+    pub analysis: bool,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self {
+            lookup: true,
+            quality: true,
+            analysis: true,
+        }
+    }
+}
+
+impl Default for ScanSettings {
+    fn default() -> Self {
+        Self {
+            extensions: ["mp3", "flac", "wav", "m4a", "ogg"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            sniff: false,
+            include_hidden: false,
+            memory_budget_mb: None,
+            pipelines: HashMap::new(),
+            analyzers: Vec::new(),
+        }
+    }
+}
+
+impl ScanSettings {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).context("Failed to read scan settings")?;
+        let mut settings: Self =
+            serde_json::from_str(&content).context("Failed to parse scan settings JSON")?;
+        settings.pipelines = settings
+            .pipelines
+            .into_iter()
+            .map(|(ext, p)| (ext.trim_start_matches('.').to_lowercase(), p))
+            .collect();
+        Ok(settings)
+    }
+
+    /// Steps to run for `path`, chosen by its extension.
+    pub fn pipeline(&self, path: &Path) -> Pipeline {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| self.pipelines.get(&e.to_lowercase()))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Audio container recognized from the file's magic bytes, if any.
+pub fn sniff(path: &Path) -> Option<&'static str> {
+    let mut header = [0u8; 12];
+    let mut file = File::open(path).ok()?;
+    let mut n = file.read(&mut header).ok()?;
+    let id3 = n >= 10 && header.starts_with(b"ID3");
+
+    // Skip a leading ID3v2 tag (size is syncsafe, 7 bits per byte) and look
+    // at what follows; MP3 usually, but FLAC and AAC files carry them too
+    if id3 {
+        let size = header[6..10]
+            .iter()
+            .fold(0u64, |acc, b| (acc << 7) | (*b & 0x7F) as u64);
+        file.seek(SeekFrom::Start(10 + size)).ok()?;
+        n = file.read(&mut header).ok()?;
+    }
+    let header = &header[..n];
+
+    if header.starts_with(b"fLaC") {
+        return Some("flac");
+    }
+    if header.starts_with(b"OggS") {
+        return Some("ogg");
+    }
+    if n >= 12 && &header[..4] == b"RIFF" && &header[8..12] == b"WAVE" {
+        return Some("wav");
+    }
+    if n >= 12 && &header[..4] == b"FORM" && matches!(&header[8..12], b"AIFF" | b"AIFC") {
+        return Some("aiff");
+    }
+    // Only audio brands; "isom" / "mp42" are mostly video
+    if n >= 12 && &header[4..8] == b"ftyp" && matches!(&header[8..12], b"M4A " | b"M4B " | b"M4P ")
+    {
+        return Some("m4a");
+    }
+    // MPEG frame sync: ADTS AAC has layer bits 00, MP3 layer III 01
+    if n >= 2 && header[0] == 0xFF && header[1] & 0xE0 == 0xE0 {
+        match header[1] & 0x06 {
+            0x00 => return Some("aac"),
+            0x02 => return Some("mp3"),
+            _ => {}
+        }
+    }
+    id3.then_some("mp3")
+}
+
+/// Temporary names used by browsers and download managers for unfinished files
+const PARTIAL_EXTENSIONS: &[&str] = &["part", "partial", "crdownload", "download"];
+/// Files modified this recently are stat'ed a second time before decoding
+const SETTLE_SECS: u64 = 10;
+const SETTLE_DELAY: Duration = Duration::from_secs(1);
+
+/// OS and NAS bookkeeping folders, skipped along with dotfiles
+const SYSTEM_NAMES: &[&str] = &[
+    "system volume information",
+    "$recycle.bin",
+    "@eadir",
+    "#recycle",
+    "#snapshot",
+    "lost+found",
+    "__macosx",
+];
+
+/// Dotfiles and dot-directories (`.Trash`, AppleDouble `._x.mp3`), the
+/// folders in `SYSTEM_NAMES`, and on Windows entries with the hidden or
+/// system attribute.
+fn is_hidden(entry: &walkdir::DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy();
+    if name.starts_with('.') || SYSTEM_NAMES.contains(&name.to_lowercase().as_str()) {
+        return true;
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+        if let Ok(metadata) = entry.metadata() {
+            return metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM)
+                != 0;
+        }
+    }
+    false
+}
+
+fn is_partial_download(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| PARTIAL_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Candidate audio files under `path`, plus unfinished downloads (which
+/// `plan_scan` quarantines instead of decoding).
+pub fn scan_directory(path: &Path, settings: &ScanSettings) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
-    let valid_extensions: HashSet<&str> =
-        ["mp3", "flac", "wav", "m4a", "ogg"].into_iter().collect();
+    let valid_extensions: HashSet<String> = settings
+        .extensions
+        .iter()
+        .map(|e| e.trim_start_matches('.').to_lowercase())
+        .collect();
 
-    for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
+    let walker = WalkDir::new(path)
+        .into_iter()
+        // The scan root itself is never skipped, even if it is hidden
+        .filter_entry(|e| settings.include_hidden || e.depth() == 0 || !is_hidden(e));
+    for entry in walker.filter_map(|e| e.ok()) {
         let path = entry.path();
         if path.is_file() {
-            if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-                if valid_extensions.contains(ext.to_lowercase().as_str()) {
-                    files.push(path.to_path_buf());
-                }
+            let listed = path
+                .extension()
+                .and_then(|s| s.to_str())
+                .is_some_and(|ext| valid_extensions.contains(&ext.to_lowercase()));
+            if listed || is_partial_download(path) || (settings.sniff && sniff(path).is_some()) {
+                files.push(path.to_path_buf());
             }
         }
     }
     Ok(files)
 }
+
+/// Why a file has to be (re)processed.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeReason {
+    NewFile,
+    Modified, // mtime or size changed
+    MissingAnalysis,
+}
+
+impl ChangeReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChangeReason::NewFile => "new file",
+            ChangeReason::Modified => "mtime/size change",
+            ChangeReason::MissingAnalysis => "missing analysis",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PendingFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub mtime: u64,
+    pub reason: ChangeReason,
+}
+
+/// Why a file was left out of a scan. Quarantined files are not indexed,
+/// so the next scan looks at them again.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QuarantineReason {
+    /// Zero bytes on disk
+    Empty,
+    /// `.part` / `.crdownload` and similar unfinished downloads
+    PartialDownload,
+    /// Size or mtime changed between two looks during the scan
+    StillWriting,
+}
+
+impl QuarantineReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            QuarantineReason::Empty => "empty file",
+            QuarantineReason::PartialDownload => "partial download",
+            QuarantineReason::StillWriting => "still being written",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct QuarantinedFile {
+    pub path: PathBuf,
+    pub reason: QuarantineReason,
+}
+
+/// One top-level directory's share of a scan.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct DirStats {
+    /// Processed and indexed
+    pub processed: usize,
+    /// Unchanged since the last scan
+    pub skipped: usize,
+    pub quarantined: usize,
+    pub errors: usize,
+    /// Worker time spent on this directory's files, summed over threads
+    /// (can exceed the wall-clock time of the scan)
+    pub busy_secs: f64,
+}
+
+/// First component of `path` below `input_dir`; "." for files directly in it.
+pub fn top_level_dir(input_dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(input_dir).unwrap_or(path);
+    let mut components = relative.components();
+    match (components.next(), components.next()) {
+        (Some(first), Some(_)) => first.as_os_str().to_string_lossy().into_owned(),
+        _ => ".".to_string(),
+    }
+}
+
+/// Result of the diff phase: what a scan would do, without decoding anything.
+#[derive(Serialize, Debug, Default)]
+pub struct ScanPlan {
+    pub to_process: Vec<PendingFile>,
+    pub unchanged: usize,
+    /// Indexed files under the input directory that no longer exist on disk;
+    /// dropped from the index by `remove_missing`
+    pub removed: Vec<PathBuf>,
+    /// Files skipped until a later scan; indexed copies of them are kept as they were
+    pub quarantined: Vec<QuarantinedFile>,
+    /// (index key, path on disk) for files whose name differs only in Unicode
+    /// normalization (NFC vs NFD) or, on Windows and macOS, in case; see
+    /// `apply_renames`
+    pub renamed: Vec<(PathBuf, PathBuf)>,
+    /// Per top-level directory counts; `processed`, `errors` and `busy_secs`
+    /// are filled in by the caller as files are processed
+    pub directories: BTreeMap<String, DirStats>,
+}
+
+/// Compare the files on disk with the index and analysis store.
+pub fn plan_scan(
+    input_dir: &Path,
+    files: &[PathBuf],
+    library: &AudioLibrary,
+    analysis_store: &AnalysisStore,
+    settings: &ScanSettings,
+) -> ScanPlan {
+    let mut plan = ScanPlan::default();
+    let by_key: HashMap<PathBuf, &PathBuf> = library
+        .files
+        .keys()
+        .map(|p| (storage::path_key(p), p))
+        .collect();
+
+    for path in files {
+        if is_partial_download(path) {
+            plan.quarantined.push(QuarantinedFile {
+                path: path.clone(),
+                reason: QuarantineReason::PartialDownload,
+            });
+            continue;
+        }
+        if let Ok(metadata) = std::fs::metadata(path) {
+            let mtime = metadata
+                .modified()
+                .unwrap_or(SystemTime::UNIX_EPOCH)
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let size = metadata.len();
+            if size == 0 {
+                plan.quarantined.push(QuarantinedFile {
+                    path: path.clone(),
+                    reason: QuarantineReason::Empty,
+                });
+                continue;
+            }
+
+            let key = match library.files.get_key_value(path) {
+                Some((key, _)) => Some(key),
+                None => by_key.get(&storage::path_key(path)).copied(),
+            };
+            if let Some(key) = key.filter(|k| *k != path) {
+                plan.renamed.push((key.clone(), path.clone()));
+            }
+
+            let reason = match key.and_then(|k| library.files.get(k)) {
+                None => Some(ChangeReason::NewFile),
+                Some(indexed) if indexed.modified_time != mtime || indexed.file_size != size => {
+                    Some(ChangeReason::Modified)
+                }
+                // Analysis may be missing if it was added later or failed before
+                // (unless this extension's pipeline doesn't analyze at all)
+                Some(indexed)
+                    if settings.pipeline(path).analysis
+                        && analysis_store.get(&indexed.path).is_none() =>
+                {
+                    Some(ChangeReason::MissingAnalysis)
+                }
+                Some(_) => None,
+            };
+
+            let dir = plan
+                .directories
+                .entry(top_level_dir(input_dir, path))
+                .or_default();
+            match reason {
+                Some(reason) => plan.to_process.push(PendingFile {
+                    path: path.clone(),
+                    size,
+                    mtime,
+                    reason,
+                }),
+                None => {
+                    dir.skipped += 1;
+                    plan.unchanged += 1;
+                }
+            }
+        }
+    }
+
+    quarantine_growing(&mut plan);
+    for file in &plan.quarantined {
+        plan.directories
+            .entry(top_level_dir(input_dir, &file.path))
+            .or_default()
+            .quarantined += 1;
+    }
+
+    // Nothing at all usually means an unmounted drive or share rather than
+    // a deleted library; keep the entries until the files are back
+    if files.is_empty() {
+        return plan;
+    }
+    let on_disk: HashSet<PathBuf> = files.iter().map(|p| storage::path_key(p)).collect();
+    let input_key = storage::path_key(input_dir);
+    plan.removed = library
+        .files
+        .keys()
+        .filter(|p| {
+            let key = storage::path_key(p);
+            key.starts_with(&input_key) && !on_disk.contains(&key)
+        })
+        .cloned()
+        .collect();
+    plan.removed.sort();
+
+    plan
+}
+
+/// Re-key index and analysis entries to the spelling found on disk, so they
+/// are found by path again and a re-processed file replaces its old entry.
+pub fn apply_renames(plan: &ScanPlan, library: &mut AudioLibrary, store: &mut AnalysisStore) {
+    for (from, to) in &plan.renamed {
+        if let Some(mut track) = library.files.remove(from) {
+            track.path = to.clone();
+            library.files.insert(to.clone(), track);
+        }
+        if let Some(vector) = store.data.remove(from) {
+            store.data.insert(to.clone(), vector);
+        }
+    }
+}
+
+/// Drop the index and analysis entries of files deleted from disk
+/// (`plan.removed`). Returns the number of tracks removed.
+pub fn remove_missing(
+    plan: &ScanPlan,
+    library: &mut AudioLibrary,
+    store: &mut AnalysisStore,
+) -> usize {
+    let mut removed = 0;
+    for path in &plan.removed {
+        if library.files.remove(path).is_some() {
+            removed += 1;
+        }
+        store.remove(path);
+    }
+    removed
+}
+
+fn stat(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let mtime = metadata
+        .modified()
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    Some((metadata.len(), mtime))
+}
+
+/// Stat recently modified pending files again after a short pause and move
+/// those that changed (copies or downloads in progress) to `quarantined`.
+fn quarantine_growing(plan: &mut ScanPlan) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    if !plan
+        .to_process
+        .iter()
+        .any(|f| now.saturating_sub(f.mtime) < SETTLE_SECS)
+    {
+        return;
+    }
+    std::thread::sleep(SETTLE_DELAY);
+
+    let mut growing = Vec::new();
+    plan.to_process.retain(|f| {
+        if now.saturating_sub(f.mtime) >= SETTLE_SECS || stat(&f.path) == Some((f.size, f.mtime)) {
+            return true;
+        }
+        growing.push(QuarantinedFile {
+            path: f.path.clone(),
+            reason: QuarantineReason::StillWriting,
+        });
+        false
+    });
+    plan.quarantined.extend(growing);
+}
+
+/// FNV-1a hash of the path in NFC; stable across runs, platforms and Unicode
+/// normalization forms (unlike `DefaultHasher`).
+pub fn stable_path_hash(path: &Path) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in unicode::nfc(&path.to_string_lossy()).as_bytes() {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Sample key of a file: the hash of its path below `input_dir` with `/`
+/// separators, so the same library mounted elsewhere samples the same files.
+fn sample_hash(input_dir: &Path, path: &Path) -> u64 {
+    let relative = path.strip_prefix(input_dir).unwrap_or(path);
+    stable_path_hash(Path::new(&relative.to_string_lossy().replace('\\', "/")))
+}
+
+/// Restrict the pending files to a deterministic subset.
+///
+/// `sample_percent` takes that share of *all* candidate files, spread across the library
+/// by hashing paths relative to `input_dir`; `limit` caps the count. Files processed by
+/// one run are unchanged on the next, so repeating the same command continues where the
+/// last run stopped.
+pub fn select_subset(
+    plan: &mut ScanPlan,
+    input_dir: &Path,
+    total_files: usize,
+    limit: Option<usize>,
+    sample_percent: Option<f64>,
+) {
+    let mut cap = limit.unwrap_or(usize::MAX);
+
+    if let Some(pct) = sample_percent {
+        plan.to_process
+            .sort_by_key(|f| (sample_hash(input_dir, &f.path), f.path.clone()));
+        let sample = ((total_files as f64) * pct / 100.0).ceil() as usize;
+        cap = cap.min(sample);
+    } else {
+        plan.to_process.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    plan.to_process.truncate(cap);
+}
+
+/// Parse "5%", "5" or "0.5%" into a percentage in (0, 100].
+pub fn parse_percent(s: &str) -> Result<f64, String> {
+    let value: f64 = s
+        .trim()
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("invalid percentage: {}", s))?;
+    if value <= 0.0 || value > 100.0 {
+        return Err("percentage must be between 0 and 100".to_string());
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan_of(dir: &str, names: &[&str]) -> ScanPlan {
+        ScanPlan {
+            to_process: names
+                .iter()
+                .map(|n| PendingFile {
+                    path: Path::new(dir).join(n),
+                    size: 1,
+                    mtime: 1,
+                    reason: ChangeReason::NewFile,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn names(plan: &ScanPlan, dir: &str) -> Vec<String> {
+        plan.to_process
+            .iter()
+            .map(|f| {
+                f.path
+                    .strip_prefix(dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_percent_accepts_sign_and_fractions() {
+        assert_eq!(parse_percent("5%"), Ok(5.0));
+        assert_eq!(parse_percent(" 5 "), Ok(5.0));
+        assert_eq!(parse_percent("0.5%"), Ok(0.5));
+        assert_eq!(parse_percent("100"), Ok(100.0));
+    }
+
+    #[test]
+    fn parse_percent_rejects_out_of_range_and_garbage() {
+        assert!(parse_percent("0").is_err());
+        assert!(parse_percent("-1%").is_err());
+        assert!(parse_percent("100.1").is_err());
+        assert!(parse_percent("five").is_err());
+        assert!(parse_percent("%").is_err());
+    }
+
+    #[test]
+    fn limit_takes_first_paths_in_order() {
+        let mut plan = plan_of("/music", &["c.mp3", "a.mp3", "b.mp3"]);
+        select_subset(&mut plan, Path::new("/music"), 3, Some(2), None);
+        assert_eq!(names(&plan, "/music"), ["a.mp3", "b.mp3"]);
+    }
+
+    #[test]
+    fn sample_is_share_of_all_files_capped_by_limit() {
+        let files: Vec<String> = (0..40).map(|i| format!("{}.flac", i)).collect();
+        let refs: Vec<&str> = files.iter().map(String::as_str).collect();
+
+        let mut plan = plan_of("/music", &refs);
+        select_subset(&mut plan, Path::new("/music"), 100, None, Some(10.0));
+        assert_eq!(plan.to_process.len(), 10);
+
+        let mut plan = plan_of("/music", &refs);
+        select_subset(&mut plan, Path::new("/music"), 100, Some(3), Some(10.0));
+        assert_eq!(plan.to_process.len(), 3);
+    }
+
+    #[test]
+    fn sample_does_not_depend_on_mount_point() {
+        let files: Vec<String> = (0..50).map(|i| format!("Artist {}/t.mp3", i)).collect();
+        let refs: Vec<&str> = files.iter().map(String::as_str).collect();
+
+        let mut here = plan_of("/mnt/music", &refs);
+        select_subset(&mut here, Path::new("/mnt/music"), 50, None, Some(20.0));
+        let mut there = plan_of("/media/nas/Music", &refs);
+        select_subset(
+            &mut there,
+            Path::new("/media/nas/Music"),
+            50,
+            None,
+            Some(20.0),
+        );
+
+        assert_eq!(here.to_process.len(), 10);
+        assert_eq!(
+            names(&here, "/mnt/music"),
+            names(&there, "/media/nas/Music")
+        );
+    }
+}
@@ -1,73 +1,1106 @@
 use axum::{
-    extract::{self, Query, State},
-    response::{Html, IntoResponse, Json},
-    routing::{get, post},
+    extract::{self, DefaultBodyLimit, Multipart, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Json, Response,
+    },
+    routing::{delete, get, post, put},
     Router,
 };
+use serde::Serialize;
 use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::net::TcpListener;
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tower_http::services::ServeFile;
+use utoipa::{IntoParams, OpenApi, ToSchema};
 
-use crate::html_template::HTML_CONTENT;
-use crate::scan_manager::ScanManager;
-use crate::storage::{AudioLibrary, IndexedTrack};
+use crate::analysis_store::SharedAnalysis;
+use crate::artists::ArtistProfile;
+use crate::bulk_edit::{self, BulkEditRequest, BulkEditResult, FieldChange, FieldChanges};
+use crate::columns::{self, SortKey, SortOrder};
+use crate::consistency::{
+    self, ConsistencyReport, FieldDisagreement, FolderIssue, ScatteredAlbum, ValueCount,
+};
+use crate::download::{self, DownloadRequest};
+use crate::events::{self, Level, LogEvent};
+use crate::html_template::{HTML_CONTENT, ICON_SVG, MANIFEST, SERVICE_WORKER};
+use crate::i18n;
+use crate::index_writer::IndexWriter;
+use crate::issues::{self, Issue, IssueKind, IssueReport};
+use crate::matching::MatchCandidate;
+use crate::near_duplicates::{NearDuplicate, PairTrack};
+use crate::neighbors::SharedNeighbors;
+use crate::organizer;
+use crate::playlist::{self, PlaylistQuery};
+use crate::presets::{FilterPreset, PresetStore};
+use crate::prompt::{Energy, PromptConstraints};
+use crate::quality::{self, RankedCopy};
+use crate::radio::{PlayHistory, RadioOptions};
+use crate::rate_limit::{self, RateLimiter};
+use crate::reports::{self, FileReport, ReportEntry, ReportKind, ReportOptions};
+use crate::scan_manager::{ResourceStats, ScanManager, ScanProgress};
+use crate::share::{self, ShareLink, ShareStore, ShareTarget};
+use crate::similarity::{self, ScoreMode, SimilaritySettings};
+use crate::spoken_word::ContentKind;
+use crate::storage::{AudioLibrary, IndexedTrack, LibrarySnapshot, SharedLibrary};
+use crate::timeline::{self, FormatUsage, ScanSnapshot, StorageTimeline, UsageEntry};
+use crate::upload::{self, RejectedUpload, UploadResult};
+use crate::works::{self, WorkNode, WorkRecording};
+
+/// Deployment options for `serve`.
+pub struct ServerOptions {
+    pub port: u16,
+    /// Public path prefix when served behind a reverse proxy, e.g. `/music`
+    pub base_path: String,
+    /// External URL of the app including `base_path`, e.g.
+    /// `https://example.com/music`, for absolute URLs in playlists
+    pub public_url: Option<String>,
+    /// Take the client's scheme, host and IP (for rate limiting) from
+    /// `X-Forwarded-Proto` / `-Host` / `-For`; only safe when a reverse
+    /// proxy sets them
+    pub trust_proxy: bool,
+    /// Origins allowed to call the API cross-origin; `*` allows any
+    pub cors_origins: Vec<String>,
+    /// Mutation requests allowed per client IP per minute (0 disables the limit)
+    pub mutation_rate_per_minute: u32,
+    /// Maximum request body size accepted by mutation endpoints
+    pub max_body_bytes: usize,
+    /// Interval for retrying `pending_lookups.json` in the background (0 disables)
+    pub lookup_retry_mins: u64,
+    /// Where `POST /api/upload` writes files; `<input_dir>/incoming` when unset
+    pub incoming_dir: Option<PathBuf>,
+    /// Bearer token required by `POST /api/upload`; uploads are disabled without one
+    pub upload_token: Option<String>,
+    /// Maximum request body size accepted by `POST /api/upload`
+    pub max_upload_bytes: usize,
+    /// Port for the gRPC control API, if enabled
+    #[cfg(feature = "grpc")]
+    pub grpc_port: Option<u16>,
+}
 
-fn euclidean_distance(a: &[f32], b: &[f32]) -> f32 {
-    if a.len() != b.len() {
-        return f32::NAN;
+/// Normalize a user-supplied base path to `/prefix` form (empty for the root).
+pub fn normalize_base_path(path: &str) -> String {
+    let trimmed = path.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", trimmed)
     }
-    a.iter()
-        .zip(b.iter())
-        .map(|(x, y)| (x - y).powi(2))
-        .sum::<f32>()
-        .sqrt()
+}
+
+fn cors_layer(origins: &[String]) -> Option<CorsLayer> {
+    if origins.is_empty() {
+        return None;
+    }
+    let allow_origin = if origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            origins
+                .iter()
+                .filter_map(|o| HeaderValue::from_str(o.trim_end_matches('/')).ok()),
+        )
+    };
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods(Any)
+            .allow_headers(Any),
+    )
 }
 
 struct AppState {
     index_path: PathBuf,
+    base_path: String,
+    public_url: Option<String>,
+    trust_proxy: bool,
+    library: Arc<SharedLibrary>,
+    /// `analysis.bin`, kept in memory for recommendations and track details
+    analysis: SharedAnalysis,
+    /// `neighbors.bin`, precomputed recommendation lists
+    neighbors: SharedNeighbors,
     input_dir: Option<PathBuf>,
     scan_manager: Arc<ScanManager>,
+    /// Applies every change to `index.json` (scans, bulk edits, lookup retries)
+    writer: IndexWriter,
+    /// Serializes writes to `play_history.json`
+    history_lock: tokio::sync::Mutex<()>,
+    /// Serializes writes to `filter_presets.json`
+    presets_lock: tokio::sync::Mutex<()>,
+    /// Serializes writes to `shares.json`
+    shares_lock: tokio::sync::Mutex<()>,
+    incoming_dir: Option<PathBuf>,
+    upload_token: Option<String>,
 }
 
-pub async fn start_server(index_dir: PathBuf, input_dir: Option<PathBuf>, port: u16) {
+pub async fn start_server(index_dir: PathBuf, input_dir: Option<PathBuf>, options: ServerOptions) {
     let index_path = index_dir.join("index.json");
     let scan_manager = Arc::new(ScanManager::new());
+    let library = Arc::new(SharedLibrary::new(index_path.clone()));
+    let writer = IndexWriter::spawn(index_path.clone(), library.clone());
 
+    let base_path = normalize_base_path(&options.base_path);
+
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_port) = options.grpc_port {
+        let service = crate::grpc::GrpcService {
+            index_dir: index_dir.clone(),
+            input_dir: input_dir.clone(),
+            scan_manager: scan_manager.clone(),
+            library: library.clone(),
+            writer: writer.clone(),
+        };
+        tokio::spawn(crate::grpc::serve(service, grpc_port));
+    }
+
+    let analysis = SharedAnalysis::new(index_dir.join("analysis.bin"));
+    if crate::neighbors::is_stale(&index_dir) {
+        let dir = index_dir.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = crate::analysis_store::AnalysisStore::load(&dir.join("analysis.bin"))
+                .and_then(|store| crate::neighbors::rebuild(&dir, &store));
+            if let Err(e) = result {
+                eprintln!("Neighbor precomputation failed: {:#}", e);
+            }
+        });
+    }
+    let neighbors = SharedNeighbors::new(index_dir.join("neighbors.bin"));
+    let incoming_dir = options
+        .incoming_dir
+        .clone()
+        .or_else(|| input_dir.as_ref().map(|d| d.join("incoming")));
     let state = Arc::new(AppState {
         index_path,
+        base_path: base_path.clone(),
+        public_url: options
+            .public_url
+            .as_deref()
+            .map(|u| u.trim().trim_end_matches('/').to_string())
+            .filter(|u| !u.is_empty()),
+        trust_proxy: options.trust_proxy,
+        library,
+        analysis,
+        neighbors,
         input_dir,
-        scan_manager,
+        scan_manager: scan_manager.clone(),
+        writer,
+        history_lock: tokio::sync::Mutex::new(()),
+        presets_lock: tokio::sync::Mutex::new(()),
+        shares_lock: tokio::sync::Mutex::new(()),
+        incoming_dir,
+        upload_token: options.upload_token.clone().filter(|t| !t.is_empty()),
     });
 
-    let app = Router::new()
+    if options.lookup_retry_mins > 0 {
+        if let Ok(client_id) = std::env::var("ACOUSTID_CLIENT_ID") {
+            tokio::spawn(retry_pending_lookups(
+                state.clone(),
+                client_id,
+                Duration::from_secs(options.lookup_retry_mins * 60),
+            ));
+        }
+    }
+
+    // Endpoints that start work or change state are rate limited per client IP
+    let limiter = Arc::new(RateLimiter::new(
+        options.mutation_rate_per_minute,
+        options.trust_proxy,
+    ));
+    // Forget clients once their bucket is full again
+    tokio::spawn(rate_limit::sweep_periodically(
+        limiter.clone(),
+        Duration::from_secs(60),
+    ));
+    // Uploads get their own body limit; the rest of the mutations stay small
+    let uploads = Router::new()
+        .route("/api/upload", post(upload_files))
+        .layer(DefaultBodyLimit::max(options.max_upload_bytes));
+    let mutations = Router::new()
+        .route("/api/scan/start", post(start_scan))
+        .route("/api/library/reload", post(reload_library))
+        .route("/api/snapshots", post(create_snapshot))
+        .route("/api/snapshots/{generation}", delete(release_snapshot))
+        .route("/api/plays", post(record_play))
+        .route("/api/download", post(download_tracks))
+        .route("/api/shares", post(create_share))
+        .route("/api/shares/{token}", delete(revoke_share))
+        .route(
+            "/api/presets/{name}",
+            put(save_preset).delete(delete_preset),
+        )
+        .route("/api/tracks/bulk", post(bulk_edit_tracks))
+        .route("/api/tracks/bulk/undo", post(undo_bulk_edit))
+        .layer(DefaultBodyLimit::max(options.max_body_bytes))
+        .merge(uploads)
+        .route_layer(middleware::from_fn_with_state(
+            limiter,
+            rate_limit::limit_by_ip,
+        ));
+
+    let routes = Router::new()
         .route("/", get(serve_index))
+        .route("/manifest.webmanifest", get(serve_manifest))
+        .route("/sw.js", get(serve_service_worker))
+        .route("/icon.svg", get(serve_icon))
+        .route("/api/openapi.json", get(serve_openapi))
+        .route("/api/docs", get(serve_api_docs))
+        .route("/api/locales", get(get_locales))
+        .route("/api/locales/{lang}", get(get_locale))
         .route("/api/tracks", get(serve_tracks))
-        .route("/api/scan/start", post(start_scan))
+        .route("/api/tracks/recent", get(get_recent_tracks))
+        .route("/api/track/{id}", get(get_track_detail))
+        .route("/api/track/{id}/waveform", get(get_track_waveform))
+        .route("/api/track/{id}/stream", get(stream_track))
+        .route("/track/{id}", get(serve_index))
         .route("/api/scan/status", get(get_scan_status))
+        .route("/api/scan/stream", get(stream_scan_progress))
+        .route("/api/events", get(stream_events))
         .route("/api/duplicates", get(get_duplicates))
+        .route("/api/duplicates/near", get(get_near_duplicates))
+        .route("/api/albums/consistency", get(get_album_consistency))
+        .route("/api/issues", get(get_issues))
+        .route("/api/reports/files", get(get_file_report))
         .route("/api/recommend", get(get_recommendations))
-        .with_state(state);
+        .route("/api/radio", get(get_radio))
+        .route("/api/artists", get(get_artists))
+        .route("/api/artists/{id}/similar", get(get_similar_artists))
+        .route("/api/artists/{id}/radio", get(get_artist_radio))
+        .route("/api/works", get(get_works))
+        .route("/api/stats", get(get_stats))
+        .route("/api/presets", get(get_presets))
+        .route("/api/stats/timeline", get(get_stats_timeline))
+        .route("/playlist.m3u", get(get_playlist))
+        .route("/api/playlist/prompt", get(get_prompt_playlist))
+        .route("/playlists/{name}", get(get_stored_playlist))
+        .route("/share/{token}", get(open_share))
+        .route("/share/{token}/{n}", get(open_share_item))
+        .merge(mutations);
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    println!("Web Dashboard available at http://{}", addr);
+    let routes = if base_path.is_empty() {
+        routes
+    } else {
+        // Nesting only matches `/music`; proxies usually forward `/music/`
+        Router::new()
+            .route(&format!("{}/", base_path), get(serve_index))
+            .nest(&base_path, routes)
+    };
+    let mut app = routes.layer(CompressionLayer::new()).with_state(state);
+    if let Some(cors) = cors_layer(&options.cors_origins) {
+        app = app.layer(cors);
+    }
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], options.port));
+    println!("Web Dashboard available at http://{}{}/", addr, base_path);
 
     let listener = TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    crate::service::notify_ready();
+    // Peer addresses are needed for per-IP rate limiting
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(crate::service::shutdown_signal())
+    .await
+    .unwrap();
+
+    // A running scan finishes its current batch and saves the index first
+    crate::service::notify_stopping();
+    if scan_manager.get_progress().is_scanning {
+        println!("Waiting for the running scan to save the index...");
+    }
+    scan_manager.stop_and_wait().await;
+    println!("Server stopped.");
+}
+
+async fn serve_index(State(state): State<Arc<AppState>>) -> Html<String> {
+    Html(HTML_CONTENT.replace("__BASE_PATH__", &state.base_path))
+}
+
+async fn serve_manifest(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/manifest+json")],
+        MANIFEST.replace("__BASE_PATH__", &state.base_path),
+    )
+}
+
+async fn serve_service_worker(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "text/javascript"),
+            // So an upgraded server's worker replaces the installed one
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        SERVICE_WORKER.replace("__BASE_PATH__", &state.base_path),
+    )
+}
+
+async fn serve_icon() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "image/svg+xml")], ICON_SVG)
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Audio Sorter API"),
+    paths(
+        get_locales,
+        get_locale,
+        serve_tracks,
+        get_recent_tracks,
+        get_track_detail,
+        get_track_waveform,
+        stream_track,
+        start_scan,
+        get_scan_status,
+        stream_scan_progress,
+        stream_events,
+        reload_library,
+        create_snapshot,
+        release_snapshot,
+        bulk_edit_tracks,
+        undo_bulk_edit,
+        upload_files,
+        get_duplicates,
+        get_near_duplicates,
+        get_album_consistency,
+        get_issues,
+        get_file_report,
+        get_stats,
+        get_stats_timeline,
+        get_works,
+        get_recommendations,
+        get_radio,
+        get_artists,
+        get_similar_artists,
+        get_artist_radio,
+        record_play,
+        download_tracks,
+        create_share,
+        revoke_share,
+        open_share,
+        open_share_item,
+        get_presets,
+        save_preset,
+        delete_preset,
+        get_playlist,
+        get_prompt_playlist,
+        get_stored_playlist
+    ),
+    components(schemas(
+        IndexedTrack,
+        TrackRow,
+        SortKey,
+        SortOrder,
+        organizer::TrackMetadata,
+        organizer::MetadataSource,
+        MatchCandidate,
+        quality::AudioQuality,
+        ContentKind,
+        RankedCopy,
+        NearDuplicate,
+        PairTrack,
+        ConsistencyReport,
+        FolderIssue,
+        FieldDisagreement,
+        ValueCount,
+        ScatteredAlbum,
+        IssueReport,
+        Issue,
+        IssueKind,
+        FileReport,
+        ReportEntry,
+        ReportKind,
+        RecentTrack,
+        TrackDetail,
+        BulkEditRequest,
+        BulkEditResult,
+        FieldChanges,
+        FieldChange,
+        ScanProgress,
+        ResourceStats,
+        SnapshotInfo,
+        ScoreMode,
+        StorageTimeline,
+        ScanSnapshot,
+        FormatUsage,
+        UsageEntry,
+        WorkNode,
+        WorkRecording,
+        Recommendation,
+        PlayRequest,
+        DownloadRequest,
+        ShareRequest,
+        ShareCreated,
+        ShareLink,
+        ShareTarget,
+        LogEvent,
+        Level,
+        FilterPreset,
+        UploadResult,
+        RejectedUpload,
+        PromptPlaylist,
+        PromptConstraints,
+        Energy,
+        ArtistProfile,
+        SimilarArtist,
+        LocaleList
+    ))
+)]
+struct ApiDoc;
+
+async fn serve_openapi(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let mut doc = ApiDoc::openapi();
+    if !state.base_path.is_empty() {
+        doc.servers = Some(vec![utoipa::openapi::Server::new(&state.base_path)]);
+    }
+    Json(doc)
+}
+
+async fn serve_api_docs(State(state): State<Arc<AppState>>) -> Html<String> {
+    Html(SWAGGER_UI_HTML.replace("__BASE_PATH__", &state.base_path))
+}
+
+#[derive(Serialize, ToSchema)]
+struct LocaleList {
+    /// e.g. `["en", "zh-CN", "ja"]`
+    available: Vec<&'static str>,
+    /// Best match for the request's `Accept-Language`, English if none
+    preferred: &'static str,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/locales",
+    responses((status = 200, description = "Dashboard languages and the one the browser asks for", body = LocaleList))
+)]
+async fn get_locales(headers: HeaderMap) -> Json<LocaleList> {
+    let accept = headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    Json(LocaleList {
+        available: i18n::LOCALES.iter().map(|(name, _)| *name).collect(),
+        preferred: i18n::negotiate(accept),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/locales/{lang}",
+    params(("lang" = String, Path, description = "Locale, e.g. `zh-CN`")),
+    responses(
+        (status = 200, description = "Flat map of message keys to translated strings with `{name}` placeholders", body = BTreeMap<String, String>),
+        (status = 404, description = "Unsupported locale")
+    )
+)]
+async fn get_locale(extract::Path(lang): extract::Path<String>) -> Response {
+    match i18n::catalog(&lang) {
+        Some(json) => ([(header::CONTENT_TYPE, "application/json")], json).into_response(),
+        None => (StatusCode::NOT_FOUND, "Unsupported locale").into_response(),
+    }
+}
+
+// Swagger UI from a CDN, like the dashboard's Vue/Tailwind
+const SWAGGER_UI_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>Audio Sorter API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+    <script>
+        SwaggerUIBundle({ url: '__BASE_PATH__/api/openapi.json', dom_id: '#swagger-ui' });
+    </script>
+</body>
+</html>
+"#;
+
+/// Serve `body` as JSON unless the client already holds this index version.
+/// The validator is the index file's modification time, so responses only change
+/// after a scan or reload; `body` isn't even built for a 304.
+fn cached_json<T: Serialize>(
+    headers: &HeaderMap,
+    modified: Option<SystemTime>,
+    body: impl FnOnce() -> T,
+) -> Response {
+    cached_json_with_analysis(headers, modified, None, body)
+}
+
+/// `cached_json` for responses that also read the analysis store: its
+/// modification time is part of the validator, so a reanalyze that leaves
+/// the index untouched still invalidates them.
+fn cached_json_with_analysis<T: Serialize>(
+    headers: &HeaderMap,
+    modified: Option<SystemTime>,
+    analysis_modified: Option<SystemTime>,
+    body: impl FnOnce() -> T,
+) -> Response {
+    let Some(index_modified) = modified else {
+        return Json(body()).into_response();
+    };
+
+    let nanos = |t: SystemTime| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    // Weak because the compression layer may re-encode the body
+    let etag = match analysis_modified {
+        Some(analysis) => format!("W/\"{:x}-{:x}\"", nanos(index_modified), nanos(analysis)),
+        None => format!("W/\"{:x}\"", nanos(index_modified)),
+    };
+    let modified = analysis_modified.map_or(index_modified, |a| a.max(index_modified));
+
+    let not_modified = match headers.get(header::IF_NONE_MATCH) {
+        Some(value) => value.to_str().is_ok_and(|v| {
+            v.split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == &etag[2..])
+        }),
+        None => headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| httpdate::parse_http_date(v).ok())
+            // HTTP dates only have second precision
+            .is_some_and(|since| {
+                modified
+                    .duration_since(since)
+                    .map_or(true, |d| d.as_secs() == 0)
+            }),
+    };
+
+    let mut response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        Json(body()).into_response()
+    };
+
+    let out = response.headers_mut();
+    if let Ok(v) = HeaderValue::from_str(&etag) {
+        out.insert(header::ETAG, v);
+    }
+    if let Ok(v) = HeaderValue::from_str(&httpdate::fmt_http_date(modified)) {
+        out.insert(header::LAST_MODIFIED, v);
+    }
+    // Always revalidate; the 304 keeps that cheap
+    out.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SnapshotParam {
+    /// Read a pinned snapshot (from `POST /api/snapshots`) instead of the live index
+    snapshot: Option<u64>,
+}
+
+/// The pinned snapshot named by `?snapshot=`, or the live library.
+/// `None` when that snapshot is gone.
+fn library_view(state: &AppState, param: &SnapshotParam) -> Option<LibrarySnapshot> {
+    match param.snapshot {
+        None => Some(state.library.snapshot()),
+        Some(generation) => state.library.pinned(generation),
+    }
+}
+
+fn snapshot_gone(param: &SnapshotParam) -> Response {
+    (
+        StatusCode::GONE,
+        format!(
+            "Snapshot {} was released or has expired",
+            param.snapshot.unwrap_or_default()
+        ),
+    )
+        .into_response()
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TrackParams {
+    /// Comma-separated vocal languages, e.g. `ja` or `zxx,en`
+    vocal_language: Option<String>,
+    year: Option<u32>,
+    /// First year of the decade, e.g. `1990`
+    decade: Option<u32>,
+    /// Comma-separated genres, e.g. `jazz` or `electronic/house`; a parent
+    /// genre also matches its sub-genres
+    genre: Option<String>,
+    /// Exact artist, ignoring ASCII case
+    artist: Option<String>,
+    /// Exact album, ignoring ASCII case
+    album: Option<String>,
+    /// Exact Chromaprint fingerprint
+    fingerprint: Option<String>,
+    /// Column to sort by instead of artist / album / disc / track
+    sort: Option<SortKey>,
+    #[serde(default)]
+    order: SortOrder,
+}
+
+impl TrackParams {
+    /// The first filter `index.db` has a column index for.
+    #[cfg(feature = "sqlite")]
+    fn indexed_filter(&self) -> Option<(crate::sqlite_index::Field, String)> {
+        use crate::sqlite_index::Field;
+        [
+            (Field::Fingerprint, &self.fingerprint),
+            (Field::Album, &self.album),
+            (Field::Artist, &self.artist),
+        ]
+        .into_iter()
+        .find_map(|(field, value)| Some((field, value.clone()?)))
+    }
+}
+
+/// The `/api/tracks` filters, parsed once per request.
+struct TrackFilter<'a> {
+    params: &'a TrackParams,
+    languages: Option<Vec<String>>,
+    genres: Option<BTreeSet<String>>,
+}
+
+impl<'a> TrackFilter<'a> {
+    fn new(params: &'a TrackParams) -> Self {
+        let languages = params.vocal_language.as_ref().map(|l| {
+            l.split(',')
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect()
+        });
+        let genres = params.genre.as_ref().map(|g| {
+            organizer::genre_list(Some(g))
+                .into_iter()
+                .map(|g| g.to_lowercase())
+                .collect()
+        });
+        Self {
+            params,
+            languages,
+            genres,
+        }
+    }
+
+    fn matches(&self, t: &IndexedTrack) -> bool {
+        let params = self.params;
+        let meta = &t.metadata;
+        let same_text = |wanted: &Option<String>, value: Option<&str>| match wanted {
+            Some(w) => value.is_some_and(|v| v.eq_ignore_ascii_case(w)),
+            None => true,
+        };
+        let language_ok = match &self.languages {
+            Some(langs) => meta
+                .vocal_language
+                .as_ref()
+                .is_some_and(|l| langs.contains(l)),
+            None => true,
+        };
+        let decade_ok = match params.decade {
+            Some(d) => meta.year.map(organizer::decade) == Some(d),
+            None => true,
+        };
+        let genre_ok = match &self.genres {
+            Some(wanted) => organizer::genre_terms(meta.genre.as_deref())
+                .iter()
+                .any(|g| wanted.contains(g)),
+            None => true,
+        };
+        language_ok
+            && (params.year.is_none() || meta.year == params.year)
+            && decade_ok
+            && genre_ok
+            && same_text(&params.artist, Some(&meta.artist))
+            && same_text(&params.album, meta.album.as_deref())
+            && (params.fingerprint.is_none() || meta.fingerprint == params.fingerprint)
+    }
+
+    /// Matching tracks from `tracks` (in the default order) as response rows.
+    fn rows(
+        &self,
+        tracks: Vec<&IndexedTrack>,
+        bpm: impl Fn(&IndexedTrack) -> Option<f32>,
+    ) -> Vec<TrackRow> {
+        let tracks: Vec<&IndexedTrack> = tracks.into_iter().filter(|t| self.matches(t)).collect();
+        let tracks = match self.params.sort {
+            Some(key) => columns::sort(tracks, key, self.params.order, &bpm),
+            None => tracks,
+        };
+        tracks
+            .into_iter()
+            .map(|t| TrackRow {
+                genres: organizer::genre_list(t.metadata.genre.as_deref()),
+                bpm: bpm(t),
+                track: t.clone(),
+            })
+            .collect()
+    }
+}
+
+/// An `/api/tracks` entry: the indexed track plus values derived from its analysis.
+#[derive(Serialize, ToSchema)]
+struct TrackRow {
+    #[serde(flatten)]
+    track: IndexedTrack,
+    /// Genres of the tag as written, split into a list
+    genres: Vec<String>,
+    /// Tempo estimated from the bliss analysis; absent until analyzed
+    bpm: Option<f32>,
+}
+
+/// With the `sqlite` feature, an artist, album or fingerprint filter on the
+/// live index is answered through the `index.db` column indexes instead of
+/// walking the whole library.
+#[utoipa::path(
+    get,
+    path = "/api/tracks",
+    params(TrackParams, SnapshotParam),
+    responses(
+        (status = 200, description = "Indexed tracks", body = Vec<TrackRow>),
+        (status = 304, description = "Index unchanged since the given ETag/date"),
+        (status = 410, description = "The requested snapshot was released or has expired")
+    )
+)]
+async fn serve_tracks(
+    State(state): State<Arc<AppState>>,
+    Query(params): extract::Query<TrackParams>,
+    Query(snapshot): extract::Query<SnapshotParam>,
+    headers: HeaderMap,
+) -> Response {
+    let filter = TrackFilter::new(&params);
+    let (analysis_modified, analysis) = state.analysis.get_with_modified();
+    let bpm = |t: &IndexedTrack| columns::estimated_bpm(analysis.get(&t.path)?);
+
+    #[cfg(feature = "sqlite")]
+    if let (None, Some((field, value))) = (snapshot.snapshot, params.indexed_filter()) {
+        let index_path = state.index_path.clone();
+        let modified = crate::storage::index_mtime(&index_path);
+        let found = tokio::task::spawn_blocking(move || {
+            AudioLibrary::find_stored(&index_path, field, &value)
+        })
+        .await;
+        match found {
+            Ok(Some(Ok(mut found))) => {
+                found.sort_by(crate::storage::compare_tracks);
+                return cached_json_with_analysis(&headers, modified, analysis_modified, || {
+                    filter.rows(found.iter().collect(), bpm)
+                });
+            }
+            Ok(Some(Err(e))) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+            // Still kept in index.json
+            Ok(None) => {}
+        }
+    }
+
+    let Some(view) = library_view(&state, &snapshot) else {
+        return snapshot_gone(&snapshot);
+    };
+    let library = view.library;
+    cached_json_with_analysis(&headers, view.modified, analysis_modified, || {
+        filter.rows(library.sorted_tracks(), bpm)
+    })
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct RecentParams {
+    /// Look-back window in days (default 7)
+    #[serde(default = "default_recent_days")]
+    days: u64,
+}
+
+fn default_recent_days() -> u64 {
+    7
+}
+
+#[derive(serde::Serialize, ToSchema)]
+struct RecentTrack {
+    #[serde(flatten)]
+    track: IndexedTrack,
+    /// "added" (new to the index) or "updated" (re-processed after a change)
+    #[schema(value_type = String)]
+    change: &'static str,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/tracks/recent",
+    params(RecentParams),
+    responses((status = 200, description = "Recently added or updated tracks, newest first", body = Vec<RecentTrack>))
+)]
+async fn get_recent_tracks(
+    State(state): State<Arc<AppState>>,
+    Query(params): extract::Query<RecentParams>,
+) -> Json<Vec<RecentTrack>> {
+    let library = state.library.get();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let cutoff = now.saturating_sub(params.days.saturating_mul(24 * 60 * 60));
+
+    let mut recent: Vec<RecentTrack> = library
+        .files
+        .values()
+        .filter(|t| t.scanned_at >= cutoff)
+        .map(|t| RecentTrack {
+            change: if t.first_seen() >= cutoff {
+                "added"
+            } else {
+                "updated"
+            },
+            track: t.clone(),
+        })
+        .collect();
+    recent.sort_by(|a, b| {
+        b.track
+            .scanned_at
+            .cmp(&a.track.scanned_at)
+            .then(a.track.path.cmp(&b.track.path))
+    });
+
+    Json(recent)
 }
 
-async fn serve_index() -> Html<&'static str> {
-    Html(HTML_CONTENT)
+#[derive(serde::Serialize, ToSchema)]
+struct TrackDetail {
+    #[serde(flatten)]
+    track: IndexedTrack,
+    /// Genres of the tag as written, split into a list
+    genres: Vec<String>,
+    /// bliss feature vector, when the track has been analyzed
+    analysis: Option<Vec<f32>>,
+    /// Label for each entry of `analysis`
+    feature_names: Vec<String>,
+    /// Gain (dB) to reach -18 LUFS, when loudness was measured
+    replay_gain_db: Option<f32>,
 }
 
-async fn serve_tracks(State(state): State<Arc<AppState>>) -> Json<Vec<IndexedTrack>> {
-    match AudioLibrary::load(&state.index_path) {
-        Ok(lib) => Json(lib.files.into_values().collect()),
-        Err(_) => Json(vec![]),
+#[utoipa::path(
+    get,
+    path = "/api/track/{id}",
+    params(("id" = String, Path, description = "Track ID")),
+    responses(
+        (status = 200, description = "Full metadata, match provenance, stream info and analysis", body = TrackDetail),
+        (status = 404, description = "Unknown track ID")
+    )
+)]
+async fn get_track_detail(
+    State(state): State<Arc<AppState>>,
+    extract::Path(id): extract::Path<String>,
+) -> Response {
+    let library = state.library.get();
+    let Some(track) = library.find_by_id(&id) else {
+        return (StatusCode::NOT_FOUND, "Unknown track ID").into_response();
+    };
+
+    let analysis = state.analysis.get().get(&track.path).cloned();
+    let feature_names = analysis
+        .as_ref()
+        .map(|a| crate::analysis_store::feature_names(a.len()))
+        .unwrap_or_default();
+
+    Json(TrackDetail {
+        genres: organizer::genre_list(track.metadata.genre.as_deref()),
+        replay_gain_db: track
+            .metadata
+            .quality
+            .as_ref()
+            .and_then(|q| q.replay_gain_db()),
+        track: track.clone(),
+        analysis,
+        feature_names,
+    })
+    .into_response()
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct WaveformParams {
+    /// Number of peak values to return (default 800)
+    #[serde(default = "default_waveform_points")]
+    points: usize,
+}
+
+fn default_waveform_points() -> usize {
+    800
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/track/{id}/waveform",
+    params(("id" = String, Path, description = "Track ID"), WaveformParams),
+    responses(
+        (status = 200, description = "Peak envelope, one value in 0..1 per point", body = Vec<f32>),
+        (status = 404, description = "Unknown track ID")
+    )
+)]
+async fn get_track_waveform(
+    State(state): State<Arc<AppState>>,
+    extract::Path(id): extract::Path<String>,
+    Query(params): extract::Query<WaveformParams>,
+) -> Response {
+    let library = state.library.get();
+    let Some(track) = library.find_by_id(&id) else {
+        return (StatusCode::NOT_FOUND, "Unknown track ID").into_response();
+    };
+
+    let path = track.path.clone();
+    let duration = track.metadata.duration;
+    let points = params.points.clamp(1, 4000);
+    // Decoding the whole file is CPU bound
+    let result =
+        tokio::task::spawn_blocking(move || crate::decode::waveform(&path, duration, points)).await;
+    match result {
+        Ok(Ok(peaks)) => Json(peaks).into_response(),
+        Ok(Err(e)) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/track/{id}/stream",
+    params(("id" = String, Path, description = "Track ID")),
+    responses(
+        (status = 200, description = "The track's audio file, with range support"),
+        (status = 404, description = "Unknown track ID")
+    )
+)]
+async fn stream_track(
+    State(state): State<Arc<AppState>>,
+    extract::Path(id): extract::Path<String>,
+    request: extract::Request,
+) -> Response {
+    let path = match state.library.get().find_by_id(&id) {
+        Some(track) => track.path.clone(),
+        None => return (StatusCode::NOT_FOUND, "Unknown track ID").into_response(),
+    };
+    serve_audio(&path, request).await
+}
+
+/// Background retry of `pending_lookups.json` while the server runs. Skips
+/// rounds during scans, which rewrite the queue; merges go through the writer.
+async fn retry_pending_lookups(state: Arc<AppState>, client_id: String, interval: Duration) {
+    let index_dir = state.index_path.parent().unwrap().to_path_buf();
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        if state.scan_manager.get_progress().is_scanning
+            || !index_dir.join("pending_lookups.json").exists()
+        {
+            continue;
+        }
+        let dir = index_dir.clone();
+        let id = client_id.clone();
+        let writer = state.writer.clone();
+        match tokio::task::spawn_blocking(move || {
+            crate::lookup_queue::flush(&writer, &dir, &id, None)
+        })
+        .await
+        {
+            Ok(Ok(summary)) if summary.resolved + summary.no_match + summary.dropped > 0 => {
+                let message = format!(
+                    "Lookup retry: {} resolved, {} no match, {} still pending",
+                    summary.resolved, summary.no_match, summary.still_pending
+                );
+                println!("{}", message);
+                events::emit(Level::Info, "lookup", message, None);
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => {
+                eprintln!("Lookup retry failed: {:#}", e);
+                events::emit(
+                    Level::Error,
+                    "lookup",
+                    format!("Lookup retry failed: {:#}", e),
+                    None,
+                );
+            }
+            Err(e) => eprintln!("Lookup retry task failed: {}", e),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct EventParams {
+    /// Only replay buffered events with a higher `seq` (browsers send this
+    /// as `Last-Event-ID` when reconnecting)
+    since: Option<u64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    params(EventParams),
+    responses((status = 200, description = "Server-sent `log` events, each a JSON LogEvent; recent ones are replayed first", content_type = "text/event-stream"))
+)]
+async fn stream_events(Query(params): Query<EventParams>, headers: HeaderMap) -> Response {
+    let since = params.since.or_else(|| {
+        headers
+            .get("last-event-id")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+    });
+    let (recent, mut rx) = events::subscribe(since);
+    let (tx, out) = tokio::sync::mpsc::channel::<LogEvent>(64);
+    tokio::spawn(async move {
+        let mut last = since.unwrap_or(0);
+        for event in recent {
+            last = event.seq;
+            if tx.send(event).await.is_err() {
+                return;
+            }
+        }
+        loop {
+            let event = match rx.recv().await {
+                // Already sent from the buffer
+                Ok(event) if event.seq <= last => continue,
+                Ok(event) => event,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => LogEvent {
+                    seq: last,
+                    at: unix_now(),
+                    level: Level::Warn,
+                    kind: "events".to_string(),
+                    message: format!("{} events were dropped for this viewer", n),
+                    path: None,
+                },
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+            };
+            last = last.max(event.seq);
+            if tx.send(event).await.is_err() {
+                return;
+            }
+        }
+    });
+    let stream =
+        tokio_stream::StreamExt::map(tokio_stream::wrappers::ReceiverStream::new(out), |event| {
+            Ok::<_, std::convert::Infallible>(
+                Event::default()
+                    .event("log")
+                    .id(event.seq.to_string())
+                    .data(serde_json::to_string(&event).unwrap_or_default()),
+            )
+        });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/scan/start",
+    responses((status = 200, description = "`{\"status\": \"started\"}` or `{\"error\": ...}`"))
+)]
 async fn start_scan(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     let input_dir = match &state.input_dir {
         Some(d) => d.clone(),
@@ -86,80 +1119,833 @@ async fn start_scan(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     let client_id = std::env::var("ACOUSTID_CLIENT_ID").ok();
     let offline = client_id.is_none(); // If no key, force offline
 
-    match state
-        .scan_manager
-        .start_scan(input_dir, index_dir, offline, client_id)
-    {
+    match state.scan_manager.start_scan(
+        input_dir,
+        index_dir,
+        offline,
+        client_id,
+        state.writer.clone(),
+    ) {
         Ok(_) => Json(json!({"status": "started"})),
         Err(e) => Json(json!({"error": e.to_string()})),
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/upload",
+    request_body(content_type = "multipart/form-data", description = "One or more audio files"),
+    responses(
+        (status = 200, description = "Files written to the incoming directory", body = UploadResult),
+        (status = 401, description = "Missing or wrong bearer token"),
+        (status = 413, description = "Upload larger than --max-upload-bytes"),
+        (status = 503, description = "Uploads are not configured (no token or incoming directory)")
+    )
+)]
+async fn upload_files(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Response {
+    let (Some(token), Some(incoming_dir)) = (&state.upload_token, &state.incoming_dir) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Uploads are disabled; start serve with --upload-token and --input-dir or --incoming-dir",
+        )
+            .into_response();
+    };
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    if !upload::token_matches(authorization, token) {
+        return (StatusCode::UNAUTHORIZED, "Missing or wrong upload token").into_response();
+    }
+    if let Err(e) = tokio::fs::create_dir_all(incoming_dir).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create {:?}: {}", incoming_dir, e),
+        )
+            .into_response();
+    }
+    let index_dir = state.index_path.parent().unwrap().to_path_buf();
+    let settings = match crate::scanner::ScanSettings::load(&index_dir.join("scan.json")) {
+        Ok(s) => s,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response(),
+    };
+
+    let mut result = UploadResult::default();
+    loop {
+        let mut field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return (e.status(), e.body_text()).into_response(),
+        };
+        let Some(original) = field.file_name().map(str::to_string) else {
+            continue;
+        };
+        let reject = |reason: &str| RejectedUpload {
+            name: original.clone(),
+            reason: reason.to_string(),
+        };
+        let Some(name) = upload::sanitize_file_name(&original) else {
+            result.rejected.push(reject("Invalid file name"));
+            continue;
+        };
+        if upload::accepted_extension(&name, &settings.extensions).is_none() {
+            result
+                .rejected
+                .push(reject("Not an extension listed in scan.json"));
+            continue;
+        }
+
+        // Written under a `.part` name, which scans skip, until complete
+        let target = match upload::create_unique(incoming_dir, &name) {
+            Ok(target) => target,
+            Err(e) => {
+                result.rejected.push(reject(&e.to_string()));
+                continue;
+            }
+        };
+        let mut partial = target.clone().into_os_string();
+        partial.push(".part");
+        let partial = PathBuf::from(partial);
+        let written: anyhow::Result<()> = async {
+            use tokio::io::AsyncWriteExt;
+            let mut file = tokio::fs::File::create(&partial).await?;
+            while let Some(chunk) = field.chunk().await? {
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+            tokio::fs::rename(&partial, &target).await?;
+            Ok(())
+        }
+        .await;
+        match written {
+            Ok(()) => result.saved.push(target.to_string_lossy().into_owned()),
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&partial).await;
+                let _ = tokio::fs::remove_file(&target).await;
+                match e.downcast::<axum::extract::multipart::MultipartError>() {
+                    // The body limit was hit mid-file; the rest of the request is lost too
+                    Ok(e) => return (e.status(), e.body_text()).into_response(),
+                    Err(e) => result.rejected.push(reject(&format!("{:#}", e))),
+                }
+            }
+        }
+    }
+
+    // Index the new files with the same defaults as `POST /api/scan/start`
+    let in_input_dir = state
+        .input_dir
+        .as_ref()
+        .is_some_and(|d| incoming_dir.starts_with(d));
+    if !result.saved.is_empty() && in_input_dir {
+        let client_id = std::env::var("ACOUSTID_CLIENT_ID").ok();
+        result.scan_started = state
+            .scan_manager
+            .scan_files(
+                result.saved.iter().map(PathBuf::from).collect(),
+                state.input_dir.clone().unwrap(),
+                index_dir,
+                client_id.is_none(),
+                client_id,
+                state.writer.clone(),
+            )
+            .is_ok();
+    }
+    Json(result).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/tracks/bulk",
+    request_body = BulkEditRequest,
+    responses(
+        (status = 200, description = "Changed fields; with `preview` nothing is written", body = BulkEditResult),
+        (status = 400, description = "No IDs or no fields to change"),
+        (status = 404, description = "Unknown track ID")
+    )
+)]
+async fn bulk_edit_tracks(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BulkEditRequest>,
+) -> Response {
+    if request.ids.is_empty() || request.set.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Nothing to edit").into_response();
+    }
+    {
+        let library = state.library.get();
+        if let Some(id) = request
+            .ids
+            .iter()
+            .find(|id| library.find_by_id(id).is_none())
+        {
+            return (StatusCode::NOT_FOUND, format!("Unknown track ID: {}", id)).into_response();
+        }
+    }
+
+    if request.preview {
+        // Nothing is written, so the writer is not needed
+        return Json(bulk_edit::preview(&state.library.get(), &request)).into_response();
+    }
+
+    edit_library(state, move |library, history_path| {
+        bulk_edit::apply(library, &request, history_path)
+    })
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/tracks/bulk/undo",
+    responses(
+        (status = 200, description = "Restored fields; `applied` is false when there is nothing to undo", body = BulkEditResult)
+    )
+)]
+async fn undo_bulk_edit(State(state): State<Arc<AppState>>) -> Response {
+    edit_library(state, bulk_edit::undo).await
+}
+
+/// Run a bulk edit through the index writer, which saves and publishes the
+/// result. Edits queue behind a running scan's batches instead of racing it;
+/// tags written by an edit whose index can't be saved are restored.
+async fn edit_library<F>(state: Arc<AppState>, edit: F) -> Response
+where
+    F: FnOnce(&mut AudioLibrary, &Path) -> anyhow::Result<(BulkEditResult, bulk_edit::Revert)>
+        + Send
+        + 'static,
+{
+    let history_path = state.index_path.with_file_name("edit_history.json");
+    match state
+        .writer
+        .apply_or_revert(move |library| {
+            let (result, revert) = edit(library, &history_path)?;
+            Ok((result, move || revert.run()))
+        })
+        .await
+    {
+        Ok(result) => Json(result).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/scan/status",
+    responses((status = 200, description = "Progress of the current or last scan", body = ScanProgress))
+)]
 async fn get_scan_status(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     let progress = state.scan_manager.get_progress();
     Json(progress)
 }
 
-async fn get_duplicates(State(state): State<Arc<AppState>>) -> Json<Vec<Vec<IndexedTrack>>> {
-    match AudioLibrary::load(&state.index_path) {
-        Ok(lib) => Json(lib.find_duplicates()),
-        Err(_) => Json(vec![]),
+#[utoipa::path(
+    get,
+    path = "/api/scan/stream",
+    responses((status = 200, description = "Server-sent `progress` events, each a JSON ScanProgress: the current state on connect, then about twice a second during a scan and once when it ends", content_type = "text/event-stream"))
+)]
+async fn stream_scan_progress(State(state): State<Arc<AppState>>) -> Response {
+    let mut rx = state.scan_manager.subscribe();
+    let (tx, out) = tokio::sync::mpsc::channel::<ScanProgress>(1);
+    tokio::spawn(async move {
+        loop {
+            let progress = rx.borrow_and_update().clone();
+            if tx.send(progress).await.is_err() {
+                return;
+            }
+            // Updates that arrive while sending collapse into the latest one
+            if rx.changed().await.is_err() {
+                return;
+            }
+        }
+    });
+    let stream = tokio_stream::StreamExt::map(
+        tokio_stream::wrappers::ReceiverStream::new(out),
+        |progress| {
+            Ok::<_, std::convert::Infallible>(
+                Event::default()
+                    .event("progress")
+                    .data(serde_json::to_string(&progress).unwrap_or_default()),
+            )
+        },
+    );
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/library/reload",
+    responses((status = 200, description = "Index re-read from disk"))
+)]
+async fn reload_library(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    state.library.invalidate();
+    state.analysis.invalidate();
+    let shared = state.library.clone();
+    let total_tracks =
+        tokio::task::spawn_blocking(move || shared.snapshot_now().library.files.len())
+            .await
+            .unwrap_or_default();
+    Json(json!({"status": "reloaded", "total_tracks": total_tracks}))
+}
+
+/// Pinned snapshots stay readable this long unless released earlier
+const SNAPSHOT_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Serialize, ToSchema)]
+struct SnapshotInfo {
+    /// Pass as `?snapshot=` to `/api/tracks` and the playlist endpoints
+    generation: u64,
+    total_tracks: usize,
+    expires_in_secs: u64,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/snapshots",
+    responses((status = 200, description = "Current library pinned for consistent multi-request exports", body = SnapshotInfo))
+)]
+async fn create_snapshot(State(state): State<Arc<AppState>>) -> Json<SnapshotInfo> {
+    let snapshot = state.library.pin(SNAPSHOT_TTL);
+    Json(SnapshotInfo {
+        generation: snapshot.generation,
+        total_tracks: snapshot.library.files.len(),
+        expires_in_secs: SNAPSHOT_TTL.as_secs(),
+    })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/snapshots/{generation}",
+    params(("generation" = u64, Path, description = "Generation returned by `POST /api/snapshots`")),
+    responses(
+        (status = 204, description = "Snapshot released"),
+        (status = 404, description = "No pinned snapshot with that generation")
+    )
+)]
+async fn release_snapshot(
+    State(state): State<Arc<AppState>>,
+    extract::Path(generation): extract::Path<u64>,
+) -> StatusCode {
+    if state.library.release(generation) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
     }
 }
 
-#[derive(serde::Deserialize)]
-struct RecommendParams {
-    path: String,
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DuplicateParams {
+    #[serde(default)]
+    include_spoken_word: bool,
 }
 
-async fn get_recommendations(
+#[utoipa::path(
+    get,
+    path = "/api/duplicates",
+    params(DuplicateParams),
+    responses(
+        (status = 200, description = "Duplicate groups, each ranked best copy first", body = Vec<Vec<RankedCopy>>),
+        (status = 304, description = "Index unchanged since the given ETag/date")
+    )
+)]
+async fn get_duplicates(
     State(state): State<Arc<AppState>>,
-    Query(params): extract::Query<RecommendParams>,
-) -> impl IntoResponse {
-    let target_path = PathBuf::from(&params.path);
-    // analysis.bin is sibling of index.json
-    let analysis_path = state.index_path.parent().unwrap().join("analysis.bin");
+    Query(params): extract::Query<DuplicateParams>,
+    headers: HeaderMap,
+) -> Response {
+    let (modified, library) = state.library.get_with_modified();
+    cached_json(&headers, modified, || {
+        library
+            .find_duplicates(params.include_spoken_word)
+            .into_iter()
+            .map(quality::rank_group)
+            .collect::<Vec<Vec<RankedCopy>>>()
+    })
+}
 
-    let store = match crate::analysis_store::AnalysisStore::load(&analysis_path) {
-        Ok(s) => s,
-        Err(_) => return Json(json!({"error": "Failed to load analysis store"})),
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct NearDuplicateParams {
+    /// Minimum fingerprint similarity over the aligned part (default 0.6)
+    min_similarity: Option<f32>,
+    #[serde(default)]
+    include_spoken_word: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/duplicates/near",
+    params(NearDuplicateParams),
+    responses(
+        (status = 200, description = "Pairs of differing fingerprints of the same audio, with start/end offsets", body = Vec<NearDuplicate>),
+        (status = 304, description = "Index unchanged since the given ETag/date")
+    )
+)]
+async fn get_near_duplicates(
+    State(state): State<Arc<AppState>>,
+    Query(params): extract::Query<NearDuplicateParams>,
+    headers: HeaderMap,
+) -> Response {
+    let (modified, library) = state.library.get_with_modified();
+    let min_similarity = params.min_similarity.unwrap_or(0.6);
+    cached_json(&headers, modified, || {
+        crate::near_duplicates::find(&library, min_similarity, params.include_spoken_word)
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/albums/consistency",
+    responses(
+        (status = 200, description = "Folders with disagreeing album tags and albums spread over several folders", body = ConsistencyReport),
+        (status = 304, description = "Index unchanged since the given ETag/date")
+    )
+)]
+async fn get_album_consistency(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    let (modified, library) = state.library.get_with_modified();
+    cached_json(&headers, modified, || consistency::check(&library))
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct IssueParams {
+    /// Comma-separated issue kinds, e.g. `mojibake,missing_album` (default: all)
+    kind: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/issues",
+    params(IssueParams),
+    responses(
+        (status = 200, description = "Decode errors, low-confidence matches, missing tags, suspected transcodes, incomplete albums and mojibake in one queue", body = IssueReport),
+        (status = 400, description = "Unknown issue kind"),
+        (status = 500, description = "Scan errors could not be read")
+    )
+)]
+async fn get_issues(
+    State(state): State<Arc<AppState>>,
+    Query(params): extract::Query<IssueParams>,
+) -> Response {
+    let kinds: Vec<IssueKind> = match params
+        .kind
+        .iter()
+        .flat_map(|k| k.split(','))
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .map(|k| serde_json::from_value(json!(k)))
+        .collect()
+    {
+        Ok(kinds) => kinds,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
     };
 
-    let target_analysis = match store.get(&target_path) {
-        Some(a) => a,
-        None => return Json(json!({"error": "Target song has no analysis data"})),
+    let errors_path = state.index_path.with_file_name("scan_errors.json");
+    let scan_errors = match issues::load_scan_errors(&errors_path) {
+        Ok(e) => e,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let quarantine_path = state.index_path.with_file_name("quarantine.json");
+    let quarantine = match issues::load_quarantine(&quarantine_path) {
+        Ok(q) => q,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     };
+    let library = state.library.get();
+    Json(issues::collect(&library, &scan_errors, &quarantine, &kinds)).into_response()
+}
 
-    let mut results = Vec::new();
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct FileReportParams {
+    /// `largest` (default), `low-bitrate` or `oddities`
+    kind: Option<ReportKind>,
+    /// Number of files to list (default 50)
+    limit: Option<usize>,
+    /// Lossy files below this bitrate count as low quality (default 160)
+    min_kbps: Option<u32>,
+    /// Bitrate assumed when estimating re-encoding savings (default 320)
+    target_kbps: Option<u32>,
+}
 
-    for (path, analysis) in &store.data {
-        if path == &target_path {
-            continue;
+#[utoipa::path(
+    get,
+    path = "/api/reports/files",
+    params(FileReportParams),
+    responses(
+        (status = 200, description = "Largest, low-bitrate or mono/low-sample-rate files with reclaimable space", body = FileReport),
+        (status = 304, description = "Index unchanged since the given ETag/date")
+    )
+)]
+async fn get_file_report(
+    State(state): State<Arc<AppState>>,
+    Query(params): extract::Query<FileReportParams>,
+    headers: HeaderMap,
+) -> Response {
+    let defaults = ReportOptions::default();
+    let opts = ReportOptions {
+        limit: params.limit.unwrap_or(defaults.limit),
+        min_kbps: params.min_kbps.unwrap_or(defaults.min_kbps),
+        target_kbps: params.target_kbps.unwrap_or(defaults.target_kbps),
+    };
+    let kind = params.kind.unwrap_or(ReportKind::Largest);
+    let (modified, library) = state.library.get_with_modified();
+    cached_json(&headers, modified, || reports::build(&library, kind, &opts))
+}
+
+/// First value of a forwarded header; proxies chained behind each other
+/// append theirs after a comma.
+fn forwarded(headers: &HeaderMap, name: &str) -> Option<String> {
+    let value = headers.get(name)?.to_str().ok()?;
+    let first = value.split(',').next()?.trim();
+    (!first.is_empty()).then(|| first.to_string())
+}
+
+/// Prefix for URLs handed to players: `--public-url` when set, else the
+/// scheme and host from `X-Forwarded-Proto` / `X-Forwarded-Host` with
+/// `--trust-proxy`, else just the base path. Root-relative URLs resolve
+/// against the host the playlist was fetched from, and unlike an absolute
+/// URL built from the `Host` header they can't be pointed elsewhere by the
+/// request.
+fn public_base(state: &AppState, headers: &HeaderMap) -> String {
+    if let Some(url) = &state.public_url {
+        return url.clone();
+    }
+    if state.trust_proxy {
+        if let Some(host) = forwarded(headers, "x-forwarded-host") {
+            let proto =
+                forwarded(headers, "x-forwarded-proto").unwrap_or_else(|| "http".to_string());
+            return format!("{}://{}{}", proto, host, state.base_path);
         }
+    }
+    state.base_path.clone()
+}
 
-        let distance = euclidean_distance(target_analysis, analysis);
-        if distance.is_nan() {
-            continue;
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct M3uParams {
+    /// List stream URLs (`/api/track/{id}/stream`) instead of local file paths,
+    /// for players on other machines
+    #[serde(default)]
+    stream: bool,
+}
+
+/// M3U of `tracks` with local paths, or with stream URLs for `params.stream`.
+fn render_m3u(
+    state: &AppState,
+    headers: &HeaderMap,
+    params: &M3uParams,
+    tracks: &[&IndexedTrack],
+) -> String {
+    if !params.stream {
+        return playlist::render_m3u(tracks);
+    }
+    let base = public_base(state, headers);
+    playlist::render_m3u_with(tracks, |t| format!("{}/api/track/{}/stream", base, t.id))
+}
+
+fn m3u_response(body: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "audio/x-mpegurl; charset=utf-8")],
+        body,
+    )
+        .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/playlist.m3u",
+    params(PlaylistQuery, M3uParams, SnapshotParam),
+    responses(
+        (status = 200, description = "Extended M3U of matching tracks", content_type = "audio/x-mpegurl"),
+        (status = 410, description = "The requested snapshot was released or has expired")
+    )
+)]
+async fn get_playlist(
+    State(state): State<Arc<AppState>>,
+    Query(query): extract::Query<PlaylistQuery>,
+    Query(m3u): extract::Query<M3uParams>,
+    Query(snapshot): extract::Query<SnapshotParam>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(view) = library_view(&state, &snapshot) else {
+        return snapshot_gone(&snapshot);
+    };
+    let library = view.library;
+    let tracks = playlist::select(&library, &query);
+    m3u_response(render_m3u(&state, &headers, &m3u, &tracks))
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct PromptParams {
+    /// Free-text descriptor, e.g. "late-night mellow jazz, no vocals, 1 hour"
+    q: String,
+    /// Makes the selection reproducible
+    seed: Option<u64>,
+    /// `m3u` for a playlist file instead of JSON
+    format: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct PromptPlaylist {
+    /// How the prompt was understood
+    constraints: PromptConstraints,
+    total_secs: f64,
+    tracks: Vec<IndexedTrack>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/playlist/prompt",
+    params(PromptParams),
+    responses((status = 200, description = "Playlist matching the prompt with the parsed constraints, or M3U with `format=m3u`", body = PromptPlaylist))
+)]
+async fn get_prompt_playlist(
+    State(state): State<Arc<AppState>>,
+    Query(params): extract::Query<PromptParams>,
+) -> Response {
+    let library = state.library.get();
+    let constraints = crate::prompt::parse(&params.q, &library);
+    let store = state.analysis.get();
+    let tracks = crate::prompt::select(&library, &store, &constraints, params.seed);
+
+    if params.format.as_deref() == Some("m3u") {
+        return m3u_response(playlist::render_m3u(&tracks));
+    }
+    Json(PromptPlaylist {
+        total_secs: tracks.iter().map(|t| t.metadata.duration).sum(),
+        tracks: tracks.into_iter().cloned().collect(),
+        constraints,
+    })
+    .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/playlists/{name}.m3u",
+    params(("name" = String, Path, description = "Playlist name from playlists.json"), M3uParams, SnapshotParam),
+    responses(
+        (status = 200, description = "Extended M3U of the stored playlist", content_type = "audio/x-mpegurl"),
+        (status = 404, description = "No stored playlist with that name"),
+        (status = 410, description = "The requested snapshot was released or has expired")
+    )
+)]
+async fn get_stored_playlist(
+    State(state): State<Arc<AppState>>,
+    extract::Path(file_name): extract::Path<String>,
+    Query(m3u): extract::Query<M3uParams>,
+    Query(snapshot): extract::Query<SnapshotParam>,
+    headers: HeaderMap,
+) -> Response {
+    let name = file_name.strip_suffix(".m3u").unwrap_or(&file_name);
+    let playlists_path = state.index_path.parent().unwrap().join("playlists.json");
+
+    let stored = match playlist::load_stored(&playlists_path) {
+        Ok(p) => p,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let Some(query) = stored.get(name) else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("No playlist named '{}'", name),
+        )
+            .into_response();
+    };
+
+    let Some(view) = library_view(&state, &snapshot) else {
+        return snapshot_gone(&snapshot);
+    };
+    let library = view.library;
+    let tracks = playlist::select(&library, query);
+    m3u_response(render_m3u(&state, &headers, &m3u, &tracks))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats",
+    responses((status = 200, description = "Track count, total size and per-decade distribution"))
+)]
+async fn get_stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let library = state.library.get();
+
+    let mut decades: BTreeMap<u32, usize> = BTreeMap::new();
+    let mut unknown_year = 0;
+    for track in library.files.values() {
+        match track.metadata.year {
+            Some(y) => *decades.entry(organizer::decade(y)).or_default() += 1,
+            None => unknown_year += 1,
         }
-        results.push((path, distance));
     }
 
-    // Sort by distance ASC
-    results.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    Json(json!({
+        "total_tracks": library.files.len(),
+        "total_size": library.files.values().map(|t| t.file_size).sum::<u64>(),
+        "decades": decades
+            .into_iter()
+            .map(|(decade, count)| json!({"decade": decade, "count": count}))
+            .collect::<Vec<_>>(),
+        "unknown_year": unknown_year,
+    }))
+}
 
-    // Top 20
-    let top_results: Vec<_> = results.into_iter().take(20).collect();
+fn default_timeline_top() -> usize {
+    10
+}
 
-    // Enrich
-    let library = match AudioLibrary::load(&state.index_path) {
-        Ok(lib) => lib,
-        Err(_) => AudioLibrary::default(),
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TimelineParams {
+    /// How many of the biggest artists and albums to return
+    #[serde(default = "default_timeline_top")]
+    top: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/timeline",
+    params(TimelineParams),
+    responses(
+        (status = 200, description = "Per-scan library size history plus storage by format, artist and album", body = StorageTimeline),
+        (status = 304, description = "Index unchanged since the given ETag/date"),
+        (status = 500, description = "Scan history could not be read")
+    )
+)]
+async fn get_stats_timeline(
+    State(state): State<Arc<AppState>>,
+    Query(params): extract::Query<TimelineParams>,
+    headers: HeaderMap,
+) -> Response {
+    let history_path = state.index_path.with_file_name("scan_history.json");
+    let snapshots = match timeline::load_history(&history_path) {
+        Ok(s) => s,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     };
+    let (modified, library) = state.library.get_with_modified();
+    cached_json(&headers, modified, || {
+        timeline::timeline(&library, snapshots, params.top)
+    })
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct WorkParams {
+    /// Return only the work containing this track
+    path: Option<String>,
+}
 
-    let enriched: Vec<_> = top_results
+#[utoipa::path(
+    get,
+    path = "/api/works",
+    params(WorkParams),
+    responses(
+        (status = 200, description = "All works, or the single work for `path`", body = Vec<WorkNode>),
+        (status = 304, description = "Index unchanged since the given ETag/date")
+    )
+)]
+async fn get_works(
+    State(state): State<Arc<AppState>>,
+    Query(params): extract::Query<WorkParams>,
+    headers: HeaderMap,
+) -> Response {
+    let (modified, library) = state.library.get_with_modified();
+    cached_json(&headers, modified, || {
+        let works = works::build_work_graph(&library);
+        match params.path {
+            Some(path) => match works::find_work_for(&works, &PathBuf::from(path)) {
+                Some(work) => json!(work),
+                None => json!({"error": "Track is not part of a known work"}),
+            },
+            None => json!(works),
+        }
+    })
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct RecommendParams {
+    /// Absolute path of the seed track
+    path: String,
+    #[serde(default)]
+    include_spoken_word: bool,
+    /// Ranking mode; defaults to `similarity.json` (or `distance`)
+    mode: Option<ScoreMode>,
+    /// Overrides `genre_weight` from `similarity.json`
+    genre_weight: Option<f32>,
+    /// Overrides `era_bonus` from `similarity.json`
+    era_bonus: Option<f32>,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+struct Recommendation {
+    path: String,
+    title: String,
+    artist: String,
+    album: String,
+    /// Euclidean distance between bliss feature vectors (lower is closer)
+    distance: f32,
+    /// Ranking score; equals `distance` unless the hybrid mode is used
+    score: f32,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/recommend",
+    params(RecommendParams),
+    responses((status = 200, description = "Up to 20 most similar tracks, or `{\"error\": ...}`", body = Vec<Recommendation>))
+)]
+async fn get_recommendations(
+    State(state): State<Arc<AppState>>,
+    Query(params): extract::Query<RecommendParams>,
+) -> impl IntoResponse {
+    let target_path = PathBuf::from(&params.path);
+    let library = state.library.get();
+
+    let settings_path = state.index_path.with_file_name("similarity.json");
+    let mut settings = match SimilaritySettings::load(&settings_path) {
+        Ok(s) => s,
+        Err(e) => return Json(json!({"error": format!("{:#}", e)})),
+    };
+    settings.mode = params.mode.unwrap_or(settings.mode);
+    settings.genre_weight = params.genre_weight.unwrap_or(settings.genre_weight);
+    settings.era_bonus = params.era_bonus.unwrap_or(settings.era_bonus);
+
+    // Audiobooks/podcasts are excluded from recommendations by default
+    let wanted = |path: &Path| {
+        params.include_spoken_word
+            || !library
+                .files
+                .get(path)
+                .is_some_and(|t| t.metadata.content_kind == ContentKind::SpokenWord)
+    };
+
+    // Precomputed after the last scan; tracks analyzed since fall back to a
+    // full pass. The hybrid mode reranks these closest 50.
+    let neighbors = state.neighbors.get();
+    let Some(candidates) = crate::neighbors::lookup(&neighbors, &state.analysis, &target_path)
+    else {
+        return Json(json!({"error": "Target song has no analysis data"}));
+    };
+    let candidates = candidates.into_iter().filter(|(path, _)| wanted(path));
+
+    let seed = library.files.get(&target_path).map(|t| &t.metadata);
+    let mut top_results: Vec<(PathBuf, f32, f32)> = candidates
+        .map(|(path, distance)| {
+            let candidate = library.files.get(&path).map(|t| &t.metadata);
+            let score = similarity::score(&settings, distance, seed, candidate);
+            (path, distance, score)
+        })
+        .collect();
+    top_results.sort_by(|a, b| a.2.total_cmp(&b.2));
+    top_results.truncate(20);
+
+    // Enrich
+    let enriched: Vec<Recommendation> = top_results
         .iter()
-        .map(|(path, dist)| {
-            let track = library.files.get(*path);
+        .map(|(path, dist, score)| {
+            let track = library.files.get(path);
             let title = track
                 .map(|t| t.metadata.title.clone())
                 .unwrap_or_else(|| "Unknown".to_string());
@@ -169,15 +1955,698 @@ async fn get_recommendations(
             let album = track
                 .and_then(|t| t.metadata.album.clone())
                 .unwrap_or_else(|| "-".to_string());
-            json!({
-                "path": path.to_string_lossy(),
-                "title": title,
-                "artist": artist,
-                "album": album,
-                "distance": dist
-            })
+            Recommendation {
+                path: path.to_string_lossy().into_owned(),
+                title,
+                artist,
+                album,
+                distance: *dist,
+                score: *score,
+            }
         })
         .collect();
 
     Json(json!(enriched))
 }
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct RadioSeed {
+    /// Absolute path of the seed track
+    path: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/radio",
+    params(RadioSeed, RadioOptions),
+    responses((status = 200, description = "Queue starting with the seed; `distance` is to the previous track, or `{\"error\": ...}`", body = Vec<Recommendation>))
+)]
+async fn get_radio(
+    State(state): State<Arc<AppState>>,
+    Query(seed): extract::Query<RadioSeed>,
+    Query(options): extract::Query<RadioOptions>,
+) -> impl IntoResponse {
+    let seed_path = PathBuf::from(&seed.path);
+    let library = state.library.get();
+    let history = match PlayHistory::load(&state.index_path.with_file_name("play_history.json")) {
+        Ok(h) => h,
+        Err(e) => return Json(json!({"error": format!("{:#}", e)})),
+    };
+    let neighbors = state.neighbors.get();
+    if crate::neighbors::lookup(&neighbors, &state.analysis, &seed_path).is_none() {
+        return Json(json!({"error": "Seed track has no analysis data"}));
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    // A long walk over a large library is CPU-bound
+    let walk = {
+        let (library, state) = (library.clone(), state.clone());
+        tokio::task::spawn_blocking(move || {
+            crate::radio::generate(&seed_path, &library, &history, &options, now, |path| {
+                crate::neighbors::lookup(&neighbors, &state.analysis, path)
+            })
+        })
+    };
+    let queue = match walk.await {
+        Ok(queue) => queue,
+        Err(e) => return Json(json!({"error": e.to_string()})),
+    };
+
+    let tracks: Vec<Recommendation> = queue
+        .into_iter()
+        .map(|pick| {
+            let meta = library.files.get(&pick.path).map(|t| &t.metadata);
+            Recommendation {
+                path: pick.path.to_string_lossy().into_owned(),
+                title: meta
+                    .map(|m| m.title.clone())
+                    .unwrap_or_else(|| "Unknown".to_string()),
+                artist: meta
+                    .map(|m| m.artist.clone())
+                    .unwrap_or_else(|| "Unknown".to_string()),
+                album: meta
+                    .and_then(|m| m.album.clone())
+                    .unwrap_or_else(|| "-".to_string()),
+                distance: pick.distance,
+                score: pick.distance,
+            }
+        })
+        .collect();
+    Json(json!(tracks))
+}
+
+#[derive(serde::Deserialize, ToSchema)]
+struct PlayRequest {
+    /// Track ID from `/api/tracks`
+    id: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/plays",
+    request_body = PlayRequest,
+    responses(
+        (status = 204, description = "Play recorded in play_history.json"),
+        (status = 404, description = "Unknown track ID")
+    )
+)]
+async fn record_play(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<PlayRequest>,
+) -> Response {
+    let library = state.library.get();
+    let Some(track) = library.find_by_id(&request.id) else {
+        return (StatusCode::NOT_FOUND, "Unknown track ID").into_response();
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let _guard = state.history_lock.lock().await;
+    let history_path = state.index_path.with_file_name("play_history.json");
+    let result = PlayHistory::load(&history_path).and_then(|mut history| {
+        history.record(track.path.clone(), now);
+        history.save(&history_path)
+    });
+    match result {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/download",
+    request_body = DownloadRequest,
+    responses(
+        (status = 200, description = "Zip of the tracks with a playlist.m3u, streamed", content_type = "application/zip"),
+        (status = 400, description = "No tracks selected"),
+        (status = 404, description = "Unknown track ID or playlist")
+    )
+)]
+async fn download_tracks(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<DownloadRequest>,
+) -> Response {
+    let library = state.library.get();
+    let mut tracks: Vec<&IndexedTrack> = Vec::new();
+    if let Some(name) = &request.playlist {
+        let playlists_path = state.index_path.with_file_name("playlists.json");
+        let stored = match playlist::load_stored(&playlists_path) {
+            Ok(p) => p,
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        };
+        let Some(query) = stored.get(name) else {
+            return (
+                StatusCode::NOT_FOUND,
+                format!("No playlist named '{}'", name),
+            )
+                .into_response();
+        };
+        tracks.extend(playlist::select(&library, query));
+    }
+    for id in &request.ids {
+        let Some(track) = library.find_by_id(id) else {
+            return (StatusCode::NOT_FOUND, format!("Unknown track ID {}", id)).into_response();
+        };
+        tracks.push(track);
+    }
+    let mut seen = HashSet::new();
+    tracks.retain(|t| seen.insert(&t.path));
+    if tracks.is_empty() {
+        return (StatusCode::BAD_REQUEST, "No tracks to download").into_response();
+    }
+
+    let names = download::entry_names(&tracks);
+    let m3u = download::render_m3u(&tracks, &names);
+    let files: Vec<(PathBuf, String)> = tracks.iter().map(|t| t.path.clone()).zip(names).collect();
+    let archive = request
+        .playlist
+        .as_deref()
+        .map(organizer::sanitize_component)
+        .unwrap_or_else(|| "tracks".to_string());
+
+    let (tx, rx) = tokio::sync::mpsc::channel(4);
+    tokio::task::spawn_blocking(move || {
+        let result = download::stream_zip(download::ChannelWriter::new(tx.clone()), &files, &m3u);
+        if let Err(e) = result {
+            eprintln!("Download failed: {:#}", e);
+            // Ends the response with an error so the client sees a broken download
+            let _ = tx.blocking_send(Err(std::io::Error::other(format!("{:#}", e))));
+        }
+    });
+    (
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.zip\"", archive.replace('"', "_")),
+            ),
+        ],
+        axum::body::Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)),
+    )
+        .into_response()
+}
+
+fn shares_path(state: &AppState) -> PathBuf {
+    state.index_path.with_file_name("shares.json")
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[derive(serde::Deserialize, ToSchema)]
+struct ShareRequest {
+    /// Track ID from `/api/tracks`
+    track_id: Option<String>,
+    /// Stored playlist from `playlists.json` (instead of `track_id`)
+    playlist: Option<String>,
+    /// Link lifetime (default 24, at most 720)
+    expires_in_hours: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ShareCreated {
+    #[serde(flatten)]
+    link: ShareLink,
+    /// Link under `--public-url` (or the forwarded host behind a trusted
+    /// proxy), otherwise its path under this server, e.g. `/share/3f…`
+    url: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/shares",
+    request_body = ShareRequest,
+    responses(
+        (status = 200, description = "New share link", body = ShareCreated),
+        (status = 400, description = "Neither or both of track_id and playlist given"),
+        (status = 404, description = "Unknown track ID or playlist")
+    )
+)]
+async fn create_share(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<ShareRequest>,
+) -> Response {
+    let target = match (request.track_id, request.playlist) {
+        (Some(id), None) => {
+            if state.library.get().find_by_id(&id).is_none() {
+                return (StatusCode::NOT_FOUND, "Unknown track ID").into_response();
+            }
+            ShareTarget::Track { id }
+        }
+        (None, Some(name)) => {
+            let playlists_path = state.index_path.with_file_name("playlists.json");
+            match playlist::load_stored(&playlists_path) {
+                // Pin the order now, so a shuffled playlist plays as listed
+                Ok(stored) if stored.contains_key(&name) => {
+                    let tracks = playlist::select(&state.library.get(), &stored[&name])
+                        .into_iter()
+                        .map(|t| t.id.clone())
+                        .collect();
+                    ShareTarget::Playlist {
+                        name,
+                        tracks: Some(tracks),
+                    }
+                }
+                Ok(_) => {
+                    return (
+                        StatusCode::NOT_FOUND,
+                        format!("No playlist named '{}'", name),
+                    )
+                        .into_response()
+                }
+                Err(e) => {
+                    return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+                }
+            }
+        }
+        _ => return (StatusCode::BAD_REQUEST, "Give either track_id or playlist").into_response(),
+    };
+    let ttl = request.expires_in_hours.unwrap_or(share::DEFAULT_TTL_HOURS);
+
+    let _guard = state.shares_lock.lock().await;
+    let path = shares_path(&state);
+    let result = ShareStore::load(&path).and_then(|mut store| {
+        let link = store.create(target, ttl, unix_now());
+        store.save(&path)?;
+        Ok(link)
+    });
+    match result {
+        Ok(link) => Json(ShareCreated {
+            url: format!("{}/share/{}", public_base(&state, &headers), link.token),
+            link,
+        })
+        .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/shares/{token}",
+    params(("token" = String, Path, description = "Share token")),
+    responses(
+        (status = 204, description = "Link revoked"),
+        (status = 404, description = "Unknown token")
+    )
+)]
+async fn revoke_share(
+    State(state): State<Arc<AppState>>,
+    extract::Path(token): extract::Path<String>,
+) -> Response {
+    let _guard = state.shares_lock.lock().await;
+    let path = shares_path(&state);
+    let result = ShareStore::load(&path).and_then(|mut store| {
+        let removed = store.revoke(&token);
+        if removed {
+            store.save(&path)?;
+        }
+        Ok(removed)
+    });
+    match result {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, "Unknown share token").into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response(),
+    }
+}
+
+/// Why a share link can't be opened.
+enum ShareError {
+    NotFound(&'static str),
+    Gone(&'static str),
+    Internal(String),
+}
+
+impl IntoResponse for ShareError {
+    fn into_response(self) -> Response {
+        match self {
+            ShareError::NotFound(message) => (StatusCode::NOT_FOUND, message).into_response(),
+            ShareError::Gone(message) => (StatusCode::GONE, message).into_response(),
+            ShareError::Internal(message) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, message).into_response()
+            }
+        }
+    }
+}
+
+/// The live link for `token`.
+fn find_share(state: &AppState, token: &str) -> Result<ShareTarget, ShareError> {
+    let store = ShareStore::load(&shares_path(state))
+        .map_err(|e| ShareError::Internal(format!("{:#}", e)))?;
+    match store.find(token) {
+        Some(link) if link.expires_at > unix_now() => Ok(link.target.clone()),
+        Some(_) => Err(ShareError::Gone("This link has expired")),
+        None => Err(ShareError::NotFound("Unknown share link")),
+    }
+}
+
+/// Tracks of a shared playlist: those pinned when the link was created that
+/// are still indexed, or for links from before pinning, the playlist as it
+/// resolves against the current index.
+fn shared_playlist(
+    state: &AppState,
+    name: &str,
+    pinned: Option<&[String]>,
+) -> Result<Vec<IndexedTrack>, ShareError> {
+    let library = state.library.get();
+    if let Some(pinned) = pinned {
+        return Ok(pinned
+            .iter()
+            .filter_map(|id| library.find_by_id(id))
+            .cloned()
+            .collect());
+    }
+    let playlists_path = state.index_path.with_file_name("playlists.json");
+    let stored =
+        playlist::load_stored(&playlists_path).map_err(|e| ShareError::Internal(e.to_string()))?;
+    let Some(query) = stored.get(name) else {
+        return Err(ShareError::Gone("The shared playlist was removed"));
+    };
+    Ok(playlist::select(&library, query)
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
+/// Stream `path` with range support, so players can seek.
+async fn serve_audio(path: &Path, request: extract::Request) -> Response {
+    match ServeFile::new(path).try_call(request).await {
+        Ok(response) => response.map(axum::body::Body::new).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/share/{token}",
+    params(("token" = String, Path, description = "Share token")),
+    responses(
+        (status = 200, description = "The shared track's audio, or an M3U of the playlist's `/share/{token}/{n}` URLs"),
+        (status = 404, description = "Unknown token"),
+        (status = 410, description = "The link expired or its track / playlist is gone")
+    )
+)]
+async fn open_share(
+    State(state): State<Arc<AppState>>,
+    extract::Path(token): extract::Path<String>,
+    request: extract::Request,
+) -> Response {
+    let target = match find_share(&state, &token) {
+        Ok(target) => target,
+        Err(e) => return e.into_response(),
+    };
+    match target {
+        ShareTarget::Track { id } => {
+            let path = match state.library.get().find_by_id(&id) {
+                Some(track) => track.path.clone(),
+                None => return (StatusCode::GONE, "The shared track was removed").into_response(),
+            };
+            serve_audio(&path, request).await
+        }
+        ShareTarget::Playlist { name, tracks } => {
+            let tracks = match shared_playlist(&state, &name, tracks.as_deref()) {
+                Ok(tracks) => tracks,
+                Err(e) => return e.into_response(),
+            };
+            let base = public_base(&state, request.headers());
+            let mut out = String::from("#EXTM3U\n");
+            for (n, t) in tracks.iter().enumerate() {
+                out.push_str(&format!(
+                    "#EXTINF:{},{} - {}\n{}/share/{}/{}\n",
+                    t.metadata.duration.round() as i64,
+                    t.metadata.artist,
+                    t.metadata.title,
+                    base,
+                    token,
+                    n + 1
+                ));
+            }
+            m3u_response(out)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/share/{token}/{n}",
+    params(
+        ("token" = String, Path, description = "Share token of a playlist"),
+        ("n" = usize, Path, description = "Position in the playlist, from 1")
+    ),
+    responses(
+        (status = 200, description = "Audio of the playlist's n-th track"),
+        (status = 404, description = "Unknown token, not a playlist link, or no such position"),
+        (status = 410, description = "The link expired or its playlist is gone")
+    )
+)]
+async fn open_share_item(
+    State(state): State<Arc<AppState>>,
+    extract::Path((token, n)): extract::Path<(String, usize)>,
+    request: extract::Request,
+) -> Response {
+    let (name, pinned) = match find_share(&state, &token) {
+        Ok(ShareTarget::Playlist { name, tracks }) => (name, tracks),
+        Ok(ShareTarget::Track { .. }) => {
+            return (StatusCode::NOT_FOUND, "Not a playlist link").into_response()
+        }
+        Err(e) => return e.into_response(),
+    };
+    let tracks = match shared_playlist(&state, &name, pinned.as_deref()) {
+        Ok(tracks) => tracks,
+        Err(e) => return e.into_response(),
+    };
+    match n.checked_sub(1).and_then(|i| tracks.get(i)) {
+        Some(track) => serve_audio(&track.path, request).await,
+        None => (StatusCode::NOT_FOUND, "No track at that position").into_response(),
+    }
+}
+
+fn presets_path(state: &AppState) -> PathBuf {
+    state.index_path.with_file_name("filter_presets.json")
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/presets",
+    responses((status = 200, description = "Saved library filter presets", body = Vec<FilterPreset>))
+)]
+async fn get_presets(State(state): State<Arc<AppState>>) -> Response {
+    match PresetStore::load(&presets_path(&state)) {
+        Ok(store) => Json(store.presets).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/presets/{name}",
+    params(("name" = String, Path, description = "Preset name")),
+    request_body = FilterPreset,
+    responses(
+        (status = 200, description = "All presets after saving", body = Vec<FilterPreset>),
+        (status = 400, description = "Empty preset name")
+    )
+)]
+async fn save_preset(
+    State(state): State<Arc<AppState>>,
+    extract::Path(name): extract::Path<String>,
+    Json(mut preset): Json<FilterPreset>,
+) -> Response {
+    let name = name.trim();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Preset name is empty").into_response();
+    }
+    preset.name = name.to_string();
+    preset.formats = preset
+        .formats
+        .iter()
+        .map(|f| f.trim().trim_start_matches('.').to_lowercase())
+        .filter(|f| !f.is_empty())
+        .collect();
+
+    let _guard = state.presets_lock.lock().await;
+    let path = presets_path(&state);
+    let result = PresetStore::load(&path).and_then(|mut store| {
+        store.upsert(preset);
+        store.save(&path)?;
+        Ok(store.presets)
+    });
+    match result {
+        Ok(presets) => Json(presets).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/presets/{name}",
+    params(("name" = String, Path, description = "Preset name")),
+    responses(
+        (status = 204, description = "Preset removed"),
+        (status = 404, description = "No preset with that name")
+    )
+)]
+async fn delete_preset(
+    State(state): State<Arc<AppState>>,
+    extract::Path(name): extract::Path<String>,
+) -> Response {
+    let _guard = state.presets_lock.lock().await;
+    let path = presets_path(&state);
+    let result = PresetStore::load(&path).and_then(|mut store| {
+        let removed = store.remove(&name);
+        if removed {
+            store.save(&path)?;
+        }
+        Ok(removed)
+    });
+    match result {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, "No preset with that name").into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/artists",
+    responses((status = 200, description = "Artists with analyzed tracks, by name", body = Vec<ArtistProfile>))
+)]
+async fn get_artists(State(state): State<Arc<AppState>>) -> Json<Vec<ArtistProfile>> {
+    let library = state.library.get();
+    let store = state.analysis.get();
+    Json(crate::artists::profiles(&library, &store))
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SimilarArtistParams {
+    /// Number of artists (default 20)
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SimilarArtist {
+    #[serde(flatten)]
+    artist: ArtistProfile,
+    /// Distance between the artists' mean bliss vectors (lower is closer)
+    distance: f32,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/artists/{id}/similar",
+    params(("id" = String, Path, description = "Artist ID from `/api/artists`"), SimilarArtistParams),
+    responses(
+        (status = 200, description = "Closest artists by average sound", body = Vec<SimilarArtist>),
+        (status = 404, description = "Unknown artist ID or no analyzed tracks")
+    )
+)]
+async fn get_similar_artists(
+    State(state): State<Arc<AppState>>,
+    extract::Path(id): extract::Path<String>,
+    Query(params): extract::Query<SimilarArtistParams>,
+) -> Response {
+    let library = state.library.get();
+    let store = state.analysis.get();
+    let profiles = crate::artists::profiles(&library, &store);
+    let Some(seed) = profiles.iter().position(|p| p.id == id) else {
+        return (StatusCode::NOT_FOUND, "Unknown artist ID").into_response();
+    };
+    let similar: Vec<SimilarArtist> =
+        crate::artists::similar(&profiles, seed, params.limit.unwrap_or(20))
+            .into_iter()
+            .map(|(i, distance)| SimilarArtist {
+                artist: profiles[i].clone(),
+                distance,
+            })
+            .collect();
+    Json(similar).into_response()
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ArtistRadioParams {
+    /// Queue length (default 25)
+    count: Option<usize>,
+    /// Similar artists to draw from besides the seed artist (default 10)
+    artists: Option<usize>,
+    /// Skip tracks played within this many days (needs `POST /api/plays`)
+    exclude_played_days: Option<u64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/artists/{id}/radio",
+    params(("id" = String, Path, description = "Artist ID from `/api/artists`"), ArtistRadioParams),
+    responses(
+        (status = 200, description = "Queue alternating the artist and similar artists; `distance` is the track artist's distance to the seed artist", body = Vec<Recommendation>),
+        (status = 404, description = "Unknown artist ID or no analyzed tracks")
+    )
+)]
+async fn get_artist_radio(
+    State(state): State<Arc<AppState>>,
+    extract::Path(id): extract::Path<String>,
+    Query(params): extract::Query<ArtistRadioParams>,
+) -> Response {
+    let library = state.library.get();
+    let store = state.analysis.get();
+    let profiles = crate::artists::profiles(&library, &store);
+    let Some(seed) = profiles.iter().position(|p| p.id == id) else {
+        return (StatusCode::NOT_FOUND, "Unknown artist ID").into_response();
+    };
+    let history = match PlayHistory::load(&state.index_path.with_file_name("play_history.json")) {
+        Ok(h) => h,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response(),
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let skip = match params.exclude_played_days {
+        Some(days) => history.played_within_days(days, now),
+        None => Default::default(),
+    };
+
+    let queue = crate::artists::radio(
+        &profiles,
+        seed,
+        &store,
+        params.count.unwrap_or(25),
+        params.artists.unwrap_or(10),
+        &skip,
+    );
+    let tracks: Vec<Recommendation> = queue
+        .into_iter()
+        .map(|(artist, path)| {
+            let meta = library.files.get(&path).map(|t| &t.metadata);
+            let distance = crate::analysis_store::euclidean_distance(
+                &profiles[seed].centroid,
+                &profiles[artist].centroid,
+            );
+            Recommendation {
+                path: path.to_string_lossy().into_owned(),
+                title: meta
+                    .map(|m| m.title.clone())
+                    .unwrap_or_else(|| "Unknown".to_string()),
+                artist: profiles[artist].name.clone(),
+                album: meta
+                    .and_then(|m| m.album.clone())
+                    .unwrap_or_else(|| "-".to_string()),
+                distance,
+                score: distance,
+            }
+        })
+        .collect();
+    Json(tracks).into_response()
+}
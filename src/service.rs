@@ -0,0 +1,237 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use tokio::sync::Notify;
+
+/// Name of the systemd unit / Windows service registered by `serve --install-service`.
+pub const SERVICE_NAME: &str = "audio-sorter";
+
+/// Woken by a service manager stop request that doesn't arrive as a signal
+/// (the Windows service control handler).
+static STOP: Notify = Notify::const_new();
+
+/// Resolves on Ctrl-C, SIGTERM (systemd stop) or a Windows service stop request.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+        _ = STOP.notified() => {},
+    }
+}
+
+/// Tell systemd (`Type=notify`) the server is accepting connections.
+/// A no-op outside systemd.
+pub fn notify_ready() {
+    #[cfg(unix)]
+    let _ = sd_notify::notify(false, &[sd_notify::NotifyState::Ready]);
+}
+
+/// Tell systemd shutdown has begun, so it waits for the index to be saved.
+pub fn notify_stopping() {
+    #[cfg(unix)]
+    let _ = sd_notify::notify(false, &[sd_notify::NotifyState::Stopping]);
+}
+
+/// Register `serve` with the platform service manager: a `Type=notify`
+/// systemd unit on Linux, an auto-start service on Windows. `args` is the
+/// `serve` command line the service runs, with absolute paths.
+pub fn install(args: &[String]) -> Result<()> {
+    let exe = std::env::current_exe().context("Failed to locate executable")?;
+    install_for(&exe, args)
+}
+
+#[cfg(target_os = "linux")]
+fn install_for(exe: &Path, args: &[String]) -> Result<()> {
+    let quote = |s: &str| {
+        if s.contains([' ', '"', '\\']) {
+            format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+        } else {
+            s.to_string()
+        }
+    };
+    let exec_start: Vec<String> = std::iter::once(exe.display().to_string())
+        .chain(args.iter().cloned())
+        .map(|a| quote(&a))
+        .collect();
+    let unit = format!(
+        "[Unit]\n\
+         Description=Audio Sorter web dashboard\n\
+         After=network-online.target\n\
+         Wants=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         ExecStart={}\n\
+         Restart=on-failure\n\
+         # A running scan finishes its batch and saves the index before exiting\n\
+         TimeoutStopSec=300\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n",
+        exec_start.join(" ")
+    );
+    let path = Path::new("/etc/systemd/system").join(format!("{}.service", SERVICE_NAME));
+    std::fs::write(&path, unit)
+        .with_context(|| format!("Failed to write {:?} (run as root)", path))?;
+    println!("Wrote {:?}. Enable it with:", path);
+    println!(
+        "  systemctl daemon-reload && systemctl enable --now {}",
+        SERVICE_NAME
+    );
+    Ok(())
+}
+
+#[cfg(windows)]
+fn install_for(exe: &Path, args: &[String]) -> Result<()> {
+    use std::ffi::OsString;
+    use windows_service::service::{
+        ServiceAccess, ServiceErrorControl, ServiceInfo, ServiceStartType, ServiceType,
+    };
+    use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .context("Failed to open the service manager (run as administrator)")?;
+    let mut launch_arguments: Vec<OsString> = args.iter().map(OsString::from).collect();
+    launch_arguments.push("--service-mode".into());
+    let info = ServiceInfo {
+        name: SERVICE_NAME.into(),
+        display_name: "Audio Sorter".into(),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: exe.to_path_buf(),
+        launch_arguments,
+        dependencies: vec![],
+        account_name: None,
+        account_password: None,
+    };
+    manager
+        .create_service(&info, ServiceAccess::QUERY_STATUS)
+        .context("Failed to create service")?;
+    println!(
+        "Installed service {:?}. Start it with: sc start {}",
+        SERVICE_NAME, SERVICE_NAME
+    );
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn install_for(_exe: &Path, _args: &[String]) -> Result<()> {
+    anyhow::bail!("--install-service supports systemd (Linux) and Windows services only")
+}
+
+/// Running under the Windows service control manager (`serve --service-mode`).
+#[cfg(windows)]
+pub mod windows {
+    use anyhow::{Context, Result};
+    use std::ffi::OsString;
+    use std::sync::mpsc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use windows_service::service::{
+        ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+        ServiceType,
+    };
+    use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+    use windows_service::{define_windows_service, service_dispatcher};
+
+    use super::{SERVICE_NAME, STOP};
+
+    /// Registration outcome from `service_main` and the receiver it parks on
+    /// until the server has shut down.
+    static STARTUP: Mutex<Option<(mpsc::Sender<Result<()>>, mpsc::Receiver<()>)>> =
+        Mutex::new(None);
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    /// Reports the service as stopped when dropped, i.e. after the server
+    /// has saved the index.
+    pub struct ServiceGuard {
+        stopped: mpsc::Sender<()>,
+    }
+
+    impl Drop for ServiceGuard {
+        fn drop(&mut self) {
+            let _ = self.stopped.send(());
+        }
+    }
+
+    /// Hand the process to the service dispatcher on a background thread and
+    /// report it running; stop requests then resolve `shutdown_signal`.
+    pub fn attach() -> Result<ServiceGuard> {
+        let (started_tx, started_rx) = mpsc::channel();
+        let (stopped_tx, stopped_rx) = mpsc::channel();
+        *STARTUP.lock().unwrap() = Some((started_tx, stopped_rx));
+        std::thread::spawn(|| {
+            if let Err(e) = service_dispatcher::start(SERVICE_NAME, ffi_service_main) {
+                eprintln!("Service dispatcher failed: {}", e);
+            }
+        });
+        started_rx
+            .recv_timeout(Duration::from_secs(30))
+            .context("Service control manager did not start the service")??;
+        Ok(ServiceGuard {
+            stopped: stopped_tx,
+        })
+    }
+
+    fn service_main(_arguments: Vec<OsString>) {
+        let Some((started, stopped)) = STARTUP.lock().unwrap().take() else {
+            return;
+        };
+        let handler = |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                STOP.notify_one();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        };
+        let status_handle = match service_control_handler::register(SERVICE_NAME, handler) {
+            Ok(handle) => handle,
+            Err(e) => {
+                let _ = started.send(Err(e.into()));
+                return;
+            }
+        };
+        let status = |state, accept, wait_hint| ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted: accept,
+            exit_code: ServiceExitCode::Win32(0),
+            checkpoint: 0,
+            wait_hint,
+            process_id: None,
+        };
+        let running = status_handle.set_service_status(status(
+            ServiceState::Running,
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+            Duration::default(),
+        ));
+        let _ = started.send(running.map_err(Into::into));
+
+        let _ = stopped.recv();
+        let _ = status_handle.set_service_status(status(
+            ServiceState::Stopped,
+            ServiceControlAccept::empty(),
+            Duration::default(),
+        ));
+    }
+}
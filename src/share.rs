@@ -0,0 +1,119 @@
+use anyhow::{Context, Result};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use utoipa::ToSchema;
+
+/// Lifetime of a link when none is requested
+pub const DEFAULT_TTL_HOURS: u64 = 24;
+/// Longest lifetime a link may be given
+pub const MAX_TTL_HOURS: u64 = 30 * 24;
+
+/// What a share link plays.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ShareTarget {
+    /// Track ID from `/api/tracks`
+    Track { id: String },
+    /// Stored playlist from `playlists.json`
+    Playlist {
+        name: String,
+        /// Track IDs the playlist resolved to when the link was created, so a
+        /// shuffle plays back in one order. Absent in links from older versions,
+        /// which resolve the playlist when opened.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tracks: Option<Vec<String>>,
+    },
+}
+
+/// One entry of `shares.json`. The token is random and only known to the
+/// person it was handed to, so opening the link needs no other credentials.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ShareLink {
+    pub token: String,
+    pub target: ShareTarget,
+    /// UNIX timestamps (seconds)
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+/// Links created through `POST /api/shares`.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ShareStore {
+    pub links: Vec<ShareLink>,
+}
+
+impl ShareStore {
+    /// Load `shares.json`. A missing file means no links were shared yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).context("Failed to read share links")?;
+        serde_json::from_str(&content).context("Failed to parse share links JSON")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content =
+            serde_json::to_string_pretty(self).context("Failed to serialize share links")?;
+        fs::write(path, content).context("Failed to write share links")
+    }
+
+    /// Add a link to `target` valid for `ttl_hours` (capped at `MAX_TTL_HOURS`),
+    /// dropping links that have expired by `now`.
+    pub fn create(&mut self, target: ShareTarget, ttl_hours: u64, now: u64) -> ShareLink {
+        self.links.retain(|l| l.expires_at > now);
+        let mut bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let link = ShareLink {
+            token: bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+            target,
+            created_at: now,
+            expires_at: now + ttl_hours.clamp(1, MAX_TTL_HOURS) * 3600,
+        };
+        self.links.push(link.clone());
+        link
+    }
+
+    /// The link with `token`, if it exists (expired or not).
+    pub fn find(&self, token: &str) -> Option<&ShareLink> {
+        self.links.iter().find(|l| l.token == token)
+    }
+
+    /// Whether a link with `token` existed.
+    pub fn revoke(&mut self, token: &str) -> bool {
+        let before = self.links.len();
+        self.links.retain(|l| l.token != token);
+        self.links.len() != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_drops_expired_links_and_caps_ttl() {
+        let mut store = ShareStore::default();
+        let old = store.create(ShareTarget::Track { id: "a".into() }, 1, 0);
+        let link = store.create(ShareTarget::Track { id: "b".into() }, 10_000, 7200);
+        assert!(store.find(&old.token).is_none());
+        assert_eq!(link.expires_at, 7200 + MAX_TTL_HOURS * 3600);
+        assert!(store.revoke(&link.token));
+        assert!(!store.revoke(&link.token));
+    }
+
+    #[test]
+    fn playlist_links_without_pinned_tracks_still_load() {
+        let json = r#"{"links":[{"token":"t","target":{"kind":"playlist","name":"mix"},"created_at":0,"expires_at":1}]}"#;
+        let store: ShareStore = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            store.links[0].target,
+            ShareTarget::Playlist {
+                name: "mix".into(),
+                tracks: None
+            }
+        );
+    }
+}
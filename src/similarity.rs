@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use utoipa::ToSchema;
+
+use crate::organizer::{self, TrackMetadata};
+
+/// How recommendations are ranked.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoreMode {
+    /// Bliss feature distance only
+    #[default]
+    Distance,
+    /// Feature distance scaled by genre overlap and, optionally, era
+    Hybrid,
+}
+
+/// Contents of `similarity.json` in the index directory, e.g.
+/// `{"mode": "hybrid", "genre_weight": 1.0, "era_bonus": 0.1}`.
+/// A missing file keeps pure feature distance.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SimilaritySettings {
+    pub mode: ScoreMode,
+    /// Distance is multiplied by up to `1 + genre_weight` as genre overlap drops to zero
+    pub genre_weight: f32,
+    /// Fraction taken off the distance when both tracks are from the same decade
+    pub era_bonus: f32,
+}
+
+impl Default for SimilaritySettings {
+    fn default() -> Self {
+        Self {
+            mode: ScoreMode::Distance,
+            genre_weight: 1.0,
+            era_bonus: 0.0,
+        }
+    }
+}
+
+impl SimilaritySettings {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).context("Failed to read similarity settings")?;
+        serde_json::from_str(&content).context("Failed to parse similarity settings JSON")
+    }
+}
+
+/// Jaccard overlap of the two tracks' genre terms (parents of hierarchical
+/// genres included, so "Electronic/House" and "Electronic/Techno" partly
+/// overlap). `None` when either track has no genre.
+pub fn genre_overlap(a: &TrackMetadata, b: &TrackMetadata) -> Option<f32> {
+    let a = organizer::genre_terms(a.genre.as_deref());
+    let b = organizer::genre_terms(b.genre.as_deref());
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let shared = a.intersection(&b).count();
+    let total = a.union(&b).count();
+    Some(shared as f32 / total as f32)
+}
+
+/// Ranking score of a candidate at feature `distance` from the seed; lower
+/// is closer. Unknown genres or years neither help nor hurt.
+pub fn score(
+    settings: &SimilaritySettings,
+    distance: f32,
+    seed: Option<&TrackMetadata>,
+    candidate: Option<&TrackMetadata>,
+) -> f32 {
+    let (ScoreMode::Hybrid, Some(seed), Some(candidate)) = (settings.mode, seed, candidate) else {
+        return distance;
+    };
+    let mut score = distance;
+    if let Some(overlap) = genre_overlap(seed, candidate) {
+        score *= 1.0 + settings.genre_weight.max(0.0) * (1.0 - overlap);
+    }
+    let same_era = match (seed.year, candidate.year) {
+        (Some(a), Some(b)) => organizer::decade(a) == organizer::decade(b),
+        _ => false,
+    };
+    if same_era {
+        score *= 1.0 - settings.era_bonus.clamp(0.0, 1.0);
+    }
+    score
+}
@@ -0,0 +1,371 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::acoustid;
+use crate::decode::{downmix_into, AudioStream};
+use crate::fingerprint;
+
+// RMS is measured over 50 ms blocks
+const BLOCK_SECS: f64 = 0.05;
+// AcoustID only needs the first two minutes of a segment
+const IDENTIFY_SECS: f64 = 120.0;
+
+#[derive(Debug, Clone)]
+pub struct SplitOptions {
+    pub min_silence_secs: f64,
+    pub threshold_db: f32,
+    pub min_track_secs: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Segment {
+    pub index: usize,
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub fingerprint: Option<String>,
+}
+
+impl Segment {
+    pub fn duration(&self) -> f64 {
+        self.end_secs - self.start_secs
+    }
+}
+
+/// Propose track boundaries by looking for sustained silence.
+/// Split points are placed in the middle of each silent gap.
+pub fn detect_segments(path: &Path, opts: &SplitOptions) -> Result<Vec<Segment>> {
+    let mut stream = AudioStream::open(path)?;
+    let sample_rate = stream.info.sample_rate as f64;
+    let channels = stream.info.channels;
+    let block_len = ((sample_rate * BLOCK_SECS) as usize).max(1);
+
+    let mut mono = Vec::new();
+    let mut block_index = 0usize;
+    let mut silence_start: Option<usize> = None;
+    let mut split_points: Vec<f64> = Vec::new();
+    let min_silence_blocks = (opts.min_silence_secs / BLOCK_SECS).ceil() as usize;
+
+    while let Some(chunk) = stream.next_chunk()? {
+        downmix_into(chunk, channels, &mut mono);
+        let full_blocks = mono.len() / block_len;
+        for b in 0..full_blocks {
+            let block = &mono[b * block_len..(b + 1) * block_len];
+            let rms = (block.iter().map(|s| s * s).sum::<f32>() / block_len as f32).sqrt();
+            let silent = 20.0 * rms.max(1e-9).log10() < opts.threshold_db;
+
+            match (silent, silence_start) {
+                (true, None) => silence_start = Some(block_index),
+                (false, Some(start)) => {
+                    // Leading silence is not a boundary
+                    if start > 0 && block_index - start >= min_silence_blocks {
+                        split_points.push((start + block_index) as f64 / 2.0 * BLOCK_SECS);
+                    }
+                    silence_start = None;
+                }
+                _ => {}
+            }
+            block_index += 1;
+        }
+        mono.drain(..full_blocks * block_len);
+    }
+
+    let duration = block_index as f64 * BLOCK_SECS + mono.len() as f64 / sample_rate;
+    // Trailing silence is ignored the same way as leading silence
+
+    let mut bounds = vec![0.0];
+    bounds.extend(split_points);
+    bounds.push(duration);
+
+    // Merge segments that are too short to be a track into their neighbour
+    let mut i = 1;
+    while bounds.len() > 2 && i < bounds.len() {
+        if bounds[i] - bounds[i - 1] < opts.min_track_secs {
+            if i == bounds.len() - 1 {
+                bounds.remove(i - 1);
+            } else {
+                bounds.remove(i);
+            }
+        } else {
+            i += 1;
+        }
+    }
+
+    Ok(bounds
+        .windows(2)
+        .enumerate()
+        .map(|(index, w)| Segment {
+            index: index + 1,
+            start_secs: w[0],
+            end_secs: w[1],
+            title: None,
+            artist: None,
+            fingerprint: None,
+        })
+        .collect())
+}
+
+/// Fingerprint each segment (and look it up online if a client ID is given).
+pub fn identify_segments(
+    path: &Path,
+    segments: &mut [Segment],
+    client_id: Option<&str>,
+) -> Result<()> {
+    let tmp_dir = std::env::temp_dir().join(format!("audio_sorter_split_{}", std::process::id()));
+    std::fs::create_dir_all(&tmp_dir).context("Failed to create temp directory")?;
+
+    let tmp_paths: Vec<PathBuf> = segments
+        .iter()
+        .map(|s| tmp_dir.join(format!("segment_{:03}.wav", s.index)))
+        .collect();
+    write_segment_wavs(path, segments, &tmp_paths, Some(IDENTIFY_SECS))?;
+
+    for (segment, tmp) in segments.iter_mut().zip(&tmp_paths) {
+        let (_, fp) = match fingerprint::compute_fingerprint(tmp) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Segment {}: fingerprint failed: {}", segment.index, e);
+                continue;
+            }
+        };
+
+        if let Some(client_id) = client_id {
+            // Use the full segment length, not the truncated temp file
+            match acoustid::lookup_fingerprint(None, client_id, segment.duration(), &fp) {
+                Ok(resp) => {
+                    let recording = resp
+                        .results
+                        .as_ref()
+                        .and_then(|r| r.first())
+                        .and_then(|r| r.recordings.as_ref())
+                        .and_then(|r| r.first());
+                    if let Some(rec) = recording {
+                        segment.title = rec.title.clone();
+                        segment.artist = rec
+                            .artists
+                            .as_ref()
+                            .and_then(|a| a.first())
+                            .map(|a| a.name.clone());
+                    }
+                }
+                Err(e) => eprintln!("Segment {}: lookup failed: {}", segment.index, e),
+            }
+        }
+
+        segment.fingerprint = Some(fp);
+    }
+
+    let _ = std::fs::remove_dir_all(&tmp_dir);
+    Ok(())
+}
+
+/// Decode the file once and write each segment to its own 16-bit PCM WAV file.
+/// `max_secs` truncates every segment (used for identification).
+pub fn write_segment_wavs(
+    path: &Path,
+    segments: &[Segment],
+    out_paths: &[PathBuf],
+    max_secs: Option<f64>,
+) -> Result<()> {
+    let mut stream = AudioStream::open(path)?;
+    let sample_rate = stream.info.sample_rate;
+    let channels = stream.info.channels;
+
+    let ranges: Vec<(u64, u64)> = segments
+        .iter()
+        .map(|s| {
+            let start = (s.start_secs * sample_rate as f64) as u64;
+            let mut end = (s.end_secs * sample_rate as f64) as u64;
+            if let Some(max) = max_secs {
+                end = end.min(start + (max * sample_rate as f64) as u64);
+            }
+            (start, end)
+        })
+        .collect();
+
+    let mut current = 0usize;
+    let mut writer: Option<WavWriter> = None;
+    let mut frame_pos = 0u64;
+
+    while let Some(chunk) = stream.next_chunk()? {
+        for frame in chunk.chunks_exact(channels) {
+            while current < ranges.len() && frame_pos >= ranges[current].1 {
+                if let Some(w) = writer.take() {
+                    w.finish()?;
+                }
+                current += 1;
+            }
+            if current >= ranges.len() {
+                break;
+            }
+            if frame_pos >= ranges[current].0 {
+                if writer.is_none() {
+                    writer = Some(WavWriter::create(
+                        &out_paths[current],
+                        sample_rate,
+                        channels as u16,
+                    )?);
+                }
+                writer.as_mut().unwrap().write_frame(frame)?;
+            }
+            frame_pos += 1;
+        }
+        if current >= ranges.len() {
+            break;
+        }
+    }
+
+    if let Some(w) = writer.take() {
+        w.finish()?;
+    }
+    Ok(())
+}
+
+/// Write a CUE sheet describing the segments of `audio_path`.
+pub fn write_cue(audio_path: &Path, segments: &[Segment], cue_path: &Path) -> Result<()> {
+    let file_name = audio_path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("audio");
+    let file_type = match audio_path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase())
+        .as_deref()
+    {
+        Some("mp3") => "MP3",
+        _ => "WAVE",
+    };
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        "FILE \"{}\" {}\n",
+        cue_escape(file_name),
+        file_type
+    ));
+    for s in segments {
+        out.push_str(&format!("  TRACK {:02} AUDIO\n", s.index));
+        let title = s
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Track {:02}", s.index));
+        out.push_str(&format!("    TITLE \"{}\"\n", cue_escape(&title)));
+        if let Some(artist) = &s.artist {
+            out.push_str(&format!("    PERFORMER \"{}\"\n", cue_escape(artist)));
+        }
+        out.push_str(&format!("    INDEX 01 {}\n", cue_timestamp(s.start_secs)));
+    }
+
+    std::fs::write(cue_path, out).context("Failed to write CUE sheet")?;
+    Ok(())
+}
+
+fn cue_escape(s: &str) -> String {
+    s.replace('"', "'")
+}
+
+/// CUE timestamps are mm:ss:ff with 75 frames per second.
+fn cue_timestamp(secs: f64) -> String {
+    let total_frames = (secs * 75.0).round() as u64;
+    let minutes = total_frames / (75 * 60);
+    let seconds = (total_frames / 75) % 60;
+    let frames = total_frames % 75;
+    format!("{:02}:{:02}:{:02}", minutes, seconds, frames)
+}
+
+/// Largest data chunk a RIFF header can describe (sizes are 32-bit and the
+/// RIFF size also counts the 36 header bytes after it)
+const MAX_WAV_DATA_BYTES: u64 = u32::MAX as u64 - 36;
+
+/// Minimal streaming 16-bit PCM WAV writer.
+struct WavWriter {
+    out: BufWriter<File>,
+    data_bytes: u64,
+}
+
+impl WavWriter {
+    fn create(path: &Path, sample_rate: u32, channels: u16) -> Result<Self> {
+        let file = File::create(path).context("Failed to create WAV file")?;
+        let mut out = BufWriter::new(file);
+        let block_align = channels * 2;
+
+        out.write_all(b"RIFF")?;
+        out.write_all(&0u32.to_le_bytes())?; // patched in finish()
+        out.write_all(b"WAVEfmt ")?;
+        out.write_all(&16u32.to_le_bytes())?;
+        out.write_all(&1u16.to_le_bytes())?; // PCM
+        out.write_all(&channels.to_le_bytes())?;
+        out.write_all(&sample_rate.to_le_bytes())?;
+        let byte_rate = u32::try_from(sample_rate as u64 * block_align as u64)
+            .context("Sample rate too high for a WAV header")?;
+        out.write_all(&byte_rate.to_le_bytes())?;
+        out.write_all(&block_align.to_le_bytes())?;
+        out.write_all(&16u16.to_le_bytes())?;
+        out.write_all(b"data")?;
+        out.write_all(&0u32.to_le_bytes())?; // patched in finish()
+
+        Ok(Self { out, data_bytes: 0 })
+    }
+
+    /// Errors instead of writing past the 4 GiB a WAV file can hold.
+    fn write_frame(&mut self, frame: &[f32]) -> Result<()> {
+        let bytes = frame.len() as u64 * 2;
+        if self.data_bytes + bytes > MAX_WAV_DATA_BYTES {
+            anyhow::bail!("Segment is too long for a WAV file (over 4 GiB)");
+        }
+        for s in frame {
+            let v = (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.out.write_all(&v.to_le_bytes())?;
+        }
+        self.data_bytes += bytes;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        let data_bytes = u32::try_from(self.data_bytes).context("WAV data over 4 GiB")?;
+        self.out.seek(SeekFrom::Start(4))?;
+        self.out.write_all(&(36 + data_bytes).to_le_bytes())?;
+        self.out.seek(SeekFrom::Start(40))?;
+        self.out.write_all(&data_bytes.to_le_bytes())?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wav_header_records_the_data_size() {
+        let path =
+            std::env::temp_dir().join(format!("audio_sorter_wav_{}.wav", std::process::id()));
+        let mut wav = WavWriter::create(&path, 44100, 2).unwrap();
+        wav.write_frame(&[0.0, 0.5]).unwrap();
+        wav.write_frame(&[-0.5, 1.0]).unwrap();
+        wav.finish().unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(bytes.len(), 44 + 8);
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 36 + 8);
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 8);
+    }
+
+    #[test]
+    fn wav_refuses_data_past_4_gib() {
+        let path =
+            std::env::temp_dir().join(format!("audio_sorter_big_{}.wav", std::process::id()));
+        let mut wav = WavWriter::create(&path, 44100, 2).unwrap();
+        wav.data_bytes = MAX_WAV_DATA_BYTES - 2;
+        let result = wav.write_frame(&[0.0, 0.0]);
+        drop(wav);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+}
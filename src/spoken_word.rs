@@ -0,0 +1,137 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
+use utoipa::ToSchema;
+
+use crate::organizer::TrackMetadata;
+
+// Long files are only treated as spoken word when something else hints at it;
+// DJ mixes and live sets are long too.
+const LONG_DURATION_SECS: f64 = 45.0 * 60.0;
+
+const SPOKEN_GENRES: &[&str] = &[
+    "audiobook",
+    "audio book",
+    "podcast",
+    "spoken word",
+    "spoken",
+    "speech",
+    "hörbuch",
+    "hörspiel",
+    "radio drama",
+    "有声书",
+    "有声读物",
+    "播客",
+    "朗読",
+    "ドラマcd",
+];
+
+const SPOKEN_FOLDERS: &[&str] = &[
+    "audiobooks",
+    "audiobook",
+    "podcasts",
+    "podcast",
+    "hörbücher",
+    "有声书",
+    "播客",
+    "オーディオブック",
+];
+
+/// Numbered chapters or episodes: "Chapter 12", "Ep. 3", "S02E05", "第3章",
+/// "第十二集", "第5話". A bare "Part 2" or "第" is not enough; long classical
+/// movements and mixes use those too.
+fn episode_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?i)\b(?:chapter|episode|ep\.?)\s*\d+|\bs\d{1,2}\s*e\d{1,3}\b|第\s*[0-9０-９一二三四五六七八九十百千]+\s*[章集話话回讲講]",
+        )
+        .unwrap()
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentKind {
+    #[default]
+    Music,
+    SpokenWord,
+}
+
+/// Heuristic spoken-word detection from tags, folder names and duration.
+pub fn detect(path: &Path, meta: &TrackMetadata) -> ContentKind {
+    if let Some(genre) = &meta.genre {
+        let genre = genre.to_lowercase();
+        if SPOKEN_GENRES.iter().any(|g| genre.contains(g)) {
+            return ContentKind::SpokenWord;
+        }
+    }
+
+    let in_spoken_folder = path.parent().into_iter().flat_map(|p| p.iter()).any(|c| {
+        let c = c.to_string_lossy().to_lowercase();
+        SPOKEN_FOLDERS.iter().any(|f| c == *f)
+    });
+    if in_spoken_folder {
+        return ContentKind::SpokenWord;
+    }
+
+    if meta.duration >= LONG_DURATION_SECS {
+        let name = format!(
+            "{} {}",
+            meta.title,
+            path.file_stem().and_then(|s| s.to_str()).unwrap_or("")
+        )
+        .to_lowercase();
+        if episode_pattern().is_match(&name) {
+            return ContentKind::SpokenWord;
+        }
+    }
+
+    ContentKind::Music
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(name: &str, duration: f64) -> ContentKind {
+        let meta = TrackMetadata {
+            title: name.to_string(),
+            duration,
+            ..Default::default()
+        };
+        detect(Path::new("/music/file.mp3"), &meta)
+    }
+
+    #[test]
+    fn long_numbered_episodes_are_spoken_word() {
+        let long = LONG_DURATION_SECS;
+        for name in [
+            "Chapter 12",
+            "Ep.3 Interview",
+            "Show S02E05",
+            "第3章",
+            "第十二集",
+            "第 5 話",
+        ] {
+            assert_eq!(kind(name, long), ContentKind::SpokenWord, "{}", name);
+        }
+    }
+
+    #[test]
+    fn long_music_with_loose_markers_stays_music() {
+        let long = LONG_DURATION_SECS;
+        for name in [
+            "Symphony No. 9, Part 2",
+            "Live Set Part 1",
+            "第九交響曲",
+            "章魚",
+            "集大成 Mix",
+            "Chapter and Verse",
+        ] {
+            assert_eq!(kind(name, long), ContentKind::Music, "{}", name);
+        }
+        assert_eq!(kind("Chapter 12", 300.0), ContentKind::Music);
+    }
+}
@@ -0,0 +1,260 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::storage::{AudioLibrary, IndexedTrack};
+
+// The default rollback journal (not WAL) updates the database file itself on
+// every commit, so `SharedLibrary` notices writes by its modification time.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS tracks (
+        path TEXT PRIMARY KEY,
+        id TEXT NOT NULL,
+        artist TEXT NOT NULL,
+        album TEXT,
+        fingerprint TEXT,
+        -- Hash of `track`, so saves skip rows that did not change
+        digest INTEGER NOT NULL,
+        -- The IndexedTrack as JSON, in the same shape as index.json entries
+        track TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS tracks_id ON tracks (id);
+    CREATE INDEX IF NOT EXISTS tracks_artist ON tracks (artist COLLATE NOCASE);
+    CREATE INDEX IF NOT EXISTS tracks_album ON tracks (album COLLATE NOCASE);
+    CREATE INDEX IF NOT EXISTS tracks_fingerprint ON tracks (fingerprint);
+";
+
+/// Columns of `index.db` with an index for `SqliteIndex::find`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// Case-insensitive
+    Artist,
+    /// Case-insensitive
+    Album,
+    Fingerprint,
+}
+
+impl Field {
+    fn condition(self) -> &'static str {
+        match self {
+            Field::Artist => "artist = ?1 COLLATE NOCASE",
+            Field::Album => "album = ?1 COLLATE NOCASE",
+            Field::Fingerprint => "fingerprint = ?1",
+        }
+    }
+}
+
+const UPSERT: &str = "
+    INSERT INTO tracks (path, id, artist, album, fingerprint, digest, track)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+    ON CONFLICT (path) DO UPDATE SET
+        id = excluded.id,
+        artist = excluded.artist,
+        album = excluded.album,
+        fingerprint = excluded.fingerprint,
+        digest = excluded.digest,
+        track = excluded.track
+    WHERE tracks.digest != excluded.digest";
+
+/// FNV-1a hash of a row's JSON. Unlike `DefaultHasher` it is the same in
+/// every build, so a new version does not rewrite rows that did not change.
+fn digest(json: &str) -> i64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in json.as_bytes() {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash as i64
+}
+
+/// The index as a SQLite database (`index.db`), used instead of `index.json`
+/// in builds with the `sqlite` feature. Saves only write the rows that were
+/// added, changed or removed, and tracks can be looked up by artist, album
+/// or fingerprint without loading the whole library.
+pub struct SqliteIndex {
+    conn: Connection,
+}
+
+impl SqliteIndex {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open index database {:?}", path))?;
+        // A CLI scan and the server may write at the same time
+        conn.busy_timeout(Duration::from_secs(10))?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create index database tables")?;
+        Ok(Self { conn })
+    }
+
+    pub fn load(&self) -> Result<AudioLibrary> {
+        let mut stmt = self.conn.prepare("SELECT track FROM tracks")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut library = AudioLibrary::default();
+        for json in rows {
+            let track: IndexedTrack =
+                serde_json::from_str(&json?).context("Failed to parse track in index database")?;
+            library.files.insert(track.path.clone(), track);
+        }
+        Ok(library)
+    }
+
+    /// Make the database match `library` in one transaction. Returns the
+    /// number of rows written or deleted.
+    pub fn save(&mut self, library: &AudioLibrary) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut stored: HashMap<String, i64> = {
+            let mut stmt = tx.prepare("SELECT path, digest FROM tracks")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        let mut written = 0;
+        {
+            let mut upsert = tx.prepare(UPSERT)?;
+            for track in library.files.values() {
+                let path = track.path.to_string_lossy();
+                let json =
+                    serde_json::to_string(track).context("Failed to serialize indexed track")?;
+                let digest = digest(&json);
+                if stored.remove(path.as_ref()) == Some(digest) {
+                    continue;
+                }
+                written += write_row(&mut upsert, track, &json, digest)?;
+            }
+            // Whatever is left is no longer in the library
+            let mut delete = tx.prepare("DELETE FROM tracks WHERE path = ?1")?;
+            for path in stored.keys() {
+                delete.execute([path])?;
+                written += 1;
+            }
+        }
+        tx.commit().context("Failed to write index database")?;
+        Ok(written)
+    }
+
+    /// Write only the rows for `paths` (known to be the only tracks changed
+    /// since the database last matched the library): upsert those still in
+    /// `library`, delete the others. Rows whose content is unchanged are left
+    /// alone. Returns the number of rows written or deleted.
+    pub fn save_changes(&mut self, library: &AudioLibrary, paths: &[PathBuf]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut written = 0;
+        {
+            let mut upsert = tx.prepare(UPSERT)?;
+            let mut delete = tx.prepare("DELETE FROM tracks WHERE path = ?1")?;
+            for path in paths {
+                match library.files.get(path) {
+                    Some(track) => {
+                        let json = serde_json::to_string(track)
+                            .context("Failed to serialize indexed track")?;
+                        written += write_row(&mut upsert, track, &json, digest(&json))?;
+                    }
+                    None => written += delete.execute([path.to_string_lossy()])?,
+                }
+            }
+        }
+        tx.commit().context("Failed to write index database")?;
+        Ok(written)
+    }
+
+    /// Tracks whose `field` equals `value`, through the field's index.
+    pub fn find(&self, field: Field, value: &str) -> Result<Vec<IndexedTrack>> {
+        let sql = format!("SELECT track FROM tracks WHERE {}", field.condition());
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([value], |row| row.get::<_, String>(0))?;
+        rows.map(|json| {
+            serde_json::from_str(&json?).context("Failed to parse track in index database")
+        })
+        .collect()
+    }
+}
+
+/// Run `UPSERT` for one track; 0 when the stored row already matches.
+fn write_row(
+    upsert: &mut rusqlite::Statement,
+    track: &IndexedTrack,
+    json: &str,
+    digest: i64,
+) -> Result<usize> {
+    Ok(upsert.execute(params![
+        track.path.to_string_lossy(),
+        track.id,
+        track.metadata.artist,
+        track.metadata.album,
+        track.metadata.fingerprint,
+        digest,
+        json
+    ])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::organizer::TrackMetadata;
+    use std::path::PathBuf;
+
+    #[test]
+    fn digest_is_fnv1a() {
+        assert_eq!(digest(""), 0xcbf29ce484222325_u64 as i64);
+        assert_eq!(digest("a"), 0xaf63dc4c8601ec8c_u64 as i64);
+    }
+
+    #[test]
+    fn saves_only_changed_rows() {
+        let db = std::env::temp_dir().join(format!("audio_sorter_index_{}.db", std::process::id()));
+        let mut library = AudioLibrary::default();
+        for name in ["a.mp3", "b.mp3"] {
+            library.upsert(PathBuf::from(name), 1, 2, 3, TrackMetadata::default());
+        }
+
+        let mut index = SqliteIndex::open(&db).unwrap();
+        let first = index.save(&library).unwrap();
+        let unchanged = index.save(&library).unwrap();
+        library.files.remove(Path::new("b.mp3"));
+        let removed = index.save(&library).unwrap();
+        let loaded = index.load().unwrap();
+        drop(index);
+        std::fs::remove_file(&db).unwrap();
+
+        assert_eq!((first, unchanged, removed), (2, 0, 1));
+        assert_eq!(loaded.files.len(), 1);
+    }
+
+    #[test]
+    fn saves_only_the_given_paths_and_finds_by_column() {
+        let db = std::env::temp_dir().join(format!("audio_sorter_find_{}.db", std::process::id()));
+        let mut library = AudioLibrary::default();
+        for (name, artist) in [("a.mp3", "Band"), ("b.mp3", "band"), ("c.mp3", "Other")] {
+            let meta = TrackMetadata {
+                artist: artist.to_string(),
+                ..Default::default()
+            };
+            library.upsert(PathBuf::from(name), 1, 2, 3, meta);
+        }
+
+        let mut index = SqliteIndex::open(&db).unwrap();
+        index.save(&library).unwrap();
+        library
+            .files
+            .get_mut(Path::new("a.mp3"))
+            .unwrap()
+            .metadata
+            .album = Some("LP".into());
+        library.files.remove(Path::new("c.mp3"));
+        let paths = ["a.mp3", "b.mp3", "c.mp3"].map(PathBuf::from);
+        let written = index.save_changes(&library, &paths).unwrap();
+        let by_artist = index.find(Field::Artist, "BAND").unwrap();
+        let by_album = index.find(Field::Album, "lp").unwrap();
+        let all = index.load().unwrap();
+        drop(index);
+        std::fs::remove_file(&db).unwrap();
+
+        // b.mp3 is unchanged, so only a.mp3 and c.mp3 are written
+        assert_eq!(written, 2);
+        assert_eq!(by_artist.len(), 2);
+        assert_eq!(by_album.len(), 1);
+        assert_eq!(by_album[0].path, PathBuf::from("a.mp3"));
+        assert_eq!(all.files.len(), 2);
+    }
+}
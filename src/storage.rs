@@ -1,55 +1,781 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use utoipa::ToSchema;
 
 use crate::organizer::TrackMetadata;
+use crate::scanner;
+use crate::spoken_word::ContentKind;
+use crate::unicode;
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct AudioLibrary {
-    pub files: HashMap<PathBuf, IndexedTrack>,
+    pub files: Tracks,
+}
+
+/// Paths added, changed or removed since the library was loaded from or
+/// saved to `db`.
+#[derive(Debug, Clone)]
+struct Changes {
+    db: PathBuf,
+    paths: HashSet<PathBuf>,
+}
+
+/// The indexed tracks by path. Reads go through `Deref` to the map; writes
+/// go through the methods below, which remember the paths they touch so an
+/// `index.db` save only has to write those rows.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct Tracks {
+    map: HashMap<PathBuf, IndexedTrack>,
+    /// `None` while the library is not known to match a database
+    #[serde(skip)]
+    changes: Mutex<Option<Changes>>,
+    /// Path of each track ID, built on first lookup and dropped by changes
+    /// that could alter IDs
+    #[serde(skip)]
+    ids: OnceLock<HashMap<String, PathBuf>>,
+}
+
+impl Clone for Tracks {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+            changes: Mutex::new(self.changes.lock().unwrap().clone()),
+            ids: self.ids.clone(),
+        }
+    }
+}
+
+impl Deref for Tracks {
+    type Target = HashMap<PathBuf, IndexedTrack>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl<'a> IntoIterator for &'a Tracks {
+    type Item = (&'a PathBuf, &'a IndexedTrack);
+    type IntoIter = std::collections::hash_map::Iter<'a, PathBuf, IndexedTrack>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.iter()
+    }
+}
+
+impl Tracks {
+    fn touch(&mut self, path: &Path) {
+        if let Some(changes) = self.changes.get_mut().unwrap() {
+            changes.paths.insert(path.to_path_buf());
+        }
+    }
+
+    pub fn insert(&mut self, path: PathBuf, track: IndexedTrack) -> Option<IndexedTrack> {
+        self.touch(&path);
+        if let Some(ids) = self.ids.get_mut() {
+            ids.insert(track.id.clone(), path.clone());
+        }
+        self.map.insert(path, track)
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<IndexedTrack> {
+        self.touch(path);
+        let removed = self.map.remove(path);
+        if let (Some(ids), Some(track)) = (self.ids.get_mut(), &removed) {
+            ids.remove(&track.id);
+        }
+        removed
+    }
+
+    pub fn get_mut(&mut self, path: &Path) -> Option<&mut IndexedTrack> {
+        self.touch(path);
+        self.ids.take();
+        self.map.get_mut(path)
+    }
+
+    /// Every track, mutably; all of them count as changed.
+    pub fn values_mut(
+        &mut self,
+    ) -> std::collections::hash_map::ValuesMut<'_, PathBuf, IndexedTrack> {
+        if let Some(changes) = self.changes.get_mut().unwrap() {
+            changes.paths.extend(self.map.keys().cloned());
+        }
+        self.ids.take();
+        self.map.values_mut()
+    }
+
+    fn ids(&self) -> &HashMap<String, PathBuf> {
+        self.ids.get_or_init(|| {
+            self.map
+                .values()
+                .map(|t| (t.id.clone(), t.path.clone()))
+                .collect()
+        })
+    }
+
+    /// The track with ID `id`, through the ID map.
+    pub fn by_id(&self, id: &str) -> Option<&IndexedTrack> {
+        let ids = self.ids();
+        // A replaced track may have left its old ID behind
+        self.map.get(ids.get(id)?).filter(|t| t.id == id)
+    }
+
+    pub fn into_values(self) -> std::collections::hash_map::IntoValues<PathBuf, IndexedTrack> {
+        self.map.into_values()
+    }
+
+    /// Start recording changes against `db`, which holds the tracks as they
+    /// are now apart from `pending`.
+    fn track_changes(&mut self, db: &Path, pending: Vec<PathBuf>) {
+        *self.changes.get_mut().unwrap() = Some(Changes {
+            db: db.to_path_buf(),
+            paths: pending.into_iter().collect(),
+        });
+    }
+
+    /// Paths to write to `db`, or `None` when the whole library has to be
+    /// compared with it (loaded from elsewhere).
+    #[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+    fn changed_since(&self, db: &Path) -> Option<Vec<PathBuf>> {
+        let changes = self.changes.lock().unwrap();
+        changes
+            .as_ref()
+            .filter(|c| c.db == db)
+            .map(|c| c.paths.iter().cloned().collect())
+    }
+
+    /// `db` now holds exactly these tracks.
+    #[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+    fn mark_saved(&self, db: &Path) {
+        *self.changes.lock().unwrap() = Some(Changes {
+            db: db.to_path_buf(),
+            paths: HashSet::new(),
+        });
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct IndexedTrack {
+    /// Stable ID derived from the path (see `track_id`)
+    #[serde(default)]
+    pub id: String,
+    #[schema(value_type = String)]
     pub path: PathBuf,
     pub file_size: u64,
     pub modified_time: u64, // UNIX timestamp (seconds)
     pub scanned_at: u64,    // UNIX timestamp (seconds)
+    #[serde(default)]
+    pub added_at: u64, // UNIX timestamp (seconds) of first indexing
     pub metadata: TrackMetadata,
 }
 
+/// Key under which two spellings of a path name the same file: NFC on every
+/// platform, and case-folded on Windows and macOS, whose default filesystems
+/// ignore case. Only for comparison; files are opened by their own path.
+pub fn path_key(path: &Path) -> PathBuf {
+    let key = unicode::nfc_path(path);
+    if cfg!(any(windows, target_os = "macos")) {
+        if let Some(s) = key.to_str() {
+            return PathBuf::from(s.to_lowercase());
+        }
+    }
+    key
+}
+
+/// Short stable identifier for a track, used in URLs instead of the full path.
+pub fn track_id(path: &Path) -> String {
+    format!("{:016x}", scanner::stable_path_hash(path))
+}
+
+impl IndexedTrack {
+    /// Fill in what older indexes lack. Returns whether anything changed.
+    fn upgrade(&mut self) -> bool {
+        let mut changed = false;
+        // Indexes written before track IDs existed
+        if self.id.is_empty() {
+            self.id = track_id(&self.path);
+            changed = true;
+        }
+        // ...or before text was stored in NFC
+        changed |= self.metadata.normalize_unicode();
+        changed
+    }
+
+    /// When the track first entered the index (older indexes only know `scanned_at`).
+    pub fn first_seen(&self) -> u64 {
+        if self.added_at > 0 {
+            self.added_at
+        } else {
+            self.scanned_at
+        }
+    }
+}
+
+/// `index.db` next to `index_path` when that names an `index.json` and the
+/// `sqlite` feature is enabled.
+fn database_path(index_path: &Path) -> Option<PathBuf> {
+    if cfg!(feature = "sqlite") && index_path.file_name()? == "index.json" {
+        Some(index_path.with_file_name("index.db"))
+    } else {
+        None
+    }
+}
+
+/// The file the index named `index_path` is actually kept in: `index.db`
+/// once a `sqlite` build has saved it, otherwise `index_path` itself.
+pub fn stored_path(index_path: &Path) -> PathBuf {
+    database_path(index_path)
+        .filter(|db| db.exists())
+        .unwrap_or_else(|| index_path.to_path_buf())
+}
+
 impl AudioLibrary {
+    /// Load `index.json`, or in `sqlite` builds the `index.db` next to it
+    /// once that exists.
     pub fn load(path: &Path) -> Result<Self> {
-        if !path.exists() {
+        let stored = stored_path(path);
+        if !stored.exists() {
             return Ok(Self::default());
         }
-        let content = fs::read_to_string(path).context("Failed to read library index file")?;
-        let library =
-            serde_json::from_str(&content).context("Failed to parse library index JSON")?;
+        #[cfg(feature = "sqlite")]
+        let mut library = if stored != path {
+            crate::sqlite_index::SqliteIndex::open(&stored)?.load()?
+        } else {
+            Self::load_json(path)?
+        };
+        #[cfg(not(feature = "sqlite"))]
+        let mut library = Self::load_json(path)?;
+        let normalized: Vec<PathBuf> = library
+            .files
+            .values_mut()
+            .filter_map(|t| t.upgrade().then(|| t.path.clone()))
+            .collect();
+        if stored != path {
+            library.files.track_changes(&stored, normalized);
+        }
+        // Build the ID map now rather than on the first request
+        library.files.ids();
         Ok(library)
     }
 
+    /// Tracks whose `field` equals `value`, read through the column index of
+    /// `index.db` without loading the library. `None` while the index named
+    /// `path` is not kept in a database.
+    #[cfg(feature = "sqlite")]
+    pub fn find_stored(
+        path: &Path,
+        field: crate::sqlite_index::Field,
+        value: &str,
+    ) -> Option<Result<Vec<IndexedTrack>>> {
+        let stored = stored_path(path);
+        if stored == path {
+            return None;
+        }
+        let found = crate::sqlite_index::SqliteIndex::open(&stored).and_then(|db| {
+            let mut tracks = db.find(field, value)?;
+            for track in &mut tracks {
+                track.upgrade();
+            }
+            Ok(tracks)
+        });
+        Some(found)
+    }
+
+    fn load_json(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).context("Failed to read library index file")?;
+        serde_json::from_str(&content).context("Failed to parse library index JSON")
+    }
+
     pub fn save(&self, path: &Path) -> Result<()> {
+        #[cfg(feature = "sqlite")]
+        if let Some(db) = database_path(path) {
+            return self.save_database(path, &db);
+        }
         let content =
             serde_json::to_string_pretty(self).context("Failed to serialize library index")?;
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).context("Failed to create library index directory")?;
         }
-        fs::write(path, content).context("Failed to write library index file")?;
+        // Write then rename, so readers never see a half-written index
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, content).context("Failed to write library index file")?;
+        fs::rename(&tmp, path).context("Failed to replace library index file")?;
         Ok(())
     }
 
-    pub fn find_duplicates(&self) -> Vec<Vec<IndexedTrack>> {
+    /// Write changed rows to `db`: only the tracks changed since the library
+    /// was loaded from or saved to it, or else every row that differs. The
+    /// first save moves the `index.json` it replaces aside as
+    /// `index.json.migrated`.
+    #[cfg(feature = "sqlite")]
+    fn save_database(&self, index_path: &Path, db: &Path) -> Result<()> {
+        if let Some(parent) = db.parent() {
+            fs::create_dir_all(parent).context("Failed to create library index directory")?;
+        }
+        let mut index = crate::sqlite_index::SqliteIndex::open(db)?;
+        match self.files.changed_since(db) {
+            Some(paths) => index.save_changes(self, &paths)?,
+            None => index.save(self)?,
+        };
+        self.files.mark_saved(db);
+        if index_path.exists() {
+            let backup = index_path.with_extension("json.migrated");
+            fs::rename(index_path, &backup).context("Failed to move migrated index aside")?;
+            println!(
+                "Migrated {:?} to {:?} (kept as {:?}).",
+                index_path, db, backup
+            );
+        }
+        Ok(())
+    }
+
+    /// Insert or replace a processed track, keeping the original `added_at`.
+    pub fn upsert(
+        &mut self,
+        path: PathBuf,
+        file_size: u64,
+        modified_time: u64,
+        scanned_at: u64,
+        metadata: TrackMetadata,
+    ) {
+        let added_at = self
+            .files
+            .get(&path)
+            .map(|t| t.first_seen())
+            .unwrap_or(scanned_at);
+        self.files.insert(
+            path.clone(),
+            IndexedTrack {
+                id: track_id(&path),
+                path,
+                file_size,
+                modified_time,
+                scanned_at,
+                added_at,
+                metadata,
+            },
+        );
+    }
+
+    /// Drop entries that `path_key` maps onto the same file (indexed under
+    /// two casings by older versions), keeping the most recently scanned one.
+    /// Returns the dropped paths.
+    pub fn remove_key_duplicates(&mut self) -> Vec<PathBuf> {
+        let mut groups: HashMap<PathBuf, Vec<&IndexedTrack>> = HashMap::new();
+        for track in self.files.values() {
+            groups.entry(path_key(&track.path)).or_default().push(track);
+        }
+
+        // (kept path, dropped paths, earliest first-seen time of the group)
+        let merges: Vec<(PathBuf, Vec<PathBuf>, u64)> = groups
+            .into_values()
+            .filter(|g| g.len() > 1)
+            .map(|mut group| {
+                group.sort_by(|a, b| (b.scanned_at, &b.path).cmp(&(a.scanned_at, &a.path)));
+                let added_at = group.iter().map(|t| t.first_seen()).min().unwrap_or(0);
+                let dropped = group[1..].iter().map(|t| t.path.clone()).collect();
+                (group[0].path.clone(), dropped, added_at)
+            })
+            .collect();
+
+        let mut removed = Vec::new();
+        for (kept, dropped, added_at) in merges {
+            for path in dropped {
+                self.files.remove(&path);
+                removed.push(path);
+            }
+            if let Some(track) = self.files.get_mut(&kept) {
+                track.added_at = added_at;
+            }
+        }
+        removed.sort();
+        removed
+    }
+
+    pub fn find_by_id(&self, id: &str) -> Option<&IndexedTrack> {
+        self.files.by_id(id)
+    }
+
+    /// All tracks in the default stable order (see `TrackOrder`).
+    pub fn sorted_tracks(&self) -> Vec<&IndexedTrack> {
+        let mut tracks: Vec<&IndexedTrack> = self.files.values().collect();
+        tracks.sort_by_cached_key(|t| TrackOrder::of(t));
+        tracks
+    }
+
+    /// Group tracks sharing a fingerprint. Spoken-word content is skipped unless requested.
+    pub fn find_duplicates(&self, include_spoken_word: bool) -> Vec<Vec<IndexedTrack>> {
         let mut groups: HashMap<String, Vec<IndexedTrack>> = HashMap::new();
 
         for track in self.files.values() {
+            if !include_spoken_word && track.metadata.content_kind == ContentKind::SpokenWord {
+                continue;
+            }
             if let Some(fp) = &track.metadata.fingerprint {
                 groups.entry(fp.clone()).or_default().push(track.clone());
             }
         }
 
-        groups.into_values().filter(|g| g.len() > 1).collect()
+        let mut groups: Vec<Vec<IndexedTrack>> =
+            groups.into_values().filter(|g| g.len() > 1).collect();
+        for group in &mut groups {
+            group.sort_by(compare_tracks);
+        }
+        groups.sort_by(|a, b| compare_tracks(&a[0], &b[0]));
+        groups
+    }
+}
+
+/// Default listing order: artist -> album -> disc -> track, then title and path
+/// as tie-breakers so the result never depends on HashMap iteration order.
+/// Text compares case-insensitively; missing album/disc/track numbers sort last.
+///
+/// Built once per track when sorting many of them
+/// (`sort_by_cached_key(|t| TrackOrder::of(t))`) instead of lowercasing the
+/// text again on every comparison.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TrackOrder<'a> {
+    artist: String,
+    /// `(missing, value)`, so present values sort first
+    album: (bool, Option<String>),
+    disc: (bool, Option<u32>),
+    track: (bool, Option<u32>),
+    title: String,
+    path: &'a Path,
+}
+
+impl<'a> TrackOrder<'a> {
+    pub fn of(track: &'a IndexedTrack) -> Self {
+        fn missing_last<T>(value: Option<T>) -> (bool, Option<T>) {
+            (value.is_none(), value)
+        }
+        let meta = &track.metadata;
+        Self {
+            artist: meta.artist.to_lowercase(),
+            album: missing_last(meta.album.as_deref().map(str::to_lowercase)),
+            disc: missing_last(meta.disc_number),
+            track: missing_last(meta.track_number),
+            title: meta.title.to_lowercase(),
+            path: &track.path,
+        }
+    }
+}
+
+/// Compare two tracks in the default listing order (see `TrackOrder`).
+pub fn compare_tracks(a: &IndexedTrack, b: &IndexedTrack) -> Ordering {
+    TrackOrder::of(a).cmp(&TrackOrder::of(b))
+}
+
+/// Modification time of the file the index named `path` is stored in.
+pub fn index_mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(stored_path(path))
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+/// A consistent, read-only view of the library. `generation` increases every
+/// time the shared library is reloaded or replaced, so two snapshots with the
+/// same generation hold identical data.
+#[derive(Clone)]
+pub struct LibrarySnapshot {
+    pub generation: u64,
+    /// Index modification time the snapshot reflects (the HTTP cache validator)
+    pub modified: Option<SystemTime>,
+    pub library: Arc<AudioLibrary>,
+}
+
+/// The published snapshot and the index file it was read from.
+struct Current {
+    path: PathBuf,
+    snapshot: RwLock<LibrarySnapshot>,
+    /// Set while a background reload is running
+    reloading: AtomicBool,
+}
+
+impl Current {
+    /// Re-read the index if it changed on disk since the published snapshot.
+    /// The file is parsed outside the lock, and the result dropped if a
+    /// `replace` published a newer library in the meantime.
+    fn reload_if_changed(&self) -> LibrarySnapshot {
+        let mtime = index_mtime(&self.path);
+        let (generation, modified) = {
+            let inner = self.snapshot.read().unwrap();
+            (inner.generation, inner.modified)
+        };
+        if modified == mtime {
+            return self.snapshot.read().unwrap().clone();
+        }
+        match AudioLibrary::load(&self.path) {
+            Ok(library) => {
+                let mut inner = self.snapshot.write().unwrap();
+                if inner.generation == generation {
+                    *inner = LibrarySnapshot {
+                        generation: generation + 1,
+                        modified: mtime,
+                        library: Arc::new(library),
+                    };
+                }
+                inner.clone()
+            }
+            // Likely caught mid-write; keep serving the previous snapshot
+            Err(e) => {
+                eprintln!("Could not reload index: {}", e);
+                self.snapshot.read().unwrap().clone()
+            }
+        }
+    }
+}
+
+/// In-memory library shared by the web handlers.
+/// The index is parsed once and only reloaded when `index.json` changes on disk
+/// (e.g. a CLI scan or enrich run in another process). Readers are never held
+/// up by a reload: it runs on a background thread while they keep getting the
+/// previous snapshot.
+///
+/// Long-running exports can `pin` the current snapshot and keep reading it by
+/// generation while scans save new versions of the index underneath.
+pub struct SharedLibrary {
+    current: Arc<Current>,
+    /// Pinned snapshots by generation, with their expiry
+    pinned: Mutex<BTreeMap<u64, (Instant, LibrarySnapshot)>>,
+}
+
+impl SharedLibrary {
+    pub fn new(path: PathBuf) -> Self {
+        let mtime = index_mtime(&path);
+        let library = AudioLibrary::load(&path).unwrap_or_else(|e| {
+            eprintln!("Could not load index: {}. Starting empty.", e);
+            AudioLibrary::default()
+        });
+        Self {
+            current: Arc::new(Current {
+                path,
+                snapshot: RwLock::new(LibrarySnapshot {
+                    generation: 1,
+                    modified: mtime,
+                    library: Arc::new(library),
+                }),
+                reloading: AtomicBool::new(false),
+            }),
+            pinned: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Current library (see `snapshot`).
+    pub fn get(&self) -> Arc<AudioLibrary> {
+        self.snapshot().library
+    }
+
+    /// Like `get`, also returning the index modification time the snapshot reflects
+    /// (used as the HTTP cache validator).
+    pub fn get_with_modified(&self) -> (Option<SystemTime>, Arc<AudioLibrary>) {
+        let snapshot = self.snapshot();
+        (snapshot.modified, snapshot.library)
+    }
+
+    /// Current snapshot with its generation. If the index file was modified,
+    /// starts reloading it in the background and returns the previous
+    /// snapshot until that is done.
+    pub fn snapshot(&self) -> LibrarySnapshot {
+        let snapshot = self.current.snapshot.read().unwrap().clone();
+        if snapshot.modified != index_mtime(&self.current.path)
+            && !self.current.reloading.swap(true, AtomicOrdering::AcqRel)
+        {
+            let current = self.current.clone();
+            std::thread::spawn(move || {
+                current.reload_if_changed();
+                current.reloading.store(false, AtomicOrdering::Release);
+            });
+        }
+        snapshot
+    }
+
+    /// Current snapshot, reloading first on this thread if the index file
+    /// was modified. Blocks; not for async handlers.
+    pub fn snapshot_now(&self) -> LibrarySnapshot {
+        self.current.reload_if_changed()
+    }
+
+    /// Swap in a freshly saved library (after a scan) without re-parsing it.
+    /// Returns the generation it was published as.
+    pub fn replace(&self, library: AudioLibrary) -> u64 {
+        let mut inner = self.current.snapshot.write().unwrap();
+        *inner = LibrarySnapshot {
+            generation: inner.generation + 1,
+            modified: index_mtime(&self.current.path),
+            library: Arc::new(library),
+        };
+        inner.generation
+    }
+
+    /// Make the next `snapshot` re-read the index from disk.
+    pub fn invalidate(&self) {
+        self.current.snapshot.write().unwrap().modified = None;
+    }
+
+    /// Keep the current snapshot readable through `pinned` for `ttl`.
+    /// Pinning the same generation again extends its expiry.
+    pub fn pin(&self, ttl: Duration) -> LibrarySnapshot {
+        let snapshot = self.snapshot();
+        let mut pinned = self.pinned.lock().unwrap();
+        let now = Instant::now();
+        pinned.retain(|_, (expires, _)| *expires > now);
+        pinned.insert(snapshot.generation, (now + ttl, snapshot.clone()));
+        snapshot
+    }
+
+    /// A pinned snapshot by generation, or `None` once released or expired.
+    pub fn pinned(&self, generation: u64) -> Option<LibrarySnapshot> {
+        let pinned = self.pinned.lock().unwrap();
+        pinned
+            .get(&generation)
+            .filter(|(expires, _)| *expires > Instant::now())
+            .map(|(_, snapshot)| snapshot.clone())
+    }
+
+    /// Drop a pinned snapshot early. Returns whether it was pinned.
+    pub fn release(&self, generation: u64) -> bool {
+        self.pinned.lock().unwrap().remove(&generation).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_key_unifies_unicode_forms() {
+        let composed = Path::new("Music/Caf\u{e9}.mp3");
+        let decomposed = Path::new("Music/Cafe\u{301}.mp3");
+        assert_eq!(path_key(composed), path_key(decomposed));
+    }
+
+    #[test]
+    fn path_key_folds_case_only_where_the_filesystem_does() {
+        let same = path_key(Path::new("Music/Song.mp3")) == path_key(Path::new("music/song.MP3"));
+        assert_eq!(same, cfg!(any(windows, target_os = "macos")));
+    }
+
+    #[test]
+    fn key_duplicates_keep_the_latest_scan_and_earliest_add() {
+        let mut library = AudioLibrary::default();
+        let old = PathBuf::from("Music/Cafe\u{301}.mp3");
+        let new = PathBuf::from("Music/Caf\u{e9}.mp3");
+        library.upsert(old.clone(), 1, 1, 100, TrackMetadata::default());
+        library.upsert(new.clone(), 1, 1, 200, TrackMetadata::default());
+
+        assert_eq!(library.remove_key_duplicates(), vec![old]);
+        assert_eq!(library.files.len(), 1);
+        assert_eq!(library.files[&new].added_at, 100);
+    }
+
+    #[test]
+    fn tracks_record_changes_once_tied_to_a_database() {
+        let mut library = AudioLibrary::default();
+        let (a, b) = (PathBuf::from("a.mp3"), PathBuf::from("b.mp3"));
+        let db = Path::new("index.db");
+        library.upsert(a.clone(), 1, 1, 1, TrackMetadata::default());
+        assert_eq!(library.files.changed_since(db), None);
+
+        library.files.track_changes(db, Vec::new());
+        library.upsert(b.clone(), 1, 1, 1, TrackMetadata::default());
+        library.files.remove(&a);
+        let mut changed = library.files.changed_since(db).unwrap();
+        changed.sort();
+        assert_eq!(changed, vec![a, b]);
+        assert_eq!(library.files.changed_since(Path::new("other.db")), None);
+
+        library.files.mark_saved(db);
+        assert_eq!(library.files.changed_since(db), Some(Vec::new()));
+    }
+
+    #[test]
+    fn find_by_id_follows_inserts_removals_and_edits() {
+        let mut library = AudioLibrary::default();
+        let (a, b) = (PathBuf::from("a.mp3"), PathBuf::from("b.mp3"));
+        library.upsert(a.clone(), 1, 1, 1, TrackMetadata::default());
+        let id_a = library.files[&a].id.clone();
+        assert_eq!(library.find_by_id(&id_a).unwrap().path, a);
+
+        library.upsert(b.clone(), 1, 1, 1, TrackMetadata::default());
+        let id_b = library.files[&b].id.clone();
+        assert_eq!(library.find_by_id(&id_b).unwrap().path, b);
+
+        library.files.remove(&a);
+        assert!(library.find_by_id(&id_a).is_none());
+
+        library.files.get_mut(&b).unwrap().id = "renamed".to_string();
+        assert!(library.find_by_id(&id_b).is_none());
+        assert_eq!(library.find_by_id("renamed").unwrap().path, b);
+    }
+
+    #[test]
+    fn snapshot_reloads_in_the_background() {
+        let dir = std::env::temp_dir().join(format!("audio_sorter_shared_{}", std::process::id()));
+        let index_path = dir.join("index.json");
+        let mut library = AudioLibrary::default();
+        library.upsert(PathBuf::from("a.mp3"), 1, 1, 1, TrackMetadata::default());
+        library.save(&index_path).unwrap();
+        let shared = SharedLibrary::new(index_path.clone());
+
+        library.upsert(PathBuf::from("b.mp3"), 1, 1, 1, TrackMetadata::default());
+        library.save(&index_path).unwrap();
+        // Make the change visible even with coarse file times
+        let later = SystemTime::now() + Duration::from_secs(10);
+        fs::File::options()
+            .write(true)
+            .open(stored_path(&index_path))
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        let stale = shared.snapshot();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut fresh = shared.snapshot();
+        while fresh.generation == stale.generation && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+            fresh = shared.snapshot();
+        }
+        let now = shared.snapshot_now();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(stale.library.files.len(), 1);
+        assert_eq!(fresh.library.files.len(), 2);
+        assert_eq!(now.generation, fresh.generation);
+    }
+
+    #[test]
+    fn default_order_ignores_case_and_puts_missing_numbers_last() {
+        let mut library = AudioLibrary::default();
+        let tracks = [
+            ("c.mp3", "beta", Some("x"), Some(1)),
+            ("b.mp3", "Alpha", None, Some(1)),
+            ("a.mp3", "alpha", Some("Z"), None),
+            ("d.mp3", "ALPHA", Some("z"), Some(2)),
+        ];
+        for (path, artist, album, track_number) in tracks {
+            let meta = TrackMetadata {
+                artist: artist.to_string(),
+                album: album.map(str::to_string),
+                track_number,
+                ..Default::default()
+            };
+            library.upsert(PathBuf::from(path), 1, 1, 1, meta);
+        }
+        let order: Vec<&Path> = library
+            .sorted_tracks()
+            .iter()
+            .map(|t| t.path.as_path())
+            .collect();
+        let expected = ["d.mp3", "a.mp3", "b.mp3", "c.mp3"].map(Path::new);
+        assert_eq!(order, expected);
     }
 }
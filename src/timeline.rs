@@ -0,0 +1,153 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+use crate::storage::{AudioLibrary, IndexedTrack};
+
+/// Oldest snapshots are dropped beyond this many scans.
+const MAX_SNAPSHOTS: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct FormatUsage {
+    pub tracks: usize,
+    pub bytes: u64,
+}
+
+/// Library size after one completed scan.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ScanSnapshot {
+    /// UNIX timestamp (seconds)
+    pub timestamp: u64,
+    pub total_tracks: usize,
+    pub total_size: u64,
+    /// Keyed by lowercase file extension
+    pub formats: BTreeMap<String, FormatUsage>,
+}
+
+/// Disk usage of one format, artist or album in the current index.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct UsageEntry {
+    pub name: String,
+    pub tracks: usize,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct StorageTimeline {
+    /// One entry per completed scan, oldest first
+    pub snapshots: Vec<ScanSnapshot>,
+    /// Current storage per format, largest first
+    pub formats: Vec<UsageEntry>,
+    pub top_artists: Vec<UsageEntry>,
+    /// Named "Album Artist — Album"
+    pub top_albums: Vec<UsageEntry>,
+}
+
+fn format_of(track: &IndexedTrack) -> String {
+    track
+        .path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+pub fn snapshot(library: &AudioLibrary) -> ScanSnapshot {
+    let mut formats: BTreeMap<String, FormatUsage> = BTreeMap::new();
+    for track in library.files.values() {
+        let usage = formats.entry(format_of(track)).or_default();
+        usage.tracks += 1;
+        usage.bytes += track.file_size;
+    }
+    ScanSnapshot {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        total_tracks: library.files.len(),
+        total_size: library.files.values().map(|t| t.file_size).sum(),
+        formats,
+    }
+}
+
+/// Load `scan_history.json`. A missing file means no scans were recorded yet.
+pub fn load_history(path: &Path) -> Result<Vec<ScanSnapshot>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).context("Failed to read scan history")?;
+    serde_json::from_str(&content).context("Failed to parse scan history JSON")
+}
+
+/// Append a snapshot of the library to the scan history.
+pub fn record(path: &Path, library: &AudioLibrary) -> Result<()> {
+    let mut history = load_history(path)?;
+    history.push(snapshot(library));
+    if history.len() > MAX_SNAPSHOTS {
+        history.drain(..history.len() - MAX_SNAPSHOTS);
+    }
+    let content = serde_json::to_string(&history).context("Failed to serialize scan history")?;
+    fs::write(path, content).context("Failed to write scan history")?;
+    Ok(())
+}
+
+/// Entries are keyed case-insensitively; the first spelling seen is kept.
+fn add(map: &mut HashMap<String, UsageEntry>, name: String, bytes: u64) {
+    let entry = map
+        .entry(name.to_lowercase())
+        .or_insert_with(|| UsageEntry {
+            name,
+            tracks: 0,
+            bytes: 0,
+        });
+    entry.tracks += 1;
+    entry.bytes += bytes;
+}
+
+fn ranked(usage: HashMap<String, UsageEntry>, limit: usize) -> Vec<UsageEntry> {
+    let mut entries: Vec<UsageEntry> = usage.into_values().collect();
+    entries.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    entries.truncate(limit);
+    entries
+}
+
+/// Growth history plus current storage breakdown, keeping the `top` biggest
+/// artists and albums.
+pub fn timeline(
+    library: &AudioLibrary,
+    snapshots: Vec<ScanSnapshot>,
+    top: usize,
+) -> StorageTimeline {
+    let mut formats: HashMap<String, UsageEntry> = HashMap::new();
+    let mut artists: HashMap<String, UsageEntry> = HashMap::new();
+    let mut albums: HashMap<String, UsageEntry> = HashMap::new();
+
+    for track in library.files.values() {
+        let meta = &track.metadata;
+        let artist = if meta.artist.is_empty() {
+            "Unknown Artist"
+        } else {
+            &meta.artist
+        };
+        add(&mut formats, format_of(track), track.file_size);
+        add(&mut artists, artist.to_string(), track.file_size);
+        if let Some(album) = meta.album.as_deref().filter(|a| !a.is_empty()) {
+            let album_artist = meta.album_artist.as_deref().unwrap_or(artist);
+            add(
+                &mut albums,
+                format!("{} — {}", album_artist, album),
+                track.file_size,
+            );
+        }
+    }
+
+    StorageTimeline {
+        snapshots,
+        formats: ranked(formats, usize::MAX),
+        top_artists: ranked(artists, top),
+        top_albums: ranked(albums, top),
+    }
+}
@@ -0,0 +1,32 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+/// `s` in Unicode NFC, borrowed when it already is (the common case).
+pub fn nfc(s: &str) -> Cow<'_, str> {
+    match is_nfc_quick(s.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(s),
+        _ => Cow::Owned(s.nfc().collect()),
+    }
+}
+
+/// Rewrite `s` in place to NFC. Returns whether it changed.
+pub fn nfc_in_place(s: &mut String) -> bool {
+    match nfc(s) {
+        Cow::Owned(normalized) => {
+            *s = normalized;
+            true
+        }
+        Cow::Borrowed(_) => false,
+    }
+}
+
+/// Key for comparing paths: macOS lists names decomposed (NFD) while tags,
+/// bundles and other systems use NFC. Only for comparison; files must be
+/// opened by the path as listed on disk.
+pub fn nfc_path(path: &Path) -> PathBuf {
+    match path.to_str() {
+        Some(s) => PathBuf::from(nfc(s).into_owned()),
+        None => path.to_path_buf(),
+    }
+}
@@ -0,0 +1,141 @@
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io;
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+use crate::organizer;
+
+/// Outcome of `POST /api/upload`.
+#[derive(Serialize, Debug, Default, ToSchema)]
+pub struct UploadResult {
+    /// Paths written in the incoming directory
+    pub saved: Vec<String>,
+    pub rejected: Vec<RejectedUpload>,
+    /// Whether indexing of the new files started; while a scan is running
+    /// they stay queued and the next scan picks them up
+    pub scan_started: bool,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct RejectedUpload {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Whether the `Authorization: Bearer ...` value matches `token`, compared
+/// without stopping at the first differing byte.
+pub fn token_matches(authorization: Option<&str>, token: &str) -> bool {
+    let Some(given) = authorization.and_then(|h| h.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    let (a, b) = (given.trim().as_bytes(), token.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// File name of an upload with any client-side directories dropped and
+/// characters invalid on common file systems replaced.
+pub fn sanitize_file_name(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next()?.trim();
+    let clean = organizer::sanitize_component(base);
+    let clean = clean.trim_start_matches('.');
+    (!clean.is_empty()).then(|| clean.to_string())
+}
+
+/// Lowercase extension of `name` if the scanner indexes it.
+pub fn accepted_extension(name: &str, extensions: &[String]) -> Option<String> {
+    let ext = Path::new(name).extension()?.to_str()?.to_lowercase();
+    extensions
+        .iter()
+        .any(|e| e.eq_ignore_ascii_case(&ext))
+        .then_some(ext)
+}
+
+/// Create `dir/name` empty, or `dir/stem (n).ext` with the first free `n`,
+/// never replacing a file another upload created meanwhile. The empty file
+/// holds the name until the finished upload is renamed over it.
+pub fn create_unique(dir: &Path, name: &str) -> io::Result<PathBuf> {
+    let path = Path::new(name);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or(name);
+    let ext = path.extension().and_then(|e| e.to_str());
+    let mut n = 0;
+    loop {
+        let target = match (n, ext) {
+            (0, _) => dir.join(name),
+            (n, Some(ext)) => dir.join(format!("{} ({}).{}", stem, n, ext)),
+            (n, None) => dir.join(format!("{} ({})", stem, n)),
+        };
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&target)
+        {
+            Ok(_) => return Ok(target),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => n += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_drops_client_directories() {
+        assert_eq!(
+            sanitize_file_name("C:\\Users\\me\\Music\\song.mp3").as_deref(),
+            Some("song.mp3")
+        );
+        assert_eq!(
+            sanitize_file_name("../../etc/passwd.flac").as_deref(),
+            Some("passwd.flac")
+        );
+    }
+
+    #[test]
+    fn sanitize_replaces_invalid_characters() {
+        assert_eq!(
+            sanitize_file_name("AC<DC>: \"Live\"?.mp3").as_deref(),
+            Some("AC_DC__ _Live__.mp3")
+        );
+        assert_eq!(
+            sanitize_file_name(" .hidden.mp3 ").as_deref(),
+            Some("hidden.mp3")
+        );
+    }
+
+    #[test]
+    fn sanitize_rejects_empty_names() {
+        assert_eq!(sanitize_file_name(""), None);
+        assert_eq!(sanitize_file_name("dir/"), None);
+        assert_eq!(sanitize_file_name(".."), None);
+    }
+
+    #[test]
+    fn bearer_token_must_match_exactly() {
+        assert!(token_matches(Some("Bearer secret"), "secret"));
+        assert!(!token_matches(Some("Bearer secret2"), "secret"));
+        assert!(!token_matches(Some("secret"), "secret"));
+        assert!(!token_matches(None, "secret"));
+    }
+
+    #[test]
+    fn create_unique_numbers_taken_names() {
+        let dir = std::env::temp_dir().join(format!("audio_sorter_upload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let first = create_unique(&dir, "song.mp3").unwrap();
+        let second = create_unique(&dir, "song.mp3").unwrap();
+        let third = create_unique(&dir, "song.mp3").unwrap();
+        let bare = create_unique(&dir, "README").unwrap();
+        let bare_again = create_unique(&dir, "README").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first, dir.join("song.mp3"));
+        assert_eq!(second, dir.join("song (1).mp3"));
+        assert_eq!(third, dir.join("song (2).mp3"));
+        assert_eq!(bare, dir.join("README"));
+        assert_eq!(bare_again, dir.join("README (1)"));
+    }
+}
@@ -2,33 +2,111 @@ use anyhow::{Context, Result};
 use std::path::Path;
 
 use crate::acoustid;
+use crate::analyzers;
+use crate::decode::DecodedAudio;
 use crate::fingerprint;
+use crate::language;
+use crate::lookup_cache::LookupCache;
+use crate::lookup_queue::LookupQueue;
+use crate::matching;
+use crate::memory_budget;
+use crate::merge_policy::MergePolicy;
 use crate::musicbrainz;
-use crate::organizer::{self, TrackMetadata};
+use crate::organizer::{self, MetadataSource, TrackMetadata};
+use crate::progress::{ScanBars, Stage};
+use crate::quality;
+use crate::scanner::Pipeline;
+use crate::spoken_word;
 use crate::ScanArgs;
 
 // Import decoder trait and implementation
 use bliss_audio::decoder::symphonia::SymphoniaDecoder;
 use bliss_audio::decoder::Decoder as DecoderTrait;
 
+/// Metadata of a processed file and its bliss analysis, if one ran.
+pub type Processed = Result<(TrackMetadata, Option<Vec<f32>>)>;
+
+/// What every `process_file` call of one scan shares.
+#[derive(Clone, Copy)]
+pub struct ScanContext<'a> {
+    pub args: &'a ScanArgs,
+    pub cache: &'a LookupCache,
+    pub queue: &'a LookupQueue,
+    pub policy: &'a MergePolicy,
+    pub bars: Option<&'a ScanBars>,
+}
+
 pub fn process_file(
     path: &Path,
-    args: &ScanArgs,
+    ctx: &ScanContext,
     client: &reqwest::blocking::Client,
-) -> Result<(TrackMetadata, Option<Vec<f32>>)> {
+    pipeline: Pipeline,
+) -> Processed {
+    let ScanContext {
+        args,
+        cache,
+        queue,
+        policy,
+        bars,
+    } = *ctx;
     // Always compute fingerprint and duration
+    if let Some(bars) = bars {
+        bars.enter(Stage::Decode, path);
+    }
     let (duration, fp) =
         fingerprint::compute_fingerprint(path).context("Fingerprint generation failed")?;
+    if let Some(bars) = bars {
+        bars.complete(Stage::Decode);
+    }
 
-    let meta = if args.offline || args.client_id.is_none() {
+    // Offline scans can still replay responses cached by earlier online runs
+    let try_lookup = if !pipeline.lookup {
+        false
+    } else if args.offline {
+        cache.is_replay_only() && !cache.is_empty()
+    } else {
+        args.client_id.is_some()
+    };
+    let mut meta = if !try_lookup {
         let mut meta = organizer::read_tags(path).context("Failed to read local tags")?;
         meta.duration = duration;
         meta.fingerprint = Some(fp.clone());
         meta
     } else {
-        match perform_online_lookup(args, client, duration, &fp) {
-            Ok(meta) => meta,
-            Err(_e) => {
+        let client_id = args.client_id.as_deref().unwrap_or_default();
+        let local = organizer::read_tags(path).ok();
+        if let Some(bars) = bars {
+            bars.enter(Stage::Lookup, path);
+        }
+        let lookup = perform_online_lookup(client_id, client, cache, duration, &fp, local.as_ref());
+        if let Some(bars) = bars {
+            bars.complete(Stage::Lookup);
+        }
+        match lookup {
+            Ok(mut meta) => {
+                queue.remove(path);
+                meta.source = MetadataSource::Online;
+                // Remember which local tag values the online match replaced
+                if let Some(local) = local {
+                    policy.apply(path, &local, &mut meta);
+                    meta.overwritten_fields = organizer::changed_fields(&local, &meta);
+                    // AcoustID doesn't know the track's position on the release
+                    meta.album_artist = local.album_artist;
+                    meta.disc_number = local.disc_number;
+                    meta.track_number = local.track_number;
+                    meta.rating = local.rating;
+                }
+                meta
+            }
+            Err(e) => {
+                // Transient failures are retried by `lookups flush`
+                if !args.offline {
+                    if acoustid::is_unavailable(&e) {
+                        queue.enqueue(path, &e);
+                    } else {
+                        queue.remove(path);
+                    }
+                }
                 let mut meta = organizer::read_tags(path)?;
                 meta.duration = duration;
                 meta.fingerprint = Some(fp.clone());
@@ -37,101 +115,216 @@ pub fn process_file(
         }
     };
 
+    if let Some(bars) = bars {
+        bars.enter(Stage::Analyze, path);
+    }
+    let file_size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    // Both decodes below hold the whole file in memory
+    let _permit =
+        (pipeline.quality || pipeline.analysis).then(|| memory_budget::acquire(file_size));
+    // Quality signals for duplicate ranking and the registered analyzers share
+    // one decode at native sample rate; quality alone streams the file instead
+    if pipeline.analysis && analyzers::any_active() {
+        if let Ok(audio) = DecodedAudio::load(path) {
+            if pipeline.quality {
+                meta.quality = quality::analyze_decoded(&audio, file_size).ok();
+            }
+            meta.features = analyzers::analyze(&audio);
+        }
+    } else if pipeline.quality {
+        meta.quality = quality::analyze_file(path, file_size).ok();
+    }
+    meta.content_kind = spoken_word::detect(path, &meta);
+    meta.vocal_language = language::detect_vocal_language(&meta);
+
     // Melody Analysis (Bliss) using Symphonia decoder
-    let analysis = match SymphoniaDecoder::song_from_path(path) {
-        Ok(song) => {
-            // Convert Analysis to Vec<f32>
-            Some(song.analysis.as_vec())
+    let analysis = if !pipeline.analysis {
+        None
+    } else {
+        match SymphoniaDecoder::song_from_path(path) {
+            Ok(song) => {
+                // Convert Analysis to Vec<f32>
+                Some(song.analysis.as_vec())
+            }
+            Err(_e) => None,
         }
-        Err(_e) => None,
     };
+    if let Some(bars) = bars {
+        bars.complete(Stage::Analyze);
+    }
 
     Ok((meta, analysis))
 }
 
+/// Online-only enrichment of an already indexed track, reusing its stored fingerprint.
+/// The current values count as the local side of `policy`.
+/// Returns `Ok(false)` when the track has no fingerprint to look up.
+pub fn enrich_track(
+    path: &Path,
+    meta: &mut TrackMetadata,
+    client_id: &str,
+    client: &reqwest::blocking::Client,
+    cache: &LookupCache,
+    policy: &MergePolicy,
+) -> Result<bool> {
+    let Some(fp) = meta.fingerprint.clone() else {
+        return Ok(false);
+    };
+
+    let mut online =
+        perform_online_lookup(client_id, client, cache, meta.duration, &fp, Some(meta))?;
+    policy.apply(path, meta, &mut online);
+    organizer::merge_online(meta, online);
+    meta.vocal_language = language::detect_vocal_language(meta);
+    Ok(true)
+}
+
+/// What cover detection learned about a track's works.
+#[derive(Default)]
+struct OriginalWork {
+    /// First recording credited to someone other than the performer, as (artist, title)
+    original: Option<(String, String)>,
+    /// Earliest recording or release date among the works' recordings
+    year: Option<u32>,
+}
+
+/// Look up `works` for cover detection, spending at most
+/// `musicbrainz::work_lookup_budget()` work lookups per track.
+fn find_original(
+    client: &reqwest::blocking::Client,
+    cache: &LookupCache,
+    works: &[&musicbrainz::MBWork],
+    performer: &str,
+) -> OriginalWork {
+    let mut found = OriginalWork::default();
+    for work in works.iter().take(musicbrainz::work_lookup_budget()) {
+        let Ok(work_data) = musicbrainz::fetch_work_recordings(client, Some(cache), &work.id)
+        else {
+            continue;
+        };
+        for rel in work_data.relations.into_iter().flatten() {
+            let Some(rec) = rel.recording else { continue };
+            let year = [rel.begin.as_deref(), rec.first_release_date.as_deref()]
+                .into_iter()
+                .flatten()
+                .filter_map(organizer::parse_year)
+                .min();
+            found.year = match (found.year, year) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            if found.original.is_none() {
+                if let Some(first_credit) = rec.artist_credit.as_ref().and_then(|c| c.first()) {
+                    if first_credit.name != performer {
+                        found.original = Some((first_credit.name.clone(), rec.title));
+                    }
+                }
+            }
+        }
+        // Further works only cost requests once an original is known
+        if found.original.is_some() {
+            break;
+        }
+    }
+    found
+}
+
+/// Look up a fingerprint and build metadata from the best-ranked candidate.
+/// `local` (the file's own tags) helps choose between candidates.
 fn perform_online_lookup(
-    args: &ScanArgs,
+    client_id: &str,
     client: &reqwest::blocking::Client,
+    cache: &LookupCache,
     duration: f64,
     fp: &str,
+    local: Option<&TrackMetadata>,
 ) -> Result<TrackMetadata> {
-    let client_id = args
-        .client_id
-        .as_ref()
-        .context("No Client ID provided for online lookup")?;
-
-    let lookup =
-        acoustid::lookup_fingerprint(client_id, duration, fp).context("AcoustID lookup failed")?;
-
-    if let Some(results) = lookup.results {
-        if let Some(best_match) = results.first() {
-            if let Some(recordings) = &best_match.recordings {
-                if let Some(recording) = recordings.first() {
-                    let rec_id = &recording.id;
-                    let title = recording.title.as_deref().unwrap_or("Unknown Title");
-                    let artist = recording
-                        .artists
-                        .as_ref()
-                        .and_then(|a| a.first())
-                        .map(|a| a.name.as_str())
-                        .unwrap_or("Unknown Artist");
-
-                    let final_artist = artist.to_string();
-                    let final_title = title.to_string();
-                    let mut original_artist = None;
-                    let mut original_title = None;
-                    let album = None; // Metadata from AcoustID is limited, usually need MB lookups for album
-
-                    match musicbrainz::fetch_recording_details(client, rec_id) {
-                        Ok(mb_rec) => {
-                            if let Some(rels) = mb_rec.relations {
-                                for rel in rels {
-                                    if let Some(work) = rel.work {
-                                        if let Ok(work_data) =
-                                            musicbrainz::fetch_work_recordings(client, &work.id)
-                                        {
-                                            if let Some(work_rels) = work_data.relations {
-                                                for wr in work_rels {
-                                                    if let Some(rec) = wr.recording {
-                                                        if let Some(credits) = rec.artist_credit {
-                                                            if let Some(first_credit) =
-                                                                credits.first()
-                                                            {
-                                                                if first_credit.name != final_artist
-                                                                {
-                                                                    original_artist = Some(
-                                                                        first_credit.name.clone(),
-                                                                    );
-                                                                    original_title =
-                                                                        Some(rec.title.clone());
-                                                                    break;
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        Err(_) => {}
-                    }
+    let lookup = acoustid::lookup_fingerprint(Some(cache), client_id, duration, fp)
+        .context("AcoustID lookup failed")?;
 
-                    return Ok(TrackMetadata {
-                        title: final_title,
-                        artist: final_artist,
-                        album,
-                        original_artist,
-                        original_title,
-                        duration,
-                        fingerprint: Some(fp.to_string()),
-                    });
-                }
-            }
+    let candidates = matching::rank(&lookup, duration, local);
+    let Some(best) = candidates.first() else {
+        let had_recordings = lookup
+            .results
+            .iter()
+            .flatten()
+            .any(|r| r.recordings.as_ref().is_some_and(|v| !v.is_empty()));
+        if had_recordings {
+            return Err(anyhow::anyhow!(
+                "AcoustID matches rejected: recording length differs from the file by more than {}s",
+                matching::max_duration_delta()
+            ));
+        }
+        return Err(anyhow::anyhow!("No valid match found online"));
+    };
+    let rec_id = best.recording_mbid.clone();
+    let score = best.score;
+    let mut title = best.title.clone();
+    let mut artist = best.artist.clone();
+    let mb_rec = musicbrainz::fetch_recording_details(client, Some(cache), &rec_id);
+
+    // Self-hosted AcoustID servers may only know recording IDs
+    if let Ok(mb_rec) = &mb_rec {
+        if title.is_none() {
+            title = Some(mb_rec.title.clone());
+        }
+        if artist.is_none() {
+            artist = mb_rec
+                .artist_credit
+                .as_ref()
+                .and_then(|c| c.first())
+                .map(|c| c.name.clone());
+        }
+    }
+
+    let final_artist = artist.unwrap_or_else(|| "Unknown Artist".to_string());
+    let final_title = title.unwrap_or_else(|| "Unknown Title".to_string());
+    let mut original_artist = None;
+    let mut original_title = None;
+    let mut original_year = None;
+    let mut work_mbid = None;
+    let mut year = None;
+    let album = None; // Metadata from AcoustID is limited, usually need MB lookups for album
+
+    if let Ok(mb_rec) = mb_rec {
+        year = mb_rec
+            .first_release_date
+            .as_deref()
+            .and_then(organizer::parse_year);
+        let works: Vec<&musicbrainz::MBWork> = mb_rec
+            .relations
+            .iter()
+            .flatten()
+            .filter_map(|rel| rel.work.as_ref())
+            .collect();
+        work_mbid = works.first().map(|w| w.id.clone());
+        let found = find_original(client, cache, &works, &final_artist);
+        if let Some((artist, title)) = found.original {
+            original_artist = Some(artist);
+            original_title = Some(title);
         }
+        // The work can't be younger than this recording
+        original_year = match (found.year, year) {
+            (Some(w), Some(own)) => Some(w.min(own)),
+            (w, _) => w,
+        };
     }
 
-    Err(anyhow::anyhow!("No valid match found online"))
+    Ok(TrackMetadata {
+        title: final_title,
+        artist: final_artist,
+        album,
+        genre: None,
+        year,
+        original_artist,
+        original_title,
+        original_year,
+        duration,
+        fingerprint: Some(fp.to_string()),
+        acoustid_score: Some(score),
+        recording_mbid: Some(rec_id),
+        match_candidates: candidates,
+        work_mbid,
+        ..Default::default()
+    })
 }
@@ -0,0 +1,186 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+use crate::storage::{AudioLibrary, IndexedTrack};
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct WorkRecording {
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+}
+
+/// A musical work with the original recording (if owned) and all owned covers.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct WorkNode {
+    pub key: String,
+    pub work_mbid: Option<String>,
+    pub title: String,
+    pub original_artist: String,
+    /// Earliest year recorded for the work by any of its covers
+    pub original_year: Option<u32>,
+    pub original: Option<WorkRecording>,
+    pub covers: Vec<WorkRecording>,
+}
+
+fn normalize(s: &str) -> String {
+    s.to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn name_key(artist: &str, title: &str) -> String {
+    format!("{}|{}", normalize(artist), normalize(title))
+}
+
+fn to_recording(track: &IndexedTrack) -> WorkRecording {
+    WorkRecording {
+        path: track.path.clone(),
+        title: track.metadata.title.clone(),
+        artist: track.metadata.artist.clone(),
+        album: track.metadata.album.clone(),
+    }
+}
+
+/// Build the work -> recordings graph from cover detection results.
+/// Works are keyed by MusicBrainz work ID when known, otherwise by original artist + title.
+/// Only works with at least one owned cover are returned. Tracks are visited
+/// in the default listing order, so which track names a work and which copy
+/// becomes its original never depends on HashMap iteration order.
+pub fn build_work_graph(library: &AudioLibrary) -> Vec<WorkNode> {
+    let tracks = library.sorted_tracks();
+    let mut works: BTreeMap<String, WorkNode> = BTreeMap::new();
+    let mut key_by_name: HashMap<String, String> = HashMap::new();
+
+    for track in &tracks {
+        let meta = &track.metadata;
+        let (Some(orig_artist), Some(orig_title)) = (&meta.original_artist, &meta.original_title)
+        else {
+            continue;
+        };
+
+        let names = name_key(orig_artist, orig_title);
+        let key = meta
+            .work_mbid
+            .clone()
+            .unwrap_or_else(|| key_by_name.get(&names).cloned().unwrap_or(names.clone()));
+        key_by_name.insert(names, key.clone());
+
+        let work = works.entry(key.clone()).or_insert_with(|| WorkNode {
+            key,
+            work_mbid: meta.work_mbid.clone(),
+            title: orig_title.clone(),
+            original_artist: orig_artist.clone(),
+            original_year: None,
+            original: None,
+            covers: Vec::new(),
+        });
+        work.original_year = match (work.original_year, meta.original_year) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        work.covers.push(to_recording(track));
+    }
+
+    // Attach owned originals: same work ID by the original artist, or matching artist + title
+    for track in &tracks {
+        let meta = &track.metadata;
+        if meta.original_artist.is_some() {
+            continue;
+        }
+
+        let key = meta
+            .work_mbid
+            .as_ref()
+            .filter(|id| works.contains_key(*id))
+            .cloned()
+            .or_else(|| {
+                key_by_name
+                    .get(&name_key(&meta.artist, &meta.title))
+                    .cloned()
+            });
+
+        if let Some(work) = key.and_then(|k| works.get_mut(&k)) {
+            if normalize(&work.original_artist) == normalize(&meta.artist)
+                && work.original.is_none()
+            {
+                work.original = Some(to_recording(track));
+            }
+        }
+    }
+
+    let mut result: Vec<WorkNode> = works.into_values().collect();
+    for work in &mut result {
+        work.covers
+            .sort_by(|a, b| a.artist.cmp(&b.artist).then(a.path.cmp(&b.path)));
+    }
+    result.sort_by(|a, b| a.title.cmp(&b.title).then(a.key.cmp(&b.key)));
+    result
+}
+
+/// Find the work a track belongs to, either as the original or as a cover.
+pub fn find_work_for<'a>(works: &'a [WorkNode], path: &Path) -> Option<&'a WorkNode> {
+    works.iter().find(|w| {
+        w.original.as_ref().is_some_and(|o| o.path == path)
+            || w.covers.iter().any(|c| c.path == path)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::organizer::TrackMetadata;
+
+    fn library() -> AudioLibrary {
+        let mut library = AudioLibrary::default();
+        let covers = [
+            ("c1.mp3", "Band A", Some("work-1")),
+            ("c2.mp3", "Band B", None),
+            ("c3.mp3", "Band C", Some("work-1")),
+        ];
+        for (path, artist, work_mbid) in covers {
+            let meta = TrackMetadata {
+                title: "Song".into(),
+                artist: artist.into(),
+                original_artist: Some("Writer".into()),
+                original_title: Some("Song".into()),
+                work_mbid: work_mbid.map(str::to_string),
+                ..Default::default()
+            };
+            library.upsert(PathBuf::from(path), 1, 1, 1, meta);
+        }
+        for path in ["o1.mp3", "o2.mp3"] {
+            let meta = TrackMetadata {
+                title: "Song".into(),
+                artist: "Writer".into(),
+                ..Default::default()
+            };
+            library.upsert(PathBuf::from(path), 1, 1, 1, meta);
+        }
+        library
+    }
+
+    #[test]
+    fn graph_does_not_depend_on_hash_order() {
+        let summary = |works: Vec<WorkNode>| -> Vec<(String, Option<PathBuf>, Vec<PathBuf>)> {
+            works
+                .into_iter()
+                .map(|w| {
+                    let covers = w.covers.into_iter().map(|c| c.path).collect();
+                    (w.key, w.original.map(|o| o.path), covers)
+                })
+                .collect()
+        };
+        let first = summary(build_work_graph(&library()));
+        // Every new library hashes with fresh keys, so iteration order varies
+        for _ in 0..20 {
+            assert_eq!(summary(build_work_graph(&library())), first);
+        }
+        assert_eq!(first[0].1, Some(PathBuf::from("o1.mp3")));
+    }
+}